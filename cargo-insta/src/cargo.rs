@@ -1,4 +1,4 @@
-use std::collections::HashSet;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
 use std::env;
 use std::error::Error;
 use std::fs;
@@ -7,10 +7,10 @@ use std::process;
 
 use insta::{PendingInlineSnapshot, Snapshot};
 use serde::Deserialize;
-use walkdir::{DirEntry, WalkDir};
 
 use crate::inline::FilePatcher;
 use crate::utils::err_msg;
+use crate::walk::{find_files, parallel_map, ScanCache};
 
 #[derive(Deserialize, Clone, Debug)]
 pub struct Target {
@@ -32,6 +32,7 @@ pub struct Metadata {
     packages: Vec<Package>,
     workspace_members: Vec<String>,
     workspace_root: String,
+    target_directory: PathBuf,
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -45,6 +46,10 @@ impl Metadata {
     pub fn workspace_root(&self) -> &Path {
         Path::new(&self.workspace_root)
     }
+
+    pub fn target_directory(&self) -> &Path {
+        &self.target_directory
+    }
 }
 
 #[derive(Deserialize, Debug)]
@@ -56,15 +61,17 @@ struct ProjectLocation {
 pub enum SnapshotContainerKind {
     Inline,
     External,
+    Golden,
 }
 
 #[derive(Debug)]
 pub struct PendingSnapshot {
-    pub id: usize,
     pub old: Option<Snapshot>,
     pub new: Snapshot,
     pub op: Operation,
     pub line: Option<u32>,
+    /// Why the old snapshot file could not be loaded.
+    pub corrupted: Option<String>,
 }
 
 impl PendingSnapshot {
@@ -82,6 +89,11 @@ impl PendingSnapshot {
         }
         rv
     }
+
+    /// Checks if the new snapshot has one of the tags, no tags match all.
+    pub fn has_any_tag(&self, tags: &[String]) -> bool {
+        tags.is_empty() || self.new.metadata().tags().iter().any(|x| tags.contains(x))
+    }
 }
 
 #[derive(Debug)]
@@ -93,65 +105,106 @@ pub struct SnapshotContainer {
     patcher: Option<FilePatcher>,
 }
 
+/// Loads the snapshots of a pending snapshot file.
+///
+/// This does not touch the source files of inline snapshots so it can be
+/// done for many files at once on different threads.
+fn load_pending_snapshots(
+    snapshot_path: &Path,
+    target_path: &Path,
+    kind: &SnapshotContainerKind,
+) -> Result<Vec<PendingSnapshot>, Box<dyn Error>> {
+    let mut snapshots = Vec::new();
+    match kind {
+        SnapshotContainerKind::External => {
+            let mut corrupted = None;
+            let old = if fs::metadata(target_path).is_err() {
+                None
+            } else {
+                // a corrupted snapshot is still reviewed so that it can
+                // be replaced by the new one.
+                match Snapshot::from_file(target_path) {
+                    Ok(old) => Some(old),
+                    Err(err) => {
+                        corrupted = Some(err.to_string());
+                        None
+                    }
+                }
+            };
+            let new = Snapshot::from_file(snapshot_path)?;
+            snapshots.push(PendingSnapshot {
+                old,
+                new,
+                op: Operation::Skip,
+                line: None,
+                corrupted,
+            });
+        }
+        SnapshotContainerKind::Golden => {
+            let pending_vec = PendingInlineSnapshot::load_batch(snapshot_path)?;
+            match pending_vec.into_iter().last() {
+                Some(PendingInlineSnapshot {
+                    new: Some(new),
+                    old,
+                    line,
+                    ..
+                }) => {
+                    snapshots.push(PendingSnapshot {
+                        old,
+                        new,
+                        op: Operation::Skip,
+                        line: Some(line),
+                        corrupted: None,
+                    });
+                }
+                _ => fs::remove_file(snapshot_path)?,
+            }
+        }
+        SnapshotContainerKind::Inline => {
+            let mut pending_vec = PendingInlineSnapshot::load_batch(snapshot_path)?;
+            if fs::metadata(target_path).is_ok() {
+                pending_vec.sort_by_key(|pending| pending.line);
+                for pending in pending_vec.into_iter() {
+                    if let Some(new) = pending.new {
+                        snapshots.push(PendingSnapshot {
+                            old: pending.old,
+                            new,
+                            op: Operation::Skip,
+                            line: Some(pending.line),
+                            corrupted: None,
+                        });
+                    }
+                }
+            }
+
+            // if we don't actually have any new pending we better delete the file.
+            // this can happen if the test code left a stale snapshot behind.
+            // The runtime code will issue something like this:
+            //   PendingInlineSnapshot::new(None, None, line).save(pending_snapshots)?;
+            if snapshots.is_empty() {
+                fs::remove_file(snapshot_path)?;
+            }
+        }
+    }
+    Ok(snapshots)
+}
+
 impl SnapshotContainer {
-    fn load(
+    fn new(
         snapshot_path: PathBuf,
         target_path: PathBuf,
         kind: SnapshotContainerKind,
+        snapshots: Vec<PendingSnapshot>,
     ) -> Result<SnapshotContainer, Box<dyn Error>> {
-        let mut snapshots = Vec::new();
         let patcher = match kind {
-            SnapshotContainerKind::External => {
-                let old = if fs::metadata(&target_path).is_err() {
-                    None
-                } else {
-                    Some(Snapshot::from_file(&target_path)?)
-                };
-                let new = Snapshot::from_file(&snapshot_path)?;
-                snapshots.push(PendingSnapshot {
-                    id: 0,
-                    old,
-                    new,
-                    op: Operation::Skip,
-                    line: None,
-                });
-                None
-            }
-            SnapshotContainerKind::Inline => {
-                let mut pending_vec = PendingInlineSnapshot::load_batch(&snapshot_path)?;
-                let mut have_new = false;
-
-                let rv = if fs::metadata(&target_path).is_ok() {
-                    let mut patcher = FilePatcher::open(&target_path)?;
-                    pending_vec.sort_by_key(|pending| pending.line);
-                    for (id, pending) in pending_vec.into_iter().enumerate() {
-                        if let Some(new) = pending.new {
-                            snapshots.push(PendingSnapshot {
-                                id,
-                                old: pending.old,
-                                new,
-                                op: Operation::Skip,
-                                line: Some(pending.line),
-                            });
-                            patcher.add_snapshot_macro(pending.line as usize);
-                            have_new = true;
-                        }
-                    }
-                    Some(patcher)
-                } else {
-                    None
-                };
-
-                // if we don't actually have any new pending we better delete the file.
-                // this can happen if the test code left a stale snapshot behind.
-                // The runtime code will issue something like this:
-                //   PendingInlineSnapshot::new(None, None, line).save(pending_snapshots)?;
-                if !have_new {
-                    fs::remove_file(&snapshot_path)?;
+            SnapshotContainerKind::Inline if !snapshots.is_empty() => {
+                let mut patcher = FilePatcher::open(&target_path)?;
+                for snapshot in &snapshots {
+                    patcher.add_snapshot_macro(snapshot.line.unwrap_or(0) as usize);
                 }
-
-                rv
+                Some(patcher)
             }
+            _ => None,
         };
 
         Ok(SnapshotContainer {
@@ -165,7 +218,9 @@ impl SnapshotContainer {
 
     pub fn snapshot_file(&self) -> Option<&Path> {
         match self.kind {
-            SnapshotContainerKind::External => Some(&self.target_path),
+            SnapshotContainerKind::External | SnapshotContainerKind::Golden => {
+                Some(&self.target_path)
+            }
             SnapshotContainerKind::Inline => None,
         }
     }
@@ -174,6 +229,14 @@ impl SnapshotContainer {
         self.snapshots.len()
     }
 
+    /// Counts the snapshots having one of the tags, no tags count all.
+    pub fn count_tagged(&self, tags: &[String]) -> usize {
+        self.snapshots
+            .iter()
+            .filter(|x| x.has_any_tag(tags))
+            .count()
+    }
+
     pub fn iter_snapshots(&mut self) -> impl Iterator<Item = &'_ mut PendingSnapshot> {
         self.snapshots.iter_mut()
     }
@@ -210,15 +273,47 @@ impl SnapshotContainer {
             } else {
                 fs::remove_file(&self.snapshot_path)?;
             }
+        } else if let SnapshotContainerKind::Golden = self.kind {
+            // golden files hold nothing but the contents
+            for snapshot in self.snapshots.iter() {
+                match snapshot.op {
+                    Operation::Accept => {
+                        fs::write(&self.target_path, snapshot.new.contents_str())?;
+                        fs::remove_file(&self.snapshot_path)?;
+                    }
+                    Operation::Reject => {
+                        fs::remove_file(&self.snapshot_path)?;
+                    }
+                    Operation::Skip => {}
+                }
+            }
         } else {
             // should only be one or this is weird
             for snapshot in self.snapshots.iter() {
+                // binary payloads are stored next to the snapshot files
+                let pending_binary = snapshot.new.metadata().binary_path(&self.snapshot_path);
                 match snapshot.op {
                     Operation::Accept => {
                         fs::rename(&self.snapshot_path, &self.target_path)?;
+                        if let Some(old_binary) = snapshot
+                            .old
+                            .as_ref()
+                            .and_then(|x| x.metadata().binary_path(&self.target_path))
+                        {
+                            fs::remove_file(old_binary).ok();
+                        }
+                        if let (Some(from), Some(to)) = (
+                            pending_binary,
+                            snapshot.new.metadata().binary_path(&self.target_path),
+                        ) {
+                            fs::rename(from, to)?;
+                        }
                     }
                     Operation::Reject => {
                         fs::remove_file(&self.snapshot_path)?;
+                        if let Some(pending_binary) = pending_binary {
+                            fs::remove_file(pending_binary).ok();
+                        }
                     }
                     Operation::Skip => {}
                 }
@@ -228,47 +323,148 @@ impl SnapshotContainer {
     }
 }
 
-fn is_hidden(entry: &DirEntry) -> bool {
-    entry
-        .file_name()
-        .to_str()
-        .map(|s| s.starts_with('.'))
-        .unwrap_or(false)
+fn scan_cache(cache_dir: Option<&Path>, name: &str, extensions: &[&str]) -> Option<ScanCache> {
+    Some(ScanCache {
+        path: cache_dir?.join(name),
+        key: extensions.join(","),
+    })
 }
 
-pub fn find_snapshots<'a>(
-    root: PathBuf,
-    extensions: &'a [&'a str],
-) -> impl Iterator<Item = Result<SnapshotContainer, Box<dyn Error>>> + 'a {
-    WalkDir::new(root.clone())
+/// Finds all snapshot files (not pending ones) below the given folders.
+///
+/// If a cache folder is given the results of the scan are cached there.
+pub fn find_snapshot_files(
+    roots: &[PathBuf],
+    extensions: &[&str],
+    cache_dir: Option<&Path>,
+) -> Vec<PathBuf> {
+    let cache = scan_cache(cache_dir, "snapshot-files.json", extensions);
+    find_files(roots, "snapshot files", cache.as_ref(), |path| {
+        path.file_name()
+            .and_then(|x| x.to_str())
+            .is_some_and(|x| has_extension(x, extensions))
+    })
+    .into_iter()
+    .map(|x| x.1)
+    .collect()
+}
+
+/// Keeps the snapshot files that have one of the tags in their metadata.
+///
+/// Files that cannot be loaded are dropped.
+pub fn filter_tagged_snapshot_files(snapshot_files: Vec<PathBuf>, tags: &[String]) -> Vec<PathBuf> {
+    let keep = parallel_map(&snapshot_files, "snapshot files", |path| {
+        Snapshot::from_file(path)
+            .map(|x| x.metadata().tags().iter().any(|x| tags.contains(x)))
+            .unwrap_or(false)
+    });
+    snapshot_files
         .into_iter()
-        .filter_entry(|e| e.file_type().is_file() || !is_hidden(e))
-        .filter_map(|e| e.ok())
-        .filter_map(move |e| {
-            let fname = e.file_name().to_string_lossy();
-            if fname.ends_with(".new")
-                && extensions.contains(&fname.rsplit('.').nth(1).unwrap_or(""))
-            {
-                let new_path = e.into_path();
-                let mut old_path = new_path.clone();
-                old_path.set_extension("");
-                Some(SnapshotContainer::load(
-                    new_path,
-                    old_path,
-                    SnapshotContainerKind::External,
-                ))
-            } else if fname.starts_with('.') && fname.ends_with(".pending-snap") {
-                let mut target_path = e.path().to_path_buf();
-                target_path.set_file_name(&fname[1..fname.len() - 13]);
-                Some(SnapshotContainer::load(
-                    e.path().to_path_buf(),
-                    target_path,
-                    SnapshotContainerKind::Inline,
-                ))
-            } else {
-                None
-            }
-        })
+        .zip(keep)
+        .filter(|x| x.1)
+        .map(|x| x.0)
+        .collect()
+}
+
+/// Groups the snapshot files whose paths only differ in the case of the
+/// file name.
+pub fn find_case_collisions<'a, I>(snapshot_files: I) -> Vec<Vec<PathBuf>>
+where
+    I: IntoIterator<Item = &'a Path>,
+{
+    let mut groups: BTreeMap<(Option<&Path>, String), BTreeSet<PathBuf>> = BTreeMap::new();
+    for path in snapshot_files {
+        let name = match path.file_name() {
+            Some(name) => name.to_string_lossy().to_lowercase(),
+            None => continue,
+        };
+        groups
+            .entry((path.parent(), name))
+            .or_default()
+            .insert(path.to_path_buf());
+    }
+    groups
+        .into_values()
+        .filter(|x| x.len() > 1)
+        .map(|x| x.into_iter().collect())
+        .collect()
+}
+
+/// Checks if a file name ends in one of the extensions.
+///
+/// Extensions can have multiple parts (eg: `snap.json`).
+fn has_extension(file_name: &str, extensions: &[&str]) -> bool {
+    extensions.iter().any(|ext| {
+        file_name
+            .strip_suffix(ext)
+            .is_some_and(|x| x.len() > 1 && x.ends_with('.'))
+    })
+}
+
+/// Returns the target path and kind of a pending snapshot file.
+fn classify_pending_file(
+    path: &Path,
+    extensions: &[&str],
+) -> Option<(PathBuf, SnapshotContainerKind)> {
+    let fname = path.file_name()?.to_string_lossy();
+    if let Some(target_name) = fname
+        .strip_suffix(".new")
+        .filter(|x| has_extension(x, extensions))
+    {
+        let target_path = path.with_file_name(target_name);
+        Some((target_path, SnapshotContainerKind::External))
+    } else if fname.starts_with('.') && fname.ends_with(".pending-snap") {
+        let mut target_path = path.to_path_buf();
+        target_path.set_file_name(&fname[1..fname.len() - 13]);
+        Some((target_path, SnapshotContainerKind::Inline))
+    } else if fname.starts_with('.') && fname.ends_with(".pending-golden") {
+        let mut target_path = path.to_path_buf();
+        target_path.set_file_name(&fname[1..fname.len() - 15]);
+        Some((target_path, SnapshotContainerKind::Golden))
+    } else {
+        None
+    }
+}
+
+/// Finds and loads all pending snapshots below the given folders.
+///
+/// The folders are scanned and the snapshots parsed on multiple threads.
+/// Every container is returned with the index of the folder it was found in.
+/// If a cache folder is given the results of the scan are cached there.
+pub fn find_snapshots(
+    roots: &[PathBuf],
+    extensions: &[&str],
+    cache_dir: Option<&Path>,
+) -> Result<Vec<(usize, SnapshotContainer)>, Box<dyn Error>> {
+    let cache = scan_cache(cache_dir, "pending-snapshots.json", extensions);
+    let pending: Vec<_> = find_files(roots, "pending snapshots", cache.as_ref(), |path| {
+        classify_pending_file(path, extensions).is_some()
+    })
+    .into_iter()
+    .filter_map(|(root, path)| {
+        let (target_path, kind) = classify_pending_file(&path, extensions)?;
+        Some((root, path, target_path, kind))
+    })
+    .collect();
+
+    // errors are not `Send` so they are carried back as strings
+    let loaded = parallel_map(
+        &pending,
+        "pending snapshots",
+        |(_, path, target_path, kind)| {
+            load_pending_snapshots(path, target_path, kind).map_err(|err| err.to_string())
+        },
+    );
+
+    let mut rv = vec![];
+    for ((root, path, target_path, kind), snapshots) in pending.into_iter().zip(loaded) {
+        let snapshots = snapshots.map_err(err_msg)?;
+        rv.push((
+            root,
+            SnapshotContainer::new(path, target_path, kind, snapshots)?,
+        ));
+    }
+    Ok(rv)
 }
 
 impl Package {
@@ -280,10 +476,8 @@ impl Package {
         &self.version
     }
 
-    pub fn iter_snapshot_containers<'a>(
-        &self,
-        extensions: &'a [&'a str],
-    ) -> impl Iterator<Item = Result<SnapshotContainer, Box<dyn Error>>> + 'a {
+    /// Returns the folders that can hold snapshots of this package.
+    pub fn snapshot_roots(&self) -> Vec<PathBuf> {
         let mut roots = Vec::new();
 
         // the manifest path's parent is always a snapshot container.  For
@@ -317,14 +511,12 @@ impl Package {
         roots.sort_by_key(|x| x.as_os_str().len());
         let mut reduced_roots = vec![];
         for root in roots {
-            if !reduced_roots.iter().any(|x| root.starts_with(&x)) {
+            if !reduced_roots.iter().any(|x| root.starts_with(x)) {
                 reduced_roots.push(root);
             }
         }
 
         reduced_roots
-            .into_iter()
-            .flat_map(move |root| find_snapshots(root, extensions))
     }
 }
 
@@ -342,7 +534,7 @@ pub fn get_package_metadata(manifest_path: Option<&Path>) -> Result<Metadata, Bo
     if let Some(manifest_path) = manifest_path {
         if !fs::metadata(manifest_path)
             .ok()
-            .map_or(false, |x| x.is_file())
+            .is_some_and(|x| x.is_file())
         {
             return Err(err_msg(
                 "the manifest-path must be a path to a Cargo.toml file",
@@ -413,3 +605,38 @@ pub fn find_packages(metadata: &Metadata, all: bool) -> Result<Vec<Package>, Box
         }
     }
 }
+
+#[test]
+fn test_find_snapshot_files_cache() {
+    use crate::walk::{make_test_dir, set_mtime};
+    use std::time::{Duration, SystemTime};
+
+    let root = make_test_dir("snapshot-files");
+    let cache_dir = root.join("target/insta");
+    fs::create_dir_all(&cache_dir).unwrap();
+    fs::write(cache_dir.join("test__cached.snap"), "").unwrap();
+    fs::create_dir_all(root.join("src/snapshots")).unwrap();
+    fs::write(root.join("src/snapshots/test__a.snap"), "").unwrap();
+    fs::write(root.join("src/snapshots/test__b.snap"), "").unwrap();
+    let past = SystemTime::now() - Duration::from_secs(3600);
+    for dir in &["", "src", "src/snapshots", "target"] {
+        set_mtime(&root.join(dir), past);
+    }
+
+    let roots = vec![root.clone()];
+    let find = || find_snapshot_files(&roots, &["snap"], Some(&cache_dir));
+    let expected = vec![
+        root.join("src/snapshots/test__a.snap"),
+        root.join("src/snapshots/test__b.snap"),
+    ];
+    assert_eq!(find(), expected);
+    assert!(cache_dir.join("snapshot-files.json").is_file());
+    // served from the cache
+    assert_eq!(find(), expected);
+
+    // deleting a file changes the modification time of its folder so the
+    // cached entry is not returned anymore
+    fs::remove_file(root.join("src/snapshots/test__a.snap")).unwrap();
+    assert_eq!(find(), vec![root.join("src/snapshots/test__b.snap")]);
+    fs::remove_dir_all(&root).unwrap();
+}
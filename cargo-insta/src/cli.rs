@@ -1,16 +1,21 @@
 use console::{set_colors_enabled, style, Key, Term};
-use insta::{print_snapshot_diff, Snapshot};
+use insta::{
+    print_snapshot_diff, shorten_snapshot_file_stem, Settings, Snapshot, SnapshotPathBase,
+    ToolConfig, FORMAT_VERSION,
+};
 use std::env;
 use std::error::Error;
+use std::fs;
 use std::path::{Path, PathBuf};
 use std::process;
 use structopt::clap::AppSettings;
 use structopt::StructOpt;
 
 use crate::cargo::{
-    find_packages, find_snapshots, get_cargo, get_package_metadata, Operation, Package,
-    SnapshotContainer,
+    filter_tagged_snapshot_files, find_case_collisions, find_packages, find_snapshot_files,
+    find_snapshots, get_cargo, get_package_metadata, Operation, Package, SnapshotContainer,
 };
+use crate::coverage::{load_usage_records, CoverageReport};
 use crate::utils::{err_msg, QuietExit};
 
 /// A helper utility to work with insta snapshots.
@@ -47,6 +52,18 @@ pub enum Command {
     /// Run tests and then reviews
     #[structopt(name = "test")]
     Test(TestCommand),
+    /// Reports which tests use which snapshots
+    #[structopt(name = "coverage")]
+    Coverage(CoverageCommand),
+    /// Converts a Jest snapshot file into insta snapshots
+    #[structopt(name = "jest-import")]
+    JestImport(JestImportCommand),
+    /// Converts insta snapshots into a Jest snapshot file
+    #[structopt(name = "jest-export")]
+    JestExport(JestExportCommand),
+    /// Upgrades snapshot files to the current file format
+    #[structopt(name = "migrate")]
+    Migrate(MigrateCommand),
 }
 
 #[derive(StructOpt, Debug, Clone)]
@@ -58,12 +75,15 @@ pub struct TargetArgs {
     /// Explicit path to the workspace root
     #[structopt(long, value_name = "PATH", parse(from_os_str))]
     pub workspace_root: Option<PathBuf>,
-    /// Sets the extensions to consider.  Defaults to `.snap`
+    /// Sets the extensions to consider.  Defaults to `.snap` and the configured extension
     #[structopt(short = "e", long, value_name = "EXTENSIONS", multiple = true)]
     pub extensions: Vec<String>,
     /// Work on all packages in the workspace
     #[structopt(long)]
     pub all: bool,
+    /// Do not use the cached results of earlier scans for snapshots
+    #[structopt(long)]
+    pub no_cache: bool,
 }
 
 #[derive(StructOpt, Debug)]
@@ -71,6 +91,9 @@ pub struct TargetArgs {
 pub struct ProcessCommand {
     #[structopt(flatten)]
     pub target_args: TargetArgs,
+    /// Only work on snapshots with this tag (can be repeated)
+    #[structopt(long = "tag", value_name = "TAG", number_of_values = 1)]
+    pub tags: Vec<String>,
     /// Do not print to stdout.
     #[structopt(short = "q", long)]
     pub quiet: bool,
@@ -122,6 +145,80 @@ pub struct TestCommand {
     pub force_update_snapshots: bool,
 }
 
+#[derive(StructOpt, Debug)]
+#[structopt(rename_all = "kebab-case")]
+pub struct CoverageCommand {
+    #[structopt(flatten)]
+    pub target_args: TargetArgs,
+    /// Package to run tests for
+    #[structopt(short = "p", long)]
+    pub package: Option<String>,
+    /// Space-separated list of features to activate
+    #[structopt(long, value_name = "FEATURES")]
+    pub features: Option<String>,
+    /// Activate all available features
+    #[structopt(long)]
+    pub all_features: bool,
+    /// Do not activate the `default` feature
+    #[structopt(long)]
+    pub no_default_features: bool,
+    /// Read a recorded INSTA_SNAPSHOT_USAGE_FILE instead of running the tests
+    #[structopt(long, value_name = "PATH", parse(from_os_str))]
+    pub input: Option<PathBuf>,
+    /// File to write the JSON report to, defaults to stdout
+    #[structopt(short = "o", long, value_name = "PATH", parse(from_os_str))]
+    pub output: Option<PathBuf>,
+    /// Only report snapshots with this tag (can be repeated)
+    #[structopt(long = "tag", value_name = "TAG", number_of_values = 1)]
+    pub tags: Vec<String>,
+}
+
+#[derive(StructOpt, Debug)]
+#[structopt(rename_all = "kebab-case")]
+pub struct JestImportCommand {
+    /// Path to the Jest snapshot file
+    #[structopt(value_name = "PATH", parse(from_os_str))]
+    pub path: PathBuf,
+    /// Module name for the snapshots, defaults to the test file name
+    #[structopt(long, value_name = "NAME")]
+    pub module: Option<String>,
+    /// Folder to write the snapshots to
+    #[structopt(
+        long,
+        value_name = "PATH",
+        parse(from_os_str),
+        default_value = "snapshots"
+    )]
+    pub out_dir: PathBuf,
+    /// Do not print to stdout.
+    #[structopt(short = "q", long)]
+    pub quiet: bool,
+}
+
+#[derive(StructOpt, Debug)]
+#[structopt(rename_all = "kebab-case")]
+pub struct JestExportCommand {
+    /// Paths to the insta snapshot files
+    #[structopt(value_name = "PATH", parse(from_os_str), required = true)]
+    pub paths: Vec<PathBuf>,
+    /// File to write the Jest snapshots to, defaults to stdout
+    #[structopt(short = "o", long, value_name = "PATH", parse(from_os_str))]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(StructOpt, Debug)]
+#[structopt(rename_all = "kebab-case")]
+pub struct MigrateCommand {
+    #[structopt(flatten)]
+    pub target_args: TargetArgs,
+    /// Only report outdated files and fail if there are any
+    #[structopt(long)]
+    pub check: bool,
+    /// Do not print to stdout.
+    #[structopt(short = "q", long)]
+    pub quiet: bool,
+}
+
 #[allow(clippy::too_many_arguments)]
 fn query_snapshot(
     workspace_root: &Path,
@@ -133,6 +230,7 @@ fn query_snapshot(
     i: usize,
     n: usize,
     snapshot_file: Option<&Path>,
+    corrupted: Option<&str>,
 ) -> Result<Operation, Box<dyn Error>> {
     term.clear_screen()?;
     println!(
@@ -148,6 +246,10 @@ fn query_snapshot(
         println!();
     }
 
+    if let Some(err) = corrupted {
+        println!("{} {}", style("warning:").bold().yellow(), err);
+        println!("the corrupted file is replaced when accepting the new snapshot.");
+    }
     print_snapshot_diff(workspace_root, new, old, snapshot_file, line);
 
     println!();
@@ -187,55 +289,146 @@ fn handle_color(color: &str) -> Result<(), Box<dyn Error>> {
     Ok(())
 }
 
-struct LocationInfo<'a> {
+struct LocationInfo {
     workspace_root: PathBuf,
     packages: Option<Vec<Package>>,
-    exts: Vec<&'a str>,
+    exts: Vec<String>,
+    cache_dir: Option<PathBuf>,
+    config: ToolConfig,
 }
 
-fn handle_target_args(target_args: &TargetArgs) -> Result<LocationInfo<'_>, Box<dyn Error>> {
-    let mut exts: Vec<&str> = target_args.extensions.iter().map(|x| x.as_str()).collect();
-    if exts.is_empty() {
-        exts.push("snap");
+impl LocationInfo {
+    fn new(
+        target_args: &TargetArgs,
+        workspace_root: PathBuf,
+        packages: Option<Vec<Package>>,
+        cache_dir: Option<PathBuf>,
+    ) -> Result<LocationInfo, Box<dyn Error>> {
+        let config = ToolConfig::from_workspace(&workspace_root).map_err(err_msg)?;
+        let mut exts = target_args.extensions.clone();
+        if exts.is_empty() {
+            exts = config.review.extensions.clone().unwrap_or_else(|| {
+                let mut exts = vec!["snap".to_string()];
+                if let Some(ref ext) = config.snapshots.extension {
+                    exts.push(ext.trim_start_matches('.').to_string());
+                }
+                exts
+            });
+        }
+        Ok(LocationInfo {
+            workspace_root,
+            packages,
+            exts,
+            cache_dir,
+            config,
+        })
+    }
+
+    fn exts(&self) -> Vec<&str> {
+        self.exts.iter().map(|x| x.as_str()).collect()
     }
+}
+
+fn handle_target_args(target_args: &TargetArgs) -> Result<LocationInfo, Box<dyn Error>> {
     match target_args.workspace_root {
-        Some(ref root) => Ok(LocationInfo {
-            workspace_root: root.clone(),
-            packages: None,
-            exts,
-        }),
+        Some(ref root) => LocationInfo::new(target_args, root.clone(), None, None),
         None => {
-            let metadata =
-                get_package_metadata(target_args.manifest_path.as_ref().map(|x| x.as_path()))?;
+            let metadata = get_package_metadata(target_args.manifest_path.as_deref())?;
             let packages = find_packages(&metadata, target_args.all)?;
-            Ok(LocationInfo {
-                workspace_root: metadata.workspace_root().to_path_buf(),
-                packages: Some(packages),
-                exts,
-            })
+            let cache_dir = if target_args.no_cache {
+                None
+            } else {
+                Some(metadata.target_directory().join("insta"))
+            };
+            LocationInfo::new(
+                target_args,
+                metadata.workspace_root().to_path_buf(),
+                Some(packages),
+                cache_dir,
+            )
         }
     }
 }
 
-fn load_snapshot_containers<'a>(
-    loc: &'a LocationInfo,
-) -> Result<Vec<(SnapshotContainer, Option<&'a Package>)>, Box<dyn Error>> {
-    let mut snapshot_containers = vec![];
-    match loc.packages {
-        Some(ref packages) => {
-            for package in packages.iter() {
-                for snapshot_container in package.iter_snapshot_containers(&loc.exts) {
-                    snapshot_containers.push((snapshot_container?, Some(package)));
-                }
-            }
+/// Returns the folders to scan along with the package each one belongs to.
+fn snapshot_roots(loc: &LocationInfo) -> (Vec<PathBuf>, Vec<Option<&Package>>) {
+    let (mut roots, mut packages): (Vec<_>, Vec<_>) = match loc.packages {
+        Some(ref packages) => packages
+            .iter()
+            .flat_map(|package| {
+                package
+                    .snapshot_roots()
+                    .into_iter()
+                    .map(move |root| (root, Some(package)))
+            })
+            .unzip(),
+        None => (vec![loc.workspace_root.clone()], vec![None]),
+    };
+
+    // snapshots configured to be relative to the workspace root can be
+    // outside of all packages.
+    let snapshots = &loc.config.snapshots;
+    if snapshots.path_base == Some(SnapshotPathBase::Workspace) {
+        let root = loc.workspace_root.join(
+            snapshots
+                .path
+                .as_deref()
+                .unwrap_or_else(|| Path::new("snapshots")),
+        );
+        if !roots.iter().any(|x| root.starts_with(x)) {
+            roots.push(root);
+            packages.push(None);
         }
-        None => {
-            for snapshot_container in find_snapshots(loc.workspace_root.clone(), &loc.exts) {
-                snapshot_containers.push((snapshot_container?, None));
-            }
+    }
+    (roots, packages)
+}
+
+#[allow(clippy::type_complexity)]
+fn load_snapshot_containers(
+    loc: &LocationInfo,
+) -> Result<Vec<(SnapshotContainer, Option<&Package>)>, Box<dyn Error>> {
+    let (roots, packages) = snapshot_roots(loc);
+    Ok(
+        find_snapshots(&roots, &loc.exts(), loc.cache_dir.as_deref())?
+            .into_iter()
+            .map(|(root, snapshot_container)| (snapshot_container, packages[root]))
+            .collect(),
+    )
+}
+
+/// Fails if pending or existing snapshot files only differ in case as they
+/// overwrite each other on case-insensitive file systems.
+fn check_case_collisions(
+    loc: &LocationInfo,
+    snapshot_containers: &[(SnapshotContainer, Option<&Package>)],
+) -> Result<(), Box<dyn Error>> {
+    let (roots, _) = snapshot_roots(loc);
+    let snapshot_files = find_snapshot_files(&roots, &loc.exts(), loc.cache_dir.as_deref());
+    let pending_files = snapshot_containers
+        .iter()
+        .filter_map(|x| x.0.snapshot_file());
+    let collisions = find_case_collisions(
+        snapshot_files
+            .iter()
+            .map(|x| x.as_path())
+            .chain(pending_files),
+    );
+    if collisions.is_empty() {
+        return Ok(());
+    }
+    for group in &collisions {
+        eprintln!(
+            "{}: snapshot files only differ in case and overwrite each other on \
+             case-insensitive file systems (macOS, Windows):",
+            style("error").red().bold()
+        );
+        for path in group {
+            let path = path.strip_prefix(&loc.workspace_root).unwrap_or(path);
+            eprintln!("  {}", path.display());
         }
     }
-    Ok(snapshot_containers)
+    eprintln!("rename the snapshots so that their names differ in more than case.");
+    Err(QuietExit(1).into())
 }
 
 fn process_snapshots(cmd: ProcessCommand, op: Option<Operation>) -> Result<(), Box<dyn Error>> {
@@ -243,8 +436,12 @@ fn process_snapshots(cmd: ProcessCommand, op: Option<Operation>) -> Result<(), B
 
     let loc = handle_target_args(&cmd.target_args)?;
     let mut snapshot_containers = load_snapshot_containers(&loc)?;
+    check_case_collisions(&loc, &snapshot_containers)?;
 
-    let snapshot_count = snapshot_containers.iter().map(|x| x.0.len()).sum();
+    let snapshot_count = snapshot_containers
+        .iter()
+        .map(|x| x.0.count_tagged(&cmd.tags))
+        .sum();
 
     if snapshot_count == 0 {
         if !cmd.quiet {
@@ -260,7 +457,10 @@ fn process_snapshots(cmd: ProcessCommand, op: Option<Operation>) -> Result<(), B
 
     for (snapshot_container, package) in snapshot_containers.iter_mut() {
         let snapshot_file = snapshot_container.snapshot_file().map(|x| x.to_path_buf());
-        for snapshot_ref in snapshot_container.iter_snapshots() {
+        for snapshot_ref in snapshot_container
+            .iter_snapshots()
+            .filter(|x| x.has_any_tag(&cmd.tags))
+        {
             num += 1;
             let op = match op {
                 Some(op) => op,
@@ -273,7 +473,8 @@ fn process_snapshots(cmd: ProcessCommand, op: Option<Operation>) -> Result<(), B
                     snapshot_ref.line,
                     num,
                     snapshot_count,
-                    snapshot_file.as_ref().map(|x| x.as_path()),
+                    snapshot_file.as_deref(),
+                    snapshot_ref.corrupted.as_deref(),
                 )?,
             };
             match op {
@@ -326,6 +527,15 @@ fn test_run(mut cmd: TestCommand, color: &str) -> Result<(), Box<dyn Error>> {
     let mut proc = process::Command::new(get_cargo());
     proc.arg("test");
 
+    // the defaults from insta.toml apply unless overridden by the switches
+    let config = handle_target_args(&cmd.target_args)?.config.test;
+    if !cmd.accept {
+        cmd.review |= config.review.unwrap_or(false);
+    }
+    cmd.accept_unseen |= config.accept_unseen.unwrap_or(false);
+    cmd.keep_pending |= config.keep_pending.unwrap_or(false);
+    cmd.no_force_pass |= config.force_pass == Some(false);
+
     // if INSTA_UPDATE is set as environment variable we're using it to
     // override some arguments.  The logic is is quite weird because we
     // don't support all of the same values and we also want to override
@@ -401,6 +611,7 @@ fn test_run(mut cmd: TestCommand, color: &str) -> Result<(), Box<dyn Error>> {
         process_snapshots(
             ProcessCommand {
                 target_args: cmd.target_args.clone(),
+                tags: vec![],
                 quiet: true,
             },
             Some(Operation::Reject),
@@ -428,6 +639,7 @@ fn test_run(mut cmd: TestCommand, color: &str) -> Result<(), Box<dyn Error>> {
         process_snapshots(
             ProcessCommand {
                 target_args: cmd.target_args.clone(),
+                tags: vec![],
                 quiet: false,
             },
             if cmd.accept {
@@ -456,6 +668,224 @@ fn test_run(mut cmd: TestCommand, color: &str) -> Result<(), Box<dyn Error>> {
     Ok(())
 }
 
+fn coverage(cmd: CoverageCommand, color: &str) -> Result<(), Box<dyn Error>> {
+    let loc = handle_target_args(&cmd.target_args)?;
+
+    let mut records = match cmd.input {
+        Some(ref input) => load_usage_records(input)?,
+        None => {
+            let usage_file = env::temp_dir().join(format!("insta-usage-{}.jsonl", process::id()));
+            fs::remove_file(&usage_file).ok();
+            let mut proc = process::Command::new(get_cargo());
+            proc.arg("test");
+            if cmd.target_args.all {
+                proc.arg("--all");
+            }
+            if let Some(ref pkg) = cmd.package {
+                proc.arg("--package");
+                proc.arg(pkg);
+            }
+            if let Some(ref manifest_path) = cmd.target_args.manifest_path {
+                proc.arg("--manifest-path");
+                proc.arg(manifest_path);
+            }
+            if let Some(ref features) = cmd.features {
+                proc.arg("--features");
+                proc.arg(features);
+            }
+            if cmd.all_features {
+                proc.arg("--all-features");
+            }
+            if cmd.no_default_features {
+                proc.arg("--no-default-features");
+            }
+            proc.arg("--no-fail-fast");
+            proc.arg("--color");
+            proc.arg(color);
+            // only record, failing snapshots are reported by `cargo insta test`
+            proc.env("INSTA_SNAPSHOT_USAGE_FILE", &usage_file);
+            proc.env("INSTA_FORCE_PASS", "1");
+            proc.env("INSTA_UPDATE", "no");
+            proc.env("INSTA_OUTPUT", "none");
+            proc.arg("--");
+            proc.arg("-q");
+            if !proc.status()?.success() {
+                eprintln!(
+                    "{} some tests failed, the report might be incomplete",
+                    style("warning:").bold().yellow()
+                );
+            }
+            let records = if fs::metadata(&usage_file).is_ok() {
+                load_usage_records(&usage_file)?
+            } else {
+                vec![]
+            };
+            fs::remove_file(&usage_file).ok();
+            records
+        }
+    };
+
+    let (roots, _) = snapshot_roots(&loc);
+    let mut snapshot_files = find_snapshot_files(&roots, &loc.exts(), loc.cache_dir.as_deref());
+    if !cmd.tags.is_empty() {
+        records.retain(|x| x.has_any_tag(&cmd.tags));
+        snapshot_files = filter_tagged_snapshot_files(snapshot_files, &cmd.tags);
+    }
+    let report = CoverageReport::new(&records, snapshot_files, &loc.workspace_root);
+
+    let rendered = serde_json::to_string_pretty(&report)?;
+    match cmd.output {
+        Some(ref path) => fs::write(path, format!("{}\n", rendered))?,
+        None => println!("{}", rendered),
+    }
+    let (referenced, total) = report.summary();
+    eprintln!(
+        "{}: {} of {} snapshot files referenced by tests",
+        style("coverage").bold(),
+        referenced,
+        total
+    );
+    Ok(())
+}
+
+fn jest_import(cmd: JestImportCommand) -> Result<(), Box<dyn Error>> {
+    let source = fs::read_to_string(&cmd.path)?;
+    let module = match cmd.module {
+        Some(module) => module,
+        // sum.test.js.snap -> sum
+        None => cmd
+            .path
+            .file_name()
+            .and_then(|x| x.to_str())
+            .and_then(|x| x.split('.').next())
+            .filter(|x| !x.is_empty())
+            .ok_or_else(|| err_msg("could not determine module name, pass --module"))?
+            .replace('-', "_"),
+    };
+    let snapshots = insta::jest::from_jest(&source, &module)?;
+    for snapshot in &snapshots {
+        let stem = format!(
+            "{}__{}",
+            snapshot.module_name(),
+            snapshot.snapshot_name().unwrap_or("")
+        );
+        let path = cmd
+            .out_dir
+            .join(format!("{}.snap", shorten_snapshot_file_stem(&stem)));
+        snapshot.save(&path)?;
+        if !cmd.quiet {
+            println!("{} {}", style("imported").green(), path.display());
+        }
+    }
+    if !cmd.quiet {
+        println!(
+            "{}: {} snapshots imported",
+            style("done").bold(),
+            snapshots.len()
+        );
+    }
+    Ok(())
+}
+
+fn jest_export(cmd: JestExportCommand) -> Result<(), Box<dyn Error>> {
+    let snapshots = cmd
+        .paths
+        .iter()
+        .map(Snapshot::from_file)
+        .collect::<Result<Vec<_>, _>>()?;
+    let rendered = insta::jest::to_jest(&snapshots);
+    match cmd.output {
+        Some(path) => fs::write(path, rendered)?,
+        None => print!("{}", rendered),
+    }
+    Ok(())
+}
+
+fn migrate(cmd: MigrateCommand) -> Result<(), Box<dyn Error>> {
+    let loc = handle_target_args(&cmd.target_args)?;
+    let (roots, _) = snapshot_roots(&loc);
+    let mut snapshot_files = find_snapshot_files(&roots, &loc.exts(), loc.cache_dir.as_deref());
+    snapshot_files.sort();
+
+    // the contents are loaded as they are stored so that saving only
+    // rewrites the header in the current format: `\r\n` line endings are
+    // kept and binary payloads are written back unchanged.
+    let mut settings = Settings::clone_current();
+    settings.set_normalize_line_endings(false);
+    let _guard = settings.bind_to_scope();
+
+    let mut outdated = 0;
+    let mut failed = 0;
+    for path in &snapshot_files {
+        let rel_path = path.strip_prefix(&loc.workspace_root).unwrap_or(path);
+        let snapshot = match Snapshot::from_file(path) {
+            Ok(snapshot) => snapshot,
+            Err(err) => {
+                eprintln!("{}: {}", style("error").red().bold(), err);
+                failed += 1;
+                continue;
+            }
+        };
+        if snapshot.metadata().format_version() >= FORMAT_VERSION {
+            continue;
+        }
+        outdated += 1;
+        if !cmd.check {
+            if let Err(err) = snapshot.save(path) {
+                eprintln!(
+                    "{}: could not write {}: {}",
+                    style("error").red().bold(),
+                    rel_path.display(),
+                    err
+                );
+                failed += 1;
+                continue;
+            }
+        }
+        if !cmd.quiet {
+            let path = rel_path;
+            println!(
+                "{} {} (from format version {})",
+                if cmd.check {
+                    style("outdated").yellow()
+                } else {
+                    style("migrated").green()
+                },
+                path.display(),
+                snapshot.metadata().format_version()
+            );
+        }
+    }
+
+    if failed > 0 {
+        eprintln!(
+            "{}: {} snapshot files could not be migrated",
+            style("error").red().bold(),
+            failed
+        );
+    }
+    if cmd.check && outdated > 0 {
+        eprintln!(
+            "{}: {} snapshot files need to be migrated, run `cargo insta migrate`",
+            style("error").red().bold(),
+            outdated
+        );
+    }
+    if failed > 0 || (cmd.check && outdated > 0) {
+        return Err(QuietExit(1).into());
+    }
+    if !cmd.quiet {
+        println!(
+            "{}: {} of {} snapshot files {}",
+            style("done").bold(),
+            outdated,
+            snapshot_files.len(),
+            if cmd.check { "outdated" } else { "migrated" }
+        );
+    }
+    Ok(())
+}
+
 pub fn run() -> Result<(), Box<dyn Error>> {
     // chop off cargo
     let mut args: Vec<_> = env::args_os().collect();
@@ -465,12 +895,152 @@ pub fn run() -> Result<(), Box<dyn Error>> {
 
     let opts = Opts::from_iter(args);
 
-    let color = opts.color.as_ref().map(|x| x.as_str()).unwrap_or("auto");
+    let color = opts.color.as_deref().unwrap_or("auto");
     handle_color(color)?;
     match opts.command {
         Command::Review(cmd) => process_snapshots(cmd, None),
         Command::Accept(cmd) => process_snapshots(cmd, Some(Operation::Accept)),
         Command::Reject(cmd) => process_snapshots(cmd, Some(Operation::Reject)),
         Command::Test(cmd) => test_run(cmd, color),
+        Command::Coverage(cmd) => coverage(cmd, color),
+        Command::JestImport(cmd) => jest_import(cmd),
+        Command::JestExport(cmd) => jest_export(cmd),
+        Command::Migrate(cmd) => migrate(cmd),
+    }
+}
+
+#[cfg(test)]
+fn make_migrate_root(name: &str) -> PathBuf {
+    let root = env::temp_dir().join(format!("insta-migrate-{}-{}", name, process::id()));
+    fs::remove_dir_all(&root).ok();
+    fs::create_dir_all(root.join("snapshots")).unwrap();
+    fs::write(
+        root.join("snapshots/test__legacy.snap"),
+        "Created: 2019-01-01T00:00:00.000000Z\nCreator: insta@0.8.0\n\
+         Source: tests/test.rs\nExpression: value\n\nHello\r\nWorld\r\n",
+    )
+    .unwrap();
+    fs::write(
+        root.join("snapshots/test__compressed.snap"),
+        "---\ncompression: gzip\n---\nH4sIAAAAAAACA8vJzEtVMOTKAVFGEMoYAJczwy4UAAAA\n",
+    )
+    .unwrap();
+    fs::write(
+        root.join("snapshots/test__binary.snap"),
+        "---\nextension: png\nbinary_hash: \"fnv1a64:5a727b3689d1f7ac\"\n---\n<binary>\n",
+    )
+    .unwrap();
+    fs::write(
+        root.join("snapshots/test__binary.snap.png"),
+        b"\x89PNG\r\n\x1a\n",
+    )
+    .unwrap();
+    fs::write(
+        root.join("snapshots/test__current.snap"),
+        "---\nformat_version: 1\n---\ncurrent\n",
+    )
+    .unwrap();
+    root
+}
+
+#[cfg(test)]
+fn run_migrate(root: &Path, check: bool) -> Result<(), Box<dyn Error>> {
+    let mut args = vec![
+        "migrate",
+        "--quiet",
+        "--workspace-root",
+        root.to_str().unwrap(),
+    ];
+    if check {
+        args.push("--check");
+    }
+    migrate(MigrateCommand::from_iter(args))
+}
+
+#[test]
+fn test_migrate() {
+    let root = make_migrate_root("default");
+    let snapshots = root.join("snapshots");
+    let current = fs::read(snapshots.join("test__current.snap")).unwrap();
+    run_migrate(&root, false).unwrap();
+
+    let legacy = fs::read_to_string(snapshots.join("test__legacy.snap")).unwrap();
+    assert!(legacy.starts_with("---\n"));
+    assert!(legacy.contains("source: tests/test.rs\n"));
+    assert!(legacy.contains("expression: value\n"));
+    assert!(legacy.contains(&format!("format_version: {}\n", FORMAT_VERSION)));
+    assert!(legacy.ends_with("---\nHello\r\nWorld\n"));
+
+    let compressed = fs::read_to_string(snapshots.join("test__compressed.snap")).unwrap();
+    assert!(compressed.contains("compression: gzip\n"));
+    assert!(!compressed.contains("line 1"));
+    let snapshot = Snapshot::from_file(snapshots.join("test__compressed.snap")).unwrap();
+    assert_eq!(snapshot.metadata().format_version(), FORMAT_VERSION);
+    assert_eq!(snapshot.contents_str(), "line 1\nline 2\nline 3");
+
+    let binary = Snapshot::from_file(snapshots.join("test__binary.snap")).unwrap();
+    assert_eq!(binary.metadata().format_version(), FORMAT_VERSION);
+    assert!(fs::read_to_string(snapshots.join("test__binary.snap"))
+        .unwrap()
+        .contains("binary_hash: \"fnv1a64:5a727b3689d1f7ac\"\n"));
+    assert_eq!(
+        binary
+            .read_binary(snapshots.join("test__binary.snap"))
+            .unwrap()
+            .as_deref(),
+        Some(&b"\x89PNG\r\n\x1a\n"[..])
+    );
+
+    assert_eq!(
+        fs::read(snapshots.join("test__current.snap")).unwrap(),
+        current
+    );
+
+    // migrated files are up to date
+    run_migrate(&root, true).unwrap();
+    fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn test_migrate_check() {
+    let root = make_migrate_root("check");
+    let read_all = || {
+        let mut files = fs::read_dir(root.join("snapshots"))
+            .unwrap()
+            .map(|entry| {
+                let path = entry.unwrap().path();
+                let contents = fs::read(&path).unwrap();
+                (path, contents)
+            })
+            .collect::<Vec<_>>();
+        files.sort();
+        files
+    };
+    let before = read_all();
+    let err = run_migrate(&root, true).unwrap_err();
+    assert_eq!(err.downcast_ref::<QuietExit>().map(|x| x.0), Some(1));
+    assert_eq!(read_all(), before);
+    fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn test_migrate_continues_after_errors() {
+    let root = make_migrate_root("errors");
+    let snapshots = root.join("snapshots");
+    fs::write(
+        snapshots.join("test__aaa_conflicted.snap"),
+        "<<<<<<< HEAD\n---\n---\nours\n=======\n---\n---\ntheirs\n>>>>>>> branch\n",
+    )
+    .unwrap();
+    let err = run_migrate(&root, false).unwrap_err();
+    assert_eq!(err.downcast_ref::<QuietExit>().map(|x| x.0), Some(1));
+    for name in &[
+        "test__legacy.snap",
+        "test__compressed.snap",
+        "test__binary.snap",
+    ] {
+        let snapshot = Snapshot::from_file(snapshots.join(name)).unwrap();
+        assert_eq!(snapshot.metadata().format_version(), FORMAT_VERSION);
     }
+    fs::remove_dir_all(&root).unwrap();
 }
@@ -0,0 +1,148 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+/// A single snapshot assertion as recorded by the runtime through
+/// `INSTA_SNAPSHOT_USAGE_FILE`.
+#[derive(Deserialize, Debug)]
+pub struct UsageRecord {
+    test: Option<String>,
+    module: String,
+    source: String,
+    line: u32,
+    snapshot: Option<PathBuf>,
+    #[serde(default)]
+    tags: Vec<String>,
+}
+
+impl UsageRecord {
+    /// Checks if the assertion ran with one of the tags.
+    pub fn has_any_tag(&self, tags: &[String]) -> bool {
+        self.tags.iter().any(|x| tags.contains(x))
+    }
+
+    /// The path of the test as accepted by `cargo test`, qualified with the
+    /// crate it is defined in.
+    fn test_id(&self) -> String {
+        let krate = self.module.split("::").next().unwrap_or("");
+        format!("{}::{}", krate, self.test.as_deref().unwrap_or("<unknown>"))
+    }
+
+    /// The snapshot file relative to the workspace or `source:line` for
+    /// inline snapshots.
+    fn snapshot_id(&self, workspace_root: &Path) -> String {
+        match self.snapshot {
+            Some(ref path) => {
+                let path = path.strip_prefix(workspace_root).unwrap_or(path);
+                path.to_string_lossy().replace('\\', "/")
+            }
+            None => format!("{}:{}", self.source, self.line),
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Default)]
+pub struct CoverageReport {
+    /// Maps snapshot files (and inline snapshots) to the tests using them.
+    snapshots: BTreeMap<String, BTreeSet<String>>,
+    /// Maps tests to the snapshots they used.
+    tests: BTreeMap<String, BTreeSet<String>>,
+    /// Snapshot files no test used.
+    unreferenced: BTreeSet<String>,
+    /// Snapshot files some test used.
+    #[serde(skip)]
+    referenced: BTreeSet<String>,
+}
+
+impl CoverageReport {
+    /// Builds a report from the usage records and all known snapshot files.
+    pub fn new<I: IntoIterator<Item = PathBuf>>(
+        records: &[UsageRecord],
+        snapshot_files: I,
+        workspace_root: &Path,
+    ) -> CoverageReport {
+        let mut rv = CoverageReport::default();
+        for record in records {
+            let test = record.test_id();
+            let snapshot = record.snapshot_id(workspace_root);
+            if record.snapshot.is_some() {
+                rv.referenced.insert(snapshot.clone());
+            }
+            rv.snapshots
+                .entry(snapshot.clone())
+                .or_default()
+                .insert(test.clone());
+            rv.tests.entry(test).or_default().insert(snapshot);
+        }
+        for path in snapshot_files {
+            // pending snapshots belong to the snapshot file they replace
+            if path.extension().is_some_and(|x| x == "new") {
+                continue;
+            }
+            let path = path.strip_prefix(workspace_root).unwrap_or(&path);
+            let id = path.to_string_lossy().replace('\\', "/");
+            if !rv.referenced.contains(&id) {
+                rv.unreferenced.insert(id);
+            }
+        }
+        rv
+    }
+
+    /// Returns the number of referenced and all snapshot files.
+    pub fn summary(&self) -> (usize, usize) {
+        let referenced = self.referenced.len();
+        (referenced, referenced + self.unreferenced.len())
+    }
+}
+
+/// Loads the usage records appended by the runtime.
+pub fn load_usage_records(path: &Path) -> Result<Vec<UsageRecord>, Box<dyn Error>> {
+    let contents = fs::read_to_string(path)?;
+    contents
+        .lines()
+        .filter(|x| !x.trim().is_empty())
+        .map(|x| Ok(serde_json::from_str(x)?))
+        .collect()
+}
+
+#[test]
+fn test_coverage_report() {
+    let usage_file = std::env::temp_dir().join(format!("insta-usage-{}", std::process::id()));
+    fs::write(
+        &usage_file,
+        r#"{"test":"test_a","module":"krate::tests","source":"src/lib.rs","line":10,"snapshot":"/ws/src/snapshots/krate__a.snap"}
+
+{"test":"test_b","module":"krate::tests","source":"src/lib.rs","line":20,"snapshot":"/ws/src/snapshots/krate__b.snap"}
+{"test":"test_b","module":"krate::tests","source":"src/lib.rs","line":21,"snapshot":null}
+"#,
+    )
+    .unwrap();
+    let records = load_usage_records(&usage_file).unwrap();
+    fs::remove_file(&usage_file).unwrap();
+    assert_eq!(records.len(), 3);
+
+    let snapshot_files = vec![
+        PathBuf::from("/ws/src/snapshots/krate__a.snap"),
+        PathBuf::from("/ws/src/snapshots/krate__b.snap"),
+        PathBuf::from("/ws/src/snapshots/krate__b.snap.new"),
+        PathBuf::from("/ws/src/snapshots/krate__unused.snap"),
+        PathBuf::from("/ws/src/snapshots/krate__unused_pending.snap.new"),
+        PathBuf::from("/ws/tests/snapshots/other__stale.snap"),
+    ];
+    let report = CoverageReport::new(&records, snapshot_files, Path::new("/ws"));
+    assert_eq!(
+        report.unreferenced.iter().collect::<Vec<_>>(),
+        vec![
+            "src/snapshots/krate__unused.snap",
+            "tests/snapshots/other__stale.snap",
+        ]
+    );
+    assert_eq!(report.summary(), (2, 4));
+    assert_eq!(
+        report.tests["krate::test_b"].iter().collect::<Vec<_>>(),
+        vec!["src/lib.rs:21", "src/snapshots/krate__b.snap"]
+    );
+}
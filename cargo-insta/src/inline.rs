@@ -6,7 +6,6 @@ use std::path::{Path, PathBuf};
 
 use insta::SnapshotContents;
 use proc_macro2::TokenTree;
-use syn;
 use syn::spanned::Spanned;
 
 #[derive(Debug)]
@@ -57,10 +56,7 @@ impl FilePatcher {
     pub fn add_snapshot_macro(&mut self, line: usize) {
         match self.find_snapshot_macro(line) {
             Some(snapshot) => {
-                assert!(self
-                    .inline_snapshots
-                    .last()
-                    .map_or(true, |x| x.end.0 <= line));
+                assert!(self.inline_snapshots.last().is_none_or(|x| x.end.0 <= line));
                 self.inline_snapshots.push(snapshot)
             }
             None => panic!("Could not find snapshot in line {}", line),
@@ -80,7 +76,7 @@ impl FilePatcher {
 
         // replace lines
         let snapshot_line_contents =
-            vec![prefix, snapshot.to_inline(inline.indentation), suffix].join("");
+            [prefix, snapshot.to_inline(inline.indentation), suffix].join("");
 
         self.lines.splice(
             inline.start.0..=inline.end.0,
@@ -151,15 +147,16 @@ impl FilePatcher {
                 tokens: &[TokenTree],
                 indentation: usize,
             ) -> bool {
-                match &tokens[tokens.len() - 2] {
-                    TokenTree::Punct(ref punct) if punct.as_char() == '@' => {}
-                    _ => {
-                        return false;
-                    }
-                }
+                // the snapshot can be followed by a failure message
+                let idx = match tokens.iter().rposition(
+                    |token| matches!(token, TokenTree::Punct(ref punct) if punct.as_char() == '@'),
+                ) {
+                    Some(idx) => idx,
+                    None => return false,
+                };
 
-                let (start, end) = match &tokens[tokens.len() - 1] {
-                    TokenTree::Literal(lit) => {
+                let (start, end) = match tokens.get(idx + 1) {
+                    Some(TokenTree::Literal(lit)) => {
                         let span = lit.span();
                         (
                             (span.start().line - 1, span.start().column),
@@ -13,14 +13,16 @@
 //! For more information see [the insta crate documentation](https://docs.rs/insta).
 mod cargo;
 mod cli;
+mod coverage;
 mod inline;
 mod utils;
+mod walk;
 
 use console::style;
 
 fn main() {
     if let Err(err) = cli::run() {
-        let exit_code = if let Some(ref exit) = err.downcast_ref::<utils::QuietExit>() {
+        let exit_code = if let Some(exit) = err.downcast_ref::<utils::QuietExit>() {
             exit.0
         } else {
             println!("{} {}", style("error:").red().bold(), err);
@@ -0,0 +1,432 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Condvar, Mutex};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use console::{style, Term};
+use serde::{Deserialize, Serialize};
+
+/// Scans finishing faster than this do not show any progress.
+const PROGRESS_DELAY: Duration = Duration::from_millis(500);
+
+/// How often the progress line is refreshed.
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Folders modified more recently than this are not cached as a change
+/// within the timestamp granularity of the file system would go unnoticed.
+const RACY_MTIME: Duration = Duration::from_secs(2);
+
+fn worker_count() -> usize {
+    thread::available_parallelism()
+        .map(|x| x.get())
+        .unwrap_or(1)
+}
+
+fn is_hidden(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|x| x.to_str())
+        .is_some_and(|x| x.starts_with('.'))
+}
+
+/// Shows the progress of long scans on stderr.
+struct Progress {
+    term: Term,
+    started: Instant,
+    shown: bool,
+}
+
+impl Progress {
+    fn new() -> Progress {
+        Progress {
+            term: Term::stderr(),
+            started: Instant::now(),
+            shown: false,
+        }
+    }
+
+    fn update<F: FnOnce() -> String>(&mut self, msg: F) {
+        if !self.term.is_term() || self.started.elapsed() < PROGRESS_DELAY {
+            return;
+        }
+        self.term.clear_line().ok();
+        self.term.write_str(&msg()).ok();
+        self.shown = true;
+    }
+
+    fn finish(&mut self) {
+        if self.shown {
+            self.term.clear_line().ok();
+        }
+    }
+}
+
+/// Runs the workers on scoped threads and reports progress while they run.
+fn run_workers<R, W, M>(work: W, mut msg: M) -> Vec<R>
+where
+    R: Send,
+    W: Fn() -> R + Sync,
+    M: FnMut() -> String,
+{
+    let mut progress = Progress::new();
+    let rv = thread::scope(|scope| {
+        let handles: Vec<_> = (0..worker_count()).map(|_| scope.spawn(&work)).collect();
+        while !handles.iter().all(|x| x.is_finished()) {
+            thread::sleep(PROGRESS_INTERVAL);
+            progress.update(&mut msg);
+        }
+        handles
+            .into_iter()
+            .map(|x| x.join().unwrap())
+            .collect::<Vec<_>>()
+    });
+    progress.finish();
+    rv
+}
+
+/// Caches the results of a scan between runs.
+///
+/// For every folder the matching files and subfolders are stored along
+/// with the modification time of the folder.  As long as that does not
+/// change the folder does not have to be listed again.
+pub struct ScanCache {
+    /// The file the cache is stored in.
+    pub path: PathBuf,
+    /// Identifies the filter of the scan, caches of other filters are
+    /// discarded.
+    pub key: String,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct CacheContents {
+    key: String,
+    dirs: HashMap<PathBuf, CachedDir>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+struct CachedDir {
+    mtime: (u64, u32),
+    dirs: Vec<String>,
+    files: Vec<String>,
+}
+
+impl ScanCache {
+    fn load(&self) -> CacheContents {
+        fs::read(&self.path)
+            .ok()
+            .and_then(|x| serde_json::from_slice::<CacheContents>(&x).ok())
+            .filter(|x| x.key == self.key)
+            .unwrap_or_default()
+    }
+
+    fn save(&self, dirs: HashMap<PathBuf, CachedDir>) {
+        let contents = CacheContents {
+            key: self.key.clone(),
+            dirs,
+        };
+        // the cache is only an optimization so failing to write it is fine
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent).ok();
+        }
+        if let Ok(json) = serde_json::to_vec(&contents) {
+            fs::write(&self.path, json).ok();
+        }
+    }
+}
+
+fn mtime_key(mtime: SystemTime) -> Option<(u64, u32)> {
+    let duration = mtime.duration_since(UNIX_EPOCH).ok()?;
+    Some((duration.as_secs(), duration.subsec_nanos()))
+}
+
+/// Lists a folder returning the matching files and not hidden subfolders.
+fn list_dir<F>(dir: &Path, filter: &F) -> (Vec<String>, Vec<String>)
+where
+    F: Fn(&Path) -> bool,
+{
+    let mut dirs = vec![];
+    let mut files = vec![];
+    for entry in fs::read_dir(dir).into_iter().flatten().flatten() {
+        let file_type = match entry.file_type() {
+            Ok(file_type) => file_type,
+            Err(_) => continue,
+        };
+        let path = entry.path();
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if file_type.is_dir() {
+            if !is_hidden(&path) {
+                dirs.push(name);
+            }
+        } else if file_type.is_file() && filter(&path) {
+            files.push(name);
+        }
+    }
+    (dirs, files)
+}
+
+#[derive(Default)]
+struct Queue {
+    dirs: Vec<(usize, PathBuf)>,
+    busy: usize,
+}
+
+/// Finds all files below the given roots on multiple threads.
+///
+/// Hidden folders and the folder of the cache are skipped and symlinks are
+/// not followed.  The files are returned sorted along with the index of the
+/// root they are in.
+pub fn find_files<F>(
+    roots: &[PathBuf],
+    what: &str,
+    cache: Option<&ScanCache>,
+    filter: F,
+) -> Vec<(usize, PathBuf)>
+where
+    F: Fn(&Path) -> bool + Sync,
+{
+    let old_cache = cache.map(|x| x.load()).unwrap_or_default();
+    let cache_dir = cache.and_then(|x| x.path.parent());
+    let now = SystemTime::now();
+    let queue = Mutex::new(Queue {
+        dirs: roots.iter().cloned().enumerate().collect(),
+        busy: 0,
+    });
+    let wakeup = Condvar::new();
+    let scanned = AtomicUsize::new(0);
+    let found = AtomicUsize::new(0);
+
+    let work = || {
+        let mut rv = vec![];
+        let mut new_cache = HashMap::new();
+        loop {
+            let (root, dir) = {
+                let mut queue = queue.lock().unwrap();
+                loop {
+                    if let Some(item) = queue.dirs.pop() {
+                        queue.busy += 1;
+                        break item;
+                    }
+                    if queue.busy == 0 {
+                        return (rv, new_cache);
+                    }
+                    queue = wakeup.wait(queue).unwrap();
+                }
+            };
+
+            let mtime = fs::metadata(&dir).and_then(|x| x.modified()).ok();
+            let cached = mtime
+                .and_then(mtime_key)
+                .and_then(|mtime| Some((mtime, old_cache.dirs.get(&dir)?)))
+                .filter(|(mtime, cached)| *mtime == cached.mtime)
+                .map(|(_, cached)| cached.clone());
+            let entry = match cached {
+                Some(entry) => entry,
+                None => {
+                    let (dirs, files) = list_dir(&dir, &filter);
+                    CachedDir {
+                        mtime: mtime.and_then(mtime_key).unwrap_or_default(),
+                        dirs,
+                        files,
+                    }
+                }
+            };
+
+            let subdirs: Vec<_> = entry
+                .dirs
+                .iter()
+                .map(|x| (root, dir.join(x)))
+                .filter(|x| Some(x.1.as_path()) != cache_dir)
+                .collect();
+            for file in &entry.files {
+                found.fetch_add(1, Ordering::Relaxed);
+                rv.push((root, dir.join(file)));
+            }
+            let settled = mtime
+                .and_then(|mtime| now.duration_since(mtime).ok())
+                .is_some_and(|x| x >= RACY_MTIME);
+            if settled {
+                new_cache.insert(dir, entry);
+            }
+            scanned.fetch_add(1, Ordering::Relaxed);
+
+            let mut queue = queue.lock().unwrap();
+            queue.dirs.extend(subdirs);
+            queue.busy -= 1;
+            wakeup.notify_all();
+        }
+    };
+
+    let (files, dirs): (Vec<_>, Vec<_>) = run_workers(work, || {
+        format!(
+            "{}: {} folders, {} {}",
+            style("scanning").bold(),
+            scanned.load(Ordering::Relaxed),
+            found.load(Ordering::Relaxed),
+            what
+        )
+    })
+    .into_iter()
+    .unzip();
+
+    if let Some(cache) = cache {
+        cache.save(dirs.into_iter().flatten().collect());
+    }
+    let mut rv: Vec<_> = files.into_iter().flatten().collect();
+    rv.sort();
+    rv
+}
+
+/// Applies a function to all items on multiple threads keeping the order.
+pub fn parallel_map<T, R, F>(items: &[T], what: &str, f: F) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(&T) -> R + Sync,
+{
+    let next = AtomicUsize::new(0);
+    let done = AtomicUsize::new(0);
+
+    let work = || {
+        let mut rv = vec![];
+        loop {
+            let idx = next.fetch_add(1, Ordering::Relaxed);
+            match items.get(idx) {
+                Some(item) => rv.push((idx, f(item))),
+                None => return rv,
+            }
+            done.fetch_add(1, Ordering::Relaxed);
+        }
+    };
+
+    let mut rv: Vec<_> = run_workers(work, || {
+        format!(
+            "{}: {} of {} {}",
+            style("loading").bold(),
+            done.load(Ordering::Relaxed),
+            items.len(),
+            what
+        )
+    })
+    .into_iter()
+    .flatten()
+    .collect();
+    rv.sort_by_key(|x| x.0);
+    rv.into_iter().map(|x| x.1).collect()
+}
+
+/// Creates an empty folder for a test below the temporary folder.
+#[cfg(test)]
+pub fn make_test_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("insta-walk-{}-{}", name, std::process::id()));
+    fs::remove_dir_all(&dir).ok();
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Sets the modification time of a folder.
+#[cfg(test)]
+pub fn set_mtime(path: &Path, mtime: SystemTime) {
+    fs::File::open(path).unwrap().set_modified(mtime).unwrap();
+}
+
+#[cfg(test)]
+fn find_test_files(root: &Path, cache: Option<&ScanCache>) -> Vec<PathBuf> {
+    find_files(&[root.to_path_buf()], "files", cache, |path| {
+        path.extension().is_some_and(|x| x == "snap")
+    })
+    .into_iter()
+    .map(|x| x.1.strip_prefix(root).unwrap().to_path_buf())
+    .collect()
+}
+
+#[test]
+fn test_find_files_matches_serial_walk() {
+    fn walk(dir: &Path, rv: &mut Vec<PathBuf>) {
+        for entry in fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.is_dir() {
+                if !is_hidden(&path) {
+                    walk(&path, rv);
+                }
+            } else if path.extension().is_some_and(|x| x == "snap") {
+                rv.push(path);
+            }
+        }
+    }
+
+    let root = make_test_dir("serial");
+    for a in 0..8 {
+        for b in 0..4 {
+            let dir = root.join(format!("crate-{}/src/mod-{}/snapshots", a, b));
+            fs::create_dir_all(&dir).unwrap();
+            fs::write(dir.join(format!("test__{}.snap", b)), "").unwrap();
+            fs::write(dir.join(format!("test__{}.snap.new", b)), "").unwrap();
+        }
+    }
+    fs::create_dir_all(root.join(".hidden")).unwrap();
+    fs::write(root.join(".hidden/test__hidden.snap"), "").unwrap();
+
+    let mut expected = vec![];
+    walk(&root, &mut expected);
+    expected.sort();
+    let expected: Vec<_> = expected
+        .into_iter()
+        .map(|x| x.strip_prefix(&root).unwrap().to_path_buf())
+        .collect();
+    assert_eq!(expected.len(), 32);
+    assert_eq!(find_test_files(&root, None), expected);
+    fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn test_find_files_cache() {
+    let root = make_test_dir("cache");
+    let cache = ScanCache {
+        path: root.join("cache/files.json"),
+        key: "snap".into(),
+    };
+    let tree = root.join("tree");
+    fs::create_dir_all(tree.join("a")).unwrap();
+    fs::write(tree.join("a/x.snap"), "").unwrap();
+    let past = SystemTime::now() - Duration::from_secs(3600);
+    set_mtime(&tree, past);
+    set_mtime(&tree.join("a"), past);
+    assert_eq!(
+        find_test_files(&tree, Some(&cache)),
+        vec![PathBuf::from("a/x.snap")]
+    );
+    assert!(cache.load().dirs.contains_key(&tree.join("a")));
+
+    // a cache hit does not list the folder again, so a file added without
+    // changing the modification time is not seen
+    fs::write(tree.join("a/y.snap"), "").unwrap();
+    set_mtime(&tree.join("a"), past);
+    assert_eq!(
+        find_test_files(&tree, Some(&cache)),
+        vec![PathBuf::from("a/x.snap")]
+    );
+
+    // a changed modification time lists the folder again
+    set_mtime(&tree.join("a"), past + Duration::from_secs(1800));
+    assert_eq!(
+        find_test_files(&tree, Some(&cache)),
+        vec![PathBuf::from("a/x.snap"), PathBuf::from("a/y.snap")]
+    );
+
+    // folders modified within the racy window are listed but not cached
+    // so that changes within the same timestamp are still picked up
+    fs::write(tree.join("a/z.snap"), "").unwrap();
+    assert_eq!(find_test_files(&tree, Some(&cache)).len(), 3);
+    assert!(!cache.load().dirs.contains_key(&tree.join("a")));
+    assert!(cache.load().dirs.contains_key(&tree));
+
+    // caches of other filters are discarded
+    let other = ScanCache {
+        path: cache.path.clone(),
+        key: "txt".into(),
+    };
+    assert!(other.load().dirs.is_empty());
+    fs::remove_dir_all(&root).unwrap();
+}
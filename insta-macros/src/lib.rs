@@ -0,0 +1,79 @@
+//! Procedural macros for insta.
+//!
+//! The macros are re-exported by insta with the `macros` feature, this
+//! crate should not be used directly.
+extern crate proc_macro;
+
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::parse::Parser;
+use syn::punctuated::Punctuated;
+use syn::{ItemFn, MetaNameValue, Token};
+
+/// Binds settings for the duration of a test function.
+///
+/// The arguments are the same keys and values `with_settings!` accepts.
+/// The settings start out as the currently bound ones and are restored
+/// when the function returns.  Async functions keep the settings bound
+/// across `.await` points.
+///
+/// ```ignore
+/// #[test]
+/// #[insta::settings(sort_maps = true, snapshot_suffix = "v2")]
+/// fn test_something() {
+///     insta::assert_yaml_snapshot!(make_map());
+/// }
+/// ```
+#[proc_macro_attribute]
+pub fn settings(args: TokenStream, input: TokenStream) -> TokenStream {
+    match expand_settings(args.into(), input.into()) {
+        Ok(rv) => rv.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+fn expand_settings(
+    args: proc_macro2::TokenStream,
+    input: proc_macro2::TokenStream,
+) -> syn::Result<proc_macro2::TokenStream> {
+    let args = Punctuated::<MetaNameValue, Token![,]>::parse_terminated.parse2(args)?;
+    let ItemFn {
+        attrs,
+        vis,
+        sig,
+        block,
+    } = syn::parse2(input)?;
+
+    let mut setters = vec![];
+    for arg in args {
+        let key = match arg.path.get_ident() {
+            Some(key) => key,
+            None => return Err(syn::Error::new_spanned(arg.path, "expected a setting name")),
+        };
+        let value = arg.value;
+        setters.push(quote! {
+            __insta_settings._private_inner_mut().#key(#value);
+        });
+    }
+
+    let body = if sig.asyncness.is_some() {
+        quote! {
+            __insta_settings.bind_async(async move #block).await
+        }
+    } else {
+        quote! {
+            let __insta_settings_guard = __insta_settings.bind_to_scope();
+            #block
+        }
+    };
+
+    Ok(quote! {
+        #(#attrs)*
+        #vis #sig {
+            #[allow(unused_mut)]
+            let mut __insta_settings = ::insta::Settings::clone_current();
+            #(#setters)*
+            #body
+        }
+    })
+}
@@ -0,0 +1,175 @@
+use std::borrow::Cow;
+use std::iter::Peekable;
+use std::str::Chars;
+
+use crate::settings::{AnsiEscapes, Settings};
+
+const COLORS: [&str; 8] = [
+    "black", "red", "green", "yellow", "blue", "magenta", "cyan", "white",
+];
+
+/// An escape sequence found in a string.
+enum Escape {
+    /// Select graphic rendition (`ESC [ ... m`) with its parameters.
+    Sgr(String),
+    /// Any other escape sequence (cursor movement, titles etc.)
+    Other,
+}
+
+/// Consumes an escape sequence after the leading `ESC`.
+fn parse_escape(chars: &mut Peekable<Chars<'_>>) -> Escape {
+    match chars.next() {
+        Some('[') => {
+            let mut params = String::new();
+            for c in chars.by_ref() {
+                match c {
+                    'm' => return Escape::Sgr(params),
+                    '\x40'..='\x7e' => return Escape::Other,
+                    c => params.push(c),
+                }
+            }
+            Escape::Other
+        }
+        // operating system commands end with BEL or ESC \
+        Some(']') => {
+            while let Some(c) = chars.next() {
+                if c == '\x07' {
+                    break;
+                }
+                if c == '\x1b' && chars.peek() == Some(&'\\') {
+                    chars.next();
+                    break;
+                }
+            }
+            Escape::Other
+        }
+        _ => Escape::Other,
+    }
+}
+
+fn color_token(kind: &str, params: &mut std::slice::Iter<'_, u32>) -> Option<String> {
+    match params.next()? {
+        5 => Some(format!("<{}:{}>", kind, params.next()?)),
+        2 => {
+            let (r, g, b) = (params.next()?, params.next()?, params.next()?);
+            Some(format!("<{}:#{:02x}{:02x}{:02x}>", kind, r, g, b))
+        }
+        _ => None,
+    }
+}
+
+fn push_sgr_tokens(rv: &mut String, params: &str) {
+    let params: Vec<u32> = if params.is_empty() {
+        vec![0]
+    } else {
+        params
+            .split([';', ':'])
+            .map(|x| x.parse().unwrap_or(0))
+            .collect()
+    };
+    let mut iter = params.iter();
+    while let Some(&param) = iter.next() {
+        let token = match param {
+            0 => "<reset>".to_string(),
+            1 => "<bold>".to_string(),
+            2 => "<dim>".to_string(),
+            3 => "<italic>".to_string(),
+            4 => "<underline>".to_string(),
+            5 => "<blink>".to_string(),
+            7 => "<reverse>".to_string(),
+            8 => "<hidden>".to_string(),
+            9 => "<strike>".to_string(),
+            22 => "</bold>".to_string(),
+            23 => "</italic>".to_string(),
+            24 => "</underline>".to_string(),
+            25 => "</blink>".to_string(),
+            27 => "</reverse>".to_string(),
+            28 => "</hidden>".to_string(),
+            29 => "</strike>".to_string(),
+            30..=37 => format!("<fg:{}>", COLORS[param as usize - 30]),
+            38 => match color_token("fg", &mut iter) {
+                Some(token) => token,
+                None => continue,
+            },
+            39 => "</fg>".to_string(),
+            40..=47 => format!("<bg:{}>", COLORS[param as usize - 40]),
+            48 => match color_token("bg", &mut iter) {
+                Some(token) => token,
+                None => continue,
+            },
+            49 => "</bg>".to_string(),
+            90..=97 => format!("<fg:bright-{}>", COLORS[param as usize - 90]),
+            100..=107 => format!("<bg:bright-{}>", COLORS[param as usize - 100]),
+            other => format!("<sgr:{}>", other),
+        };
+        rv.push_str(&token);
+    }
+}
+
+fn rewrite_ansi(s: &str, tokens: bool) -> String {
+    let mut rv = String::with_capacity(s.len());
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\x1b' {
+            rv.push(c);
+            continue;
+        }
+        match parse_escape(&mut chars) {
+            Escape::Sgr(params) if tokens => push_sgr_tokens(&mut rv, &params),
+            Escape::Sgr(_) | Escape::Other => {}
+        }
+    }
+    rv
+}
+
+/// Removes all ANSI escape sequences from a string.
+///
+/// This is useful to snapshot the output of command line tools that
+/// color their output.
+pub fn strip_ansi_escapes(s: &str) -> String {
+    rewrite_ansi(s, false)
+}
+
+/// Replaces ANSI color and style escape sequences with readable tokens.
+///
+/// Styles become tokens like `<bold>`, `<fg:red>`, `<bg:bright-blue>` or
+/// `<fg:#ff8800>` and are closed by `<reset>` or tokens like `</bold>` and
+/// `</fg>`.  Escape sequences that do not change the style, such as cursor
+/// movement, are removed.
+pub fn ansi_to_tokens(s: &str) -> String {
+    rewrite_ansi(s, true)
+}
+
+/// Applies the ANSI escape handling of the current settings.
+pub(crate) fn normalize_ansi_escapes(s: &str) -> Cow<'_, str> {
+    if !s.contains('\x1b') {
+        return Cow::Borrowed(s);
+    }
+    match Settings::with(|settings| settings.ansi_escapes()) {
+        AnsiEscapes::Keep => Cow::Borrowed(s),
+        AnsiEscapes::Strip => Cow::Owned(strip_ansi_escapes(s)),
+        AnsiEscapes::Tokens => Cow::Owned(ansi_to_tokens(s)),
+    }
+}
+
+#[test]
+fn test_strip_ansi_escapes() {
+    assert_eq!(
+        strip_ansi_escapes("\x1b[1;31merror\x1b[0m: \x1b]0;title\x07done\x1b[2K"),
+        "error: done"
+    );
+}
+
+#[test]
+fn test_ansi_to_tokens() {
+    assert_eq!(
+        ansi_to_tokens(
+            "\x1b[1;31merror\x1b[0m: \x1b[38;5;208mwarn\x1b[39m \x1b[48;2;255;136;0mx\x1b[m"
+        ),
+        "<bold><fg:red>error<reset>: <fg:208>warn</fg> <bg:#ff8800>x<reset>"
+    );
+    assert_eq!(
+        ansi_to_tokens("\x1b[94;4mlink\x1b[24m"),
+        "<fg:bright-blue><underline>link</underline>"
+    );
+}
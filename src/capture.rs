@@ -0,0 +1,135 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::os::unix::io::AsRawFd;
+use std::panic;
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+use lazy_static::lazy_static;
+
+lazy_static! {
+    static ref CAPTURE_LOCK: Mutex<()> = Mutex::new(());
+}
+
+static CAPTURE_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// The output captured by [`capture_output`](fn.capture_output.html).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CapturedOutput {
+    /// Everything written to stdout.
+    pub stdout: String,
+    /// Everything written to stderr.
+    pub stderr: String,
+}
+
+/// Temporarily points a file descriptor to a fresh temporary file.
+struct Redirect {
+    fd: i32,
+    saved: i32,
+    file: File,
+}
+
+impl Redirect {
+    fn new(fd: i32) -> io::Result<Redirect> {
+        let path = std::env::temp_dir().join(format!(
+            ".insta-capture-{}-{}",
+            process::id(),
+            CAPTURE_COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create_new(true)
+            .open(&path)?;
+        fs::remove_file(&path).ok();
+
+        let saved = unsafe { libc::dup(fd) };
+        if saved < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        if unsafe { libc::dup2(file.as_raw_fd(), fd) } < 0 {
+            let err = io::Error::last_os_error();
+            unsafe { libc::close(saved) };
+            return Err(err);
+        }
+        Ok(Redirect { fd, saved, file })
+    }
+
+    fn finish(mut self) -> io::Result<String> {
+        self.restore();
+        let mut rv = String::new();
+        self.file.seek(SeekFrom::Start(0))?;
+        self.file.read_to_string(&mut rv)?;
+        Ok(rv)
+    }
+
+    fn restore(&mut self) {
+        if self.saved >= 0 {
+            unsafe {
+                libc::dup2(self.saved, self.fd);
+                libc::close(self.saved);
+            }
+            self.saved = -1;
+        }
+    }
+}
+
+impl Drop for Redirect {
+    fn drop(&mut self) {
+        self.restore();
+    }
+}
+
+/// Runs a closure and captures what it writes to stdout and stderr.
+///
+/// The capturing happens on the file descriptor level so it picks up
+/// output of `std::io::stdout()`, `std::io::stderr()`, child processes
+/// inheriting the streams and native code alike.  The return value of
+/// the closure is passed through.
+///
+/// Note that the test harness intercepts the `print!` and `eprint!` family
+/// of macros on the test thread before they reach the file descriptors.
+/// Output of these macros is only captured when the tests are run with
+/// `--nocapture`.  Code under test that should be snapshotted reliably
+/// therefore is best written against `std::io::stdout()`.
+///
+/// Since file descriptors are shared by the whole process, output of
+/// other threads written during the capture ends up in the captured
+/// output as well.  Concurrent calls to this function are serialized.
+///
+/// ```rust
+/// # use insta::{assert_snapshot, capture_output};
+/// use std::io::Write;
+///
+/// let (rv, output) = capture_output(|| {
+///     writeln!(std::io::stdout(), "Hello World!").unwrap();
+///     42
+/// });
+/// assert_eq!(rv, 42);
+/// assert_snapshot!(output.stdout, @"Hello World!");
+/// ```
+///
+/// This is only available on unix platforms.
+pub fn capture_output<R, F: FnOnce() -> R>(f: F) -> (R, CapturedOutput) {
+    let _guard = CAPTURE_LOCK.lock().unwrap_or_else(|x| x.into_inner());
+    io::stdout().flush().ok();
+    io::stderr().flush().ok();
+
+    let stdout = Redirect::new(libc::STDOUT_FILENO).expect("failed to capture stdout");
+    let stderr = Redirect::new(libc::STDERR_FILENO).expect("failed to capture stderr");
+
+    let rv = panic::catch_unwind(panic::AssertUnwindSafe(f));
+    io::stdout().flush().ok();
+    io::stderr().flush().ok();
+
+    let output = CapturedOutput {
+        stderr: stderr.finish().expect("failed to read captured stderr"),
+        stdout: stdout.finish().expect("failed to read captured stdout"),
+    };
+
+    match rv {
+        Ok(rv) => (rv, output),
+        Err(panic) => panic::resume_unwind(panic),
+    }
+}
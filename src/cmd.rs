@@ -0,0 +1,93 @@
+use std::borrow::BorrowMut;
+use std::fmt::Write;
+use std::process::Command;
+
+use crate::content::Content;
+use crate::serialization::SnapshotSerializer;
+
+/// Runs a command and converts its output into the content of a snapshot.
+///
+/// The content is a map of `success`, `exit_code`, `stdout` and `stderr` so
+/// that redactions can target the individual parts (eg: `.exit_code`).  The
+/// exit code is `None` if the process was terminated by a signal and output
+/// that is not valid UTF-8 is converted lossily.
+pub fn command_to_content<C: BorrowMut<Command>>(mut cmd: C) -> Content {
+    let cmd = cmd.borrow_mut();
+    let output = match cmd.output() {
+        Ok(output) => output,
+        Err(err) => panic!("could not run {:?}: {}", cmd, err),
+    };
+    Content::Map(vec![
+        (
+            Content::from("success"),
+            Content::from(output.status.success()),
+        ),
+        (
+            Content::from("exit_code"),
+            match output.status.code() {
+                Some(code) => Content::from(code),
+                None => Content::None,
+            },
+        ),
+        (
+            Content::from("stdout"),
+            Content::from(String::from_utf8_lossy(&output.stdout).into_owned()),
+        ),
+        (
+            Content::from("stderr"),
+            Content::from(String::from_utf8_lossy(&output.stderr).into_owned()),
+        ),
+    ])
+}
+
+/// Renders the content of `command_to_content` as text.
+///
+/// The status fields come first, followed by stdout and stderr verbatim
+/// in their own sections so that multi line output stays readable:
+///
+/// ```text
+/// success: false
+/// exit_code: 1
+/// ----- stdout -----
+///
+/// ----- stderr -----
+/// error: no such file
+/// ```
+#[doc(hidden)]
+pub struct CommandOutput;
+
+impl SnapshotSerializer for CommandOutput {
+    fn serialize(&self, content: &Content) -> String {
+        let entries = match *content {
+            Content::Map(ref entries) => entries,
+            _ => panic!("command output must be a map"),
+        };
+        let get = |name: &str| {
+            entries
+                .iter()
+                .find(|(key, _)| key.as_str() == Some(name))
+                .map(|(_, value)| value)
+        };
+
+        let mut rv = String::new();
+        for name in &["success", "exit_code"] {
+            if let Some(value) = get(name) {
+                let value = match value.as_str() {
+                    Some(value) => value.to_string(),
+                    None => serde_json::to_string(value).unwrap(),
+                };
+                writeln!(rv, "{}: {}", name, value).unwrap();
+            }
+        }
+        for name in &["stdout", "stderr"] {
+            if let Some(value) = get(name) {
+                writeln!(rv, "----- {} -----", name).unwrap();
+                rv.push_str(value.as_str().unwrap_or(""));
+                if !rv.ends_with('\n') {
+                    rv.push('\n');
+                }
+            }
+        }
+        rv
+    }
+}
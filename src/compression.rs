@@ -0,0 +1,55 @@
+use std::error::Error;
+use std::io::{Read, Write};
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+
+/// The width of the lines of base64 in compressed snapshot files.
+const LINE_WIDTH: usize = 76;
+
+/// Compresses the contents of a snapshot for storage.
+///
+/// The contents are compressed with gzip and stored as base64 in lines of
+/// 76 characters so that snapshot files stay text files.
+pub fn compress(contents: &str) -> String {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::best());
+    encoder.write_all(contents.as_bytes()).unwrap();
+    let encoded = STANDARD.encode(encoder.finish().unwrap());
+    let mut rv = String::with_capacity(encoded.len() + encoded.len() / LINE_WIDTH);
+    for (idx, chunk) in encoded.as_bytes().chunks(LINE_WIDTH).enumerate() {
+        if idx > 0 {
+            rv.push('\n');
+        }
+        rv.push_str(std::str::from_utf8(chunk).unwrap());
+    }
+    rv
+}
+
+/// Restores the contents of a snapshot stored with `compress`.
+pub fn decompress(stored: &str) -> Result<String, Box<dyn Error>> {
+    let encoded: String = stored.split_whitespace().collect();
+    let compressed = STANDARD
+        .decode(encoded)
+        .map_err(|err| format!("the compressed contents are not valid base64: {}", err))?;
+    let mut rv = String::new();
+    GzDecoder::new(&compressed[..])
+        .read_to_string(&mut rv)
+        .map_err(|err| format!("the compressed contents are corrupted: {}", err))?;
+    Ok(rv)
+}
+
+#[test]
+fn test_compression_roundtrip() {
+    let contents = "line\n".repeat(1000);
+    let stored = compress(&contents);
+    assert!(stored.len() < contents.len() / 10);
+    assert!(stored.lines().all(|line| line.len() <= LINE_WIDTH));
+    assert_eq!(decompress(&stored).unwrap(), contents);
+    assert!(decompress("not base64!")
+        .unwrap_err()
+        .to_string()
+        .starts_with("the compressed contents are not valid base64"));
+}
@@ -0,0 +1,511 @@
+use std::collections::BTreeMap;
+#[cfg(feature = "redactions")]
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use lazy_static::lazy_static;
+use serde::Deserialize;
+
+use crate::runtime::get_cargo_workspace;
+use crate::settings::{ActualSettings, SnapshotPathBase, Whitespace};
+use crate::utils::style;
+#[cfg(feature = "redactions")]
+use crate::{redaction::Selector, settings::Redactions};
+
+/// The files the configuration is looked up in relative to the workspace.
+const CONFIG_FILES: &[&str] = &["insta.toml", ".config/insta.toml"];
+
+lazy_static! {
+    static ref TOOL_CONFIG: Result<ToolConfig, String> = ToolConfig::from_current_workspace();
+    static ref DEFAULT_TOOL_CONFIG: ToolConfig = ToolConfig::default();
+}
+
+/// The workspace wide configuration from `insta.toml`.
+///
+/// All values are optional.  Environment variables take precedence over
+/// the behavior configured here and settings bound in tests take
+/// precedence over the configured defaults.
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct ToolConfig {
+    /// The file the configuration was loaded from.
+    #[serde(skip)]
+    pub path: Option<PathBuf>,
+    /// Parts of the file that were ignored as insta was built without the
+    /// features they need.
+    #[serde(skip)]
+    pub warnings: Vec<String>,
+    /// Defaults for the `INSTA_*` environment variables.
+    pub behavior: BehaviorConfig,
+    /// Defaults for the snapshot settings.
+    pub snapshots: SnapshotsConfig,
+    /// Redactions applied by default, mapping selectors to replacements.
+    pub redactions: BTreeMap<String, String>,
+    /// Named redaction sets, mapping names to selectors and replacements.
+    pub redaction_sets: BTreeMap<String, BTreeMap<String, String>>,
+    /// Filters applied by default, mapping regular expressions to
+    /// replacements.
+    pub filters: BTreeMap<String, String>,
+    /// Defaults for `cargo insta test`.
+    pub test: TestConfig,
+    /// Defaults for the commands of `cargo insta` working on snapshots.
+    pub review: ReviewConfig,
+}
+
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct BehaviorConfig {
+    /// Like `INSTA_UPDATE`.
+    pub update: Option<String>,
+    /// Like `INSTA_OUTPUT`.
+    pub output: Option<String>,
+    /// Like `INSTA_FORCE_PASS`.
+    pub force_pass: Option<bool>,
+    /// Like `INSTA_FORCE_UPDATE_SNAPSHOTS`.
+    pub force_update: Option<bool>,
+}
+
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct SnapshotsConfig {
+    /// Like `Settings::set_snapshot_path`.
+    pub path: Option<PathBuf>,
+    /// Like `Settings::set_snapshot_path_base`.
+    pub path_base: Option<SnapshotPathBase>,
+    /// Like `Settings::set_snapshot_extension`.
+    pub extension: Option<String>,
+    /// Like `Settings::set_prepend_module_to_snapshot`.
+    pub prepend_module: Option<bool>,
+    /// Like `Settings::set_name_from_test_path`.
+    pub name_from_test_path: Option<bool>,
+    /// Like `Settings::set_sort_maps`.
+    pub sort_maps: Option<bool>,
+    /// Like `Settings::set_omit_expression`.
+    pub omit_expression: Option<bool>,
+    /// Like `Settings::set_require_full_match`.
+    pub require_full_match: Option<bool>,
+    /// Like `Settings::set_allow_empty`.
+    pub allow_empty: Option<bool>,
+    /// Like `Settings::set_wildcards`.
+    pub wildcards: Option<bool>,
+    /// Like `Settings::set_normalize_line_endings`.
+    pub normalize_line_endings: Option<bool>,
+    /// Like `Settings::set_trailing_whitespace`.
+    pub trailing_whitespace: Option<Whitespace>,
+    /// Like `Settings::set_final_newline`.
+    pub final_newline: Option<Whitespace>,
+    /// Like `Settings::set_json_indent`.
+    pub json_indent: Option<usize>,
+    /// Like `Settings::set_json_array_width`.
+    pub json_array_width: Option<usize>,
+    /// Like `Settings::set_image_threshold`.
+    pub image_threshold: Option<f64>,
+    /// Like `Settings::set_image_max_diff_ratio`.
+    pub image_max_diff_ratio: Option<f64>,
+    /// Like `Settings::set_compression_threshold`.
+    pub compression_threshold: Option<usize>,
+}
+
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct TestConfig {
+    /// Follow up with a review (`--review`).
+    pub review: Option<bool>,
+    /// Accept new snapshots (`--accept-unseen`).
+    pub accept_unseen: Option<bool>,
+    /// Keep pending snapshots from earlier runs (`--keep-pending`).
+    pub keep_pending: Option<bool>,
+    /// Force pass snapshot assertions (disabled by `--no-force-pass`).
+    pub force_pass: Option<bool>,
+}
+
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct ReviewConfig {
+    /// The snapshot file extensions to consider (`--extensions`).
+    pub extensions: Option<Vec<String>>,
+}
+
+#[cfg(feature = "toml")]
+fn parse_config(contents: &str) -> Result<ToolConfig, String> {
+    toml::from_str(contents).map_err(|err| err.to_string())
+}
+
+#[cfg(not(feature = "toml"))]
+fn parse_config(_contents: &str) -> Result<ToolConfig, String> {
+    Ok(ToolConfig {
+        warnings: vec!["the file is ignored as insta was built without the `toml` feature".into()],
+        ..ToolConfig::default()
+    })
+}
+
+impl ToolConfig {
+    /// Loads the configuration of a workspace.
+    ///
+    /// The first of `insta.toml` and `.config/insta.toml` in the workspace
+    /// root that exists is used.  Without a file the default configuration
+    /// is returned.
+    ///
+    /// Invalid files (syntax errors, unknown keys, invalid selectors or
+    /// regular expressions) are reported as errors.  Parts of the file that
+    /// need a feature insta was built without are ignored and listed in
+    /// `warnings`, which includes the whole file without the `toml` feature.
+    pub fn from_workspace(workspace_root: &Path) -> Result<ToolConfig, String> {
+        for name in CONFIG_FILES {
+            let path = workspace_root.join(name);
+            let contents = match fs::read_to_string(&path) {
+                Ok(contents) => contents,
+                Err(_) => continue,
+            };
+            let mut rv = parse_config(&contents)
+                .and_then(|mut config| config.validate().map(|()| config))
+                .map_err(|err| format!("invalid config {}: {}", path.display(), err))?;
+            rv.path = Some(path);
+            return Ok(rv);
+        }
+        Ok(ToolConfig::default())
+    }
+
+    /// Loads the configuration of the workspace of the running test.
+    ///
+    /// The warnings are printed here which happens once per test binary.
+    fn from_current_workspace() -> Result<ToolConfig, String> {
+        let manifest_dir = match env::var("CARGO_MANIFEST_DIR") {
+            Ok(manifest_dir) => manifest_dir,
+            Err(_) => return Ok(ToolConfig::default()),
+        };
+        let config = ToolConfig::from_workspace(get_cargo_workspace(&manifest_dir))?;
+        if let Some(ref path) = config.path {
+            for warning in &config.warnings {
+                eprintln!(
+                    "{} {}: {}",
+                    style("warning:").bold().yellow(),
+                    path.display(),
+                    warning
+                );
+            }
+        }
+        Ok(config)
+    }
+
+    /// Checks the selectors and regular expressions and collects warnings
+    /// for the keys that need a disabled feature.
+    fn validate(&mut self) -> Result<(), String> {
+        #[cfg(feature = "redactions")]
+        {
+            let selectors = self
+                .redactions
+                .keys()
+                .chain(self.redaction_sets.values().flat_map(|set| set.keys()));
+            for selector in selectors {
+                Selector::parse(selector).map_err(|err| format!("{:?} is an {}", selector, err))?;
+            }
+        }
+        #[cfg(feature = "filters")]
+        for regex in self.filters.keys() {
+            regex::Regex::new(regex)
+                .map_err(|err| format!("invalid filter {:?}: {}", regex, err))?;
+        }
+
+        let unsupported: &[(bool, &str, &str)] = &[
+            #[cfg(not(feature = "redactions"))]
+            (!self.redactions.is_empty(), "redactions", "redactions"),
+            #[cfg(not(feature = "redactions"))]
+            (
+                !self.redaction_sets.is_empty(),
+                "redaction_sets",
+                "redactions",
+            ),
+            #[cfg(not(feature = "filters"))]
+            (!self.filters.is_empty(), "filters", "filters"),
+            #[cfg(not(feature = "image"))]
+            (
+                self.snapshots.image_threshold.is_some(),
+                "snapshots.image_threshold",
+                "image",
+            ),
+            #[cfg(not(feature = "image"))]
+            (
+                self.snapshots.image_max_diff_ratio.is_some(),
+                "snapshots.image_max_diff_ratio",
+                "image",
+            ),
+            #[cfg(not(feature = "compression"))]
+            (
+                self.snapshots.compression_threshold.is_some(),
+                "snapshots.compression_threshold",
+                "compression",
+            ),
+        ];
+        for &(is_set, key, feature) in unsupported {
+            if is_set {
+                self.warnings.push(format!(
+                    "`{}` is ignored as insta was built without the `{}` feature",
+                    key, feature
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Applies the configured defaults to the settings.
+    pub(crate) fn apply_to_settings(&self, settings: &mut ActualSettings) {
+        let snapshots = &self.snapshots;
+        if let Some(ref path) = snapshots.path {
+            settings.snapshot_path(path);
+        }
+        if let Some(value) = snapshots.path_base {
+            settings.snapshot_path_base(value);
+        }
+        if let Some(ref value) = snapshots.extension {
+            settings.snapshot_extension(value.as_str());
+        }
+        if let Some(value) = snapshots.prepend_module {
+            settings.prepend_module_to_snapshot(value);
+        }
+        if let Some(value) = snapshots.name_from_test_path {
+            settings.name_from_test_path(value);
+        }
+        if let Some(value) = snapshots.sort_maps {
+            settings.sort_maps(value);
+        }
+        if let Some(value) = snapshots.omit_expression {
+            settings.omit_expression(value);
+        }
+        if let Some(value) = snapshots.require_full_match {
+            settings.require_full_match(value);
+        }
+        if let Some(value) = snapshots.allow_empty {
+            settings.allow_empty(value);
+        }
+        if let Some(value) = snapshots.wildcards {
+            settings.wildcards(value);
+        }
+        if let Some(value) = snapshots.normalize_line_endings {
+            settings.normalize_line_endings(value);
+        }
+        if let Some(value) = snapshots.trailing_whitespace {
+            settings.trailing_whitespace(value);
+        }
+        if let Some(value) = snapshots.final_newline {
+            settings.final_newline(value);
+        }
+        if let Some(value) = snapshots.json_indent {
+            settings.json_indent(value);
+        }
+        if let Some(value) = snapshots.json_array_width {
+            settings.json_array_width(Some(value));
+        }
+        #[cfg(feature = "image")]
+        {
+            if let Some(value) = snapshots.image_threshold {
+                settings.image_threshold(value);
+            }
+            if let Some(value) = snapshots.image_max_diff_ratio {
+                settings.image_max_diff_ratio(value);
+            }
+        }
+        #[cfg(feature = "compression")]
+        if let Some(value) = snapshots.compression_threshold {
+            settings.compression_threshold(Some(value));
+        }
+        // the selectors were validated when loading the file
+        #[cfg(feature = "redactions")]
+        for (selector, replacement) in &self.redactions {
+            if let Ok(selector) = Selector::parse(selector) {
+                settings.add_redaction(selector, replacement.as_str());
+            }
+        }
+        #[cfg(feature = "filters")]
+        for (regex, replacement) in &self.filters {
+            settings.filters.add(regex, replacement.as_str());
+        }
+    }
+}
+
+#[cfg(feature = "redactions")]
+impl ToolConfig {
+    /// Parses the configured redaction sets.
+    pub(crate) fn redaction_sets(&self) -> HashMap<String, Redactions> {
+        self.redaction_sets
+            .iter()
+            .map(|(name, set)| {
+                let mut redactions = Redactions::default();
+                for (selector, replacement) in set {
+                    if let Ok(selector) = Selector::parse(selector) {
+                        redactions.push(selector, replacement.as_str());
+                    }
+                }
+                (name.clone(), redactions)
+            })
+            .collect()
+    }
+}
+
+/// Returns the configuration of the workspace of the running test.
+///
+/// The workspace is located through `CARGO_MANIFEST_DIR` which cargo sets
+/// when running tests.  The file is only read once.  An invalid file falls
+/// back to the default configuration here, the error is reported by the
+/// assertions through [`tool_config_error`].
+pub(crate) fn tool_config() -> &'static ToolConfig {
+    match *TOOL_CONFIG {
+        Ok(ref config) => config,
+        Err(_) => &DEFAULT_TOOL_CONFIG,
+    }
+}
+
+/// Returns the error of loading the configuration of the running test.
+pub(crate) fn tool_config_error() -> Option<&'static str> {
+    TOOL_CONFIG.as_ref().err().map(|err| err.as_str())
+}
+
+#[cfg(feature = "toml")]
+#[test]
+fn test_from_workspace() {
+    let root = env::temp_dir().join(format!("insta-config-{}", std::process::id()));
+    fs::create_dir_all(root.join(".config")).unwrap();
+
+    assert!(ToolConfig::from_workspace(&root).unwrap().path.is_none());
+
+    fs::write(
+        root.join(".config/insta.toml"),
+        r#"
+[behavior]
+update = "no"
+force_pass = true
+
+[snapshots]
+path = "golden"
+sort_maps = true
+
+[redactions]
+".id" = "[id]"
+
+[filters]
+'\d+ms' = "[duration]"
+
+[redaction_sets.timestamps]
+".created_at" = "[timestamp]"
+
+[test]
+review = true
+
+[review]
+extensions = ["snap", "txt"]
+"#,
+    )
+    .unwrap();
+    let config = ToolConfig::from_workspace(&root).unwrap();
+    assert_eq!(config.path, Some(root.join(".config/insta.toml")));
+    assert_eq!(config.behavior.update.as_deref(), Some("no"));
+    assert_eq!(config.behavior.force_pass, Some(true));
+    assert_eq!(config.snapshots.path, Some(PathBuf::from("golden")));
+    assert_eq!(
+        config.redactions.get(".id").map(|x| x.as_str()),
+        Some("[id]")
+    );
+    assert_eq!(
+        config.filters.get(r"\d+ms").map(|x| x.as_str()),
+        Some("[duration]")
+    );
+    #[cfg(feature = "redactions")]
+    assert!(config.redaction_sets().contains_key("timestamps"));
+    assert_eq!(config.test.review, Some(true));
+    assert_eq!(
+        config.review.extensions,
+        Some(vec!["snap".to_string(), "txt".to_string()])
+    );
+
+    // the file in the workspace root wins
+    fs::write(root.join("insta.toml"), "[snapshots]\nsort_maps = false\n").unwrap();
+    let config = ToolConfig::from_workspace(&root).unwrap();
+    assert_eq!(config.snapshots.sort_maps, Some(false));
+
+    fs::write(root.join("insta.toml"), "[snapshots]\nunknown = 1\n").unwrap();
+    assert!(ToolConfig::from_workspace(&root)
+        .unwrap_err()
+        .contains("unknown field"));
+
+    fs::remove_dir_all(&root).unwrap();
+}
+
+#[cfg(not(feature = "toml"))]
+#[test]
+fn test_from_workspace_without_toml() {
+    let root = env::temp_dir().join(format!("insta-config-no-toml-{}", std::process::id()));
+    fs::create_dir_all(&root).unwrap();
+    fs::write(root.join("insta.toml"), "[snapshots]\nsort_maps = true\n").unwrap();
+
+    let config = ToolConfig::from_workspace(&root).unwrap();
+    assert_eq!(config.path, Some(root.join("insta.toml")));
+    assert_eq!(config.snapshots.sort_maps, None);
+    assert_eq!(config.warnings.len(), 1);
+    assert!(config.warnings[0].contains("`toml` feature"));
+
+    fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn test_validate() {
+    let mut config = ToolConfig::default();
+    config.redactions.insert(".id".into(), "[id]".into());
+    config.filters.insert(r"\d+ms".into(), "[duration]".into());
+    config.snapshots.compression_threshold = Some(1024);
+    config.validate().unwrap();
+    assert_eq!(
+        config.warnings.iter().any(|x| x.contains("`redactions`")),
+        cfg!(not(feature = "redactions"))
+    );
+    assert_eq!(
+        config.warnings.iter().any(|x| x.contains("`filters`")),
+        cfg!(not(feature = "filters"))
+    );
+    assert_eq!(
+        config
+            .warnings
+            .iter()
+            .any(|x| x.contains("compression_threshold")),
+        cfg!(not(feature = "compression"))
+    );
+
+    let mut config = ToolConfig::default();
+    config.redaction_sets.insert(
+        "broken".into(),
+        vec![("[invalid".to_string(), "x".to_string())]
+            .into_iter()
+            .collect(),
+    );
+    assert_eq!(config.validate().is_err(), cfg!(feature = "redactions"));
+
+    let mut config = ToolConfig::default();
+    config.filters.insert("(unclosed".into(), "x".into());
+    assert_eq!(config.validate().is_err(), cfg!(feature = "filters"));
+}
+
+#[cfg(all(feature = "redactions", feature = "toml"))]
+#[test]
+fn test_apply_redactions() {
+    use crate::serialization::{serialize_value, SerializationFormat, SnapshotLocation};
+    use crate::settings::Settings;
+
+    let config: ToolConfig = toml::from_str(
+        r#"
+[redactions]
+".id" = "[id]"
+".**.token" = "[token]"
+"#,
+    )
+    .unwrap();
+    let mut settings = Settings::new();
+    config.apply_to_settings(settings._private_inner_mut());
+    settings.bind(|| {
+        let value = serde_json::json!({"id": 42, "auth": {"token": "s3cr3t"}});
+        assert_eq!(
+            serialize_value(&value, SerializationFormat::Json, SnapshotLocation::File),
+            "{\n  \"auth\": {\n    \"token\": \"[token]\"\n  },\n  \"id\": \"[id]\"\n}"
+        );
+    });
+}
@@ -1,11 +1,22 @@
 // this module is based on the content module in serde::private::ser
+use serde::de::{self, Deserialize, Deserializer, MapAccess, SeqAccess, Visitor};
 use serde::ser::{self, Serialize, Serializer};
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::fmt;
 use std::marker::PhantomData;
+use std::sync::Arc;
 
 /// Represents variable typed content.
 ///
-/// This is used internally for the serialization system to
-/// represent values before the actual snapshots are written.
+/// This is the tree that values implementing `serde::Serialize` are converted
+/// into before the actual snapshots are written.  Sorting of maps, redactions
+/// and all other transformations operate on this structure, which is why it's
+/// exposed: custom normalizers can be written against it with the traversal
+/// methods [`walk`](#method.walk), [`walk_with_path`](#method.walk_with_path),
+/// [`map`](#method.map) and [`fold`](#method.fold).
+///
+/// The variants mirror the serde data model.
 #[derive(Debug, Clone)]
 pub enum Content {
     Bool(bool),
@@ -24,36 +35,38 @@ pub enum Content {
     F64(f64),
 
     Char(char),
-    String(String),
+    /// A string.  Strings are shared so that map keys repeated across many
+    /// maps are only stored once (see [`ContentSerializer`]).
+    String(Arc<str>),
     Bytes(Vec<u8>),
 
-    #[doc(hidden)]
+    /// An absent optional value.
     None,
-    #[doc(hidden)]
+    /// A present optional value.
     Some(Box<Content>),
 
-    #[doc(hidden)]
+    /// The unit value `()`.
     Unit,
-    #[doc(hidden)]
+    /// A unit struct (`struct Foo;`) with its name.
     UnitStruct(&'static str),
-    #[doc(hidden)]
+    /// A unit enum variant: enum name, variant index and variant name.
     UnitVariant(&'static str, u32, &'static str),
-    #[doc(hidden)]
+    /// A newtype struct (`struct Foo(T)`) with its name.
     NewtypeStruct(&'static str, Box<Content>),
-    #[doc(hidden)]
+    /// A newtype enum variant: enum name, variant index, variant name and value.
     NewtypeVariant(&'static str, u32, &'static str, Box<Content>),
 
     Seq(Vec<Content>),
-    #[doc(hidden)]
+    /// A tuple.
     Tuple(Vec<Content>),
-    #[doc(hidden)]
+    /// A tuple struct with its name.
     TupleStruct(&'static str, Vec<Content>),
-    #[doc(hidden)]
+    /// A tuple enum variant: enum name, variant index, variant name and fields.
     TupleVariant(&'static str, u32, &'static str, Vec<Content>),
     Map(Vec<(Content, Content)>),
-    #[doc(hidden)]
+    /// A struct with its name and fields in declaration order.
     Struct(&'static str, Vec<(&'static str, Content)>),
-    #[doc(hidden)]
+    /// A struct enum variant: enum name, variant index, variant name and fields.
     StructVariant(
         &'static str,
         u32,
@@ -62,6 +75,82 @@ pub enum Content {
     ),
 }
 
+/// A single step in a [`ContentPath`](struct.ContentPath.html).
+#[derive(Debug)]
+#[cfg_attr(not(feature = "redactions"), allow(dead_code))]
+pub(crate) enum PathItem {
+    /// A map key.
+    Content(Content),
+    /// A struct field.
+    Field(&'static str),
+    /// An index into a sequence together with the length of the sequence.
+    Index(u64, u64),
+}
+
+impl PathItem {
+    pub(crate) fn as_str(&self) -> Option<&str> {
+        match *self {
+            PathItem::Content(ref content) => content.as_str(),
+            PathItem::Field(s) => Some(s),
+            PathItem::Index(..) => None,
+        }
+    }
+}
+
+/// Represents a path in a content tree.
+///
+/// This is passed to dynamic redactions and to the callback of
+/// [`Content::walk_with_path`](enum.Content.html#method.walk_with_path).
+/// It can be converted into a string with `to_string` to see a stringified
+/// path (eg: `.users.0.name`).
+#[derive(Clone, Debug)]
+pub struct ContentPath<'a>(pub(crate) &'a [PathItem]);
+
+impl<'a> ContentPath<'a> {
+    /// Returns the number of segments in the path.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Returns `true` if this path points to the root of the tree.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Returns the struct field or string map key of the last segment.
+    pub fn last_key(&self) -> Option<&str> {
+        self.0.last().and_then(|x| x.as_str())
+    }
+
+    /// Returns the sequence index of the last segment.
+    pub fn last_index(&self) -> Option<usize> {
+        match self.0.last() {
+            Some(PathItem::Index(idx, _)) => Some(*idx as usize),
+            _ => None,
+        }
+    }
+}
+
+impl<'a> fmt::Display for ContentPath<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for item in self.0.iter() {
+            write!(f, ".")?;
+            match *item {
+                PathItem::Content(ref ctx) => {
+                    if let Some(s) = ctx.as_str() {
+                        write!(f, "{}", s)?;
+                    } else {
+                        write!(f, "<content>")?;
+                    }
+                }
+                PathItem::Field(name) => write!(f, "{}", name)?,
+                PathItem::Index(idx, _) => write!(f, "{}", idx)?,
+            }
+        }
+        Ok(())
+    }
+}
+
 #[derive(PartialEq, PartialOrd, Debug)]
 pub enum Key<'a> {
     Bool(bool),
@@ -75,6 +164,7 @@ pub enum Key<'a> {
 
 impl<'a> Eq for Key<'a> {}
 
+#[allow(clippy::derive_ord_xor_partial_ord)]
 impl<'a> Ord for Key<'a> {
     fn cmp(&self, other: &Self) -> std::cmp::Ordering {
         self.partial_cmp(other).unwrap_or(std::cmp::Ordering::Less)
@@ -103,7 +193,6 @@ impl_from!(i64, I64);
 impl_from!(f32, F32);
 impl_from!(f64, F64);
 impl_from!(char, Char);
-impl_from!(String, String);
 impl_from!(Vec<u8>, Bytes);
 
 impl From<()> for Content {
@@ -112,9 +201,15 @@ impl From<()> for Content {
     }
 }
 
+impl From<String> for Content {
+    fn from(value: String) -> Content {
+        Content::String(value.into())
+    }
+}
+
 impl<'a> From<&'a str> for Content {
     fn from(value: &'a str) -> Content {
-        Content::String(value.to_string())
+        Content::String(value.into())
     }
 }
 
@@ -128,11 +223,16 @@ impl Content {
     /// Returns the value as string
     pub fn as_str(&self) -> Option<&str> {
         match *self {
-            Content::String(ref s) => Some(s.as_str()),
+            Content::String(ref s) => Some(s),
             _ => None,
         }
     }
 
+    /// Checks if the value is `None` or unit which both serialize as `null`
+    pub fn is_nil(&self) -> bool {
+        matches!(*self, Content::None | Content::Unit)
+    }
+
     pub(crate) fn as_key(&self) -> Key<'_> {
         match *self {
             Content::Bool(val) => Key::Bool(val),
@@ -145,7 +245,7 @@ impl Content {
             Content::I64(val) => Key::I64(val),
             Content::F32(val) => Key::F64(val.into()),
             Content::F64(val) => Key::F64(val),
-            Content::String(ref val) => Key::Str(&val.as_str()),
+            Content::String(ref val) => Key::Str(val),
             Content::Bytes(ref val) => Key::Bytes(&val[..]),
             Content::Some(ref val) => val.as_key(),
             _ => Key::Other,
@@ -233,6 +333,156 @@ impl Content {
             _ => {}
         }
     }
+
+    /// Recursively walks the content structure mutably, tracking the path.
+    ///
+    /// This works like [`walk`](#method.walk) but the callback is also given
+    /// the path of the current node relative to the root.  Map keys are not
+    /// visited as they do not have a path of their own.  Returning `false`
+    /// from the callback skips the children of the current node.
+    pub fn walk_with_path<F>(&mut self, visit: &mut F)
+    where
+        F: FnMut(&mut Content, ContentPath<'_>) -> bool,
+    {
+        self.walk_with_path_impl(visit, &mut vec![]);
+    }
+
+    fn walk_with_path_impl<F>(&mut self, visit: &mut F, path: &mut Vec<PathItem>)
+    where
+        F: FnMut(&mut Content, ContentPath<'_>) -> bool,
+    {
+        if !visit(self, ContentPath(path)) {
+            return;
+        }
+
+        fn walk_seq<F>(vec: &mut [Content], visit: &mut F, path: &mut Vec<PathItem>)
+        where
+            F: FnMut(&mut Content, ContentPath<'_>) -> bool,
+        {
+            let len = vec.len() as u64;
+            for (idx, inner) in vec.iter_mut().enumerate() {
+                path.push(PathItem::Index(idx as u64, len));
+                inner.walk_with_path_impl(visit, path);
+                path.pop();
+            }
+        }
+
+        fn walk_fields<F>(
+            vec: &mut [(&'static str, Content)],
+            visit: &mut F,
+            path: &mut Vec<PathItem>,
+        ) where
+            F: FnMut(&mut Content, ContentPath<'_>) -> bool,
+        {
+            for (key, inner) in vec.iter_mut() {
+                path.push(PathItem::Field(key));
+                inner.walk_with_path_impl(visit, path);
+                path.pop();
+            }
+        }
+
+        match *self {
+            Content::Some(ref mut inner)
+            | Content::NewtypeStruct(_, ref mut inner)
+            | Content::NewtypeVariant(_, _, _, ref mut inner) => {
+                inner.walk_with_path_impl(visit, path);
+            }
+            Content::Seq(ref mut vec)
+            | Content::Tuple(ref mut vec)
+            | Content::TupleStruct(_, ref mut vec)
+            | Content::TupleVariant(_, _, _, ref mut vec) => walk_seq(vec, visit, path),
+            Content::Map(ref mut vec) => {
+                for (key, inner) in vec.iter_mut() {
+                    path.push(PathItem::Content(key.clone()));
+                    inner.walk_with_path_impl(visit, path);
+                    path.pop();
+                }
+            }
+            Content::Struct(_, ref mut vec) | Content::StructVariant(_, _, _, ref mut vec) => {
+                walk_fields(vec, visit, path)
+            }
+            _ => {}
+        }
+    }
+
+    /// Transforms the content tree bottom up.
+    ///
+    /// The children of a node are transformed before the node itself is
+    /// passed to the callback, so the callback always sees already mapped
+    /// children.  Map keys are mapped as well.
+    pub fn map<F: FnMut(Content) -> Content>(self, f: &mut F) -> Content {
+        fn map_seq<F: FnMut(Content) -> Content>(vec: Vec<Content>, f: &mut F) -> Vec<Content> {
+            vec.into_iter().map(|x| x.map(f)).collect()
+        }
+
+        fn map_fields<F: FnMut(Content) -> Content>(
+            vec: Vec<(&'static str, Content)>,
+            f: &mut F,
+        ) -> Vec<(&'static str, Content)> {
+            vec.into_iter().map(|(k, v)| (k, v.map(f))).collect()
+        }
+
+        let mapped = match self {
+            Content::Some(inner) => Content::Some(Box::new(inner.map(f))),
+            Content::NewtypeStruct(name, inner) => {
+                Content::NewtypeStruct(name, Box::new(inner.map(f)))
+            }
+            Content::NewtypeVariant(name, idx, variant, inner) => {
+                Content::NewtypeVariant(name, idx, variant, Box::new(inner.map(f)))
+            }
+            Content::Seq(vec) => Content::Seq(map_seq(vec, f)),
+            Content::Tuple(vec) => Content::Tuple(map_seq(vec, f)),
+            Content::TupleStruct(name, vec) => Content::TupleStruct(name, map_seq(vec, f)),
+            Content::TupleVariant(name, idx, variant, vec) => {
+                Content::TupleVariant(name, idx, variant, map_seq(vec, f))
+            }
+            Content::Map(vec) => {
+                Content::Map(vec.into_iter().map(|(k, v)| (k.map(f), v.map(f))).collect())
+            }
+            Content::Struct(name, vec) => Content::Struct(name, map_fields(vec, f)),
+            Content::StructVariant(name, idx, variant, vec) => {
+                Content::StructVariant(name, idx, variant, map_fields(vec, f))
+            }
+            other => other,
+        };
+        f(mapped)
+    }
+
+    /// Folds over all nodes of the content tree.
+    ///
+    /// Nodes are visited top down (a node before its children) and map keys
+    /// are visited before their values.
+    pub fn fold<T, F: FnMut(T, &Content) -> T>(&self, init: T, f: &mut F) -> T {
+        let mut acc = f(init, self);
+        match *self {
+            Content::Some(ref inner)
+            | Content::NewtypeStruct(_, ref inner)
+            | Content::NewtypeVariant(_, _, _, ref inner) => inner.fold(acc, f),
+            Content::Seq(ref vec)
+            | Content::Tuple(ref vec)
+            | Content::TupleStruct(_, ref vec)
+            | Content::TupleVariant(_, _, _, ref vec) => {
+                for inner in vec {
+                    acc = inner.fold(acc, f);
+                }
+                acc
+            }
+            Content::Map(ref vec) => {
+                for (key, value) in vec {
+                    acc = key.fold(acc, f);
+                    acc = value.fold(acc, f);
+                }
+                acc
+            }
+            Content::Struct(_, ref vec) | Content::StructVariant(_, _, _, ref vec) => {
+                for (_, value) in vec {
+                    acc = value.fold(acc, f);
+                }
+                acc
+            }
+            _ => acc,
+        }
+    }
 }
 
 impl Serialize for Content {
@@ -292,7 +542,7 @@ impl Serialize for Content {
             Content::Map(ref entries) => {
                 use serde::ser::SerializeMap;
                 let mut map = serializer.serialize_map(Some(entries.len()))?;
-                for &(ref k, ref v) in entries {
+                for (k, v) in entries {
                     map.serialize_entry(k, v)?;
                 }
                 map.end()
@@ -317,6 +567,76 @@ impl Serialize for Content {
     }
 }
 
+/// The map keys seen while serializing a value.
+#[derive(Default)]
+struct KeyInterner {
+    keys: HashSet<Arc<str>>,
+    in_key: bool,
+}
+
+thread_local! {
+    static KEY_INTERNER: RefCell<Option<KeyInterner>> = const { RefCell::new(None) };
+}
+
+/// Runs a function with map keys being interned.
+///
+/// All string map keys serialized in the function share their storage with
+/// earlier keys of the same value.  Serializing thousands of maps with the
+/// same keys thus only allocates every key once.
+pub(crate) fn with_interned_keys<R, F: FnOnce() -> R>(f: F) -> R {
+    struct Reset(bool);
+
+    impl Drop for Reset {
+        fn drop(&mut self) {
+            if self.0 {
+                KEY_INTERNER.with(|x| *x.borrow_mut() = None);
+            }
+        }
+    }
+
+    let _reset = Reset(KEY_INTERNER.with(|x| {
+        let mut interner = x.borrow_mut();
+        interner.is_none() && {
+            *interner = Some(KeyInterner::default());
+            true
+        }
+    }));
+    f()
+}
+
+/// Serializes a map key marking the strings in it for interning.
+fn serialize_key_content<T, E>(key: &T) -> Result<Content, E>
+where
+    T: Serialize + ?Sized,
+    E: ser::Error,
+{
+    let set_in_key = |value: bool| {
+        KEY_INTERNER.with(|x| match *x.borrow_mut() {
+            Some(ref mut interner) => std::mem::replace(&mut interner.in_key, value),
+            None => false,
+        })
+    };
+    let was_in_key = set_in_key(true);
+    let rv = key.serialize(ContentSerializer::<E>::new());
+    set_in_key(was_in_key);
+    rv
+}
+
+fn intern_str(value: &str) -> Arc<str> {
+    KEY_INTERNER.with(|x| match *x.borrow_mut() {
+        Some(ref mut interner) if interner.in_key => match interner.keys.get(value) {
+            Some(key) => key.clone(),
+            None => {
+                let key: Arc<str> = value.into();
+                interner.keys.insert(key.clone());
+                key
+            }
+        },
+        _ => value.into(),
+    })
+}
+
+/// Serializes values into [`Content`].
 pub struct ContentSerializer<E> {
     error: PhantomData<E>,
 }
@@ -391,7 +711,7 @@ where
     }
 
     fn serialize_str(self, value: &str) -> Result<Content, E> {
-        Ok(Content::String(value.to_owned()))
+        Ok(Content::String(intern_str(value)))
     }
 
     fn serialize_bytes(self, value: &[u8]) -> Result<Content, E> {
@@ -402,9 +722,9 @@ where
         Ok(Content::None)
     }
 
-    fn serialize_some<T: ?Sized>(self, value: &T) -> Result<Content, E>
+    fn serialize_some<T>(self, value: &T) -> Result<Content, E>
     where
-        T: Serialize,
+        T: Serialize + ?Sized,
     {
         Ok(Content::Some(Box::new(value.serialize(self)?)))
     }
@@ -426,13 +746,9 @@ where
         Ok(Content::UnitVariant(name, variant_index, variant))
     }
 
-    fn serialize_newtype_struct<T: ?Sized>(
-        self,
-        name: &'static str,
-        value: &T,
-    ) -> Result<Content, E>
+    fn serialize_newtype_struct<T>(self, name: &'static str, value: &T) -> Result<Content, E>
     where
-        T: Serialize,
+        T: Serialize + ?Sized,
     {
         Ok(Content::NewtypeStruct(
             name,
@@ -440,7 +756,7 @@ where
         ))
     }
 
-    fn serialize_newtype_variant<T: ?Sized>(
+    fn serialize_newtype_variant<T>(
         self,
         name: &'static str,
         variant_index: u32,
@@ -448,7 +764,7 @@ where
         value: &T,
     ) -> Result<Content, E>
     where
-        T: Serialize,
+        T: Serialize + ?Sized,
     {
         Ok(Content::NewtypeVariant(
             name,
@@ -545,9 +861,9 @@ where
     type Ok = Content;
     type Error = E;
 
-    fn serialize_element<T: ?Sized>(&mut self, value: &T) -> Result<(), E>
+    fn serialize_element<T>(&mut self, value: &T) -> Result<(), E>
     where
-        T: Serialize,
+        T: Serialize + ?Sized,
     {
         let value = value.serialize(ContentSerializer::<E>::new())?;
         self.elements.push(value);
@@ -571,9 +887,9 @@ where
     type Ok = Content;
     type Error = E;
 
-    fn serialize_element<T: ?Sized>(&mut self, value: &T) -> Result<(), E>
+    fn serialize_element<T>(&mut self, value: &T) -> Result<(), E>
     where
-        T: Serialize,
+        T: Serialize + ?Sized,
     {
         let value = value.serialize(ContentSerializer::<E>::new())?;
         self.elements.push(value);
@@ -598,9 +914,9 @@ where
     type Ok = Content;
     type Error = E;
 
-    fn serialize_field<T: ?Sized>(&mut self, value: &T) -> Result<(), E>
+    fn serialize_field<T>(&mut self, value: &T) -> Result<(), E>
     where
-        T: Serialize,
+        T: Serialize + ?Sized,
     {
         let value = value.serialize(ContentSerializer::<E>::new())?;
         self.fields.push(value);
@@ -627,9 +943,9 @@ where
     type Ok = Content;
     type Error = E;
 
-    fn serialize_field<T: ?Sized>(&mut self, value: &T) -> Result<(), E>
+    fn serialize_field<T>(&mut self, value: &T) -> Result<(), E>
     where
-        T: Serialize,
+        T: Serialize + ?Sized,
     {
         let value = value.serialize(ContentSerializer::<E>::new())?;
         self.fields.push(value);
@@ -659,18 +975,18 @@ where
     type Ok = Content;
     type Error = E;
 
-    fn serialize_key<T: ?Sized>(&mut self, key: &T) -> Result<(), E>
+    fn serialize_key<T>(&mut self, key: &T) -> Result<(), E>
     where
-        T: Serialize,
+        T: Serialize + ?Sized,
     {
-        let key = key.serialize(ContentSerializer::<E>::new())?;
+        let key = serialize_key_content::<_, E>(key)?;
         self.key = Some(key);
         Ok(())
     }
 
-    fn serialize_value<T: ?Sized>(&mut self, value: &T) -> Result<(), E>
+    fn serialize_value<T>(&mut self, value: &T) -> Result<(), E>
     where
-        T: Serialize,
+        T: Serialize + ?Sized,
     {
         let key = self
             .key
@@ -685,12 +1001,12 @@ where
         Ok(Content::Map(self.entries))
     }
 
-    fn serialize_entry<K: ?Sized, V: ?Sized>(&mut self, key: &K, value: &V) -> Result<(), E>
+    fn serialize_entry<K, V>(&mut self, key: &K, value: &V) -> Result<(), E>
     where
-        K: Serialize,
-        V: Serialize,
+        K: Serialize + ?Sized,
+        V: Serialize + ?Sized,
     {
-        let key = key.serialize(ContentSerializer::<E>::new())?;
+        let key = serialize_key_content::<_, E>(key)?;
         let value = value.serialize(ContentSerializer::<E>::new())?;
         self.entries.push((key, value));
         Ok(())
@@ -710,9 +1026,9 @@ where
     type Ok = Content;
     type Error = E;
 
-    fn serialize_field<T: ?Sized>(&mut self, key: &'static str, value: &T) -> Result<(), E>
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<(), E>
     where
-        T: Serialize,
+        T: Serialize + ?Sized,
     {
         let value = value.serialize(ContentSerializer::<E>::new())?;
         self.fields.push((key, value));
@@ -739,9 +1055,9 @@ where
     type Ok = Content;
     type Error = E;
 
-    fn serialize_field<T: ?Sized>(&mut self, key: &'static str, value: &T) -> Result<(), E>
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<(), E>
     where
-        T: Serialize,
+        T: Serialize + ?Sized,
     {
         let value = value.serialize(ContentSerializer::<E>::new())?;
         self.fields.push((key, value));
@@ -757,3 +1073,168 @@ where
         ))
     }
 }
+
+/// Parses a JSON document into content, keeping the order of object keys.
+pub(crate) fn parse_json(s: &str) -> Option<Content> {
+    serde_json::from_str::<JsonContent>(s).ok().map(|x| x.0)
+}
+
+struct JsonContent(Content);
+
+impl<'de> Deserialize<'de> for JsonContent {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<JsonContent, D::Error> {
+        deserializer
+            .deserialize_any(JsonContentVisitor)
+            .map(JsonContent)
+    }
+}
+
+struct JsonContentVisitor;
+
+impl<'de> Visitor<'de> for JsonContentVisitor {
+    type Value = Content;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "any JSON value")
+    }
+
+    fn visit_bool<E: de::Error>(self, v: bool) -> Result<Content, E> {
+        Ok(Content::Bool(v))
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<Content, E> {
+        Ok(Content::I64(v))
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<Content, E> {
+        Ok(Content::U64(v))
+    }
+
+    fn visit_f64<E: de::Error>(self, v: f64) -> Result<Content, E> {
+        Ok(Content::F64(v))
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Content, E> {
+        Ok(Content::String(v.into()))
+    }
+
+    fn visit_string<E: de::Error>(self, v: String) -> Result<Content, E> {
+        Ok(Content::String(v.into()))
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<Content, E> {
+        Ok(Content::None)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Content, A::Error> {
+        let mut items = vec![];
+        while let Some(JsonContent(item)) = seq.next_element()? {
+            items.push(item);
+        }
+        Ok(Content::Seq(items))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Content, A::Error> {
+        let mut entries = vec![];
+        while let Some((JsonContent(key), JsonContent(value))) = map.next_entry()? {
+            entries.push((key, value));
+        }
+        Ok(Content::Map(entries))
+    }
+}
+
+#[cfg(test)]
+fn to_content<S: Serialize>(value: &S) -> Content {
+    value
+        .serialize(ContentSerializer::<serde::de::value::Error>::new())
+        .unwrap()
+}
+
+#[test]
+fn test_content_fold() {
+    let content = to_content(&vec![vec![1u32, 2], vec![3]]);
+    let sum = content.fold(0, &mut |acc, node| acc + node.as_u64().unwrap_or(0));
+    assert_eq!(sum, 6);
+}
+
+#[test]
+fn test_content_map() {
+    let content = to_content(&(1u32, "a", Some(2u32))).map(&mut |node| match node {
+        Content::U32(val) => Content::U32(val * 10),
+        other => other,
+    });
+    let values = content.fold(vec![], &mut |mut acc, node| {
+        acc.extend(node.as_u64());
+        acc
+    });
+    assert_eq!(values, vec![10, 20]);
+}
+
+#[test]
+fn test_content_walk_with_path() {
+    #[derive(serde::Serialize)]
+    struct User {
+        id: u32,
+        tags: Vec<&'static str>,
+    }
+
+    let mut content = to_content(&vec![User {
+        id: 1,
+        tags: vec!["a", "b"],
+    }]);
+    let mut paths = vec![];
+    content.walk_with_path(&mut |node, path| {
+        if path.last_key() == Some("id") {
+            *node = Content::from("[id]");
+        }
+        paths.push(path.to_string());
+        true
+    });
+    assert_eq!(
+        paths,
+        vec!["", ".0", ".0.id", ".0.tags", ".0.tags.0", ".0.tags.1"]
+    );
+    let redacted = content.fold(vec![], &mut |mut acc, node| {
+        acc.extend(node.as_str().map(|x| x.to_string()));
+        acc
+    });
+    assert_eq!(redacted, vec!["[id]", "a", "b"]);
+}
+
+#[test]
+fn test_parse_json_keeps_key_order() {
+    let content = parse_json(r#"{"b": [1, -2, 1.5], "a": null}"#).unwrap();
+    let keys = match content {
+        Content::Map(ref entries) => entries
+            .iter()
+            .filter_map(|(k, _)| k.as_str())
+            .collect::<Vec<_>>(),
+        _ => panic!("expected map"),
+    };
+    assert_eq!(keys, vec!["b", "a"]);
+    assert!(parse_json("{not json").is_none());
+}
+
+#[test]
+fn test_interned_map_keys() {
+    let mut map = std::collections::BTreeMap::new();
+    map.insert("key".to_string(), "key".to_string());
+    let content = with_interned_keys(|| to_content(&vec![map.clone(), map]));
+    let strings: Vec<&Arc<str>> = match content {
+        Content::Seq(ref items) => items
+            .iter()
+            .flat_map(|item| match *item {
+                Content::Map(ref entries) => entries.iter().flat_map(|(k, v)| vec![k, v]),
+                _ => panic!("expected map"),
+            })
+            .map(|x| match *x {
+                Content::String(ref s) => s,
+                _ => panic!("expected string"),
+            })
+            .collect(),
+        _ => panic!("expected seq"),
+    };
+    // keys share their storage, values do not
+    assert!(Arc::ptr_eq(strings[0], strings[2]));
+    assert!(!Arc::ptr_eq(strings[1], strings[3]));
+}
@@ -0,0 +1,90 @@
+use sqlx::{Column, ColumnIndex, Decode, Row, Type, TypeInfo, ValueRef};
+
+use crate::content::Content;
+
+/// Converts the rows of a query result into content for snapshots.
+///
+/// **Feature:** `sqlx`
+///
+/// Every row becomes a map of column names to values so redactions can
+/// target columns (eg: `[].id`).  Values are rendered according to their
+/// database type: `NULL` as none, integers, floats, booleans and text as
+/// such and binary data as bytes.  Values of other types are rendered as
+/// `[unsupported TYPE]` and should be cast in the query.
+///
+/// This works with the rows of any `sqlx` database:
+///
+/// ```ignore
+/// let rows = sqlx::query("select id, name from users")
+///     .fetch_all(&pool)
+///     .await?;
+/// insta::assert_yaml_snapshot!(insta::rows_to_content(&rows), {
+///     "[].id" => "[id]",
+/// });
+/// ```
+pub fn rows_to_content<R>(rows: &[R]) -> Content
+where
+    R: Row,
+    usize: ColumnIndex<R>,
+    for<'r> i64: Decode<'r, R::Database> + Type<R::Database>,
+    for<'r> i32: Decode<'r, R::Database> + Type<R::Database>,
+    for<'r> i16: Decode<'r, R::Database> + Type<R::Database>,
+    for<'r> f64: Decode<'r, R::Database> + Type<R::Database>,
+    for<'r> f32: Decode<'r, R::Database> + Type<R::Database>,
+    for<'r> bool: Decode<'r, R::Database> + Type<R::Database>,
+    for<'r> String: Decode<'r, R::Database> + Type<R::Database>,
+    for<'r> Vec<u8>: Decode<'r, R::Database> + Type<R::Database>,
+{
+    Content::Seq(
+        rows.iter()
+            .map(|row| {
+                Content::Map(
+                    row.columns()
+                        .iter()
+                        .map(|column| {
+                            let value = value_to_content(row, column.ordinal());
+                            (Content::from(column.name()), value)
+                        })
+                        .collect(),
+                )
+            })
+            .collect(),
+    )
+}
+
+fn value_to_content<R>(row: &R, idx: usize) -> Content
+where
+    R: Row,
+    usize: ColumnIndex<R>,
+    for<'r> i64: Decode<'r, R::Database> + Type<R::Database>,
+    for<'r> i32: Decode<'r, R::Database> + Type<R::Database>,
+    for<'r> i16: Decode<'r, R::Database> + Type<R::Database>,
+    for<'r> f64: Decode<'r, R::Database> + Type<R::Database>,
+    for<'r> f32: Decode<'r, R::Database> + Type<R::Database>,
+    for<'r> bool: Decode<'r, R::Database> + Type<R::Database>,
+    for<'r> String: Decode<'r, R::Database> + Type<R::Database>,
+    for<'r> Vec<u8>: Decode<'r, R::Database> + Type<R::Database>,
+{
+    let type_info = match row.try_get_raw(idx) {
+        Ok(value) if value.is_null() => return Content::None,
+        Ok(value) => value.type_info().into_owned(),
+        Err(err) => return Content::from(format!("[error {}]", err)),
+    };
+
+    // the order matters as some databases consider integers compatible
+    // with floats and booleans.
+    macro_rules! try_decode {
+        ($($ty:ty),*) => {
+            $(
+                if <$ty as Type<R::Database>>::compatible(&type_info) {
+                    if let Ok(value) = row.try_get_unchecked::<$ty, _>(idx) {
+                        return Content::from(value);
+                    }
+                }
+            )*
+        };
+    }
+    try_decode!(i64, i32, i16, f64, f32, bool, String, Vec<u8>);
+
+    Content::from(format!("[unsupported {}]", type_info.name()))
+}
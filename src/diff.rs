@@ -0,0 +1,196 @@
+use std::collections::HashMap;
+
+use difference::{Changeset, Difference};
+
+/// Inputs with more line pairs than this are not diffed with `difference`
+/// at once as it needs memory proportional to the product of the lengths.
+const DIFF_THRESHOLD: usize = 10_000_000;
+
+#[derive(Clone, Copy, PartialEq)]
+enum Kind {
+    Same,
+    Add,
+    Rem,
+}
+
+/// Collects the diff merging consecutive lines of the same kind.
+struct Builder {
+    diffs: Vec<Difference>,
+    kind: Kind,
+    lines: Vec<String>,
+}
+
+impl Builder {
+    fn push(&mut self, kind: Kind, line: &str) {
+        if kind != self.kind {
+            self.flush();
+            self.kind = kind;
+        }
+        self.lines.push(line.to_string());
+    }
+
+    fn push_all(&mut self, kind: Kind, lines: &[&str]) {
+        for line in lines {
+            self.push(kind, line);
+        }
+    }
+
+    fn flush(&mut self) {
+        if self.lines.is_empty() {
+            return;
+        }
+        let text = self.lines.join("\n");
+        self.lines.clear();
+        self.diffs.push(match self.kind {
+            Kind::Same => Difference::Same(text),
+            Kind::Add => Difference::Add(text),
+            Kind::Rem => Difference::Rem(text),
+        });
+    }
+}
+
+/// Finds the lines that occur exactly once in both inputs and returns the
+/// longest sequence of them that is in the same order in both.
+fn unique_anchors(a: &[&str], b: &[&str]) -> Vec<(usize, usize)> {
+    let mut counts: HashMap<&str, (usize, usize, usize)> = HashMap::new();
+    for (idx, line) in a.iter().enumerate() {
+        let entry = counts.entry(line).or_insert((0, 0, idx));
+        entry.0 += 1;
+    }
+    let mut pairs = vec![];
+    for (idx, line) in b.iter().enumerate() {
+        if let Some(entry) = counts.get_mut(line) {
+            entry.1 += 1;
+            if entry.1 == 1 {
+                pairs.push((entry.2, idx));
+            }
+        }
+    }
+    pairs.retain(|(a_idx, _)| counts.get(a[*a_idx]).is_some_and(|x| x.0 == 1 && x.1 == 1));
+    pairs.sort_unstable();
+
+    // longest increasing subsequence of the positions in `b`
+    let mut tails: Vec<usize> = vec![];
+    let mut prev = vec![None; pairs.len()];
+    for (idx, &(_, b_idx)) in pairs.iter().enumerate() {
+        let pos = tails.partition_point(|&x| pairs[x].1 < b_idx);
+        if pos > 0 {
+            prev[idx] = Some(tails[pos - 1]);
+        }
+        if pos == tails.len() {
+            tails.push(idx);
+        } else {
+            tails[pos] = idx;
+        }
+    }
+    let mut rv = vec![];
+    let mut cur = tails.last().copied();
+    while let Some(idx) = cur {
+        rv.push(pairs[idx]);
+        cur = prev[idx];
+    }
+    rv.reverse();
+    rv
+}
+
+fn diff_lines(a: &[&str], b: &[&str], out: &mut Builder) {
+    let prefix = a.iter().zip(b).take_while(|(x, y)| x == y).count();
+    let suffix = a[prefix..]
+        .iter()
+        .rev()
+        .zip(b[prefix..].iter().rev())
+        .take_while(|(x, y)| x == y)
+        .count();
+    out.push_all(Kind::Same, &a[..prefix]);
+    let (a_mid, b_mid) = (&a[prefix..a.len() - suffix], &b[prefix..b.len() - suffix]);
+
+    if a_mid.is_empty() || b_mid.is_empty() {
+        out.push_all(Kind::Rem, a_mid);
+        out.push_all(Kind::Add, b_mid);
+    } else if a_mid.len() * b_mid.len() <= DIFF_THRESHOLD {
+        let changeset = Changeset::new(&a_mid.join("\n"), &b_mid.join("\n"), "\n");
+        for diff in changeset.diffs {
+            let (kind, text) = match diff {
+                Difference::Same(text) => (Kind::Same, text),
+                Difference::Add(text) => (Kind::Add, text),
+                Difference::Rem(text) => (Kind::Rem, text),
+            };
+            for line in text.split('\n') {
+                out.push(kind, line);
+            }
+        }
+    } else {
+        let anchors = unique_anchors(a_mid, b_mid);
+        if anchors.is_empty() {
+            out.push_all(Kind::Rem, a_mid);
+            out.push_all(Kind::Add, b_mid);
+        } else {
+            let (mut a_pos, mut b_pos) = (0, 0);
+            for (a_idx, b_idx) in anchors {
+                diff_lines(&a_mid[a_pos..a_idx], &b_mid[b_pos..b_idx], out);
+                out.push(Kind::Same, a_mid[a_idx]);
+                a_pos = a_idx + 1;
+                b_pos = b_idx + 1;
+            }
+            diff_lines(&a_mid[a_pos..], &b_mid[b_pos..], out);
+        }
+    }
+
+    out.push_all(Kind::Same, &a[a.len() - suffix..]);
+}
+
+/// Calculates the line based `difference::Changeset` of two inputs.
+///
+/// Small inputs are diffed with `difference` directly.  Larger inputs are
+/// first split into chunks at the common prefix and suffix and at lines
+/// that are unique in both inputs (like patience diff) and only the chunks
+/// are diffed.  This keeps memory bounded while still producing a useful
+/// diff for huge snapshots.  Chunks that are still too large and cannot be
+/// split further are shown as removed and added.
+pub(crate) fn get_changeset(orig: &str, edit: &str) -> Changeset {
+    let orig_cnt = orig.lines().count();
+    let edited_cnt = edit.lines().count();
+    if orig_cnt * edited_cnt <= DIFF_THRESHOLD {
+        return Changeset::new(orig, edit, "\n");
+    }
+
+    let a: Vec<&str> = orig.lines().collect();
+    let b: Vec<&str> = edit.lines().collect();
+    let mut out = Builder {
+        diffs: vec![],
+        kind: Kind::Same,
+        lines: vec![],
+    };
+    diff_lines(&a, &b, &mut out);
+    out.flush();
+    Changeset {
+        diffs: out.diffs,
+        // following two fields will not be read anyway
+        split: String::new(),
+        distance: 0,
+    }
+}
+
+#[test]
+fn test_large_changeset() {
+    let orig: Vec<String> = (0..5000).map(|x| format!("line {}", x)).collect();
+    let mut edit = orig.clone();
+    edit[10] = "changed".into();
+    edit.remove(4000);
+    edit.insert(2000, "inserted".into());
+    let changeset = get_changeset(&orig.join("\n"), &edit.join("\n"));
+
+    let changes: Vec<_> = changeset
+        .diffs
+        .iter()
+        .filter_map(|diff| match diff {
+            Difference::Same(_) => None,
+            Difference::Add(text) => Some(format!("+{}", text)),
+            Difference::Rem(text) => Some(format!("-{}", text)),
+        })
+        .collect();
+    assert_eq!(
+        changes,
+        vec!["-line 10", "+changed", "+inserted", "-line 4000"]
+    );
+}
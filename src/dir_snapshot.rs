@@ -0,0 +1,216 @@
+use std::fmt::Write;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::to_snapshot::ToSnapshot;
+
+/// Renders a directory tree with the contents of its files.
+///
+/// This is what [`assert_dir_snapshot!`](macro.assert_dir_snapshot.html)
+/// uses.  Every file is written with its path relative to the directory
+/// (with `/` as separator) in a header line followed by its contents, in
+/// the order of the paths.  Files that are not valid UTF-8 or larger than
+/// the size limit are only listed with their size, symlinks with their
+/// target and empty directories with a trailing `/`:
+///
+/// ```text
+/// === Cargo.toml ===
+/// [package]
+/// name = "generated"
+/// === assets/logo.png ===
+/// <binary, 1532 bytes>
+/// === src/main.rs ===
+/// fn main() {}
+/// ```
+///
+/// The limits and ignore patterns are configured with the builder methods:
+///
+/// ```no_run
+/// # use insta::{assert_snapshot, DirSnapshot};
+/// assert_snapshot!(DirSnapshot::new("target/generated")
+///     .ignore("*.lock")
+///     .ignore("cache/**")
+///     .max_lines(50));
+/// ```
+#[derive(Debug, Clone)]
+pub struct DirSnapshot {
+    path: PathBuf,
+    ignore: Vec<String>,
+    max_file_size: u64,
+    max_lines: Option<usize>,
+}
+
+impl DirSnapshot {
+    /// Creates a snapshot of the directory at the given path.
+    ///
+    /// Relative paths are resolved against the working directory, which is
+    /// the root of the crate when running tests with cargo.
+    pub fn new<P: AsRef<Path>>(path: P) -> DirSnapshot {
+        DirSnapshot {
+            path: path.as_ref().to_path_buf(),
+            ignore: Vec::new(),
+            max_file_size: 64 * 1024,
+            max_lines: None,
+        }
+    }
+
+    /// Leaves out files and directories matching a pattern.
+    ///
+    /// Patterns are matched against the relative path.  `*` matches any
+    /// characters but `/`, `**` also matches `/` and `?` matches a single
+    /// character.  Patterns without a `/` are matched against the names of
+    /// files and directories at any depth, so `target` leaves out every
+    /// directory named `target` together with its contents.  Directories
+    /// whose entries are all left out are listed like empty ones (eg:
+    /// `cache/` for `cache/**`).
+    pub fn ignore<S: Into<String>>(mut self, pattern: S) -> DirSnapshot {
+        self.ignore.push(pattern.into());
+        self
+    }
+
+    /// Sets the size in bytes up to which the contents of files are shown.
+    ///
+    /// The default is 64 KiB.
+    pub fn max_file_size(mut self, value: u64) -> DirSnapshot {
+        self.max_file_size = value;
+        self
+    }
+
+    /// Sets the number of lines shown per file.
+    ///
+    /// Longer files are cut off with a line counting the remaining lines.
+    /// The default is to show all lines.
+    pub fn max_lines(mut self, value: usize) -> DirSnapshot {
+        self.max_lines = Some(value);
+        self
+    }
+
+    fn is_ignored(&self, rel_path: &str) -> bool {
+        let name = rel_path.rsplit('/').next().unwrap_or(rel_path);
+        self.ignore.iter().any(|pattern| {
+            if pattern.contains('/') {
+                glob_match(pattern.as_bytes(), rel_path.as_bytes())
+            } else {
+                glob_match(pattern.as_bytes(), name.as_bytes())
+            }
+        })
+    }
+
+    /// Collects the paths of all files and empty directories.
+    fn collect(&self, dir: &Path, prefix: &str, rv: &mut Vec<(String, PathBuf)>) {
+        let entries = match fs::read_dir(dir) {
+            Ok(entries) => entries
+                .map(|entry| entry.and_then(|x| Ok((x.path(), x.file_type()?))))
+                .collect::<Result<Vec<_>, _>>()
+                .unwrap_or_else(|err| panic!("could not read {}: {}", dir.display(), err)),
+            Err(err) => panic!("could not read {}: {}", dir.display(), err),
+        };
+        // directories whose entries are all ignored count as empty
+        let entries: Vec<_> = entries
+            .into_iter()
+            .map(|(path, file_type)| {
+                let name = path.file_name().unwrap().to_string_lossy();
+                (format!("{}{}", prefix, name), path, file_type)
+            })
+            .filter(|(rel_path, _, _)| !self.is_ignored(rel_path))
+            .collect();
+        if entries.is_empty() && !prefix.is_empty() {
+            rv.push((prefix.to_string(), dir.to_path_buf()));
+        }
+        for (rel_path, path, file_type) in entries {
+            // symlinks are not followed so that links to parent directories
+            // do not recurse forever.
+            if file_type.is_dir() {
+                self.collect(&path, &format!("{}/", rel_path), rv);
+            } else {
+                rv.push((rel_path, path));
+            }
+        }
+    }
+}
+
+impl ToSnapshot for DirSnapshot {
+    fn to_snapshot(&self) -> String {
+        let mut files = Vec::new();
+        self.collect(&self.path, "", &mut files);
+        files.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let mut rv = String::new();
+        for (rel_path, path) in files {
+            writeln!(rv, "=== {} ===", rel_path).unwrap();
+            if rel_path.ends_with('/') {
+                continue;
+            }
+            if let Ok(target) = fs::read_link(&path) {
+                writeln!(rv, "<symlink to {}>", target.display()).unwrap();
+                continue;
+            }
+            let size = fs::metadata(&path)
+                .unwrap_or_else(|err| panic!("could not read {}: {}", path.display(), err))
+                .len();
+            if size > self.max_file_size {
+                writeln!(rv, "<{} bytes>", size).unwrap();
+                continue;
+            }
+            let bytes = fs::read(&path)
+                .unwrap_or_else(|err| panic!("could not read {}: {}", path.display(), err));
+            let contents = match String::from_utf8(bytes) {
+                Ok(contents) => contents,
+                Err(_) => {
+                    writeln!(rv, "<binary, {} bytes>", size).unwrap();
+                    continue;
+                }
+            };
+            let mut lines = contents.lines();
+            for line in lines.by_ref().take(self.max_lines.unwrap_or(usize::MAX)) {
+                writeln!(rv, "{}", line).unwrap();
+            }
+            let remaining = lines.count();
+            if remaining > 0 {
+                writeln!(rv, "<{} more lines>", remaining).unwrap();
+            }
+        }
+        rv
+    }
+}
+
+/// Matches a path against a glob pattern with `*`, `**` and `?`.
+fn glob_match(pattern: &[u8], path: &[u8]) -> bool {
+    match pattern.split_first() {
+        None => path.is_empty(),
+        Some((b'*', rest)) if rest.first() == Some(&b'*') => {
+            let rest = &rest[1..];
+            match rest.strip_prefix(b"/") {
+                // `**/` matches any number of whole directories
+                Some(rest) => (0..=path.len())
+                    .filter(|&idx| idx == 0 || path[idx - 1] == b'/')
+                    .any(|idx| glob_match(rest, &path[idx..])),
+                None => (0..=path.len()).any(|idx| glob_match(rest, &path[idx..])),
+            }
+        }
+        Some((b'*', rest)) => {
+            let end = path.iter().position(|&x| x == b'/').unwrap_or(path.len());
+            (0..=end).any(|idx| glob_match(rest, &path[idx..]))
+        }
+        Some((b'?', rest)) => match path.split_first() {
+            Some((&c, path_rest)) if c != b'/' => glob_match(rest, path_rest),
+            _ => false,
+        },
+        Some((&c, rest)) => match path.split_first() {
+            Some((&p, path_rest)) if p == c => glob_match(rest, path_rest),
+            _ => false,
+        },
+    }
+}
+
+#[test]
+fn test_glob_match() {
+    assert!(glob_match(b"*.lock", b"Cargo.lock"));
+    assert!(!glob_match(b"*.lock", b"dir/Cargo.lock"));
+    assert!(glob_match(b"cache/**", b"cache/a/b.txt"));
+    assert!(glob_match(b"**/*.rs", b"main.rs"));
+    assert!(glob_match(b"**/*.rs", b"src/bin/main.rs"));
+    assert!(!glob_match(b"**/x", b"ax"));
+    assert!(glob_match(b"file?.txt", b"file1.txt"));
+    assert!(!glob_match(b"file?.txt", b"file10.txt"));
+}
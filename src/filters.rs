@@ -0,0 +1,97 @@
+use std::borrow::Cow;
+
+use regex::Regex;
+
+use crate::settings::Settings;
+
+/// Represents stored filters.
+///
+/// Filters are regular expressions with replacements that are applied to
+/// the contents of snapshots before they are compared.  Unlike redactions
+/// they also work for snapshots that do not undergo serialization.
+#[derive(Clone, Default)]
+pub struct Filters {
+    rules: Vec<(Regex, String)>,
+}
+
+impl<'a> From<Vec<(&'a str, &'a str)>> for Filters {
+    fn from(value: Vec<(&'a str, &'a str)>) -> Filters {
+        let mut rv = Filters::default();
+        for (regex, replacement) in value {
+            rv.add(regex, replacement);
+        }
+        rv
+    }
+}
+
+impl Filters {
+    /// Adds a filter.
+    ///
+    /// The replacement can refer to capture groups like `$1` or `${name}`.
+    /// Panics if the regular expression is invalid.
+    pub(crate) fn add<S: Into<String>>(&mut self, regex: &str, replacement: S) {
+        self.rules.push((
+            Regex::new(regex).expect("invalid regex for snapshot filter"),
+            replacement.into(),
+        ));
+    }
+
+    /// Adds the filters of other filters that are not already set.
+    pub(crate) fn extend(&mut self, other: &Filters) {
+        for (regex, replacement) in &other.rules {
+            if !self
+                .rules
+                .iter()
+                .any(|x| x.0.as_str() == regex.as_str() && x.1 == *replacement)
+            {
+                self.rules.push((regex.clone(), replacement.clone()));
+            }
+        }
+    }
+
+    /// Removes all filters.
+    pub(crate) fn clear(&mut self) {
+        self.rules.clear();
+    }
+
+    /// Checks if no filters are set.
+    pub(crate) fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Applies all filters in the order they were added.
+    pub(crate) fn apply_to<'s>(&self, s: &'s str) -> Cow<'s, str> {
+        let mut rv = Cow::Borrowed(s);
+        for (regex, replacement) in &self.rules {
+            if let Cow::Owned(replaced) = regex.replace_all(&rv, replacement.as_str()) {
+                rv = Cow::Owned(replaced);
+            }
+        }
+        rv
+    }
+}
+
+/// Applies the filters of the current settings.
+pub(crate) fn apply_filters(s: &str) -> Cow<'_, str> {
+    Settings::with(|settings| {
+        let filters = settings.filters();
+        if filters.is_empty() {
+            return Cow::Borrowed(s);
+        }
+        filters.apply_to(s)
+    })
+}
+
+#[test]
+fn test_apply_filters() {
+    let filters = Filters::from(vec![
+        (r"/tmp/\.tmp\w+", "[TEMP]"),
+        (r"(\d+)ms", "[N]ms"),
+        (r"0x(?P<addr>[0-9a-f]+)", "[ptr]"),
+    ]);
+    assert_eq!(
+        filters.apply_to("wrote /tmp/.tmpA8dz3/out in 125ms at 0x7ffd1c"),
+        "wrote [TEMP]/out in [N]ms at [ptr]"
+    );
+    assert!(matches!(filters.apply_to("untouched"), Cow::Borrowed(_)));
+}
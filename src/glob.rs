@@ -17,7 +17,7 @@ pub fn glob_exec<F: FnMut(&Path)>(base: &Path, pattern: &str, mut f: F) {
         let path = file.path();
 
         let mut settings = Settings::clone_current();
-        settings.set_input_file(&path);
+        settings.set_input_file(path);
         settings.set_snapshot_suffix(path.file_name().unwrap().to_str().unwrap());
 
         settings.bind(|| {
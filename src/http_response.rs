@@ -0,0 +1,108 @@
+use http::Response;
+
+use crate::content::{parse_json, Content};
+
+/// A response that can be snapshotted with
+/// [`assert_response_snapshot!`](macro.assert_response_snapshot.html).
+///
+/// **Feature:** `http`
+///
+/// This is implemented for `http::Response`.  The responses of other HTTP
+/// clients can be snapshotted by implementing it for a wrapper type around
+/// them (or by converting them into an `http::Response`).
+pub trait HttpResponse {
+    /// The status code.
+    fn status(&self) -> u16;
+
+    /// The headers in the order they are rendered in.
+    fn headers(&self) -> Vec<(&str, &[u8])>;
+
+    /// The raw body.
+    fn body(&self) -> &[u8];
+}
+
+impl<B: AsRef<[u8]>> HttpResponse for Response<B> {
+    fn status(&self) -> u16 {
+        Response::status(self).as_u16()
+    }
+
+    fn headers(&self) -> Vec<(&str, &[u8])> {
+        Response::headers(self)
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_bytes()))
+            .collect()
+    }
+
+    fn body(&self) -> &[u8] {
+        Response::body(self).as_ref()
+    }
+}
+
+/// Headers that change between requests and are replaced in snapshots.
+const VOLATILE_HEADERS: &[&str] = &[
+    "age",
+    "content-length",
+    "date",
+    "etag",
+    "expires",
+    "last-modified",
+    "request-id",
+    "server-timing",
+    "x-amzn-requestid",
+    "x-correlation-id",
+    "x-request-id",
+    "x-runtime",
+    "x-trace-id",
+];
+
+/// Converts a response into the content stored in the snapshot.
+///
+/// The response is rendered as a map of status, headers and body.  Values
+/// of volatile headers are replaced with `[redacted]` and JSON bodies are
+/// parsed so they are pretty printed and can be targeted by redactions
+/// (eg: `.body.id`).  Other UTF-8 bodies are stored as strings, anything
+/// else as bytes.
+pub fn response_to_content<R: HttpResponse + ?Sized>(response: &R) -> Content {
+    let headers = response.headers();
+    let is_json = headers
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case("content-type"))
+        .and_then(|(_, value)| std::str::from_utf8(value).ok())
+        .is_some_and(|x| {
+            let mime = x.split(';').next().unwrap_or("").trim();
+            mime == "application/json" || mime.ends_with("+json")
+        });
+    let headers = headers
+        .into_iter()
+        .map(|(name, value)| {
+            let value = if VOLATILE_HEADERS
+                .iter()
+                .any(|x| x.eq_ignore_ascii_case(name))
+            {
+                Content::from("[redacted]")
+            } else {
+                match std::str::from_utf8(value) {
+                    Ok(value) => Content::from(value),
+                    Err(_) => Content::from(value),
+                }
+            };
+            (Content::from(name), value)
+        })
+        .collect();
+
+    let body = response.body();
+    let body = match std::str::from_utf8(body) {
+        Ok("") => Content::None,
+        Ok(text) => is_json
+            .then(|| parse_json(text))
+            .flatten()
+            .unwrap_or_else(|| Content::from(text)),
+        Err(_) => Content::from(body),
+    };
+
+    Content::Map(vec![
+        (Content::from("status"), Content::from(response.status())),
+        (Content::from("headers"), Content::Map(headers)),
+        (Content::from("body"), body),
+    ])
+}
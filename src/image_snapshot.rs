@@ -0,0 +1,177 @@
+use std::error::Error;
+use std::fmt::Write;
+use std::fs;
+use std::io::Cursor;
+use std::path::Path;
+
+use image::{ImageFormat, Rgba, RgbaImage};
+
+use crate::runtime::{assert_snapshot_impl, Binary, ReferenceValue};
+use crate::settings::Settings;
+use crate::utils::{fnv1a, style};
+
+/// The largest possible value of `color_delta`.
+const MAX_COLOR_DELTA: f64 = 35215.0;
+
+fn decode_image(bytes: &[u8]) -> RgbaImage {
+    match image::load_from_memory(bytes) {
+        Ok(image) => image.into_rgba8(),
+        Err(err) => panic!("could not decode image for snapshot: {}", err),
+    }
+}
+
+fn rgba_to_text(image: &RgbaImage) -> String {
+    let mut rv = String::new();
+    writeln!(rv, "size: {}x{}", image.width(), image.height()).unwrap();
+    write!(rv, "pixels: fnv1a64:{:016x}", fnv1a(image.as_raw())).unwrap();
+    rv
+}
+
+/// Renders an encoded image into a normalized text representation.
+///
+/// The image is decoded and converted to 8 bit RGBA so that only the
+/// pixels end up in the snapshot.  Encoding level details such as
+/// metadata chunks, the compression level or the color type of the
+/// encoding do not change the output.
+pub fn image_to_text(bytes: &[u8]) -> String {
+    rgba_to_text(&decode_image(bytes))
+}
+
+/// Converts a pixel to YIQ after blending it over white.
+fn to_yiq(pixel: &Rgba<u8>) -> (f64, f64, f64) {
+    let alpha = f64::from(pixel[3]) / 255.0;
+    let blend = |c: u8| 255.0 + (f64::from(c) - 255.0) * alpha;
+    let (r, g, b) = (blend(pixel[0]), blend(pixel[1]), blend(pixel[2]));
+    (
+        r * 0.298_895_31 + g * 0.586_622_47 + b * 0.114_482_23,
+        r * 0.595_977_99 - g * 0.274_176_10 - b * 0.321_801_89,
+        r * 0.211_470_17 - g * 0.522_617_11 + b * 0.311_146_94,
+    )
+}
+
+/// Measures the perceived difference of two pixels.
+///
+/// This is the weighted distance in the YIQ color space that `pixelmatch`
+/// uses.
+fn color_delta(a: &Rgba<u8>, b: &Rgba<u8>) -> f64 {
+    let (y1, i1, q1) = to_yiq(a);
+    let (y2, i2, q2) = to_yiq(b);
+    let (y, i, q) = (y1 - y2, i1 - i2, q1 - q2);
+    0.5053 * y * y + 0.299 * i * i + 0.1957 * q * q
+}
+
+/// Compares an image against the stored reference image.
+///
+/// Returns the number of changed pixels and an image highlighting them in
+/// red over a faded copy of the reference, or `None` if the sizes differ.
+fn diff_images(
+    reference: &RgbaImage,
+    image: &RgbaImage,
+    threshold: f64,
+) -> Option<(usize, RgbaImage)> {
+    if reference.dimensions() != image.dimensions() {
+        return None;
+    }
+    let max_delta = MAX_COLOR_DELTA * threshold * threshold;
+    let mut changed = 0;
+    let mut diff = RgbaImage::new(image.width(), image.height());
+    for ((x, y, pixel), old) in image.enumerate_pixels().zip(reference.pixels()) {
+        if color_delta(old, pixel) > max_delta {
+            changed += 1;
+            diff.put_pixel(x, y, Rgba([255, 0, 0, 255]));
+        } else {
+            let luma = (255.0 + (to_yiq(old).0 - 255.0) * 0.1) as u8;
+            diff.put_pixel(x, y, Rgba([luma, luma, luma, 255]));
+        }
+    }
+    Some((changed, diff))
+}
+
+/// Checks if an image is close enough to the stored reference.
+///
+/// On a mismatch a diff image is written next to the reference
+/// (`foo.snap.diff.png` for `foo.snap.png`).
+fn matches_reference(image: &RgbaImage, stored: &[u8], path: &Path) -> bool {
+    let diff_path = path.with_extension("diff.png");
+    let reference = match image::load_from_memory(stored) {
+        Ok(reference) => reference.into_rgba8(),
+        Err(_) => return false,
+    };
+    let (threshold, max_diff_ratio) =
+        Settings::with(|settings| (settings.image_threshold(), settings.image_max_diff_ratio()));
+    let (changed, diff) = match diff_images(&reference, image, threshold) {
+        Some(rv) => rv,
+        None => {
+            fs::remove_file(&diff_path).ok();
+            return false;
+        }
+    };
+    let pixels = (image.width() as usize) * (image.height() as usize);
+    if changed as f64 <= max_diff_ratio * pixels as f64 {
+        fs::remove_file(&diff_path).ok();
+        return true;
+    }
+    if diff.save_with_format(&diff_path, ImageFormat::Png).is_ok() {
+        eprintln!(
+            "{} {} of {} pixels changed, see {}",
+            style("image mismatch:").yellow(),
+            changed,
+            pixels,
+            style(diff_path.display()).cyan().underlined(),
+        );
+    }
+    false
+}
+
+#[doc(hidden)]
+pub fn assert_image_snapshot(
+    refval: ReferenceValue<'_>,
+    bytes: &[u8],
+    manifest_dir: &str,
+    module_path: &str,
+    file: &str,
+    line: u32,
+    expr: &str,
+) -> Result<(), Box<dyn Error>> {
+    let image = decode_image(bytes);
+    let mut png = Vec::new();
+    image.write_to(&mut Cursor::new(&mut png), ImageFormat::Png)?;
+    let compare = |stored: &[u8], path: &Path| matches_reference(&image, stored, path);
+    assert_snapshot_impl(
+        refval,
+        None,
+        &rgba_to_text(&image),
+        manifest_dir,
+        module_path,
+        file,
+        line,
+        Some(expr),
+        None,
+        Some(Binary {
+            extension: "png",
+            data: &png,
+            compare: Some(&compare),
+        }),
+    )
+}
+
+#[test]
+fn test_diff_images() {
+    let reference = RgbaImage::from_pixel(4, 4, Rgba([100, 150, 200, 255]));
+    let mut image = reference.clone();
+    image.put_pixel(0, 0, Rgba([102, 150, 200, 255]));
+    image.put_pixel(3, 3, Rgba([255, 0, 0, 255]));
+
+    assert_eq!(diff_images(&reference, &image, 0.0).unwrap().0, 2);
+    let (changed, diff) = diff_images(&reference, &image, 0.1).unwrap();
+    assert_eq!(changed, 1);
+    assert_eq!(diff.get_pixel(3, 3), &Rgba([255, 0, 0, 255]));
+    assert_ne!(diff.get_pixel(0, 0), &Rgba([255, 0, 0, 255]));
+
+    // invisible pixels do not count as changed
+    let clear = RgbaImage::from_pixel(4, 4, Rgba([0, 0, 0, 0]));
+    let other = RgbaImage::from_pixel(4, 4, Rgba([255, 0, 0, 0]));
+    assert_eq!(diff_images(&clear, &other, 0.0).unwrap().0, 0);
+
+    assert!(diff_images(&reference, &RgbaImage::new(4, 3), 1.0).is_none());
+}
@@ -0,0 +1,338 @@
+//! Conversion between insta snapshots and Jest snapshot files.
+//!
+//! Jest stores all snapshots of a test file in a single `.snap` file
+//! that is a JavaScript module assigning template literals to
+//! `exports[...]`.  The functions in this module convert such a file
+//! into insta snapshots and back:
+//!
+//! ```
+//! let snapshots = insta::jest::from_jest(
+//!     "exports[`adds numbers 1`] = `3`;\n",
+//!     "sum",
+//! ).unwrap();
+//! assert_eq!(snapshots[0].snapshot_name(), Some("adds_numbers"));
+//! assert_eq!(snapshots[0].contents_str(), "3");
+//! ```
+//!
+//! Jest keys are turned into snapshot names by replacing everything that
+//! is not alphanumeric with underscores.  The trailing counter Jest appends
+//! to every key turns into insta's `-N` suffix.  Keys that end up with the
+//! same name get a `_2`, `_3`, ... suffix, and the original key is stored in
+//! the metadata so that exporting restores it.  The `cargo insta jest-import`
+//! and `cargo insta jest-export` commands are built on top of these functions.
+use std::collections::HashSet;
+use std::error::Error;
+use std::fmt;
+
+use crate::snapshot::{MetaData, Snapshot};
+
+const JEST_HEADER: &str = "// Jest Snapshot v1, https://goo.gl/fbAQLP";
+
+/// An error that occurred while parsing a Jest snapshot file.
+#[derive(Debug)]
+pub struct JestParseError {
+    line: usize,
+    message: &'static str,
+}
+
+impl JestParseError {
+    /// Returns the line (1-based) at which the error occurred.
+    pub fn line(&self) -> usize {
+        self.line
+    }
+}
+
+impl fmt::Display for JestParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} in line {}", self.message, self.line)
+    }
+}
+
+impl Error for JestParseError {}
+
+struct Parser<'a> {
+    source: &'a str,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn rest(&self) -> &'a str {
+        &self.source[self.pos..]
+    }
+
+    fn fail(&self, message: &'static str) -> JestParseError {
+        JestParseError {
+            line: self.source[..self.pos].matches('\n').count() + 1,
+            message,
+        }
+    }
+
+    fn skip_trivia(&mut self) {
+        loop {
+            let rest = self.rest();
+            let trimmed = rest.trim_start();
+            self.pos += rest.len() - trimmed.len();
+            if trimmed.starts_with("//") {
+                self.pos += trimmed.find('\n').unwrap_or(trimmed.len());
+            } else if trimmed.starts_with("/*") {
+                match trimmed.find("*/") {
+                    Some(end) => self.pos += end + 2,
+                    None => self.pos = self.source.len(),
+                }
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn expect(&mut self, token: &str, message: &'static str) -> Result<(), JestParseError> {
+        self.skip_trivia();
+        if self.rest().starts_with(token) {
+            self.pos += token.len();
+            Ok(())
+        } else {
+            Err(self.fail(message))
+        }
+    }
+
+    fn template_literal(&mut self) -> Result<String, JestParseError> {
+        self.expect("`", "expected template literal")?;
+        let mut rv = String::new();
+        let mut chars = self.rest().char_indices();
+        while let Some((idx, c)) = chars.next() {
+            match c {
+                '`' => {
+                    self.pos += idx + 1;
+                    return Ok(rv);
+                }
+                '\\' => match chars.next() {
+                    Some((_, c)) => rv.push(c),
+                    None => break,
+                },
+                c => rv.push(c),
+            }
+        }
+        self.pos = self.source.len();
+        Err(self.fail("unterminated template literal"))
+    }
+}
+
+/// Parses a Jest snapshot file into its keys and values.
+///
+/// The values are returned as they are stored by Jest with the extra
+/// line breaks around multi-line values removed.
+pub fn parse_jest_snapshots(source: &str) -> Result<Vec<(String, String)>, JestParseError> {
+    let mut parser = Parser { source, pos: 0 };
+    let mut rv = vec![];
+    loop {
+        parser.skip_trivia();
+        if parser.rest().is_empty() {
+            break;
+        }
+        parser.expect("exports[", "expected exports")?;
+        let key = parser.template_literal()?;
+        parser.expect("]", "expected closing bracket")?;
+        parser.expect("=", "expected assignment")?;
+        let value = parser.template_literal()?;
+        parser.expect(";", "expected semicolon")?;
+        let value = match value.strip_prefix('\n').and_then(|x| x.strip_suffix('\n')) {
+            Some(value) => value.to_string(),
+            None => value,
+        };
+        rv.push((key, value));
+    }
+    Ok(rv)
+}
+
+fn key_to_snapshot_name(key: &str) -> String {
+    let (base, counter) = match key.rsplit_once(' ') {
+        Some((base, counter)) if counter.parse::<usize>().is_ok() => (base, counter),
+        _ => (key, "1"),
+    };
+    let mut name = String::new();
+    for c in base.chars() {
+        if c.is_alphanumeric() {
+            name.push(c);
+        } else if !name.is_empty() && !name.ends_with('_') {
+            name.push('_');
+        }
+    }
+    let name = name.trim_end_matches('_');
+    let name = if name.is_empty() { "snapshot" } else { name };
+    if counter == "1" {
+        name.to_string()
+    } else {
+        format!("{}-{}", name, counter)
+    }
+}
+
+/// Makes a snapshot name unique by adding a counter to its base.
+fn dedup_snapshot_name(name: String, taken: &mut HashSet<String>) -> String {
+    if taken.insert(name.clone()) {
+        return name;
+    }
+    let (base, counter) = match name.rsplit_once('-') {
+        Some((base, counter)) if counter.parse::<usize>().is_ok() => (base, Some(counter)),
+        _ => (name.as_str(), None),
+    };
+    (2..)
+        .map(|idx| match counter {
+            Some(counter) => format!("{}_{}-{}", base, idx, counter),
+            None => format!("{}_{}", base, idx),
+        })
+        .find(|candidate| taken.insert(candidate.clone()))
+        .unwrap()
+}
+
+fn snapshot_name_to_key(name: &str) -> String {
+    match name.rsplit_once('-') {
+        Some((base, counter)) if counter.parse::<usize>().is_ok() => {
+            format!("{} {}", base, counter)
+        }
+        _ => format!("{} 1", name),
+    }
+}
+
+/// Converts the contents of a Jest snapshot file into insta snapshots.
+///
+/// All snapshots are placed into the given module.
+pub fn from_jest(source: &str, module_name: &str) -> Result<Vec<Snapshot>, Box<dyn Error>> {
+    let mut taken = HashSet::new();
+    Ok(parse_jest_snapshots(source)?
+        .into_iter()
+        .map(|(key, value)| {
+            let name = dedup_snapshot_name(key_to_snapshot_name(&key), &mut taken);
+            Snapshot::from_components(
+                module_name.to_string(),
+                Some(name),
+                MetaData {
+                    jest_key: Some(key),
+                    ..MetaData::default()
+                },
+                value.into(),
+            )
+        })
+        .collect())
+}
+
+fn escape_template_literal(s: &str) -> String {
+    s.replace('\\', "\\\\")
+        .replace('`', "\\`")
+        .replace("${", "\\${")
+}
+
+/// Renders insta snapshots as a Jest snapshot file.
+///
+/// Entries are sorted by key like Jest does.  Imported snapshots are
+/// exported under their original key, others under their name (or the
+/// module name if they do not have one).
+pub fn to_jest(snapshots: &[Snapshot]) -> String {
+    let mut entries: Vec<_> = snapshots
+        .iter()
+        .map(|snapshot| {
+            let key = match snapshot.metadata().jest_key() {
+                Some(key) => key.to_string(),
+                None => snapshot_name_to_key(
+                    snapshot
+                        .snapshot_name()
+                        .unwrap_or_else(|| snapshot.module_name()),
+                ),
+            };
+            let value = snapshot.contents_str();
+            let value = if value.contains('\n') {
+                format!("\n{}\n", value)
+            } else {
+                value.to_string()
+            };
+            (key, value)
+        })
+        .collect();
+    entries.sort();
+
+    let mut rv = String::from(JEST_HEADER);
+    rv.push('\n');
+    for (key, value) in entries {
+        rv.push_str(&format!(
+            "\nexports[`{}`] = `{}`;\n",
+            escape_template_literal(&key),
+            escape_template_literal(&value)
+        ));
+    }
+    rv
+}
+
+#[test]
+fn test_parse_jest_snapshots() {
+    let source = r#"// Jest Snapshot v1, https://goo.gl/fbAQLP
+
+exports[`renders \`code\` 1`] = `
+<div>
+  \${value} \\ done
+</div>
+`;
+
+exports[`sum 1`] = `3`;
+"#;
+    let snapshots = parse_jest_snapshots(source).unwrap();
+    assert_eq!(
+        snapshots,
+        vec![
+            (
+                "renders `code` 1".to_string(),
+                "<div>\n  ${value} \\ done\n</div>".to_string()
+            ),
+            ("sum 1".to_string(), "3".to_string()),
+        ]
+    );
+    assert_eq!(
+        to_jest(&from_jest(source, "test").unwrap()),
+        "// Jest Snapshot v1, https://goo.gl/fbAQLP\n\
+         \n\
+         exports[`renders \\`code\\` 1`] = `\n<div>\n  \\${value} \\\\ done\n</div>\n`;\n\
+         \n\
+         exports[`sum 1`] = `3`;\n"
+    );
+}
+
+#[test]
+fn test_parse_jest_snapshots_error() {
+    let err = parse_jest_snapshots("exports[`a 1`] = `b`;\nexports[`c 1`] = `d`\n").unwrap_err();
+    assert_eq!(err.to_string(), "expected semicolon in line 3");
+    assert!(parse_jest_snapshots("exports[`a 1`] = `b").is_err());
+}
+
+#[test]
+fn test_jest_key_names() {
+    assert_eq!(key_to_snapshot_name("Button renders 1"), "Button_renders");
+    assert_eq!(key_to_snapshot_name("Button > click 2"), "Button_click-2");
+    assert_eq!(key_to_snapshot_name("!!! 1"), "snapshot");
+    assert_eq!(snapshot_name_to_key("Button_click-2"), "Button_click 2");
+    assert_eq!(snapshot_name_to_key("Button_renders"), "Button_renders 1");
+}
+
+#[test]
+fn test_jest_key_collisions() {
+    let source = "exports[`a-b 1`] = `1`;\n\
+                  exports[`a b 1`] = `2`;\n\
+                  exports[`a b 2`] = `3`;\n\
+                  exports[`a/b 2`] = `4`;\n";
+    let snapshots = from_jest(source, "test").unwrap();
+    let names: Vec<_> = snapshots
+        .iter()
+        .map(|x| x.snapshot_name().unwrap())
+        .collect();
+    assert_eq!(names, vec!["a_b", "a_b_2", "a_b-2", "a_b_2-2"]);
+    assert_eq!(snapshots[1].metadata().jest_key(), Some("a b 1"));
+    assert_eq!(
+        to_jest(&snapshots),
+        "// Jest Snapshot v1, https://goo.gl/fbAQLP\n\
+         \n\
+         exports[`a b 1`] = `2`;\n\
+         \n\
+         exports[`a b 2`] = `3`;\n\
+         \n\
+         exports[`a-b 1`] = `1`;\n\
+         \n\
+         exports[`a/b 2`] = `4`;\n"
+    );
+}
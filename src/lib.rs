@@ -74,8 +74,11 @@
 //!
 //! This crate exports multiple macros for snapshot testing:
 //!
-//! - `assert_snapshot!` for comparing basic string snapshots.
+//! - `assert_snapshot!` for comparing basic string snapshots (or `Display`
+//!   outputs of values).
 //! - `assert_debug_snapshot!` for comparing `Debug` outputs of values.
+//! - `assert_compact_debug_snapshot!` for comparing single line `Debug` outputs
+//!   of values.
 //! - `assert_display_snapshot!` for comparing `Display` outputs of values.
 //! - `assert_csv_snapshot!` for comparing CSV serialized output of
 //!   types implementing `serde::Serialize`. (requires the `csv` feature)
@@ -87,6 +90,20 @@
 //!   types implementing `serde::Serialize`. (requires the `ron` feature)
 //! - `assert_json_snapshot!` for comparing JSON serialized output of
 //!   types implementing `serde::Serialize`.
+//! - `assert_compact_json_snapshot!` for comparing single line JSON serialized
+//!   output of types implementing `serde::Serialize`.
+//! - `assert_binary_snapshot!` for comparing binary data (eg: images or
+//!   archives).  The data is stored in its own file next to the snapshot.
+//! - `assert_dir_snapshot!` for comparing the files and contents of a directory
+//!   tree (see `DirSnapshot`).
+//! - `assert_cmd_snapshot!` for comparing the exit status and output of a
+//!   command. (requires the `cmd` feature)
+//! - `assert_html_snapshot!` for comparing pretty printed HTML. (requires the
+//!   `html` feature)
+//! - `assert_xml_snapshot!` for comparing canonical XML. (requires the `xml`
+//!   feature)
+//! - `assert_custom_snapshot!` for comparing output of types implementing
+//!   `serde::Serialize` in a custom format (see `SnapshotSerializer`).
 //!
 //! Snapshots are stored in the `snapshots` folder right next to the test file
 //! where this is used.  The name of the file is `<module>__<name>.snap` where
@@ -110,6 +127,7 @@
 //! ---
 //! expression: "vec![1, 2, 3]"
 //! source: tests/test_basic.rs
+//! format_version: 1
 //! ---
 //! [
 //!     1,
@@ -118,6 +136,13 @@
 //! ]
 //! ```
 //!
+//! The `format_version` in the header is the version of the file format.  Files
+//! written by older versions of insta are read as before and upgraded in place
+//! with `cargo insta migrate`, which only rewrites the header so that the
+//! changes can be reviewed like any other diff.  `cargo insta migrate --check`
+//! lists outdated files and fails if there are any, for instance on CI.  Files
+//! that cannot be read are reported and skipped and make the command fail.
+//!
 //! # Snapshot updating
 //!
 //! During test runs snapshots will be updated according to the `INSTA_UPDATE`
@@ -133,6 +158,12 @@
 //! - `new`: write new snapshots into `.snap.new` files
 //! - `no`: does not update snapshot files at all (just runs tests)
 //!
+//! `Settings::set_snapshot_update` and `Settings::set_force_pass` override
+//! `INSTA_UPDATE` and `INSTA_FORCE_PASS` for the assertions within the bound
+//! settings.  Tests that expect an assertion to fail should use them instead
+//! of setting the environment variables which affects all tests running in
+//! parallel.
+//!
 //! When `new` or `auto` is used as mode the `cargo-insta` command can be used
 //! to review the snapshots conveniently:
 //!
@@ -147,6 +178,33 @@
 //!
 //! For more information invoke `cargo insta --help`.
 //!
+//! For quick iterations on a single test the review can also happen right in
+//! the test run.  With `INSTA_INTERACTIVE=1` and `--nocapture` every changed
+//! file snapshot is shown and a prompt asks to accept, reject or skip it.
+//! Accepted snapshots are written in place and the assertion passes, skipped
+//! ones are stored according to `INSTA_UPDATE`.  The prompt only shows up on a
+//! terminal outside of CI:
+//!
+//! ```text
+//! $ INSTA_INTERACTIVE=1 cargo test test_something -- --nocapture
+//! ```
+//!
+//! Large suites can be reviewed in slices by tagging snapshots with
+//! `Settings::set_tags`.  The tags are stored in the snapshot metadata and
+//! `cargo insta review --tag api-contract` (or `accept` and `reject`) only work
+//! on pending snapshots with one of the given tags.  `cargo insta coverage
+//! --tag` limits the report the same way.  Tags are written whenever a
+//! snapshot is written, add them to matching snapshots with
+//! `--force-update-snapshots`.
+//!
+//! To explain to reviewers what a snapshot asserts `Settings::set_description`
+//! stores a description in the snapshot metadata.  It is shown next to the
+//! source during review and, like all metadata, not compared.
+//!
+//! Structured context (eg: the input a snapshot was created from) can be
+//! attached with `Settings::set_info` which takes any serializable value.  The
+//! description and info are shown when reviewing and when an assertion fails.
+//!
 //! # Test assertions
 //!
 //! By default the tests will fail when the snapshot assertion fails.  However
@@ -166,6 +224,18 @@
 //! $ cargo insta test --review
 //! ```
 //!
+//! Like with `assert_eq!` a message can be added to the panic of a failed
+//! assertion.  It is passed last as format string and arguments and is only
+//! formatted if the assertion fails.  Unnamed snapshots need `_` in place of
+//! the name to take a message:
+//!
+//! ```no_run
+//! # use insta::*;
+//! # let case = "empty input";
+//! assert_debug_snapshot!("vector", vec![1, 2, 3], "case: {}", case);
+//! assert_debug_snapshot!(_, vec![1, 2, 3], "case: {}", case);
+//! ```
+//!
 //! # Named snapshots
 //!
 //! All snapshot assertion functions let you leave out the snapshot name in
@@ -199,6 +269,33 @@
 //! `second_snapshot` for the second value.  Without explicit naming the
 //! snapshots would be called `something` and `something-2`.
 //!
+//! The counter is kept per test and counts the unnamed assertions in the order
+//! they run, so the names stay the same between runs as long as the assertions
+//! run in the same order.  Assertions in loops are numbered the same way.
+//!
+//! If the value is expected to be the same in every iteration wrap the loop
+//! in `allow_duplicates!` to assert a single snapshot instead.
+//!
+//! The name does not have to be a literal, any expression evaluating to a
+//! string works.  This gives table driven tests a snapshot per case:
+//!
+//! ```no_run
+//! # use insta::assert_snapshot;
+//! # struct Case { id: u32, input: &'static str }
+//! # let cases: Vec<Case> = vec![];
+//! for case in &cases {
+//!     assert_snapshot!(format!("roundtrip-{}", case.id), case.input);
+//! }
+//! ```
+//!
+//! The test name is taken from the thread the test runs on.  With
+//! `Settings::set_name_from_test_path` enabled an assertion in a helper
+//! function outside of the test's module is named after the module of the
+//! test instead of the helper's, and tests generated into a module named
+//! after a test function (eg: `rstest` cases) include that module name.
+//! Where no useful name can be inferred (eg: assertions on other threads)
+//! `Settings::set_test_name` sets it explicitly.
+//!
 //! # Test Output Control
 //!
 //! Insta by default will output quite a lot of information as tests run.  For
@@ -208,8 +305,16 @@
 //! * `diff` (default): prints the diffs
 //! * `summary`: prints only summaries (name of snapshot files etc.)
 //! * `minimal`: like `summary` but more minimal
+//! * `diagnostics`: prints failures as rustc JSON diagnostics on stdout so
+//!   that editors can show them inline (eg: by pointing rust-analyzer's
+//!   `checkOnSave` override command at `cargo test`)
 //! * `none`: insta will not output any extra information
 //!
+//! Failed assertions name the snapshot file, where the new snapshot was stored
+//! and the command to review it with.  `INSTA_REVIEW_HINT` replaces the
+//! suggested `cargo insta review` with another command (eg: when building with
+//! a different build system) and `INSTA_REVIEW_HINT=0` disables these hints.
+//!
 //! # Redactions
 //!
 //! **Feature:** `redactions`
@@ -233,9 +338,17 @@
 //! - `[start:]`: selects all items starting with `start`
 //! - `[start:end]`: selects all items from `start` to `end` (end excluding,
 //!   supports negative indexing).
+//! - `.{/regex/}`: selects all keys matching the regular expression (eg:
+//!   `.**.{/_at$/}` selects all keys ending in `_at` at any depth)
 //! - `.*`: selects all keys on that depth
+//! - `.{keys}`: selects the keys of a map instead of the values (eg:
+//!   `.requests.{keys}` to redact generated ids used as keys).  Can only be
+//!   used at the end of a selector.
 //! - `.**`: performs a deep match (zero or more items).  Can only be used once.
 //!
+//! Multiple selectors can be separated by commas to apply the same redaction
+//! to all of them (eg: `.created_at, .updated_at, .deleted_at`).
+//!
 //! Example usage:
 //!
 //! ```no_run
@@ -289,6 +402,102 @@
 //! # }
 //! ```
 //!
+//! Redactions can be limited to some values with
+//! [`conditional_redaction`](fn.conditional_redaction.html) or
+//! `Settings::add_conditional_redaction`, for instance to only redact optional
+//! values that are set and keep `null` visible.
+//!
+//! Values that are just noise can also be left out of the snapshot entirely
+//! with `Settings::add_removal` which matches map entries, struct fields and
+//! sequence items like any other redaction selector.
+//!
+//! Volatile identifiers can be replaced with numbered placeholders using
+//! [`placeholder_redaction`](fn.placeholder_redaction.html).  Equal values
+//! get the same placeholder so references between them stay visible:
+//!
+//! ```rust
+//! # #[cfg(feature = "redactions")] {
+//! # use insta::*;
+//! # let mut settings = Settings::new();
+//! settings.add_redaction(".users[].id", placeholder_redaction("id"));
+//! settings.add_redaction(".posts[].author_id", placeholder_redaction("id"));
+//! # }
+//! ```
+//!
+//! The [`matchers`](matchers/index.html) module has redactions for common
+//! volatile values like UUIDs and timestamps that also assert that the value
+//! has the expected shape, so a redaction does not hide a regression.
+//!
+//! Redactions that should apply to every serialized snapshot of a workspace
+//! can be declared in the `[redactions]` section of the
+//! [configuration file](#configuration-file) without any code changes.
+//!
+//! Redactions that are shared by many tests can be registered once as a
+//! named set with [`register_redaction_set`](fn.register_redaction_set.html)
+//! or in the [configuration file](#configuration-file).  They are then
+//! applied with `Settings::apply_redaction_set` or the `redaction_set` key of
+//! `with_settings!`:
+//!
+//! ```rust
+//! # #[cfg(feature = "redactions")] {
+//! # use insta::*;
+//! register_redaction_set("timestamps", vec![
+//!     (".created_at", "[timestamp]".into()),
+//!     (".**.updated_at", "[timestamp]".into()),
+//! ]);
+//! with_settings!({redaction_set => "timestamps"}, {
+//!     // ...
+//! });
+//! # }
+//! ```
+//!
+//! # Content Model
+//!
+//! Before serialization all values are converted into a
+//! [`Content`](enum.Content.html) tree.  This is the structure that map
+//! sorting and redactions operate on and it can also be used directly to
+//! write custom normalizers.  `Content` can be walked mutably (optionally
+//! with the [`ContentPath`](struct.ContentPath.html) of every node), mapped
+//! bottom up or folded:
+//!
+//! ```rust
+//! # use insta::Content;
+//! fn strip_floats(content: Content) -> Content {
+//!     content.map(&mut |node| match node {
+//!         Content::F32(_) | Content::F64(_) => Content::from("[float]"),
+//!         other => other,
+//!     })
+//! }
+//! ```
+//!
+//! # Filters
+//!
+//! **Feature:** `filters`
+//!
+//! Redactions only work for serialized snapshots.  To scrub values like
+//! temporary paths, durations or pointers from any snapshot, including the
+//! ones of `assert_snapshot!` and `assert_debug_snapshot!`, filters can be
+//! registered on the settings.  A filter is a regular expression whose matches
+//! are replaced in the snapshot contents before they are compared:
+//!
+//! ```rust
+//! # #[cfg(feature = "filters")] {
+//! # use insta::*;
+//! let mut settings = Settings::clone_current();
+//! settings.add_filter(r"/tmp/\.tmp\w+", "[TEMP_DIR]");
+//! settings.add_filter(r"\b\d+ms\b", "[duration]");
+//! settings.bind(|| {
+//!     assert_snapshot!("done in 125ms", @"done in [duration]");
+//! });
+//! # }
+//! ```
+//!
+//! The replacement can refer to capture groups with `$1` or `${name}`.
+//!
+//! Instead of replacing them, volatile fragments can also be matched loosely by
+//! enabling `Settings::set_wildcards`.  Then `[..]` in a stored snapshot matches
+//! any text within a line and a line of only `...` matches any number of lines.
+//!
 //! # Globbing
 //!
 //! **Feature:** `glob`
@@ -339,15 +548,83 @@
 //! After the initial test failure you can run `cargo insta review` to
 //! accept the change.  The file will then be updated automatically.
 //!
+//! All assertion macros support inline snapshots, including the serialized
+//! formats.  With redactions the snapshot follows the redactions:
+//!
+//! ```no_run
+//! # #[cfg(feature = "redactions")] {
+//! # use insta::*;
+//! # let user = ();
+//! assert_json_snapshot!(user, {
+//!     ".id" => "[id]",
+//! }, @"");
+//! # }
+//! ```
+//!
+//! # Golden Files
+//!
+//! Existing golden file suites can keep their files with
+//! `assert_matches_file!`.  It compares a value against the contents of a file
+//! at a path relative to the crate instead of a `.snap` file and the file holds
+//! nothing but the value.  The path can be computed, so suites iterating over
+//! their cases keep working:
+//!
+//! ```no_run
+//! # use insta::assert_matches_file;
+//! # let cases: Vec<(&str, String)> = vec![];
+//! for (name, output) in &cases {
+//!     assert_matches_file!(output, format!("tests/golden/{}.txt", name));
+//! }
+//! ```
+//!
+//! Mismatches store the new value in a pending file next to the golden file
+//! (or update it in place, depending on `INSTA_UPDATE`) which `cargo insta
+//! review` offers to accept into the golden file.
+//!
+//! # Doctests
+//!
+//! Snapshot assertions also work in doctests.  Since rustdoc compiles every
+//! doctest as a separate crate, insta detects this and names snapshots after
+//! the file containing the doctest instead of the module (eg: snapshots of
+//! doctests in `src/lib.rs` are stored as `snapshots/doctest__lib__*.snap`).
+//! Unnamed snapshots are named after the line of the assertion, so giving
+//! them an explicit name is recommended.  Inline snapshots are compared as
+//! usual but cannot be updated by `cargo insta review` as rustdoc does not
+//! report reliable line numbers:
+//!
+//! ```
+//! insta::assert_snapshot!(format!("{:?}", (1, "two")), @r###"(1, "two")"###);
+//! ```
+//!
 //! # Features
 //!
 //! The following features exist:
 //!
 //! * `csv`: enables CSV support (`assert_csv_snapshot!`)
 //! * `ron`: enables RON support (`assert_ron_snapshot!`)
-//! * `toml`: enables TOML support (`assert_toml_snapshot!`)
+//! * `toml`: enables TOML support (`assert_toml_snapshot!`) and the `insta.toml`
+//!   configuration file
 //! * `redactions`: enables support for redactions
+//! * `filters`: enables regex filters for the contents of all snapshots
 //! * `glob`: enables support for globbing (`glob!`)
+//! * `proptest`: enables snapshots of property test counterexamples
+//!   (`assert_counterexample_snapshot!`)
+//! * `tracing`: enables capturing `tracing` output for snapshots
+//!   (`capture_tracing`)
+//! * `http`: enables snapshots of `http::Response`s and other responses
+//!   implementing `HttpResponse` (`assert_response_snapshot!`)
+//! * `cmd`: enables snapshots of the output of commands (`assert_cmd_snapshot!`)
+//! * `html`: enables snapshots of normalized HTML (`assert_html_snapshot!`)
+//! * `xml`: enables snapshots of canonical XML (`assert_xml_snapshot!`)
+//! * `prost`: enables snapshots of protobuf messages (`assert_protobuf_snapshot!`)
+//! * `sqlx`: enables `rows_to_content` to snapshot `sqlx` query results
+//! * `image`: enables snapshots of decoded images (`assert_image_snapshot!`)
+//!   that are compared pixel by pixel with a configurable tolerance
+//! * `compression`: enables `Settings::set_compression_threshold` to store large
+//!   snapshot files compressed
+//! * `chrono`, `time`: enable `Settings::set_redact_timestamps` for datetimes
+//!   serialized by these crates
+//! * `macros`: enables the `#[insta::settings(...)]` attribute for tests
 //! * `colors`: enables color output (enabled by default)
 //!
 //! # Settings
@@ -355,6 +632,79 @@
 //! There are some settings that can be changed on a per-thread (and thus
 //! per-test) basis.  For more information see [settings](struct.Settings.html).
 //!
+//! With the `macros` feature settings can also be bound for a whole test
+//! with an attribute that takes the same keys as `with_settings!`:
+//!
+//! ```no_run
+//! # #[cfg(feature = "macros")]
+//! #[test]
+//! #[insta::settings(sort_maps = true, snapshot_suffix = "v2")]
+//! fn test_something() {
+//!     insta::assert_yaml_snapshot!(vec![1, 2, 3]);
+//! }
+//! ```
+//!
+//! # Configuration File
+//!
+//! Workspace wide defaults can be placed in an `insta.toml` (or
+//! `.config/insta.toml`) next to the workspace `Cargo.toml`.  The file is read
+//! once per test run (this requires the `toml` feature) and by `cargo insta`.
+//! Environment variables override the `behavior` section, settings bound in a
+//! test override the `snapshots` section and the redactions, and command line
+//! switches override the `test` and `review` sections:
+//!
+//! ```toml
+//! [behavior]
+//! update = "no"          # like INSTA_UPDATE
+//! output = "summary"     # like INSTA_OUTPUT
+//! force_pass = false     # like INSTA_FORCE_PASS
+//! force_update = false   # like INSTA_FORCE_UPDATE_SNAPSHOTS
+//!
+//! [snapshots]
+//! path = "snapshots"
+//! path_base = "source-file"  # or "crate" or "workspace"
+//! extension = "snap"  # eg: "snap.json" for syntax highlighting
+//! prepend_module = true
+//! name_from_test_path = false  # name snapshots of helpers after the test
+//! sort_maps = true
+//! omit_expression = false
+//! require_full_match = false
+//! allow_empty = true
+//! wildcards = false  # `[..]` and `...` in stored snapshots match anything
+//! normalize_line_endings = true  # compare `\r\n` like `\n`
+//! trailing_whitespace = "preserve"  # or "normalize" to ignore it at line ends
+//! final_newline = "normalize"  # or "preserve" to compare it
+//! json_indent = 2
+//! json_array_width = 80  # join short arrays of scalars onto one line
+//! image_threshold = 0.1  # tolerated color difference of image pixels
+//! image_max_diff_ratio = 0.01
+//! compression_threshold = 1048576  # store larger snapshots compressed
+//!
+//! [redactions]           # applied to all serialized snapshots
+//! ".id" = "[id]"
+//!
+//! [filters]
+//! '\d+ms' = "[duration]"
+//!
+//! [redaction_sets.timestamps] # applied with `Settings::apply_redaction_set`
+//! ".created_at" = "[timestamp]"
+//!
+//! [test]                 # defaults for `cargo insta test`
+//! review = true
+//! accept_unseen = false
+//! keep_pending = false
+//! force_pass = true
+//!
+//! [review]               # defaults for `cargo insta review` and friends
+//! extensions = ["snap"]
+//! ```
+//!
+//! An invalid file (syntax errors, unknown keys, invalid selectors or regular
+//! expressions) fails every assertion with the error.  Without the `toml` feature
+//! the file is ignored and keys that need a feature insta was built without
+//! (`redactions`, `filters`, `image_*` and `compression_threshold`) are skipped,
+//! both with a warning.
+//!
 //! # Legacy Snapshot Formats
 //!
 //! With insta 0.11 the snapshot format was improved for inline snapshots.  The
@@ -382,26 +732,129 @@
 //! rg --files -lg '*.snap' "$(pwd)" | grep -vFf "$INSTA_SNAPSHOT_REFERENCES_FILE" | xargs rm
 //! rm -f $INSTA_SNAPSHOT_REFERENCES_FILE
 //! ```
+//!
+//! For a more detailed picture `INSTA_SNAPSHOT_USAGE_FILE` records which
+//! test used which snapshot as one JSON object per line.  The
+//! `cargo insta coverage` command runs the tests with it and reports the
+//! snapshots per test, the tests per snapshot and unreferenced snapshot files
+//! as JSON.  This can for instance be used to only re-run the tests affected
+//! by a changed snapshot.
+//!
+//! # Other Build Systems
+//!
+//! Snapshot files are located relative to the source file of the test.  Insta
+//! takes the path reported by `file!()` and joins it to the workspace root
+//! which it asks `cargo metadata` for.  Build systems such as Bazel, or
+//! remote execution with path sandboxing, often run tests without cargo or
+//! compile sources from a different path than the one in the repository.
+//! Both steps can be controlled:
+//!
+//! * the workspace root can be set with `INSTA_WORKSPACE_ROOT` or
+//!   [`Settings::set_workspace_root`](struct.Settings.html#method.set_workspace_root)
+//!   in which case cargo is not invoked.
+//! * prefixes of the `file!()` paths can be rewritten with
+//!   [`Settings::add_source_path_remap`](struct.Settings.html#method.add_source_path_remap).
+//!   The remapped path is also what is stored as source in the snapshot.
+//!
+//! ```no_run
+//! # use insta::Settings;
+//! let mut settings = Settings::clone_current();
+//! settings.set_workspace_root(std::env::var("BUILD_WORKSPACE_DIRECTORY").unwrap());
+//! settings.add_source_path_remap("external/my_crate", "my_crate");
+//! settings.bind_to_thread();
+//! ```
 #[macro_use]
 mod macros;
+mod ansi;
+mod config;
 mod content;
-mod runtime;
+mod diff;
+mod dir_snapshot;
+pub mod jest;
+pub mod runtime;
 mod serialization;
 mod settings;
 mod snapshot;
+mod to_snapshot;
 mod utils;
 
 #[cfg(feature = "redactions")]
 mod redaction;
 
+#[cfg(feature = "redactions")]
+pub mod matchers;
+
+#[cfg(feature = "filters")]
+mod filters;
+
 #[cfg(feature = "glob")]
 mod glob;
 
+#[cfg(feature = "proptest")]
+mod property;
+
+#[cfg(feature = "tracing")]
+mod log_capture;
+
+#[cfg(unix)]
+mod capture;
+
+#[cfg(feature = "http")]
+mod http_response;
+
+#[cfg(feature = "cmd")]
+mod cmd;
+
+#[cfg(any(feature = "html", feature = "xml"))]
+mod markup;
+
+#[cfg(feature = "prost")]
+mod protobuf;
+
+#[cfg(feature = "sqlx")]
+mod database;
+
+#[cfg(any(feature = "chrono", feature = "time"))]
+mod timestamp;
+
+#[cfg(feature = "image")]
+mod image_snapshot;
+
+#[cfg(feature = "compression")]
+mod compression;
+
 #[cfg(test)]
 mod test;
 
-pub use crate::settings::Settings;
+pub use crate::ansi::{ansi_to_tokens, strip_ansi_escapes};
+pub use crate::content::{Content, ContentPath};
+pub use crate::dir_snapshot::DirSnapshot;
+pub use crate::runtime::DiffRenderer;
+pub use crate::serialization::{documents, Documents, SnapshotSerializer};
+pub use crate::settings::{
+    AnsiEscapes, EnumRepr, Settings, SettingsBindDropGuard, SnapshotPathBase, SnapshotUpdate,
+    Whitespace,
+};
 pub use crate::snapshot::{MetaData, Snapshot};
+pub use crate::to_snapshot::ToSnapshot;
+
+#[cfg(feature = "csv")]
+pub use crate::settings::CsvQuoteStyle;
+
+#[cfg(feature = "tracing")]
+pub use crate::log_capture::capture_tracing;
+
+#[cfg(unix)]
+pub use crate::capture::{capture_output, CapturedOutput};
+
+#[cfg(feature = "sqlx")]
+pub use crate::database::rows_to_content;
+
+#[cfg(feature = "http")]
+pub use crate::http_response::HttpResponse;
+
+#[cfg(feature = "macros")]
+pub use insta_macros::settings;
 
 /// Exposes some library internals.
 ///
@@ -409,35 +862,66 @@ pub use crate::snapshot::{MetaData, Snapshot};
 /// are exposed for documentation primarily.
 pub mod internals {
     pub use crate::content::Content;
+    pub use crate::content::ContentPath;
+    #[cfg(feature = "filters")]
+    pub use crate::filters::Filters;
     pub use crate::runtime::AutoName;
     pub use crate::snapshot::{MetaData, SnapshotContents};
     #[cfg(feature = "redactions")]
-    pub use crate::{
-        redaction::{ContentPath, Redaction},
-        settings::Redactions,
-    };
+    pub use crate::{redaction::Redaction, settings::Redactions};
 }
 
 // exported for cargo-insta only
 #[doc(hidden)]
 pub use crate::{
-    runtime::print_snapshot_diff, snapshot::PendingInlineSnapshot, snapshot::SnapshotContents,
+    config::ToolConfig, runtime::print_snapshot_diff, runtime::shorten_snapshot_file_stem,
+    snapshot::PendingInlineSnapshot, snapshot::SnapshotContents, snapshot::FORMAT_VERSION,
 };
 
 // useful for redactions
 #[cfg(feature = "redactions")]
-pub use crate::redaction::dynamic_redaction;
+pub use crate::redaction::{
+    conditional_redaction, dynamic_redaction, embedded_json, placeholder_redaction,
+    rounded_redaction, sorted_redaction,
+};
+#[cfg(feature = "redactions")]
+pub use crate::{redaction::SelectorParseError, settings::register_redaction_set};
 
 // these are here to make the macros work
 #[doc(hidden)]
 pub mod _macro_support {
     pub use crate::content::Content;
-    pub use crate::runtime::{assert_snapshot, get_cargo_workspace, AutoName, ReferenceValue};
+    pub use crate::runtime::{
+        assert_binary_snapshot, assert_golden_file, assert_macro_snapshot, get_cargo_workspace,
+        resolve_source_file, with_allow_duplicates, AutoName, ReferenceValue,
+    };
     pub use crate::serialization::{serialize_value, SerializationFormat, SnapshotLocation};
+    pub use crate::to_snapshot::{RenderDisplay, RenderToSnapshot, SnapshotValue};
 
     #[cfg(feature = "glob")]
     pub use crate::glob::glob_exec;
 
+    #[cfg(feature = "proptest")]
+    pub use crate::property::find_counterexample;
+
+    #[cfg(feature = "http")]
+    pub use crate::http_response::response_to_content;
+
+    #[cfg(feature = "cmd")]
+    pub use crate::cmd::{command_to_content, CommandOutput};
+
+    #[cfg(feature = "html")]
+    pub use crate::markup::html_to_text;
+
+    #[cfg(feature = "xml")]
+    pub use crate::markup::{xml_to_content, XmlOutput};
+
+    #[cfg(feature = "prost")]
+    pub use crate::protobuf::protobuf_to_text;
+
+    #[cfg(feature = "image")]
+    pub use crate::image_snapshot::{assert_image_snapshot, image_to_text};
+
     #[cfg(feature = "redactions")]
     pub use crate::{
         redaction::Redaction, redaction::Selector, serialization::serialize_value_redacted,
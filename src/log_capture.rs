@@ -0,0 +1,197 @@
+use std::fmt::{self, Write};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Level, Metadata, Subscriber};
+
+/// Collects fields as `key=value` pairs, the `message` field is kept apart.
+#[derive(Default)]
+struct FieldCollector {
+    message: Option<String>,
+    fields: String,
+}
+
+impl Visit for FieldCollector {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.message = Some(value.to_string());
+        } else {
+            self.record_debug(field, &value);
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == "message" {
+            self.message = Some(format!("{:?}", value));
+        } else {
+            if !self.fields.is_empty() {
+                self.fields.push(' ');
+            }
+            write!(self.fields, "{}={:?}", field.name(), value).unwrap();
+        }
+    }
+}
+
+struct SpanData {
+    name: &'static str,
+    fields: String,
+    refs: usize,
+}
+
+#[derive(Default)]
+struct State {
+    next_id: AtomicU64,
+    spans: Mutex<Vec<(u64, SpanData)>>,
+    stack: Mutex<Vec<u64>>,
+    output: Mutex<String>,
+}
+
+/// A subscriber that renders events into a string.
+///
+/// Timestamps and thread information are never recorded and spans are
+/// rendered by name and fields instead of their ids so the output is
+/// stable across runs.
+struct CollectingSubscriber {
+    max_level: Level,
+    state: Arc<State>,
+}
+
+impl CollectingSubscriber {
+    fn with_span<R, F: FnOnce(&mut SpanData) -> R>(&self, id: &Id, f: F) -> Option<R> {
+        let mut spans = self.state.spans.lock().unwrap();
+        spans
+            .iter_mut()
+            .find(|(span_id, _)| *span_id == id.into_u64())
+            .map(|(_, data)| f(data))
+    }
+}
+
+impl Subscriber for CollectingSubscriber {
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        *metadata.level() <= self.max_level
+    }
+
+    fn new_span(&self, span: &Attributes<'_>) -> Id {
+        let mut collector = FieldCollector::default();
+        span.record(&mut collector);
+        let id = self.state.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+        self.state.spans.lock().unwrap().push((
+            id,
+            SpanData {
+                name: span.metadata().name(),
+                fields: collector.fields,
+                refs: 1,
+            },
+        ));
+        Id::from_u64(id)
+    }
+
+    fn record(&self, span: &Id, values: &Record<'_>) {
+        let mut collector = FieldCollector::default();
+        values.record(&mut collector);
+        self.with_span(span, |data| {
+            if !collector.fields.is_empty() {
+                if !data.fields.is_empty() {
+                    data.fields.push(' ');
+                }
+                data.fields.push_str(&collector.fields);
+            }
+        });
+    }
+
+    fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+
+    fn event(&self, event: &Event<'_>) {
+        let mut collector = FieldCollector::default();
+        event.record(&mut collector);
+
+        let metadata = event.metadata();
+        let mut line = format!("{:>5} {}:", metadata.level(), metadata.target());
+        {
+            let spans = self.state.spans.lock().unwrap();
+            for id in self.state.stack.lock().unwrap().iter() {
+                if let Some((_, data)) = spans.iter().find(|(span_id, _)| span_id == id) {
+                    if data.fields.is_empty() {
+                        write!(line, " {}:", data.name).unwrap();
+                    } else {
+                        write!(line, " {}{{{}}}:", data.name, data.fields).unwrap();
+                    }
+                }
+            }
+        }
+        if let Some(message) = collector.message {
+            write!(line, " {}", message).unwrap();
+        }
+        if !collector.fields.is_empty() {
+            write!(line, " {}", collector.fields).unwrap();
+        }
+
+        let mut output = self.state.output.lock().unwrap();
+        output.push_str(&line);
+        output.push('\n');
+    }
+
+    fn enter(&self, span: &Id) {
+        self.state.stack.lock().unwrap().push(span.into_u64());
+    }
+
+    fn exit(&self, span: &Id) {
+        let mut stack = self.state.stack.lock().unwrap();
+        if let Some(pos) = stack.iter().rposition(|x| *x == span.into_u64()) {
+            stack.remove(pos);
+        }
+    }
+
+    fn clone_span(&self, id: &Id) -> Id {
+        self.with_span(id, |data| data.refs += 1);
+        id.clone()
+    }
+
+    fn try_close(&self, id: Id) -> bool {
+        let mut spans = self.state.spans.lock().unwrap();
+        if let Some(pos) = spans
+            .iter()
+            .position(|(span_id, _)| *span_id == id.into_u64())
+        {
+            spans[pos].1.refs -= 1;
+            if spans[pos].1.refs == 0 {
+                spans.remove(pos);
+                return true;
+            }
+        }
+        false
+    }
+}
+
+/// Captures `tracing` output of a closure for snapshotting.
+///
+/// A collecting subscriber that records all events up to `max_level` is
+/// installed for the duration of the closure.  Every event is rendered as
+/// one line with the level, target, the spans it happened in and its
+/// fields.  Timestamps are never included and spans are rendered by name
+/// so the output can be passed straight to `assert_snapshot!`:
+///
+/// ```rust
+/// # use insta::{assert_snapshot, capture_tracing};
+/// let output = capture_tracing(tracing::Level::INFO, || {
+///     let span = tracing::info_span!("request", id = 42);
+///     let _guard = span.enter();
+///     tracing::info!(target: "app", status = 200, "handled");
+///     tracing::debug!(target: "app", "not captured");
+/// });
+/// assert_snapshot!(output, @" INFO app: request{id=42}: handled status=200");
+/// ```
+///
+/// **Feature:** `tracing`
+pub fn capture_tracing<F: FnOnce()>(max_level: Level, f: F) -> String {
+    let state = Arc::new(State::default());
+    let subscriber = CollectingSubscriber {
+        max_level,
+        state: state.clone(),
+    };
+    tracing::subscriber::with_default(subscriber, f);
+    let output = state.output.lock().unwrap();
+    output.clone()
+}
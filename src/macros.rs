@@ -9,9 +9,14 @@
 /// Example:
 ///
 /// ```no_run,ignore
-/// assert_csv_snapshot!(vec[1, 2, 3]);
+/// assert_csv_snapshot!(vec![record_1, record_2]);
 /// ```
 ///
+/// Sequences of records are written as one CSV record per item with the
+/// header of the first one, sequences of scalars as a single record.  The
+/// delimiter, quoting and header handling can be changed with the `csv_*`
+/// settings (eg: `Settings::set_csv_delimiter`).
+///
 /// The third argument to the macro can be an object expression for redaction.
 /// It's in the form `{ selector => replacement }`.  For more information
 /// about redactions see [redactions](index.html#redactions).
@@ -21,23 +26,26 @@
 #[cfg(feature = "csv")]
 #[macro_export]
 macro_rules! assert_csv_snapshot {
-    ($value:expr, @$snapshot:literal) => {{
-        $crate::_assert_serialized_snapshot!($value, Csv, @$snapshot);
+    (_, $value:expr, $($fmt:tt)+) => {{
+        $crate::_assert_serialized_snapshot!(($($fmt)+) $crate::_macro_support::AutoName, $value, Csv);
     }};
-    ($value:expr, {$($k:expr => $v:expr),*$(,)?}, @$snapshot:literal) => {{
-        $crate::_assert_serialized_snapshot!($value, {$($k => $v),*}, Csv, @$snapshot);
+    ($value:expr, @$snapshot:literal $(, $($fmt:tt)+)?) => {{
+        $crate::_assert_serialized_snapshot!(($($($fmt)+)?) $value, Csv, @$snapshot);
     }};
-    ($value:expr, {$($k:expr => $v:expr),*$(,)?}) => {{
-        $crate::_assert_serialized_snapshot!($crate::_macro_support::AutoName, $value, {$($k => $v),*}, Csv);
+    ($value:expr, {$($k:expr => $v:expr),*$(,)?}, @$snapshot:literal $(, $($fmt:tt)+)?) => {{
+        $crate::_assert_serialized_snapshot!(($($($fmt)+)?) $value, {$($k => $v),*}, Csv, @$snapshot);
     }};
-    ($name:expr, $value:expr) => {{
-        $crate::_assert_serialized_snapshot!(Some($name), $value, Csv);
+    ($value:expr, {$($k:expr => $v:expr),*$(,)?} $(, $($fmt:tt)+)?) => {{
+        $crate::_assert_serialized_snapshot!(($($($fmt)+)?) $crate::_macro_support::AutoName, $value, {$($k => $v),*}, Csv);
     }};
-    ($name:expr, $value:expr, {$($k:expr => $v:expr),*$(,)?}) => {{
-        $crate::_assert_serialized_snapshot!(Some($name), $value, {$($k => $v),*}, Csv);
+    ($name:expr, $value:expr, {$($k:expr => $v:expr),*$(,)?} $(, $($fmt:tt)+)?) => {{
+        $crate::_assert_serialized_snapshot!(($($($fmt)+)?) Some($name), $value, {$($k => $v),*}, Csv);
+    }};
+    ($name:expr, $value:expr $(, $($fmt:tt)+)?) => {{
+        $crate::_assert_serialized_snapshot!(($($($fmt)+)?) Some($name), $value, Csv);
     }};
     ($value:expr) => {{
-        $crate::_assert_serialized_snapshot!($crate::_macro_support::AutoName, $value, Csv);
+        $crate::_assert_serialized_snapshot!(() $crate::_macro_support::AutoName, $value, Csv);
     }};
 }
 
@@ -65,23 +73,413 @@ macro_rules! assert_csv_snapshot {
 #[cfg(feature = "toml")]
 #[macro_export]
 macro_rules! assert_toml_snapshot {
+    (_, $value:expr, $($fmt:tt)+) => {{
+        $crate::_assert_serialized_snapshot!(($($fmt)+) $crate::_macro_support::AutoName, $value, Toml);
+    }};
+    ($value:expr, @$snapshot:literal $(, $($fmt:tt)+)?) => {{
+        $crate::_assert_serialized_snapshot!(($($($fmt)+)?) $value, Toml, @$snapshot);
+    }};
+    ($value:expr, {$($k:expr => $v:expr),*$(,)?}, @$snapshot:literal $(, $($fmt:tt)+)?) => {{
+        $crate::_assert_serialized_snapshot!(($($($fmt)+)?) $value, {$($k => $v),*}, Toml, @$snapshot);
+    }};
+    ($value:expr, {$($k:expr => $v:expr),*$(,)?} $(, $($fmt:tt)+)?) => {{
+        $crate::_assert_serialized_snapshot!(($($($fmt)+)?) $crate::_macro_support::AutoName, $value, {$($k => $v),*}, Toml);
+    }};
+    ($name:expr, $value:expr, {$($k:expr => $v:expr),*$(,)?} $(, $($fmt:tt)+)?) => {{
+        $crate::_assert_serialized_snapshot!(($($($fmt)+)?) Some($name), $value, {$($k => $v),*}, Toml);
+    }};
+    ($name:expr, $value:expr $(, $($fmt:tt)+)?) => {{
+        $crate::_assert_serialized_snapshot!(($($($fmt)+)?) Some($name), $value, Toml);
+    }};
+    ($value:expr) => {{
+        $crate::_assert_serialized_snapshot!(() $crate::_macro_support::AutoName, $value, Toml);
+    }};
+}
+
+/// Asserts a protobuf message snapshot in text format.
+///
+/// **Feature:** `prost`
+///
+/// The message needs to implement `prost_reflect::ReflectMessage` which
+/// types generated with `prost-reflect-build` do.  Unlike the debug
+/// representation of generated types the text format only depends on the
+/// schema, so it stays stable across prost versions.
+///
+/// ```ignore
+/// assert_protobuf_snapshot!(user);
+/// assert_protobuf_snapshot!(user, @"name: \"Jane\"");
+/// ```
+#[cfg(feature = "prost")]
+#[macro_export]
+macro_rules! assert_protobuf_snapshot {
+    ($value:expr, @$snapshot:literal) => {{
+        let value = $crate::_macro_support::protobuf_to_text(&$value);
+        $crate::assert_snapshot!(value, stringify!($value), @$snapshot);
+    }};
+    ($name:expr, $value:expr) => {{
+        let value = $crate::_macro_support::protobuf_to_text(&$value);
+        $crate::assert_snapshot!(Some($name), value, stringify!($value));
+    }};
+    ($value:expr) => {{
+        let value = $crate::_macro_support::protobuf_to_text(&$value);
+        $crate::assert_snapshot!($crate::_macro_support::AutoName, value, stringify!($value));
+    }};
+}
+
+/// Asserts a snapshot of normalized HTML.
+///
+/// **Feature:** `html`
+///
+/// The value can be anything that implements `AsRef<str>`.  It is parsed
+/// and pretty printed before it is compared so that whitespace and the
+/// order of attributes in templates do not show up in diffs: every element
+/// goes on its own line indented by its depth, whitespace in text is
+/// collapsed and attributes are sorted.  The parser is forgiving and closes
+/// unclosed elements implicitly.
+///
+/// ```no_run
+/// # use insta::*;
+/// assert_html_snapshot!("<ul class='nav'  id=main><li>Home<li>About</ul>", @r###"
+/// <ul class="nav" id="main">
+///   <li>Home</li>
+///   <li>About</li>
+/// </ul>
+/// "###);
+/// ```
+#[cfg(feature = "html")]
+#[macro_export]
+macro_rules! assert_html_snapshot {
+    ($value:expr, @$snapshot:literal) => {{
+        let value = $crate::_macro_support::html_to_text(AsRef::<str>::as_ref(&$value));
+        $crate::assert_snapshot!(value, stringify!($value), @$snapshot);
+    }};
+    ($name:expr, $value:expr) => {{
+        let value = $crate::_macro_support::html_to_text(AsRef::<str>::as_ref(&$value));
+        $crate::assert_snapshot!(Some($name), value, stringify!($value));
+    }};
+    ($value:expr) => {{
+        let value = $crate::_macro_support::html_to_text(AsRef::<str>::as_ref(&$value));
+        $crate::assert_snapshot!($crate::_macro_support::AutoName, value, stringify!($value));
+    }};
+}
+
+/// Asserts a snapshot of canonical XML.
+///
+/// **Feature:** `xml`
+///
+/// The value can be anything that implements `AsRef<str>`.  It is parsed
+/// and written back with consistent indentation and attributes sorted by
+/// name, so that the formatting of the payload does not show up in diffs:
+///
+/// ```no_run
+/// # use insta::*;
+/// assert_xml_snapshot!(r#"<entry id='1' lang="en"><title>Hello</title></entry>"#, @r###"
+/// <entry id="1" lang="en">
+///   <title>Hello</title>
+/// </entry>
+/// "###);
+/// ```
+///
+/// Redactions use the regular selectors which walk the elements by name.
+/// Attributes are selected with an `@` prefix and repeated elements are
+/// all selected:
+///
+#[cfg_attr(feature = "redactions", doc = " ```no_run")]
+#[cfg_attr(not(feature = "redactions"), doc = " ```ignore")]
+/// # use insta::*;
+/// # let feed = "";
+/// assert_xml_snapshot!(feed, {
+///     ".feed.entry.updated" => "[timestamp]",
+///     ".feed.entry[\"@id\"]" => "[id]",
+/// });
+/// ```
+#[cfg(feature = "xml")]
+#[macro_export]
+macro_rules! assert_xml_snapshot {
+    ($value:expr, @$snapshot:literal) => {{
+        let content = $crate::_macro_support::xml_to_content(AsRef::<str>::as_ref(&$value));
+        let value = $crate::_macro_support::serialize_value(
+            &content,
+            $crate::_macro_support::SerializationFormat::Custom(&$crate::_macro_support::XmlOutput),
+            $crate::_macro_support::SnapshotLocation::Inline
+        );
+        $crate::assert_snapshot!(value, stringify!($value), @$snapshot);
+    }};
+    ($value:expr, {$($k:expr => $v:expr),*$(,)?}, @$snapshot:literal) => {{
+        let content = $crate::_macro_support::xml_to_content(AsRef::<str>::as_ref(&$value));
+        let (vec, value) = $crate::_prepare_snapshot_for_redaction!(
+            content, {$($k => $v),*}, ($crate::_macro_support::XmlOutput), Inline
+        );
+        $crate::assert_snapshot!(value, stringify!($value), @$snapshot);
+    }};
+    ($value:expr, {$($k:expr => $v:expr),*$(,)?}) => {{
+        let content = $crate::_macro_support::xml_to_content(AsRef::<str>::as_ref(&$value));
+        let (vec, value) = $crate::_prepare_snapshot_for_redaction!(
+            content, {$($k => $v),*}, ($crate::_macro_support::XmlOutput), File
+        );
+        $crate::assert_snapshot!($crate::_macro_support::AutoName, value, stringify!($value));
+    }};
+    ($name:expr, $value:expr) => {{
+        let content = $crate::_macro_support::xml_to_content(AsRef::<str>::as_ref(&$value));
+        let value = $crate::_macro_support::serialize_value(
+            &content,
+            $crate::_macro_support::SerializationFormat::Custom(&$crate::_macro_support::XmlOutput),
+            $crate::_macro_support::SnapshotLocation::File
+        );
+        $crate::assert_snapshot!(Some($name), value, stringify!($value));
+    }};
+    ($name:expr, $value:expr, {$($k:expr => $v:expr),*$(,)?}) => {{
+        let content = $crate::_macro_support::xml_to_content(AsRef::<str>::as_ref(&$value));
+        let (vec, value) = $crate::_prepare_snapshot_for_redaction!(
+            content, {$($k => $v),*}, ($crate::_macro_support::XmlOutput), File
+        );
+        $crate::assert_snapshot!(Some($name), value, stringify!($value));
+    }};
+    ($value:expr) => {{
+        let content = $crate::_macro_support::xml_to_content(AsRef::<str>::as_ref(&$value));
+        let value = $crate::_macro_support::serialize_value(
+            &content,
+            $crate::_macro_support::SerializationFormat::Custom(&$crate::_macro_support::XmlOutput),
+            $crate::_macro_support::SnapshotLocation::File
+        );
+        $crate::assert_snapshot!($crate::_macro_support::AutoName, value, stringify!($value));
+    }};
+}
+
+/// Asserts a snapshot of binary data.
+///
+/// The first argument is the name of the snapshot with the extension of
+/// the file the data is stored in (eg: `"logo.png"`).  If only the
+/// extension is given (eg: `".wasm"`) the snapshot is named after the test
+/// like unnamed snapshots.  The data is stored as is next to the `.snap`
+/// file (eg: `snapshots/crate__logo.snap.png`) and compared byte by byte.
+/// The `.snap` file records the size and a hash of the data so that
+/// changes can be reviewed.  The hash in the metadata is verified when the
+/// snapshot is loaded so that a corrupted file (or a Git LFS pointer that
+/// was not checked out) fails with an error instead of a confusing diff.
+/// Binary snapshots cannot be inline.
+///
+/// ```ignore
+/// assert_binary_snapshot!(".wasm", build_module());
+/// assert_binary_snapshot!("logo.png", std::fs::read("logo.png").unwrap());
+/// ```
+#[macro_export]
+macro_rules! assert_binary_snapshot {
+    ($name:expr, $value:expr) => {{
+        $crate::_macro_support::assert_binary_snapshot(
+            ::std::convert::AsRef::<str>::as_ref(&$name),
+            ::std::convert::AsRef::<[u8]>::as_ref(&$value),
+            env!("CARGO_MANIFEST_DIR"),
+            module_path!(),
+            file!(),
+            line!(),
+            stringify!($value),
+        )
+        .unwrap()
+    }};
+}
+
+/// Asserts a snapshot of an encoded image.
+///
+/// **Feature:** `image`
+///
+/// The value is the encoded image as bytes (eg: the contents of a PNG
+/// file).  It is decoded before the comparison and the snapshot stores the
+/// size and a hash of the RGBA pixels.  An image that is re-encoded with
+/// different metadata or a different compression level but has the same
+/// pixels therefore still matches the snapshot.  PNG, JPEG, GIF, BMP and
+/// WebP images are supported.
+///
+/// Snapshot files also store the image as PNG next to the `.snap` file
+/// (eg: `foo.snap.png`) which new images are compared against pixel by
+/// pixel.  Small differences (eg: from rendering on another GPU) can be
+/// tolerated with `Settings::set_image_threshold` and
+/// `Settings::set_image_max_diff_ratio`.  If an image does not match, the
+/// changed pixels are marked red in a diff image (eg: `foo.snap.diff.png`).
+/// Inline image snapshots only store the hash and have to match exactly.
+///
+/// ```ignore
+/// assert_image_snapshot!(std::fs::read("chart.png").unwrap());
+/// assert_image_snapshot!("chart", render_chart());
+/// ```
+#[cfg(feature = "image")]
+#[macro_export]
+macro_rules! assert_image_snapshot {
     ($value:expr, @$snapshot:literal) => {{
-        $crate::_assert_serialized_snapshot!($value, Toml, @$snapshot);
+        let value = $crate::_macro_support::image_to_text(::std::convert::AsRef::<[u8]>::as_ref(&$value));
+        $crate::assert_snapshot!(value, stringify!($value), @$snapshot);
+    }};
+    ($name:expr, $value:expr) => {{
+        $crate::_macro_support::assert_image_snapshot(
+            // Creates a ReferenceValue::Named variant
+            $name.into(),
+            ::std::convert::AsRef::<[u8]>::as_ref(&$value),
+            env!("CARGO_MANIFEST_DIR"),
+            module_path!(),
+            file!(),
+            line!(),
+            stringify!($value),
+        )
+        .unwrap()
+    }};
+    ($value:expr) => {{
+        $crate::assert_image_snapshot!($crate::_macro_support::AutoName, $value)
+    }};
+}
+
+/// Asserts a snapshot of an HTTP response.
+///
+/// **Feature:** `http`
+///
+/// The response is stored in YAML format as a map of status, headers and
+/// body.  Volatile headers such as `date`, `content-length` or request ids
+/// are replaced with `[redacted]`, JSON bodies are pretty printed and the
+/// body of any other UTF-8 response is stored as string.  Responses are
+/// any type implementing [`HttpResponse`](trait.HttpResponse.html), which
+/// includes `http::Response` with bodies implementing `AsRef<[u8]>` (eg:
+/// `String`, `Vec<u8>` or `Bytes`).  Responses of other clients can be
+/// supported by implementing the trait for a wrapper.
+///
+/// Redactions can be applied like with `assert_yaml_snapshot!` and
+/// selectors start at the map described above:
+///
+#[cfg_attr(feature = "redactions", doc = " ```no_run")]
+#[cfg_attr(not(feature = "redactions"), doc = " ```ignore")]
+/// # use insta::*;
+/// # let response = http::Response::new(String::new());
+/// assert_response_snapshot!(response, {
+///     ".body.id" => "[id]",
+/// });
+/// ```
+#[cfg(feature = "http")]
+#[macro_export]
+macro_rules! assert_response_snapshot {
+    ($value:expr, @$snapshot:literal) => {{
+        let content = $crate::_macro_support::response_to_content(&$value);
+        let value = $crate::_macro_support::serialize_value(
+            &content,
+            $crate::_macro_support::SerializationFormat::Yaml,
+            $crate::_macro_support::SnapshotLocation::Inline
+        );
+        $crate::assert_snapshot!(value, stringify!($value), @$snapshot);
     }};
     ($value:expr, {$($k:expr => $v:expr),*$(,)?}, @$snapshot:literal) => {{
-        $crate::_assert_serialized_snapshot!($value, {$($k => $v),*}, Toml, @$snapshot);
+        let content = $crate::_macro_support::response_to_content(&$value);
+        let (vec, value) = $crate::_prepare_snapshot_for_redaction!(content, {$($k => $v),*}, Yaml, Inline);
+        $crate::assert_snapshot!(value, stringify!($value), @$snapshot);
     }};
     ($value:expr, {$($k:expr => $v:expr),*$(,)?}) => {{
-        $crate::_assert_serialized_snapshot!($crate::_macro_support::AutoName, $value, {$($k => $v),*}, Toml);
+        let content = $crate::_macro_support::response_to_content(&$value);
+        let (vec, value) = $crate::_prepare_snapshot_for_redaction!(content, {$($k => $v),*}, Yaml, File);
+        $crate::assert_snapshot!($crate::_macro_support::AutoName, value, stringify!($value));
     }};
     ($name:expr, $value:expr) => {{
-        $crate::_assert_serialized_snapshot!(Some($name), $value, Toml);
+        let content = $crate::_macro_support::response_to_content(&$value);
+        let value = $crate::_macro_support::serialize_value(
+            &content,
+            $crate::_macro_support::SerializationFormat::Yaml,
+            $crate::_macro_support::SnapshotLocation::File
+        );
+        $crate::assert_snapshot!(Some($name), value, stringify!($value));
     }};
     ($name:expr, $value:expr, {$($k:expr => $v:expr),*$(,)?}) => {{
-        $crate::_assert_serialized_snapshot!(Some($name), $value, {$($k => $v),*}, Toml);
+        let content = $crate::_macro_support::response_to_content(&$value);
+        let (vec, value) = $crate::_prepare_snapshot_for_redaction!(content, {$($k => $v),*}, Yaml, File);
+        $crate::assert_snapshot!(Some($name), value, stringify!($value));
     }};
     ($value:expr) => {{
-        $crate::_assert_serialized_snapshot!($crate::_macro_support::AutoName, $value, Toml);
+        let content = $crate::_macro_support::response_to_content(&$value);
+        let value = $crate::_macro_support::serialize_value(
+            &content,
+            $crate::_macro_support::SerializationFormat::Yaml,
+            $crate::_macro_support::SnapshotLocation::File
+        );
+        $crate::assert_snapshot!($crate::_macro_support::AutoName, value, stringify!($value));
+    }};
+}
+
+/// Asserts a snapshot of the output of a `std::process::Command`.
+///
+/// **Feature:** `cmd`
+///
+/// The command is run to completion and its exit status, stdout and stderr
+/// are stored together in one snapshot.  The command can be passed by value
+/// or as `&mut Command`:
+///
+/// ```no_run
+/// # use insta::*;
+/// # use std::process::Command;
+/// assert_cmd_snapshot!(Command::new("echo").arg("Hello World!"), @r###"
+/// success: true
+/// exit_code: 0
+/// ----- stdout -----
+/// Hello World!
+/// ----- stderr -----
+/// "###);
+/// ```
+///
+/// Filters configured in the settings apply to the whole snapshot which is
+/// the easiest way to replace paths or timings in the output.  Redactions
+/// select the fields `.success`, `.exit_code`, `.stdout` and `.stderr`:
+///
+#[cfg_attr(feature = "redactions", doc = " ```no_run")]
+#[cfg_attr(not(feature = "redactions"), doc = " ```ignore")]
+/// # use insta::*;
+/// # use std::process::Command;
+/// assert_cmd_snapshot!(Command::new("cargo").arg("--version"), {
+///     ".stdout" => "[version]",
+/// });
+/// ```
+#[cfg(feature = "cmd")]
+#[macro_export]
+macro_rules! assert_cmd_snapshot {
+    ($cmd:expr, @$snapshot:literal) => {{
+        let content = $crate::_macro_support::command_to_content($cmd);
+        let value = $crate::_macro_support::serialize_value(
+            &content,
+            $crate::_macro_support::SerializationFormat::Custom(&$crate::_macro_support::CommandOutput),
+            $crate::_macro_support::SnapshotLocation::Inline
+        );
+        $crate::assert_snapshot!(value, stringify!($cmd), @$snapshot);
+    }};
+    ($cmd:expr, {$($k:expr => $v:expr),*$(,)?}, @$snapshot:literal) => {{
+        let content = $crate::_macro_support::command_to_content($cmd);
+        let (vec, value) = $crate::_prepare_snapshot_for_redaction!(
+            content, {$($k => $v),*}, ($crate::_macro_support::CommandOutput), Inline
+        );
+        $crate::assert_snapshot!(value, stringify!($cmd), @$snapshot);
+    }};
+    ($cmd:expr, {$($k:expr => $v:expr),*$(,)?}) => {{
+        let content = $crate::_macro_support::command_to_content($cmd);
+        let (vec, value) = $crate::_prepare_snapshot_for_redaction!(
+            content, {$($k => $v),*}, ($crate::_macro_support::CommandOutput), File
+        );
+        $crate::assert_snapshot!($crate::_macro_support::AutoName, value, stringify!($cmd));
+    }};
+    ($name:expr, $cmd:expr) => {{
+        let content = $crate::_macro_support::command_to_content($cmd);
+        let value = $crate::_macro_support::serialize_value(
+            &content,
+            $crate::_macro_support::SerializationFormat::Custom(&$crate::_macro_support::CommandOutput),
+            $crate::_macro_support::SnapshotLocation::File
+        );
+        $crate::assert_snapshot!(Some($name), value, stringify!($cmd));
+    }};
+    ($name:expr, $cmd:expr, {$($k:expr => $v:expr),*$(,)?}) => {{
+        let content = $crate::_macro_support::command_to_content($cmd);
+        let (vec, value) = $crate::_prepare_snapshot_for_redaction!(
+            content, {$($k => $v),*}, ($crate::_macro_support::CommandOutput), File
+        );
+        $crate::assert_snapshot!(Some($name), value, stringify!($cmd));
+    }};
+    ($cmd:expr) => {{
+        let content = $crate::_macro_support::command_to_content($cmd);
+        let value = $crate::_macro_support::serialize_value(
+            &content,
+            $crate::_macro_support::SerializationFormat::Custom(&$crate::_macro_support::CommandOutput),
+            $crate::_macro_support::SnapshotLocation::File
+        );
+        $crate::assert_snapshot!($crate::_macro_support::AutoName, value, stringify!($cmd));
     }};
 }
 
@@ -127,27 +525,33 @@ macro_rules! assert_toml_snapshot {
 /// just use an empty string (`@""`).  For more information see
 /// [inline snapshots](index.html#inline-snapshots).
 ///
+/// A stream of values (eg: events) can be stored as separate YAML
+/// documents by wrapping them with [`documents`](fn.documents.html).
+///
 /// The snapshot name is optional but can be provided as first argument.
 /// For more information see [named snapshots](index.html#named-snapshots)
 #[macro_export]
 macro_rules! assert_yaml_snapshot {
-    ($value:expr, @$snapshot:literal) => {{
-        $crate::_assert_serialized_snapshot!($value, Yaml, @$snapshot);
+    (_, $value:expr, $($fmt:tt)+) => {{
+        $crate::_assert_serialized_snapshot!(($($fmt)+) $crate::_macro_support::AutoName, $value, Yaml);
     }};
-    ($value:expr, {$($k:expr => $v:expr),*$(,)?}, @$snapshot:literal) => {{
-        $crate::_assert_serialized_snapshot!($value, {$($k => $v),*}, Yaml, @$snapshot);
+    ($value:expr, @$snapshot:literal $(, $($fmt:tt)+)?) => {{
+        $crate::_assert_serialized_snapshot!(($($($fmt)+)?) $value, Yaml, @$snapshot);
     }};
-    ($value:expr, {$($k:expr => $v:expr),*$(,)?}) => {{
-        $crate::_assert_serialized_snapshot!($crate::_macro_support::AutoName, $value, {$($k => $v),*}, Yaml);
+    ($value:expr, {$($k:expr => $v:expr),*$(,)?}, @$snapshot:literal $(, $($fmt:tt)+)?) => {{
+        $crate::_assert_serialized_snapshot!(($($($fmt)+)?) $value, {$($k => $v),*}, Yaml, @$snapshot);
     }};
-    ($name:expr, $value:expr) => {{
-        $crate::_assert_serialized_snapshot!(Some($name), $value, Yaml);
+    ($value:expr, {$($k:expr => $v:expr),*$(,)?} $(, $($fmt:tt)+)?) => {{
+        $crate::_assert_serialized_snapshot!(($($($fmt)+)?) $crate::_macro_support::AutoName, $value, {$($k => $v),*}, Yaml);
     }};
-    ($name:expr, $value:expr, {$($k:expr => $v:expr),*$(,)?}) => {{
-        $crate::_assert_serialized_snapshot!(Some($name), $value, {$($k => $v),*}, Yaml);
+    ($name:expr, $value:expr, {$($k:expr => $v:expr),*$(,)?} $(, $($fmt:tt)+)?) => {{
+        $crate::_assert_serialized_snapshot!(($($($fmt)+)?) Some($name), $value, {$($k => $v),*}, Yaml);
+    }};
+    ($name:expr, $value:expr $(, $($fmt:tt)+)?) => {{
+        $crate::_assert_serialized_snapshot!(($($($fmt)+)?) Some($name), $value, Yaml);
     }};
     ($value:expr) => {{
-        $crate::_assert_serialized_snapshot!($crate::_macro_support::AutoName, $value, Yaml);
+        $crate::_assert_serialized_snapshot!(() $crate::_macro_support::AutoName, $value, Yaml);
     }};
 }
 
@@ -175,23 +579,26 @@ macro_rules! assert_yaml_snapshot {
 #[cfg(feature = "ron")]
 #[macro_export]
 macro_rules! assert_ron_snapshot {
-    ($value:expr, @$snapshot:literal) => {{
-        $crate::_assert_serialized_snapshot!($value, Ron, @$snapshot);
+    (_, $value:expr, $($fmt:tt)+) => {{
+        $crate::_assert_serialized_snapshot!(($($fmt)+) $crate::_macro_support::AutoName, $value, Ron);
     }};
-    ($value:expr, {$($k:expr => $v:expr),*$(,)?}, @$snapshot:literal) => {{
-        $crate::_assert_serialized_snapshot!($value, {$($k => $v),*}, Ron, @$snapshot);
+    ($value:expr, @$snapshot:literal $(, $($fmt:tt)+)?) => {{
+        $crate::_assert_serialized_snapshot!(($($($fmt)+)?) $value, Ron, @$snapshot);
     }};
-    ($value:expr, {$($k:expr => $v:expr),*$(,)?}) => {{
-        $crate::_assert_serialized_snapshot!($crate::_macro_support::AutoName, $value, {$($k => $v),*}, Ron);
+    ($value:expr, {$($k:expr => $v:expr),*$(,)?}, @$snapshot:literal $(, $($fmt:tt)+)?) => {{
+        $crate::_assert_serialized_snapshot!(($($($fmt)+)?) $value, {$($k => $v),*}, Ron, @$snapshot);
     }};
-    ($name:expr, $value:expr) => {{
-        $crate::_assert_serialized_snapshot!(Some($name), $value, Ron);
+    ($value:expr, {$($k:expr => $v:expr),*$(,)?} $(, $($fmt:tt)+)?) => {{
+        $crate::_assert_serialized_snapshot!(($($($fmt)+)?) $crate::_macro_support::AutoName, $value, {$($k => $v),*}, Ron);
     }};
-    ($name:expr, $value:expr, {$($k:expr => $v:expr),*$(,)?}) => {{
-        $crate::_assert_serialized_snapshot!(Some($name), $value, {$($k => $v),*}, Ron);
+    ($name:expr, $value:expr, {$($k:expr => $v:expr),*$(,)?} $(, $($fmt:tt)+)?) => {{
+        $crate::_assert_serialized_snapshot!(($($($fmt)+)?) Some($name), $value, {$($k => $v),*}, Ron);
+    }};
+    ($name:expr, $value:expr $(, $($fmt:tt)+)?) => {{
+        $crate::_assert_serialized_snapshot!(($($($fmt)+)?) Some($name), $value, Ron);
     }};
     ($value:expr) => {{
-        $crate::_assert_serialized_snapshot!($crate::_macro_support::AutoName, $value, Ron);
+        $crate::_assert_serialized_snapshot!(() $crate::_macro_support::AutoName, $value, Ron);
     }};
 }
 
@@ -216,68 +623,170 @@ macro_rules! assert_ron_snapshot {
 /// For more information see [named snapshots](index.html#named-snapshots)
 #[macro_export]
 macro_rules! assert_json_snapshot {
-    ($value:expr, @$snapshot:literal) => {{
-        $crate::_assert_serialized_snapshot!($value, Json, @$snapshot);
+    (_, $value:expr, $($fmt:tt)+) => {{
+        $crate::_assert_serialized_snapshot!(($($fmt)+) $crate::_macro_support::AutoName, $value, Json);
     }};
-    ($value:expr, {$($k:expr => $v:expr),*$(,)?}, @$snapshot:literal) => {{
-        $crate::_assert_serialized_snapshot!($value, {$($k => $v),*}, Json, @$snapshot);
+    ($value:expr, @$snapshot:literal $(, $($fmt:tt)+)?) => {{
+        $crate::_assert_serialized_snapshot!(($($($fmt)+)?) $value, Json, @$snapshot);
     }};
-    ($value:expr, {$($k:expr => $v:expr),*$(,)?}) => {{
-        $crate::_assert_serialized_snapshot!($crate::_macro_support::AutoName, $value, {$($k => $v),*}, Json);
+    ($value:expr, {$($k:expr => $v:expr),*$(,)?}, @$snapshot:literal $(, $($fmt:tt)+)?) => {{
+        $crate::_assert_serialized_snapshot!(($($($fmt)+)?) $value, {$($k => $v),*}, Json, @$snapshot);
     }};
-    ($name:expr, $value:expr) => {{
-        $crate::_assert_serialized_snapshot!(Some($name), $value, Json);
+    ($value:expr, {$($k:expr => $v:expr),*$(,)?} $(, $($fmt:tt)+)?) => {{
+        $crate::_assert_serialized_snapshot!(($($($fmt)+)?) $crate::_macro_support::AutoName, $value, {$($k => $v),*}, Json);
     }};
-    ($name:expr, $value:expr, {$($k:expr => $v:expr),*$(,)?}) => {{
-        $crate::_assert_serialized_snapshot!(Some($name), $value, {$($k => $v),*}, Json);
+    ($name:expr, $value:expr, {$($k:expr => $v:expr),*$(,)?} $(, $($fmt:tt)+)?) => {{
+        $crate::_assert_serialized_snapshot!(($($($fmt)+)?) Some($name), $value, {$($k => $v),*}, Json);
+    }};
+    ($name:expr, $value:expr $(, $($fmt:tt)+)?) => {{
+        $crate::_assert_serialized_snapshot!(($($($fmt)+)?) Some($name), $value, Json);
     }};
     ($value:expr) => {{
-        $crate::_assert_serialized_snapshot!($crate::_macro_support::AutoName, $value, Json);
+        $crate::_assert_serialized_snapshot!(() $crate::_macro_support::AutoName, $value, Json);
+    }};
+}
+
+/// Asserts a `Serialize` snapshot in compact JSON format.
+///
+/// This works exactly like [`assert_json_snapshot`](macro.assert_json_snapshot.html)
+/// but serializes the value on a single line without pretty printing.  This
+/// keeps inline snapshots of small values short but makes diffs of larger
+/// values hard to read.
+///
+/// Example:
+///
+/// ```no_run
+/// # use insta::*;
+/// assert_compact_json_snapshot!(vec![1, 2, 3], @"[1,2,3]");
+/// ```
+///
+/// The third argument to the macro can be an object expression for redaction.
+/// It's in the form `{ selector => replacement }`.  For more information
+/// about redactions see [redactions](index.html#redactions).
+///
+/// The snapshot name is optional but can be provided as first argument.
+/// For more information see [named snapshots](index.html#named-snapshots)
+#[macro_export]
+macro_rules! assert_compact_json_snapshot {
+    (_, $value:expr, $($fmt:tt)+) => {{
+        $crate::_assert_serialized_snapshot!(($($fmt)+) $crate::_macro_support::AutoName, $value, JsonCompact);
+    }};
+    ($value:expr, @$snapshot:literal $(, $($fmt:tt)+)?) => {{
+        $crate::_assert_serialized_snapshot!(($($($fmt)+)?) $value, JsonCompact, @$snapshot);
+    }};
+    ($value:expr, {$($k:expr => $v:expr),*$(,)?}, @$snapshot:literal $(, $($fmt:tt)+)?) => {{
+        $crate::_assert_serialized_snapshot!(($($($fmt)+)?) $value, {$($k => $v),*}, JsonCompact, @$snapshot);
+    }};
+    ($value:expr, {$($k:expr => $v:expr),*$(,)?} $(, $($fmt:tt)+)?) => {{
+        $crate::_assert_serialized_snapshot!(($($($fmt)+)?) $crate::_macro_support::AutoName, $value, {$($k => $v),*}, JsonCompact);
+    }};
+    ($name:expr, $value:expr, {$($k:expr => $v:expr),*$(,)?} $(, $($fmt:tt)+)?) => {{
+        $crate::_assert_serialized_snapshot!(($($($fmt)+)?) Some($name), $value, {$($k => $v),*}, JsonCompact);
+    }};
+    ($name:expr, $value:expr $(, $($fmt:tt)+)?) => {{
+        $crate::_assert_serialized_snapshot!(($($($fmt)+)?) Some($name), $value, JsonCompact);
+    }};
+    ($value:expr) => {{
+        $crate::_assert_serialized_snapshot!(() $crate::_macro_support::AutoName, $value, JsonCompact);
+    }};
+}
+
+/// Asserts a `Serialize` snapshot in a custom format.
+///
+/// The first argument is a [`SnapshotSerializer`](trait.SnapshotSerializer.html)
+/// that renders the value.  Otherwise this works exactly like
+/// [`assert_yaml_snapshot`](macro.assert_yaml_snapshot.html) including
+/// redactions and named snapshots.
+///
+/// ```no_run
+/// # use insta::*;
+/// # struct MyFormat;
+/// # impl SnapshotSerializer for MyFormat {
+/// #     fn serialize(&self, _content: &Content) -> String { String::new() }
+/// # }
+/// assert_custom_snapshot!(MyFormat, vec![1, 2, 3]);
+/// assert_custom_snapshot!(MyFormat, "numbers", vec![1, 2, 3]);
+/// ```
+#[macro_export]
+macro_rules! assert_custom_snapshot {
+    ($serializer:expr, _, $value:expr, $($fmt:tt)+) => {{
+        $crate::_assert_serialized_snapshot!(($($fmt)+) $crate::_macro_support::AutoName, $value, ($serializer));
+    }};
+    ($serializer:expr, $value:expr, @$snapshot:literal $(, $($fmt:tt)+)?) => {{
+        $crate::_assert_serialized_snapshot!(($($($fmt)+)?) $value, ($serializer), @$snapshot);
+    }};
+    ($serializer:expr, $value:expr, {$($k:expr => $v:expr),*$(,)?}, @$snapshot:literal $(, $($fmt:tt)+)?) => {{
+        $crate::_assert_serialized_snapshot!(($($($fmt)+)?) $value, {$($k => $v),*}, ($serializer), @$snapshot);
+    }};
+    ($serializer:expr, $value:expr, {$($k:expr => $v:expr),*$(,)?} $(, $($fmt:tt)+)?) => {{
+        $crate::_assert_serialized_snapshot!(($($($fmt)+)?) $crate::_macro_support::AutoName, $value, {$($k => $v),*}, ($serializer));
+    }};
+    ($serializer:expr, $name:expr, $value:expr, {$($k:expr => $v:expr),*$(,)?} $(, $($fmt:tt)+)?) => {{
+        $crate::_assert_serialized_snapshot!(($($($fmt)+)?) Some($name), $value, {$($k => $v),*}, ($serializer));
+    }};
+    ($serializer:expr, $name:expr, $value:expr $(, $($fmt:tt)+)?) => {{
+        $crate::_assert_serialized_snapshot!(($($($fmt)+)?) Some($name), $value, ($serializer));
+    }};
+    ($serializer:expr, $value:expr) => {{
+        $crate::_assert_serialized_snapshot!(() $crate::_macro_support::AutoName, $value, ($serializer));
     }};
 }
 
+#[doc(hidden)]
+#[macro_export]
+macro_rules! _serialization_format {
+    (($serializer:expr)) => {
+        $crate::_macro_support::SerializationFormat::Custom(&$serializer)
+    };
+    ($format:ident) => {
+        $crate::_macro_support::SerializationFormat::$format
+    };
+}
+
 #[doc(hidden)]
 #[macro_export]
 macro_rules! _assert_serialized_snapshot {
-    ($value:expr, $format:ident, @$snapshot:literal) => {{
+    (($($fmt:tt)*) $value:expr, $format:tt, @$snapshot:literal) => {{
         let value = $crate::_macro_support::serialize_value(
             &$value,
-            $crate::_macro_support::SerializationFormat::$format,
+            $crate::_serialization_format!($format),
             $crate::_macro_support::SnapshotLocation::Inline
         );
         $crate::assert_snapshot!(
+            @message ($($fmt)*)
+            $crate::_macro_support::ReferenceValue::Inline($snapshot),
             value,
-            stringify!($value),
-            @$snapshot
+            stringify!($value)
         );
     }};
-    ($value:expr, {$($k:expr => $v:expr),*$(,)?}, $format:ident, @$snapshot:literal) => {{
+    (($($fmt:tt)*) $value:expr, {$($k:expr => $v:expr),*$(,)?}, $format:tt, @$snapshot:literal) => {{
         let (vec, value) = $crate::_prepare_snapshot_for_redaction!($value, {$($k => $v),*}, $format, Inline);
-        $crate::assert_snapshot!(value, stringify!($value), @$snapshot);
+        $crate::assert_snapshot!(
+            @message ($($fmt)*)
+            $crate::_macro_support::ReferenceValue::Inline($snapshot),
+            value,
+            stringify!($value)
+        );
     }};
-    ($name:expr, $value:expr, $format:ident) => {{
+    (($($fmt:tt)*) $name:expr, $value:expr, $format:tt) => {{
         let value = $crate::_macro_support::serialize_value(
             &$value,
-            $crate::_macro_support::SerializationFormat::$format,
+            $crate::_serialization_format!($format),
             $crate::_macro_support::SnapshotLocation::File
         );
-        $crate::assert_snapshot!(
-            $name,
-            value,
-            stringify!($value)
-        );
+        $crate::assert_snapshot!(@message ($($fmt)*) $name, value, stringify!($value));
     }};
-    ($name:expr, $value:expr, {$($k:expr => $v:expr),*$(,)?}, $format:ident) => {{
+    (($($fmt:tt)*) $name:expr, $value:expr, {$($k:expr => $v:expr),*$(,)?}, $format:tt) => {{
         let (vec, value) = $crate::_prepare_snapshot_for_redaction!($value, {$($k => $v),*}, $format, File);
-        $crate::assert_snapshot!($name, value, stringify!($value));
-    }}
+        $crate::assert_snapshot!(@message ($($fmt)*) $name, value, stringify!($value));
+    }};
 }
 
 #[cfg(feature = "redactions")]
 #[doc(hidden)]
 #[macro_export]
 macro_rules! _prepare_snapshot_for_redaction {
-    ($value:expr, {$($k:expr => $v:expr),*$(,)?}, $format:ident, $location:ident) => {
+    ($value:expr, {$($k:expr => $v:expr),*$(,)?}, $format:tt, $location:ident) => {
         {
             let vec = vec![
                 $((
@@ -288,7 +797,7 @@ macro_rules! _prepare_snapshot_for_redaction {
             let value = $crate::_macro_support::serialize_value_redacted(
                 &$value,
                 &vec,
-                $crate::_macro_support::SerializationFormat::$format,
+                $crate::_serialization_format!($format),
                 $crate::_macro_support::SnapshotLocation::$location
             );
             (vec, value)
@@ -300,7 +809,7 @@ macro_rules! _prepare_snapshot_for_redaction {
 #[doc(hidden)]
 #[macro_export]
 macro_rules! _prepare_snapshot_for_redaction {
-    ($value:expr, {$($k:expr => $v:expr),*$(,)?}, $format:ident, $location:ident) => {
+    ($value:expr, {$($k:expr => $v:expr),*$(,)?}, $format:tt, $location:ident) => {
         compile_error!("insta was compiled without redaction support.");
     };
 }
@@ -315,13 +824,27 @@ macro_rules! _prepare_snapshot_for_redaction {
 /// [unnamed snapshots](index.html#unnamed-snapshots)
 #[macro_export]
 macro_rules! assert_debug_snapshot {
-    ($value:expr, @$snapshot:literal) => {{
+    (_, $value:expr, $($fmt:tt)+) => {{
         let value = format!("{:#?}", $value);
-        $crate::assert_snapshot!(value, stringify!($value), @$snapshot);
+        $crate::assert_snapshot!(
+            @message ($($fmt)+)
+            $crate::_macro_support::AutoName,
+            value,
+            stringify!($value)
+        );
     }};
-    ($name:expr, $value:expr) => {{
+    ($value:expr, @$snapshot:literal $(, $($fmt:tt)+)?) => {{
         let value = format!("{:#?}", $value);
-        $crate::assert_snapshot!(Some($name), value, stringify!($value));
+        $crate::assert_snapshot!(
+            @message ($($($fmt)+)?)
+            $crate::_macro_support::ReferenceValue::Inline($snapshot),
+            value,
+            stringify!($value)
+        );
+    }};
+    ($name:expr, $value:expr $(, $($fmt:tt)+)?) => {{
+        let value = format!("{:#?}", $value);
+        $crate::assert_snapshot!(@message ($($($fmt)+)?) Some($name), value, stringify!($value));
     }};
     ($value:expr) => {{
         let value = format!("{:#?}", $value);
@@ -329,6 +852,46 @@ macro_rules! assert_debug_snapshot {
     }};
 }
 
+/// Asserts a `Debug` snapshot in compact form.
+///
+/// This works exactly like [`assert_debug_snapshot`](macro.assert_debug_snapshot.html)
+/// but formats the value with `{:?}` instead of `{:#?}`, which keeps values
+/// with many flat fields on a single line.
+///
+/// ```no_run
+/// # use insta::*;
+/// assert_compact_debug_snapshot!((1..=3).collect::<Vec<_>>(), @"[1, 2, 3]");
+/// ```
+#[macro_export]
+macro_rules! assert_compact_debug_snapshot {
+    (_, $value:expr, $($fmt:tt)+) => {{
+        let value = format!("{:?}", $value);
+        $crate::assert_snapshot!(
+            @message ($($fmt)+)
+            $crate::_macro_support::AutoName,
+            value,
+            stringify!($value)
+        );
+    }};
+    ($value:expr, @$snapshot:literal $(, $($fmt:tt)+)?) => {{
+        let value = format!("{:?}", $value);
+        $crate::assert_snapshot!(
+            @message ($($($fmt)+)?)
+            $crate::_macro_support::ReferenceValue::Inline($snapshot),
+            value,
+            stringify!($value)
+        );
+    }};
+    ($name:expr, $value:expr $(, $($fmt:tt)+)?) => {{
+        let value = format!("{:?}", $value);
+        $crate::assert_snapshot!(@message ($($($fmt)+)?) Some($name), value, stringify!($value));
+    }};
+    ($value:expr) => {{
+        let value = format!("{:?}", $value);
+        $crate::assert_snapshot!($crate::_macro_support::AutoName, value, stringify!($value));
+    }};
+}
+
 /// Asserts a `Display` snapshot.
 ///
 /// The value needs to implement the `fmt::Display` trait.
@@ -337,13 +900,27 @@ macro_rules! assert_debug_snapshot {
 /// [unnamed snapshots](index.html#unnamed-snapshots)
 #[macro_export]
 macro_rules! assert_display_snapshot {
-    ($value:expr, @$snapshot:literal) => {{
+    (_, $value:expr, $($fmt:tt)+) => {{
         let value = format!("{}", $value);
-        $crate::assert_snapshot!(value, stringify!($value), @$snapshot);
+        $crate::assert_snapshot!(
+            @message ($($fmt)+)
+            $crate::_macro_support::AutoName,
+            value,
+            stringify!($value)
+        );
     }};
-    ($name:expr, $value:expr) => {{
+    ($value:expr, @$snapshot:literal $(, $($fmt:tt)+)?) => {{
         let value = format!("{}", $value);
-        $crate::assert_snapshot!(Some($name), value, stringify!($value));
+        $crate::assert_snapshot!(
+            @message ($($($fmt)+)?)
+            $crate::_macro_support::ReferenceValue::Inline($snapshot),
+            value,
+            stringify!($value)
+        );
+    }};
+    ($name:expr, $value:expr $(, $($fmt:tt)+)?) => {{
+        let value = format!("{}", $value);
+        $crate::assert_snapshot!(@message ($($($fmt)+)?) Some($name), value, stringify!($value));
     }};
     ($value:expr) => {{
         let value = format!("{}", $value);
@@ -351,11 +928,23 @@ macro_rules! assert_display_snapshot {
     }};
 }
 
+#[doc(hidden)]
+#[macro_export]
+macro_rules! _snapshot_value {
+    ($value:expr) => {{
+        #[allow(unused_imports)]
+        use $crate::_macro_support::{RenderDisplay as _, RenderToSnapshot as _};
+        (&$crate::_macro_support::SnapshotValue(&$value)).render_snapshot()
+    }};
+}
+
 /// Asserts a string snapshot.
 ///
 /// This is the most simplistic of all assertion methods.  It just accepts
 /// a string to store as snapshot an does not apply any other transformations
-/// on it.  This is useful to build ones own primitives.
+/// on it.  This is useful to build ones own primitives.  Besides strings it
+/// accepts all types implementing [`ToSnapshot`](trait.ToSnapshot.html) or
+/// `Display`, so there is no need to call `.to_string()` first.
 ///
 /// ```no_run
 /// # use insta::*;
@@ -368,10 +957,69 @@ macro_rules! assert_display_snapshot {
 ///
 /// Additionally the name is optional.  For more information see
 /// [unnamed snapshots](index.html#unnamed-snapshots)
+///
+/// Like with `assert_eq!` a format string and its arguments can be passed
+/// last to add a message to the panic of a failed assertion.  This works
+/// with all forms of the debug, display and serialization macros, including
+/// inline snapshots and redactions.  The message is only formatted if the
+/// assertion fails.  As the name of a snapshot can be left out, unnamed
+/// snapshots need `_` in place of the name to take a message (inline
+/// snapshots do not):
+///
+/// ```no_run
+/// # use insta::*;
+/// # let case = "empty input";
+/// assert_snapshot!("name", "value", "case: {}", case);
+/// assert_snapshot!(_, "value", "case: {}", case);
+/// assert_snapshot!("value", @"value", "case: {}", case);
+/// ```
+///
+/// A string literal as third argument of a named snapshot is taken as the
+/// message rather than the debug expression (as it was up to insta 1.1).  To
+/// store a fixed debug expression pass it as another expression such as a
+/// variable or `concat!("...")`.
 #[macro_export]
 macro_rules! assert_snapshot {
-    ($value:expr, @$snapshot:literal) => {
+    (@message () $name:expr, $value:expr, $debug_expr:expr) => {
+        $crate::_macro_support::assert_macro_snapshot(
+            // Creates a ReferenceValue::Named variant
+            $name.into(),
+            &$crate::_snapshot_value!($value),
+            env!("CARGO_MANIFEST_DIR"),
+            module_path!(),
+            file!(),
+            line!(),
+            $debug_expr,
+            None,
+        )
+        .unwrap()
+    };
+    (@message ($($fmt:tt)+) $name:expr, $value:expr, $debug_expr:expr) => {
+        $crate::_macro_support::assert_macro_snapshot(
+            // Creates a ReferenceValue::Named variant
+            $name.into(),
+            &$crate::_snapshot_value!($value),
+            env!("CARGO_MANIFEST_DIR"),
+            module_path!(),
+            file!(),
+            line!(),
+            $debug_expr,
+            // only formatted if the assertion fails
+            Some(format_args!($($fmt)+)),
+        )
+        .unwrap()
+    };
+    (_, $value:expr, $($fmt:tt)+) => {
+        $crate::assert_snapshot!(
+            @message ($($fmt)+)
+            $crate::_macro_support::AutoName,
+            $value,
+            stringify!($value)
+        )
+    };
+    ($value:expr, @$snapshot:literal $(, $($fmt:tt)+)?) => {
         $crate::assert_snapshot!(
+            @message ($($($fmt)+)?)
             $crate::_macro_support::ReferenceValue::Inline($snapshot),
             $value,
             stringify!($value)
@@ -379,29 +1027,114 @@ macro_rules! assert_snapshot {
     };
     ($value:expr, $debug_expr:expr, @$snapshot:literal) => {
         $crate::assert_snapshot!(
+            @message ()
             $crate::_macro_support::ReferenceValue::Inline($snapshot),
             $value,
             $debug_expr
         )
     };
     ($name:expr, $value:expr) => {
-        $crate::assert_snapshot!($name, $value, stringify!($value))
+        $crate::assert_snapshot!(@message () $name, $value, stringify!($value))
+    };
+    ($name:expr, $value:expr, $fmt:literal $($arg:tt)*) => {
+        $crate::assert_snapshot!(@message ($fmt $($arg)*) $name, $value, stringify!($value))
     };
     ($name:expr, $value:expr, $debug_expr:expr) => {
-        $crate::_macro_support::assert_snapshot(
-            // Creates a ReferenceValue::Named variant
-            $name.into(),
-            &$value,
+        $crate::assert_snapshot!(@message () $name, $value, $debug_expr)
+    };
+    ($value:expr) => {
+        $crate::assert_snapshot!($crate::_macro_support::AutoName, $value, stringify!($value))
+    };
+}
+
+/// Embeds a snapshot file into the test binary.
+///
+/// The path is relative to the current file, like with `include_str!`.  The
+/// result can be passed in place of a snapshot name to any of the assertion
+/// macros.  The snapshot is then compared without any file system access
+/// which makes it possible to run snapshot tests on targets without a file
+/// system such as `wasm32-unknown-unknown`:
+///
+/// ```ignore
+/// assert_debug_snapshot!(
+///     insta::embedded_snapshot!("snapshots/test__vector.snap"),
+///     vec![1, 2, 3]
+/// );
+/// ```
+///
+/// Mismatches are reported as diffs but no new snapshots are written.  To
+/// create or update the embedded snapshot run the test on a native target
+/// with a regular name first.
+#[macro_export]
+macro_rules! embedded_snapshot {
+    ($path:expr) => {
+        $crate::_macro_support::ReferenceValue::Embedded(include_str!($path))
+    };
+}
+
+/// Asserts a snapshot of a directory tree and the contents of its files.
+///
+/// This is a shorthand for asserting a [`DirSnapshot`](struct.DirSnapshot.html)
+/// with the default limits, which also describes the format.  To ignore
+/// files or change the limits pass a `DirSnapshot` to
+/// [`assert_snapshot!`](macro.assert_snapshot.html) instead.
+///
+/// ```no_run
+/// # use insta::*;
+/// assert_dir_snapshot!("target/generated");
+/// assert_dir_snapshot!("client", "target/generated/client");
+/// ```
+#[macro_export]
+macro_rules! assert_dir_snapshot {
+    ($path:expr, @$snapshot:literal) => {{
+        $crate::assert_snapshot!($crate::DirSnapshot::new($path), stringify!($path), @$snapshot);
+    }};
+    ($name:expr, $path:expr) => {{
+        $crate::assert_snapshot!($name, $crate::DirSnapshot::new($path), stringify!($path));
+    }};
+    ($path:expr) => {{
+        $crate::assert_snapshot!(
+            $crate::_macro_support::AutoName,
+            $crate::DirSnapshot::new($path),
+            stringify!($path)
+        );
+    }};
+}
+
+/// Asserts that a value matches an external golden file.
+///
+/// The file is any existing file, not a snapshot managed by insta, and is
+/// expected to hold exactly the value (no metadata header).  The path is
+/// relative to the root of the crate and can be any string expression
+/// (eg: a `format!` per test case).  Accepts the same values as
+/// [`assert_snapshot!`](macro.assert_snapshot.html).
+///
+/// ```no_run
+/// # use insta::*;
+/// # let output = "";
+/// assert_matches_file!(output, "tests/golden/output.txt");
+/// ```
+///
+/// Mismatches are reported with a diff and `INSTA_UPDATE` is honored.  The
+/// golden file is either updated in place or the new value is stored in a
+/// pending file next to it which `cargo insta review` picks up and writes
+/// to the golden file when accepted.
+#[macro_export]
+macro_rules! assert_matches_file {
+    ($value:expr, $path:expr) => {
+        $crate::assert_matches_file!($value, $path, stringify!($value))
+    };
+    ($value:expr, $path:expr, $debug_expr:expr) => {
+        $crate::_macro_support::assert_golden_file(
+            &$crate::_snapshot_value!($value),
+            ::std::convert::AsRef::<str>::as_ref(&$path),
             env!("CARGO_MANIFEST_DIR"),
             module_path!(),
             file!(),
             line!(),
             $debug_expr,
         )
-        .unwrap();
-    };
-    ($value:expr) => {
-        $crate::assert_snapshot!($crate::_macro_support::AutoName, $value, stringify!($value))
+        .unwrap()
     };
 }
 
@@ -409,25 +1142,59 @@ macro_rules! assert_snapshot {
 ///
 /// This macro lets you bind some settings temporarily.  The first argument
 /// takes key value pairs that should be set, the second is the block to
-/// execute.  All settings can be set (`sort_maps => value` maps roughly
-/// to `set_sort_maps(value)`).
+/// execute.  The settings start out as the currently bound ones.  Every
+/// setting can be set, the keys are named after the `set_*` methods of
+/// [`Settings`](struct.Settings.html) and take the same values
+/// (`sort_maps => value` maps to `set_sort_maps(value)`).  `tags` and
+/// `source_path_remaps` replace all tags and remaps with the given ones.
 ///
 /// ```rust
-/// insta::with_settings!({sort_maps => true}, {
+/// insta::with_settings!({
+///     sort_maps => true,
+///     snapshot_suffix => "linux",
+///     description => "the list of users",
+///     tags => ["api-contract"],
+/// }, {
 ///     // run snapshot test here
 /// });
 /// ```
 #[macro_export]
 macro_rules! with_settings {
     ({$($k:ident => $v:expr),*$(,)?}, $body:block) => {{
-        let mut settings = $crate::Settings::new();
+        let mut settings = $crate::Settings::clone_current();
         $(
-            settings._private_inner_mut().$k = $v.into();
+            settings._private_inner_mut().$k($v);
         )*
         settings.bind(|| $body)
     }}
 }
 
+/// Permits repeating the same snapshot assertion, for instance in a loop.
+///
+/// Every assertion in the body is only checked against its snapshot the
+/// first time it runs.  When it runs again the value has to be identical to
+/// the first one, otherwise the assertion fails with a diff of the two
+/// values.  Without this an assertion in a loop would create a new snapshot
+/// with a numbered name on every iteration.
+///
+/// ```no_run
+/// # use insta::*;
+/// # fn render(_: usize) -> String { String::new() }
+/// allow_duplicates! {
+///     for width in [40, 80, 120] {
+///         assert_snapshot!(render(width));
+///     }
+/// }
+/// ```
+#[macro_export]
+macro_rules! allow_duplicates {
+    ($($x:tt)*) => {
+        $crate::_macro_support::with_allow_duplicates(|| {
+            $($x)*
+        })
+    }
+}
+
 /// Executes a closure for all input files matching a glob.
 ///
 /// The closure is passed the path to the file.
@@ -435,8 +1202,7 @@ macro_rules! with_settings {
 #[macro_export]
 macro_rules! glob {
     ($glob:expr, $closure:expr) => {{
-        let base = $crate::_macro_support::get_cargo_workspace(env!("CARGO_MANIFEST_DIR"))
-            .join(file!())
+        let base = $crate::_macro_support::resolve_source_file(env!("CARGO_MANIFEST_DIR"), file!())
             .parent()
             .unwrap()
             .canonicalize()
@@ -444,3 +1210,34 @@ macro_rules! glob {
         $crate::_macro_support::glob_exec(&base, $glob, $closure);
     }};
 }
+
+/// Snapshots the minimized counterexample of a failing property test.
+///
+/// The first argument is the name of the snapshot, the second a proptest
+/// strategy and the third the property as a closure returning
+/// `Result<(), TestCaseError>` (so `prop_assert!` and friends can be used).
+/// The property is run with a deterministic seed.  If it fails, the shrunk
+/// input together with the failure message is stored as YAML snapshot named
+/// `<name>-counterexample` and the test fails.  Once reviewed and accepted
+/// the snapshot serves as regression fixture for the bug.
+///
+/// ```rust,ignore
+/// use proptest::prelude::*;
+///
+/// insta::assert_counterexample_snapshot!("parse", any::<String>(), |input| {
+///     prop_assert!(parse(&input).is_ok());
+///     Ok(())
+/// });
+/// ```
+#[cfg(feature = "proptest")]
+#[macro_export]
+macro_rules! assert_counterexample_snapshot {
+    ($name:expr, $strategy:expr, $test:expr) => {{
+        if let Some(counterexample) = $crate::_macro_support::find_counterexample(&$strategy, $test)
+        {
+            let failure = counterexample.failure.clone();
+            $crate::assert_yaml_snapshot!(format!("{}-counterexample", $name), counterexample);
+            panic!("property failed: {}", failure);
+        }
+    }};
+}
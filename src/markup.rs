@@ -0,0 +1,604 @@
+#[cfg(feature = "xml")]
+use crate::content::Content;
+#[cfg(feature = "xml")]
+use crate::serialization::SnapshotSerializer;
+
+/// Elements that never have children or an end tag.
+const VOID_ELEMENTS: &[&str] = &[
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "source", "track",
+    "wbr",
+];
+
+/// Elements whose contents are kept verbatim instead of being parsed.
+const RAW_TEXT_ELEMENTS: &[&str] = &["pre", "script", "style", "textarea"];
+
+#[cfg(feature = "html")]
+/// Raw text elements whose whitespace is significant.
+const PREFORMATTED_ELEMENTS: &[&str] = &["pre", "textarea"];
+
+type Attrs = Vec<(String, Option<String>)>;
+
+#[derive(Debug)]
+enum Node {
+    Element {
+        name: String,
+        attrs: Attrs,
+        children: Vec<Node>,
+    },
+    Text(String),
+    Raw(String),
+    Comment(String),
+    Doctype(String),
+    Cdata(String),
+    Instruction(String),
+}
+
+/// A forgiving parser for HTML and XML.
+///
+/// Unknown end tags are ignored and elements that are still open at an end
+/// tag of an outer element or at the end of the input are closed
+/// implicitly.  For HTML tag and attribute names are lowercased and void
+/// and raw text elements are understood, for XML CDATA sections and
+/// processing instructions.
+struct Parser<'a> {
+    input: &'a str,
+    pos: usize,
+    html: bool,
+}
+
+impl<'a> Parser<'a> {
+    fn new(input: &'a str, html: bool) -> Parser<'a> {
+        Parser {
+            input,
+            pos: 0,
+            html,
+        }
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.input[self.pos..]
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    /// Consumes everything up to a delimiter and the delimiter itself.
+    fn take_until(&mut self, delimiter: &str) -> &'a str {
+        let rest = self.rest();
+        match rest.find(delimiter) {
+            Some(idx) => {
+                self.pos += idx + delimiter.len();
+                &rest[..idx]
+            }
+            None => {
+                self.pos = self.input.len();
+                rest
+            }
+        }
+    }
+
+    fn take_while<F: Fn(char) -> bool>(&mut self, f: F) -> &'a str {
+        let rest = self.rest();
+        let end = rest.find(|c| !f(c)).unwrap_or(rest.len());
+        self.pos += end;
+        &rest[..end]
+    }
+
+    fn is_tag_start(&self, rest: &str) -> bool {
+        let mut chars = rest.chars();
+        chars.next() == Some('<')
+            && chars.next().is_some_and(|c| {
+                c.is_ascii_alphabetic() || c == '/' || c == '!' || (c == '?' && !self.html)
+            })
+    }
+
+    fn normalize_name(&self, name: &str) -> String {
+        if self.html {
+            name.to_ascii_lowercase()
+        } else {
+            name.to_string()
+        }
+    }
+
+    fn parse(mut self) -> Vec<Node> {
+        let mut stack: Vec<(String, Attrs, Vec<Node>)> = Vec::new();
+        let mut root = Vec::new();
+
+        fn close(stack: &mut Vec<(String, Attrs, Vec<Node>)>, root: &mut Vec<Node>) {
+            let (name, attrs, children) = stack.pop().unwrap();
+            let node = Node::Element {
+                name,
+                attrs,
+                children,
+            };
+            match stack.last_mut() {
+                Some(parent) => parent.2.push(node),
+                None => root.push(node),
+            }
+        }
+
+        while self.pos < self.input.len() {
+            let rest = self.rest();
+            let node = if rest.starts_with("<!--") {
+                self.pos += 4;
+                Node::Comment(self.take_until("-->").trim().to_string())
+            } else if rest.starts_with("</") {
+                self.pos += 2;
+                let name = self.take_until(">").trim();
+                let name = self.normalize_name(name);
+                if let Some(idx) = stack.iter().rposition(|x| x.0 == name) {
+                    while stack.len() > idx {
+                        close(&mut stack, &mut root);
+                    }
+                }
+                continue;
+            } else if !self.html && rest.starts_with("<![CDATA[") {
+                self.pos += 9;
+                Node::Cdata(self.take_until("]]>").to_string())
+            } else if !self.html && rest.starts_with("<?") {
+                self.pos += 2;
+                Node::Instruction(self.take_until("?>").trim().to_string())
+            } else if rest.starts_with("<!") {
+                self.pos += 2;
+                Node::Doctype(self.take_until(">").trim().to_string())
+            } else if self.is_tag_start(rest) {
+                self.pos += 1;
+                let name = self.take_while(|c| !c.is_whitespace() && c != '/' && c != '>');
+                let name = self.normalize_name(name);
+                let (attrs, self_closing) = self.parse_attrs();
+                if self.html && RAW_TEXT_ELEMENTS.contains(&name.as_str()) && !self_closing {
+                    let end = format!("</{}", name);
+                    let rest = self.rest();
+                    let raw = match rest.to_ascii_lowercase().find(&end) {
+                        Some(idx) => {
+                            self.pos += idx;
+                            self.take_until(">");
+                            &rest[..idx]
+                        }
+                        None => {
+                            self.pos = self.input.len();
+                            rest
+                        }
+                    };
+                    let children = if raw.trim().is_empty() {
+                        vec![]
+                    } else {
+                        vec![Node::Raw(raw.to_string())]
+                    };
+                    Node::Element {
+                        name,
+                        attrs,
+                        children,
+                    }
+                } else if self_closing || (self.html && VOID_ELEMENTS.contains(&name.as_str())) {
+                    Node::Element {
+                        name,
+                        attrs,
+                        children: vec![],
+                    }
+                } else {
+                    stack.push((name, attrs, vec![]));
+                    continue;
+                }
+            } else {
+                // a `<` that does not start a tag is part of the text
+                let mut end = rest.len();
+                let mut search = rest.chars().next().map_or(0, char::len_utf8);
+                while let Some(idx) = rest[search..].find('<') {
+                    if self.is_tag_start(&rest[search + idx..]) {
+                        end = search + idx;
+                        break;
+                    }
+                    search += idx + 1;
+                }
+                self.pos += end;
+                Node::Text(rest[..end].to_string())
+            };
+            match stack.last_mut() {
+                Some(parent) => parent.2.push(node),
+                None => root.push(node),
+            }
+        }
+
+        while !stack.is_empty() {
+            close(&mut stack, &mut root);
+        }
+        root
+    }
+
+    /// Parses the attributes of a start tag up to and including the `>`.
+    fn parse_attrs(&mut self) -> (Attrs, bool) {
+        let mut attrs = Vec::new();
+        loop {
+            self.skip_whitespace();
+            let rest = self.rest();
+            if rest.is_empty() {
+                return (attrs, false);
+            } else if rest.starts_with("/>") {
+                self.pos += 2;
+                return (attrs, true);
+            } else if rest.starts_with('>') {
+                self.pos += 1;
+                return (attrs, false);
+            } else if rest.starts_with('/') {
+                self.pos += 1;
+                continue;
+            }
+            let name = self.take_while(|c| !c.is_whitespace() && c != '=' && c != '>' && c != '/');
+            let name = self.normalize_name(name);
+            self.skip_whitespace();
+            let value = if self.rest().starts_with('=') {
+                self.pos += 1;
+                self.skip_whitespace();
+                let value = match self.rest().chars().next() {
+                    Some(quote) if quote == '"' || quote == '\'' => {
+                        self.pos += 1;
+                        self.take_until(if quote == '"' { "\"" } else { "'" })
+                    }
+                    _ => self.take_while(|c| !c.is_whitespace() && c != '>'),
+                };
+                Some(value.to_string())
+            } else {
+                None
+            };
+            attrs.push((name, value));
+        }
+    }
+}
+
+#[cfg(feature = "html")]
+fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(feature = "html")]
+fn render_start_tag(name: &str, attrs: &[(String, Option<String>)]) -> String {
+    let mut attrs = attrs.iter().collect::<Vec<_>>();
+    attrs.sort_by(|a, b| a.0.cmp(&b.0));
+    let mut rv = format!("<{}", name);
+    for (name, value) in attrs {
+        match *value {
+            Some(ref value) => {
+                rv.push_str(&format!(" {}=\"{}\"", name, value.replace('"', "&quot;")))
+            }
+            None => rv.push_str(&format!(" {}", name)),
+        }
+    }
+    rv.push('>');
+    rv
+}
+
+#[cfg(feature = "html")]
+/// Indents raw text by the given prefix after removing its indentation.
+fn push_dedented(raw: &str, prefix: &str, out: &mut Vec<String>) {
+    let lines = raw
+        .trim_matches('\n')
+        .lines()
+        .map(str::trim_end)
+        .collect::<Vec<_>>();
+    let indentation = lines
+        .iter()
+        .filter(|line| !line.is_empty())
+        .map(|line| line.len() - line.trim_start().len())
+        .min()
+        .unwrap_or(0);
+    for line in lines {
+        if line.is_empty() {
+            out.push(String::new());
+        } else {
+            out.push(format!("{}{}", prefix, &line[indentation..]));
+        }
+    }
+}
+
+#[cfg(feature = "html")]
+fn render(nodes: &[Node], depth: usize, out: &mut Vec<String>) {
+    let prefix = "  ".repeat(depth);
+    for node in nodes {
+        match *node {
+            Node::Element {
+                ref name,
+                ref attrs,
+                ref children,
+            } => {
+                let start = render_start_tag(name, attrs);
+                if VOID_ELEMENTS.contains(&name.as_str()) {
+                    out.push(format!("{}{}", prefix, start));
+                    continue;
+                }
+                match children.as_slice() {
+                    [] => out.push(format!("{}{}</{}>", prefix, start, name)),
+                    [Node::Raw(ref raw)] if PREFORMATTED_ELEMENTS.contains(&name.as_str()) => {
+                        out.push(format!("{}{}{}</{}>", prefix, start, raw, name))
+                    }
+                    [Node::Raw(ref raw)] => {
+                        out.push(format!("{}{}", prefix, start));
+                        push_dedented(raw, &format!("{}  ", prefix), out);
+                        out.push(format!("{}</{}>", prefix, name));
+                    }
+                    [Node::Text(ref text)] => {
+                        out.push(format!(
+                            "{}{}{}</{}>",
+                            prefix,
+                            start,
+                            collapse_whitespace(text),
+                            name
+                        ));
+                    }
+                    children => {
+                        out.push(format!("{}{}", prefix, start));
+                        render(children, depth + 1, out);
+                        out.push(format!("{}</{}>", prefix, name));
+                    }
+                }
+            }
+            Node::Text(ref text) => {
+                let text = collapse_whitespace(text);
+                if !text.is_empty() {
+                    out.push(format!("{}{}", prefix, text));
+                }
+            }
+            Node::Raw(ref raw) => push_dedented(raw, &prefix, out),
+            Node::Comment(ref text) => out.push(format!("{}<!-- {} -->", prefix, text)),
+            Node::Doctype(ref text) => {
+                let text = match text.get(..7) {
+                    Some(keyword) if keyword.eq_ignore_ascii_case("doctype") => {
+                        format!("DOCTYPE{}", &text[7..])
+                    }
+                    _ => text.to_string(),
+                };
+                out.push(format!("{}<!{}>", prefix, text))
+            }
+            Node::Cdata(ref text) => out.push(format!("{}<![CDATA[{}]]>", prefix, text)),
+            Node::Instruction(ref text) => out.push(format!("{}<?{}?>", prefix, text)),
+        }
+    }
+}
+
+#[cfg(feature = "html")]
+/// Normalizes HTML into a stable pretty printed form.
+///
+/// Every element and text goes on its own line indented by its depth, with
+/// elements that only contain text kept on one line.  Whitespace in text is
+/// collapsed and attributes are sorted by name and double quoted.  The
+/// contents of `pre` and `textarea` are kept verbatim, the ones of `script`
+/// and `style` are only re-indented.
+pub fn html_to_text(html: &str) -> String {
+    let nodes = Parser::new(html, true).parse();
+    let mut out = Vec::new();
+    render(&nodes, 0, &mut out);
+    out.join("\n")
+}
+
+/// Decodes the predefined and numeric character references of XML.
+///
+/// Unknown or malformed references are kept as they are.
+#[cfg(feature = "xml")]
+fn decode_entities(text: &str) -> String {
+    let mut rv = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(idx) = rest.find('&') {
+        rv.push_str(&rest[..idx]);
+        rest = &rest[idx..];
+        let decoded = rest.find(';').and_then(|end| {
+            let c = match &rest[1..end] {
+                "amp" => '&',
+                "lt" => '<',
+                "gt" => '>',
+                "quot" => '"',
+                "apos" => '\'',
+                entity => {
+                    let code = match entity.strip_prefix("#x") {
+                        Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                        None => entity.strip_prefix('#')?.parse().ok()?,
+                    };
+                    char::from_u32(code)?
+                }
+            };
+            Some((c, end))
+        });
+        match decoded {
+            Some((c, end)) => {
+                rv.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                rv.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    rv.push_str(rest);
+    rv
+}
+
+/// Escapes text for XML, including `"` for attribute values.
+#[cfg(feature = "xml")]
+fn escape_xml(text: &str, attribute: bool) -> String {
+    let mut rv = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => rv.push_str("&amp;"),
+            '<' => rv.push_str("&lt;"),
+            '>' => rv.push_str("&gt;"),
+            '"' if attribute => rv.push_str("&quot;"),
+            c => rv.push(c),
+        }
+    }
+    rv
+}
+
+#[cfg(feature = "xml")]
+fn node_to_entry(node: Node) -> Option<(Content, Content)> {
+    let (key, value) = match node {
+        Node::Element {
+            name,
+            attrs,
+            children,
+        } => {
+            let mut entries = attrs
+                .into_iter()
+                .map(|(name, value)| {
+                    (
+                        Content::from(format!("@{}", name)),
+                        Content::from(decode_entities(&value.unwrap_or_default())),
+                    )
+                })
+                .collect::<Vec<_>>();
+            entries.extend(children.into_iter().filter_map(node_to_entry));
+            let value = match entries.as_slice() {
+                [(key, _)] if key.as_str() == Some("#text") => entries.pop().unwrap().1,
+                _ => Content::Map(entries),
+            };
+            return Some((Content::from(name), value));
+        }
+        Node::Text(text) | Node::Raw(text) => match text.trim() {
+            "" => return None,
+            text => ("#text".to_string(), decode_entities(text)),
+        },
+        Node::Comment(text) => ("#comment".to_string(), text),
+        Node::Cdata(text) => ("#cdata".to_string(), text),
+        Node::Doctype(text) => match text.split_once(char::is_whitespace) {
+            Some((keyword, rest)) => (format!("!{}", keyword), rest.trim().to_string()),
+            None => (format!("!{}", text), String::new()),
+        },
+        Node::Instruction(text) => match text.split_once(char::is_whitespace) {
+            Some((target, rest)) => (format!("?{}", target), rest.trim().to_string()),
+            None => (format!("?{}", text), String::new()),
+        },
+    };
+    Some((Content::from(key), Content::from(value)))
+}
+
+/// Parses XML into the content of a snapshot.
+///
+/// Every element becomes a map entry named after the element so that
+/// selectors walk the element tree (eg: `.feed.entry.title`).  Attributes
+/// are entries prefixed with `@` (eg: `.feed.entry["@id"]`) and text is a
+/// `#text` entry, or the value of the element itself if it has neither
+/// attributes nor children.  Repeated elements are repeated entries.
+/// Comments, CDATA sections, processing instructions and the doctype are
+/// kept as `#comment`, `#cdata`, `?target` and `!DOCTYPE` entries.  Entities
+/// in text and attribute values are decoded.
+#[cfg(feature = "xml")]
+pub fn xml_to_content(xml: &str) -> Content {
+    let nodes = Parser::new(xml, false).parse();
+    Content::Map(nodes.into_iter().filter_map(node_to_entry).collect())
+}
+
+#[cfg(feature = "xml")]
+fn scalar_to_string(content: &Content) -> String {
+    match *content {
+        Content::None | Content::Unit => String::new(),
+        ref content => match content.as_str() {
+            Some(value) => value.to_string(),
+            None => serde_json::to_string(content).unwrap(),
+        },
+    }
+}
+
+#[cfg(feature = "xml")]
+fn render_xml_element(name: &str, value: &Content, depth: usize, out: &mut Vec<String>) {
+    let prefix = "  ".repeat(depth);
+    let entries = match *value {
+        Content::Map(ref entries) => entries.as_slice(),
+        Content::Seq(ref items) => {
+            for item in items {
+                render_xml_element(name, item, depth, out);
+            }
+            return;
+        }
+        Content::None | Content::Unit => &[],
+        ref value => {
+            out.push(format!(
+                "{}<{}>{}</{}>",
+                prefix,
+                name,
+                escape_xml(&scalar_to_string(value), false),
+                name
+            ));
+            return;
+        }
+    };
+
+    let (mut attrs, children): (Vec<_>, Vec<_>) = entries
+        .iter()
+        .partition(|(key, _)| key.as_str().is_some_and(|x| x.starts_with('@')));
+    attrs.sort_by(|a, b| a.0.as_str().cmp(&b.0.as_str()));
+    let mut start = format!("<{}", name);
+    for (key, value) in attrs {
+        start.push_str(&format!(
+            " {}=\"{}\"",
+            &key.as_str().unwrap()[1..],
+            escape_xml(&scalar_to_string(value), true)
+        ));
+    }
+
+    match children.as_slice() {
+        [] => out.push(format!("{}{}/>", prefix, start)),
+        [(key, value)] if key.as_str() == Some("#text") => out.push(format!(
+            "{}{}>{}</{}>",
+            prefix,
+            start,
+            escape_xml(&scalar_to_string(value), false),
+            name
+        )),
+        _ => {
+            out.push(format!("{}{}>", prefix, start));
+            render_xml_entries(children.into_iter(), depth + 1, out);
+            out.push(format!("{}</{}>", prefix, name));
+        }
+    }
+}
+
+#[cfg(feature = "xml")]
+fn render_xml_entries<'a, I>(entries: I, depth: usize, out: &mut Vec<String>)
+where
+    I: Iterator<Item = &'a (Content, Content)>,
+{
+    let prefix = "  ".repeat(depth);
+    for (key, value) in entries {
+        let key = key.as_str().unwrap_or("");
+        let value_str = || scalar_to_string(value);
+        match key {
+            "#text" => out.push(format!("{}{}", prefix, escape_xml(&value_str(), false))),
+            "#cdata" => out.push(format!("{}<![CDATA[{}]]>", prefix, value_str())),
+            "#comment" => out.push(format!("{}<!-- {} -->", prefix, value_str())),
+            _ if key.starts_with('?') || key.starts_with('!') => {
+                let value = value_str();
+                let close = if key.starts_with('?') { "?" } else { "" };
+                if value.is_empty() {
+                    out.push(format!("{}<{}{}>", prefix, key, close));
+                } else {
+                    out.push(format!("{}<{} {}{}>", prefix, key, value, close));
+                }
+            }
+            _ if key.starts_with('@') => {}
+            _ => render_xml_element(key, value, depth, out),
+        }
+    }
+}
+
+/// Renders the content of `xml_to_content` as canonical XML.
+///
+/// Elements are indented by two spaces per level, attributes are sorted
+/// by name and double quoted and elements without children are written
+/// as empty element tags (`<entry/>`).  Text and attribute values are
+/// escaped, so redactions can use any replacement.
+#[doc(hidden)]
+#[cfg(feature = "xml")]
+pub struct XmlOutput;
+
+#[cfg(feature = "xml")]
+impl SnapshotSerializer for XmlOutput {
+    fn serialize(&self, content: &Content) -> String {
+        let mut out = Vec::new();
+        match *content {
+            Content::Map(ref entries) => render_xml_entries(entries.iter(), 0, &mut out),
+            ref other => out.push(escape_xml(&scalar_to_string(other), false)),
+        }
+        out.join("\n")
+    }
+}
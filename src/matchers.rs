@@ -0,0 +1,63 @@
+//! Ready-made redactions for common volatile values.
+//!
+//! Unlike a static redaction the matchers in this module check that the
+//! value looks like what it is supposed to be before replacing it.  A
+//! value of the wrong shape fails the assertion so that a redaction does
+//! not hide a regression in the data:
+//!
+//! ```
+//! # use insta::{Settings, matchers};
+//! let mut settings = Settings::new();
+//! settings.add_redaction(".id", matchers::uuid());
+//! settings.add_redaction(".created_at", matchers::iso_timestamp());
+//! settings.add_redaction(".workdir", matchers::unix_path());
+//! ```
+use lazy_static::lazy_static;
+use regex::Regex;
+
+use crate::content::Content;
+use crate::redaction::{dynamic_redaction, Redaction};
+
+lazy_static! {
+    static ref UUID_RE: Regex = Regex::new(
+        r"^[0-9a-fA-F]{8}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{12}$"
+    )
+    .unwrap();
+    static ref ISO_TIMESTAMP_RE: Regex = Regex::new(
+        r"^\d{4}-\d{2}-\d{2}(?:[Tt ]\d{2}:\d{2}:\d{2}(?:\.\d+)?(?:[Zz]|[+-]\d{2}:?\d{2})?)?$"
+    )
+    .unwrap();
+    static ref UNIX_PATH_RE: Regex = Regex::new(r"^/[^\x00]*$").unwrap();
+}
+
+/// Creates a redaction that replaces strings matching a regex.
+///
+/// Values that are not strings or do not match fail with a message
+/// naming what was expected.
+fn matcher(what: &'static str, regex: &'static Regex, replacement: &'static str) -> Redaction {
+    dynamic_redaction(move |value, path| {
+        match value {
+            Content::String(ref s) if regex.is_match(s) => {}
+            ref other => panic!("expected {} at {}, got {:?}", what, path, other),
+        }
+        replacement
+    })
+}
+
+/// Matches UUIDs in the hyphenated form and replaces them with `[uuid]`.
+pub fn uuid() -> Redaction {
+    matcher("a UUID", &UUID_RE, "[uuid]")
+}
+
+/// Matches ISO 8601 dates and timestamps and replaces them with
+/// `[timestamp]`.
+///
+/// Fractional seconds and an offset or `Z` suffix are optional.
+pub fn iso_timestamp() -> Redaction {
+    matcher("an ISO 8601 timestamp", &ISO_TIMESTAMP_RE, "[timestamp]")
+}
+
+/// Matches absolute unix paths and replaces them with `[path]`.
+pub fn unix_path() -> Redaction {
+    matcher("an absolute unix path", &UNIX_PATH_RE, "[path]")
+}
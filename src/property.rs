@@ -0,0 +1,34 @@
+use proptest::strategy::Strategy;
+use proptest::test_runner::{Config, TestCaseError, TestError, TestRunner};
+use serde::Serialize;
+
+/// A minimized counterexample of a failed property.
+#[derive(Debug, Serialize)]
+pub struct Counterexample<T> {
+    pub input: T,
+    pub failure: String,
+}
+
+/// Runs a property and returns the minimized counterexample if it fails.
+///
+/// The runner is seeded deterministically so that the same counterexample
+/// (and thus the same snapshot) is produced on every run.
+pub fn find_counterexample<S, F>(strategy: &S, test: F) -> Option<Counterexample<S::Value>>
+where
+    S: Strategy,
+    F: Fn(S::Value) -> Result<(), TestCaseError>,
+{
+    let config = Config {
+        failure_persistence: None,
+        ..Config::default()
+    };
+    let mut runner = TestRunner::new_with_rng(config, TestRunner::deterministic().new_rng());
+    match runner.run(strategy, test) {
+        Ok(()) => None,
+        Err(TestError::Fail(reason, input)) => Some(Counterexample {
+            input,
+            failure: reason.to_string(),
+        }),
+        Err(TestError::Abort(reason)) => panic!("property test aborted: {}", reason),
+    }
+}
@@ -0,0 +1,15 @@
+use prost_reflect::text_format::FormatOptions;
+use prost_reflect::ReflectMessage;
+
+/// Renders a protobuf message in the canonical text format.
+///
+/// The message is transcoded into a dynamic message through its descriptor
+/// so the output only depends on the schema and not on the code generator.
+/// Fields are written in the order of their field numbers, one per line,
+/// and `google.protobuf.Any` values are expanded.
+pub fn protobuf_to_text<M: ReflectMessage>(message: &M) -> String {
+    let options = FormatOptions::new().pretty(true).expand_any(true);
+    message
+        .transcode_to_dynamic()
+        .to_text_format_with_options(&options)
+}
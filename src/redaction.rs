@@ -1,10 +1,18 @@
 use pest::Parser;
 use pest_derive::Parser;
+use regex::Regex;
 use std::borrow::Cow;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::error::Error;
 use std::fmt;
 
-use crate::content::Content;
+use crate::content::{parse_json, Content, ContentPath, PathItem};
 
+/// The error of parsing an invalid selector.
+///
+/// The `Display` implementation points at the offending part of the
+/// selector and explains what was expected there.
 #[derive(Debug)]
 pub struct SelectorParseError(pest::error::Error<Rule>);
 
@@ -16,36 +24,50 @@ impl SelectorParseError {
             pest::error::LineColLocation::Span((_, col), _) => col,
         }
     }
-}
 
-/// Represents a path for a callback function.
-///
-/// This can be converted into a string with `to_string` to see a stringified
-/// path that the selector matched.
-#[derive(Clone, Debug)]
-pub struct ContentPath<'a>(&'a [PathItem]);
+    /// Returns the start and end byte offsets of the offending part of the
+    /// selector.
+    ///
+    /// Both are the same if the error is at a single position.
+    pub fn span(&self) -> (usize, usize) {
+        match self.0.location {
+            pest::error::InputLocation::Pos(pos) => (pos, pos),
+            pest::error::InputLocation::Span(span) => span,
+        }
+    }
+}
 
-impl<'a> fmt::Display for ContentPath<'a> {
+impl fmt::Display for SelectorParseError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        for item in self.0.iter() {
-            write!(f, ".")?;
-            match *item {
-                PathItem::Content(ref ctx) => {
-                    if let Some(s) = ctx.as_str() {
-                        write!(f, "{}", s)?;
-                    } else {
-                        write!(f, "<content>")?;
-                    }
-                }
-                PathItem::Field(name) => write!(f, "{}", name)?,
-                PathItem::Index(idx, _) => write!(f, "{}", idx)?,
-            }
-        }
-        Ok(())
+        write!(f, "invalid selector\n{}", self.0)
     }
 }
 
-/// Replaces a value with another one.
+impl Error for SelectorParseError {}
+
+/// Describes the grammar rules in errors.
+fn describe_rule(rule: &Rule) -> String {
+    match *rule {
+        Rule::EOI => "the end of the selector",
+        Rule::selector => "a selector",
+        Rule::identity => "`.`",
+        Rule::ident => "an identifier",
+        Rule::key => "a key like `.foo`",
+        Rule::keys => "`.{keys}`",
+        Rule::key_regex => "a key regex like `.{/^id_/}`",
+        Rule::wildcard => "`.*`",
+        Rule::deep_wildcard => "`.**`",
+        Rule::subscript => "a subscript like `[0]` or `[\"foo\"]`",
+        Rule::full_range => "`[]`",
+        Rule::range => "a range like `[1:3]`",
+        Rule::range_to => "a range like `[:3]`",
+        Rule::range_from => "a range like `[1:]`",
+        Rule::int => "an integer",
+        Rule::string => "a quoted string",
+        ref other => return format!("{:?}", other),
+    }
+    .into()
+}
 
 /// Represents a redaction.
 pub enum Redaction {
@@ -53,6 +75,11 @@ pub enum Redaction {
     Static(Content),
     /// Redaction with new content.
     Dynamic(Box<dyn Fn(Content, ContentPath<'_>) -> Content + Sync + Send>),
+    /// Removes the value entirely.
+    ///
+    /// Matched map entries, struct fields and sequence items are left out
+    /// of the output.  A removed root value becomes unit.
+    Remove,
 }
 
 macro_rules! impl_from {
@@ -100,7 +127,7 @@ impl<'a> From<&'a [u8]> for Redaction {
 /// be used to perform assertions before replacing the value.
 ///
 /// The closure is passed two arguments: the value as [`Content`](internals/enum.Content.html)
-/// and the path that was selected (as [`ContentPath`](internals/struct.ContentPath.html)).
+/// and the path that was selected (as [`ContentPath`](struct.ContentPath.html)).
 ///
 /// Example:
 ///
@@ -129,12 +156,190 @@ where
     Redaction::Dynamic(Box::new(move |c, p| func(c, p).into()))
 }
 
+/// Creates a redaction that only applies if a predicate holds.
+///
+/// The predicate is called with the selected value and its path and the
+/// replacement is only applied if it returns `true`.  Otherwise the value
+/// is kept as it is.  This can be used to redact optional values only if
+/// they are set and keep `null` visible in the snapshot:
+///
+/// ```rust
+/// # use insta::{Settings, conditional_redaction};
+/// # let mut settings = Settings::new();
+/// settings.add_redaction(
+///     ".token",
+///     conditional_redaction(|value, _| !value.is_nil(), "[token]"),
+/// );
+/// ```
+pub fn conditional_redaction<P, R>(predicate: P, replacement: R) -> Redaction
+where
+    P: Fn(&Content, ContentPath<'_>) -> bool + Send + Sync + 'static,
+    R: Into<Redaction>,
+{
+    let replacement = replacement.into();
+    Redaction::Dynamic(Box::new(move |value, path| {
+        if predicate(&value, path.clone()) {
+            replacement.redact(value, path.0)
+        } else {
+            value
+        }
+    }))
+}
+
+/// Creates a redaction that expands strings holding embedded JSON.
+///
+/// Fields that carry pre-serialized JSON as a string would otherwise end up
+/// as a single escaped line in the snapshot.  With this redaction the string
+/// is parsed and rendered as a regular nested value instead.  Strings that
+/// are not valid JSON are left untouched.
+///
+/// Example:
+///
+/// ```rust
+/// # use insta::{Settings, embedded_json};
+/// # let mut settings = Settings::new();
+/// settings.add_redaction(".payload", embedded_json());
+/// ```
+pub fn embedded_json() -> Redaction {
+    dynamic_redaction(|value, _| match value.as_str().and_then(parse_json) {
+        Some(parsed) => parsed,
+        None => value,
+    })
+}
+
+/// Creates a redaction that sorts sequences.
+///
+/// This is useful for sequences filled from sets or other sources with a
+/// nondeterministic order.  Scalar values are sorted by value, other values
+/// by their JSON representation.  Values that are not sequences are left
+/// untouched.
+///
+/// Example:
+///
+/// ```rust
+/// # use insta::{Settings, sorted_redaction};
+/// # let mut settings = Settings::new();
+/// settings.add_redaction(".members", sorted_redaction());
+/// ```
+pub fn sorted_redaction() -> Redaction {
+    fn json(value: &Content) -> String {
+        serde_json::to_string(value).unwrap_or_default()
+    }
+
+    dynamic_redaction(|mut value, _| {
+        if let Content::Seq(ref mut items) = value {
+            items.sort_by(|a, b| {
+                a.as_key()
+                    .cmp(&b.as_key())
+                    .then_with(|| json(a).cmp(&json(b)))
+            });
+        }
+        value
+    })
+}
+
+/// Creates a redaction that rounds floats to a number of decimal places.
+///
+/// All floats in the value at the selector (including the ones nested in
+/// sequences or structs) are rounded.  This keeps snapshots of computed
+/// values stable if the last bits differ across platforms.
+///
+/// Example:
+///
+/// ```rust
+/// # use insta::{Settings, rounded_redaction};
+/// # let mut settings = Settings::new();
+/// settings.add_redaction(".geometry", rounded_redaction(4));
+/// ```
+pub fn rounded_redaction(decimals: usize) -> Redaction {
+    let factor = 10f64.powi(decimals as i32);
+    dynamic_redaction(move |mut value, _| {
+        value.walk(&mut |content| {
+            match content {
+                Content::F32(x) => *x = ((f64::from(*x) * factor).round() / factor) as f32,
+                Content::F64(x) => *x = (*x * factor).round() / factor,
+                _ => {}
+            }
+            true
+        });
+        value
+    })
+}
+
+thread_local! {
+    static PLACEHOLDERS: RefCell<Option<HashMap<String, Vec<String>>>> = const { RefCell::new(None) };
+}
+
+/// Runs a function numbering placeholder redactions across all redactions
+/// applied in it.
+///
+/// Nested calls share the numbering of the outermost one so that all
+/// redactions of one snapshot see the same placeholders.
+pub(crate) fn with_placeholders<R, F: FnOnce() -> R>(f: F) -> R {
+    struct Reset(bool);
+
+    impl Drop for Reset {
+        fn drop(&mut self) {
+            if self.0 {
+                PLACEHOLDERS.with(|x| *x.borrow_mut() = None);
+            }
+        }
+    }
+
+    let _reset = Reset(PLACEHOLDERS.with(|x| {
+        let mut placeholders = x.borrow_mut();
+        placeholders.is_none() && {
+            *placeholders = Some(HashMap::new());
+            true
+        }
+    }));
+    f()
+}
+
+/// Creates a redaction that replaces values with numbered placeholders.
+///
+/// Every distinct value is replaced with `[name:1]`, `[name:2]` and so on
+/// in the order they are encountered.  Equal values get the same
+/// placeholder, also across selectors using the same name, so references
+/// between values stay visible in the snapshot.  The numbering starts over
+/// for every snapshot.
+///
+/// Example:
+///
+/// ```rust
+/// # use insta::{Settings, placeholder_redaction};
+/// # let mut settings = Settings::new();
+/// settings.add_redaction(".users[].id", placeholder_redaction("id"));
+/// settings.add_redaction(".posts[].author_id", placeholder_redaction("id"));
+/// ```
+pub fn placeholder_redaction(name: &str) -> Redaction {
+    let name = name.to_string();
+    dynamic_redaction(move |value, _| {
+        let key = serde_json::to_string(&value).unwrap_or_default();
+        let idx = PLACEHOLDERS.with(|x| match *x.borrow_mut() {
+            Some(ref mut placeholders) => {
+                let seen = placeholders.entry(name.clone()).or_default();
+                match seen.iter().position(|x| *x == key) {
+                    Some(idx) => idx,
+                    None => {
+                        seen.push(key);
+                        seen.len() - 1
+                    }
+                }
+            }
+            None => 0,
+        });
+        format!("[{}:{}]", name, idx + 1)
+    })
+}
+
 impl Redaction {
     /// Performs the redaction of the value at the given path.
     fn redact(&self, value: Content, path: &[PathItem]) -> Content {
         match *self {
             Redaction::Static(ref new_val) => new_val.clone(),
             Redaction::Dynamic(ref callback) => callback(value, ContentPath(path)),
+            Redaction::Remove => Content::Unit,
         }
     }
 }
@@ -143,22 +348,7 @@ impl Redaction {
 #[grammar = "select_grammar.pest"]
 pub struct SelectParser;
 
-#[derive(Debug)]
-pub enum PathItem {
-    Content(Content),
-    Field(&'static str),
-    Index(u64, u64),
-}
-
 impl PathItem {
-    fn as_str(&self) -> Option<&str> {
-        match *self {
-            PathItem::Content(ref content) => content.as_str(),
-            PathItem::Field(s) => Some(s),
-            PathItem::Index(..) => None,
-        }
-    }
-
     fn as_u64(&self) -> Option<u64> {
         match *self {
             PathItem::Content(ref content) => content.as_u64(),
@@ -190,11 +380,23 @@ impl PathItem {
     }
 }
 
+/// A regular expression matching keys.
+#[derive(Debug, Clone)]
+pub struct KeyRegex(Regex);
+
+impl PartialEq for KeyRegex {
+    fn eq(&self, other: &KeyRegex) -> bool {
+        self.0.as_str() == other.0.as_str()
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum Segment<'a> {
     DeepWildcard,
     Wildcard,
     Key(Cow<'a, str>),
+    KeyRegex(KeyRegex),
+    Keys,
     Index(u64),
     Range(Option<i64>, Option<i64>),
 }
@@ -207,7 +409,7 @@ pub struct Selector<'a> {
 impl<'a> Selector<'a> {
     pub fn parse(selector: &'a str) -> Result<Selector<'a>, SelectorParseError> {
         let pair = SelectParser::parse(Rule::selectors, selector)
-            .map_err(SelectorParseError)?
+            .map_err(|err| SelectorParseError(err.renamed_rules(describe_rule)))?
             .next()
             .unwrap();
         let mut rv = vec![];
@@ -236,6 +438,21 @@ impl<'a> Selector<'a> {
                         Segment::DeepWildcard
                     }
                     Rule::key => Segment::Key(Cow::Borrowed(&segment_pair.as_str()[1..])),
+                    Rule::keys => Segment::Keys,
+                    Rule::key_regex => {
+                        let pattern = segment_pair.as_str();
+                        match Regex::new(&pattern[3..pattern.len() - 2]) {
+                            Ok(regex) => Segment::KeyRegex(KeyRegex(regex)),
+                            Err(err) => {
+                                return Err(SelectorParseError(pest::error::Error::new_from_span(
+                                    pest::error::ErrorVariant::CustomError {
+                                        message: format!("invalid key regex: {}", err),
+                                    },
+                                    segment_pair.as_span(),
+                                )))
+                            }
+                        }
+                    }
                     Rule::subscript => {
                         let subscript_rule = segment_pair.into_inner().next().unwrap();
                         match subscript_rule.as_rule() {
@@ -285,6 +502,16 @@ impl<'a> Selector<'a> {
                     _ => unreachable!(),
                 });
             }
+            if let Some(idx) = segments.iter().position(|x| *x == Segment::Keys) {
+                if idx + 1 != segments.len() {
+                    return Err(SelectorParseError(pest::error::Error::new_from_pos(
+                        pest::error::ErrorVariant::CustomError {
+                            message: "{keys} can only be used at the end of a selector".into(),
+                        },
+                        pest::Position::from_start(selector),
+                    )));
+                }
+            }
             rv.push(segments);
         }
 
@@ -301,6 +528,8 @@ impl<'a> Selector<'a> {
                         .into_iter()
                         .map(|x| match x {
                             Segment::Key(x) => Segment::Key(Cow::Owned(x.into_owned())),
+                            Segment::KeyRegex(x) => Segment::KeyRegex(x),
+                            Segment::Keys => Segment::Keys,
                             Segment::Index(x) => Segment::Index(x),
                             Segment::Wildcard => Segment::Wildcard,
                             Segment::DeepWildcard => Segment::DeepWildcard,
@@ -316,7 +545,9 @@ impl<'a> Selector<'a> {
         match *segment {
             Segment::Wildcard => true,
             Segment::DeepWildcard => true,
-            Segment::Key(ref k) => element.as_str() == Some(&k),
+            Segment::Keys => true,
+            Segment::Key(ref k) => element.as_str() == Some(k),
+            Segment::KeyRegex(ref r) => element.as_str().is_some_and(|x| r.0.is_match(x)),
             Segment::Index(i) => element.as_u64() == Some(i),
             Segment::Range(start, end) => element.range_check(start, end),
         }
@@ -357,9 +588,20 @@ impl<'a> Selector<'a> {
         }
     }
 
-    pub fn is_match(&self, path: &[PathItem]) -> bool {
+    pub(crate) fn is_match(&self, path: &[PathItem]) -> bool {
         for selector in &self.selectors {
-            if self.selector_is_match(&selector, path) {
+            if selector.last() != Some(&Segment::Keys) && self.selector_is_match(selector, path) {
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Checks if the key that is the last item of the path is selected
+    /// with `{keys}`.
+    fn is_key_match(&self, path: &[PathItem]) -> bool {
+        for selector in &self.selectors {
+            if selector.last() == Some(&Segment::Keys) && self.selector_is_match(selector, path) {
                 return true;
             }
         }
@@ -367,7 +609,12 @@ impl<'a> Selector<'a> {
     }
 
     pub fn redact(&self, value: Content, redaction: &Redaction) -> Content {
-        self.redact_impl(value, redaction, &mut vec![])
+        with_placeholders(|| self.redact_impl(value, redaction, &mut vec![]))
+    }
+
+    /// Checks if the value at the path is removed by the redaction.
+    fn is_removed(&self, redaction: &Redaction, path: &[PathItem]) -> bool {
+        matches!(*redaction, Redaction::Remove) && self.is_match(path)
     }
 
     fn redact_seq(
@@ -379,9 +626,13 @@ impl<'a> Selector<'a> {
         let len = seq.len();
         seq.into_iter()
             .enumerate()
-            .map(|(idx, value)| {
+            .filter_map(|(idx, value)| {
                 path.push(PathItem::Index(idx as u64, len as u64));
-                let new_value = self.redact_impl(value, redaction, path);
+                let new_value = if self.is_removed(redaction, path) {
+                    None
+                } else {
+                    Some(self.redact_impl(value, redaction, path))
+                };
                 path.pop();
                 new_value
             })
@@ -395,11 +646,15 @@ impl<'a> Selector<'a> {
         path: &mut Vec<PathItem>,
     ) -> Vec<(&'static str, Content)> {
         seq.into_iter()
-            .map(|(key, value)| {
+            .filter_map(|(key, value)| {
                 path.push(PathItem::Field(key));
-                let new_value = self.redact_impl(value, redaction, path);
+                let new_value = if self.is_removed(redaction, path) {
+                    None
+                } else {
+                    Some((key, self.redact_impl(value, redaction, path)))
+                };
                 path.pop();
-                (key, new_value)
+                new_value
             })
             .collect()
     }
@@ -410,17 +665,29 @@ impl<'a> Selector<'a> {
         redaction: &Redaction,
         path: &mut Vec<PathItem>,
     ) -> Content {
-        if self.is_match(&path) {
+        if self.is_match(path) {
             redaction.redact(value, path)
         } else {
             match value {
                 Content::Map(map) => Content::Map(
                     map.into_iter()
-                        .map(|(key, value)| {
+                        .filter_map(|(key, value)| {
                             path.push(PathItem::Content(key.clone()));
-                            let new_value = self.redact_impl(value, redaction, path);
+                            let rv = if self.is_removed(redaction, path) {
+                                None
+                            } else if self.is_key_match(path) {
+                                match *redaction {
+                                    Redaction::Remove => None,
+                                    _ => {
+                                        let new_value = self.redact_impl(value, redaction, path);
+                                        Some((redaction.redact(key, path), new_value))
+                                    }
+                                }
+                            } else {
+                                Some((key, self.redact_impl(value, redaction, path)))
+                            };
                             path.pop();
-                            (key, new_value)
+                            rv
                         })
                         .collect(),
                 ),
@@ -461,12 +728,12 @@ impl<'a> Selector<'a> {
 
 #[test]
 fn test_range_checks() {
-    assert_eq!(PathItem::Index(0, 10).range_check(None, Some(-1)), true);
-    assert_eq!(PathItem::Index(9, 10).range_check(None, Some(-1)), false);
-    assert_eq!(PathItem::Index(0, 10).range_check(Some(1), Some(-1)), false);
-    assert_eq!(PathItem::Index(1, 10).range_check(Some(1), Some(-1)), true);
-    assert_eq!(PathItem::Index(9, 10).range_check(Some(1), Some(-1)), false);
-    assert_eq!(PathItem::Index(0, 10).range_check(Some(1), None), false);
-    assert_eq!(PathItem::Index(1, 10).range_check(Some(1), None), true);
-    assert_eq!(PathItem::Index(9, 10).range_check(Some(1), None), true);
+    assert!(PathItem::Index(0, 10).range_check(None, Some(-1)));
+    assert!(!PathItem::Index(9, 10).range_check(None, Some(-1)));
+    assert!(!PathItem::Index(0, 10).range_check(Some(1), Some(-1)));
+    assert!(PathItem::Index(1, 10).range_check(Some(1), Some(-1)));
+    assert!(!PathItem::Index(9, 10).range_check(Some(1), Some(-1)));
+    assert!(!PathItem::Index(0, 10).range_check(Some(1), None));
+    assert!(PathItem::Index(1, 10).range_check(Some(1), None));
+    assert!(PathItem::Index(9, 10).range_check(Some(1), None));
 }
@@ -1,28 +1,47 @@
+//! Runtime support for snapshot assertions.
+//!
+//! Most users interact with this through the assertion macros.  The
+//! [`assert_snapshot`](fn.assert_snapshot.html) function together with
+//! [`SnapshotContext`](struct.SnapshotContext.html) exposes the same engine
+//! for custom test harnesses that cannot rely on macro expansion.
+
 use std::borrow::Cow;
+use std::cell::RefCell;
 use std::collections::BTreeMap;
 use std::env;
 use std::error::Error;
 use std::fmt;
 use std::fs;
-use std::io::Write;
+use std::io::{self, BufRead, IsTerminal, Write};
 use std::path::{Path, PathBuf};
 use std::process::{Command, Stdio};
 use std::str;
 use std::sync::Mutex;
 use std::thread;
+use std::time::SystemTime;
 
 use difference::{Changeset, Difference};
 use lazy_static::lazy_static;
 
 use serde::Deserialize;
 
-use crate::settings::Settings;
-use crate::snapshot::{MetaData, PendingInlineSnapshot, Snapshot, SnapshotContents};
-use crate::utils::{is_ci, style};
+use crate::ansi::normalize_ansi_escapes;
+use crate::config::{tool_config, tool_config_error};
+use crate::diff::get_changeset;
+#[cfg(feature = "filters")]
+use crate::filters::apply_filters;
+use crate::settings::{Settings, SnapshotPathBase, Whitespace};
+use crate::snapshot::{
+    MetaData, PendingInlineSnapshot, Snapshot, SnapshotContents, LARGE_SNAPSHOT_SIZE,
+};
+use crate::utils::{fnv1a, is_ci, style};
 
 lazy_static! {
     static ref WORKSPACES: Mutex<BTreeMap<String, &'static Path>> = Mutex::new(BTreeMap::new());
     static ref TEST_NAME_COUNTERS: Mutex<BTreeMap<String, usize>> = Mutex::new(BTreeMap::new());
+    static ref SNAPSHOT_CACHE: Mutex<BTreeMap<PathBuf, (SystemTime, u64, Snapshot)>> =
+        Mutex::new(BTreeMap::new());
+    static ref PROMPT_LOCK: Mutex<()> = Mutex::new(());
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -37,6 +56,7 @@ enum OutputBehavior {
     Diff,
     Summary,
     Minimal,
+    Diagnostics,
     Nothing,
 }
 
@@ -96,7 +116,6 @@ fn format_rust_expression(value: &str) -> Cow<'_, str> {
 
 #[test]
 fn test_format_rust_expression() {
-    use crate::assert_snapshot;
     assert_snapshot!(format_rust_expression("vec![1,2,3]"), @"vec![1, 2, 3]");
     assert_snapshot!(format_rust_expression("vec![1,2,3].iter()"), @"vec![1, 2, 3].iter()");
     assert_snapshot!(format_rust_expression(r#"    "aoeu""#), @r###""aoeu""###);
@@ -105,7 +124,13 @@ fn test_format_rust_expression() {
 }
 
 fn update_snapshot_behavior(unseen: bool) -> UpdateBehavior {
-    match env::var("INSTA_UPDATE").ok().as_deref() {
+    let config = &tool_config().behavior;
+    match Settings::with(|settings| settings.snapshot_update())
+        .map(|x| x.as_str().to_string())
+        .or_else(|| env::var("INSTA_UPDATE").ok())
+        .or_else(|| config.update.clone())
+        .as_deref()
+    {
         None | Some("") | Some("auto") => {
             if is_ci() {
                 UpdateBehavior::NoUpdate
@@ -123,14 +148,109 @@ fn update_snapshot_behavior(unseen: bool) -> UpdateBehavior {
             }
         }
         Some("no") => UpdateBehavior::NoUpdate,
-        _ => panic!("invalid value for INSTA_UPDATE"),
+        _ => panic!("invalid value for INSTA_UPDATE (or behavior.update in insta.toml)"),
+    }
+}
+
+/// What to do with a changed snapshot according to the interactive prompt.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum PromptChoice {
+    Accept,
+    Reject,
+    Skip,
+}
+
+/// Asks whether a changed snapshot should be accepted.
+///
+/// This is only done if `INSTA_INTERACTIVE=1` is set and the tests run
+/// locally on a terminal with `--nocapture` as the diff would not be
+/// visible otherwise.
+fn prompt_for_snapshot() -> Option<PromptChoice> {
+    if env::var("INSTA_INTERACTIVE").as_deref() != Ok("1") || is_ci() {
+        return None;
+    }
+    let nocapture = env::args().any(|x| x == "--nocapture")
+        || env::var("RUST_TEST_NOCAPTURE").is_ok_and(|x| x != "0");
+    if !nocapture || !io::stdin().is_terminal() || !io::stderr().is_terminal() {
+        return None;
+    }
+
+    // tests can run in parallel, only ask one question at the time.
+    let _guard = PROMPT_LOCK.lock().unwrap_or_else(|x| x.into_inner());
+    loop {
+        eprint!(
+            "{} accept (a), reject (r) or skip (s)? ",
+            style("snapshot changed:").bold()
+        );
+        let mut answer = String::new();
+        if io::stdin().read_line(&mut answer).ok()? == 0 {
+            return None;
+        }
+        match answer.trim() {
+            "a" | "accept" => return Some(PromptChoice::Accept),
+            "r" | "reject" => return Some(PromptChoice::Reject),
+            "s" | "skip" => return Some(PromptChoice::Skip),
+            _ => {}
+        }
+    }
+}
+
+fn is_large_snapshot_file(snapshot_file: &Path) -> bool {
+    fs::metadata(snapshot_file).is_ok_and(|x| x.len() > LARGE_SNAPSHOT_SIZE as u64)
+}
+
+/// Loads a snapshot file, reusing the parsed snapshot of earlier loads.
+///
+/// Tests that are run repeatedly or share snapshots would otherwise read and
+/// parse the same files over and over.  Entries are keyed by the path and
+/// invalidated when the modification time or size of the file changes.
+fn load_snapshot_cached(snapshot_file: &Path) -> Result<Option<Snapshot>, Box<dyn Error>> {
+    let metadata = match fs::metadata(snapshot_file) {
+        Ok(metadata) => metadata,
+        Err(_) => return Ok(None),
+    };
+    let mtime = metadata.modified()?;
+    let len = metadata.len();
+    // we really do not care about poisoning here.
+    let mut cache = SNAPSHOT_CACHE.lock().unwrap_or_else(|x| x.into_inner());
+    if let Some((cached_mtime, cached_len, snapshot)) = cache.get(snapshot_file) {
+        if *cached_mtime == mtime && *cached_len == len {
+            return Ok(Some(snapshot.clone()));
+        }
     }
+    let snapshot = Snapshot::from_file(snapshot_file)?;
+    // caching large snapshots would keep them in memory for the whole run
+    if len > LARGE_SNAPSHOT_SIZE as u64 {
+        return Ok(Some(snapshot));
+    }
+    cache.insert(snapshot_file.to_path_buf(), (mtime, len, snapshot.clone()));
+    Ok(Some(snapshot))
+}
+
+/// Rejects snapshot files with a legacy metadata header.
+///
+/// This is used if the `require_full_match` setting is enabled.
+fn check_full_match(snapshot_file: &Path) -> Result<(), Box<dyn Error>> {
+    let mut first_line = String::new();
+    match fs::File::open(snapshot_file) {
+        Ok(f) => io::BufReader::new(f).read_line(&mut first_line)?,
+        Err(_) => return Ok(()),
+    };
+    if first_line.trim_end() != "---" {
+        return Err(format!(
+            "snapshot file {} uses a legacy format which is rejected as \
+             require_full_match is set.  Rewrite it with `cargo insta test \
+             --accept --force-update-snapshots`.",
+            snapshot_file.display()
+        )
+        .into());
+    }
+    Ok(())
 }
 
 fn memoize_snapshot_file(snapshot_file: &Path) {
     if let Ok(path) = env::var("INSTA_SNAPSHOT_REFERENCES_FILE") {
         let mut f = fs::OpenOptions::new()
-            .write(true)
             .append(true)
             .create(true)
             .open(path)
@@ -140,27 +260,71 @@ fn memoize_snapshot_file(snapshot_file: &Path) {
     }
 }
 
+/// Records which test used which snapshot if requested.
+///
+/// Every assertion appends one JSON object per line to the file named by
+/// `INSTA_SNAPSHOT_USAGE_FILE`.  `cargo insta coverage` aggregates these
+/// into a report.
+fn record_snapshot_usage(
+    test_name: Option<&str>,
+    manifest_dir: &str,
+    module_path: &str,
+    file: &str,
+    line: u32,
+    snapshot_file: Option<&Path>,
+) {
+    if let Ok(path) = env::var("INSTA_SNAPSHOT_USAGE_FILE") {
+        let thread = thread::current();
+        let record = serde_json::json!({
+            "test": test_name.or_else(|| thread.name()),
+            "manifest_dir": manifest_dir,
+            "module": module_path,
+            "source": path_to_storage(file),
+            "line": line,
+            "snapshot": snapshot_file.map(path_to_storage),
+            "tags": Settings::with(|settings| settings.tags().to_vec()),
+        });
+        let mut f = fs::OpenOptions::new()
+            .append(true)
+            .create(true)
+            .open(path)
+            .unwrap();
+        f.write_all(format!("{}\n", record).as_bytes()).unwrap();
+    }
+}
+
 fn output_snapshot_behavior() -> OutputBehavior {
-    match env::var("INSTA_OUTPUT").ok().as_deref() {
+    let config = &tool_config().behavior;
+    match env::var("INSTA_OUTPUT")
+        .ok()
+        .or_else(|| config.output.clone())
+        .as_deref()
+    {
         None | Some("") | Some("diff") => OutputBehavior::Diff,
         Some("summary") => OutputBehavior::Summary,
         Some("minimal") => OutputBehavior::Minimal,
+        Some("diagnostics") => OutputBehavior::Diagnostics,
         Some("none") => OutputBehavior::Nothing,
-        _ => panic!("invalid value for INSTA_OUTPUT"),
+        _ => panic!("invalid value for INSTA_OUTPUT (or behavior.output in insta.toml)"),
     }
 }
 
 fn force_update_snapshots() -> bool {
     match env::var("INSTA_FORCE_UPDATE_SNAPSHOTS").ok().as_deref() {
-        None | Some("") | Some("0") => false,
+        None => tool_config().behavior.force_update.unwrap_or(false),
+        Some("") | Some("0") => false,
         Some("1") => true,
         _ => panic!("invalid value for INSTA_FORCE_UPDATE_SNAPSHOTS"),
     }
 }
 
 fn should_fail_in_tests() -> bool {
+    if let Some(force_pass) = Settings::with(|settings| settings.force_pass()) {
+        return !force_pass;
+    }
     match env::var("INSTA_FORCE_PASS").ok().as_deref() {
-        None | Some("") | Some("0") => true,
+        None => !tool_config().behavior.force_pass.unwrap_or(false),
+        Some("") | Some("0") => true,
         Some("1") => false,
         _ => panic!("invalid value for INSTA_FORCE_PASS"),
     }
@@ -172,11 +336,16 @@ fn get_cargo() -> String {
         .unwrap_or_else(|| "cargo".to_string())
 }
 
+#[doc(hidden)]
 pub fn get_cargo_workspace(manifest_dir: &str) -> &Path {
     // we really do not care about poisoning here.
     let mut workspaces = WORKSPACES.lock().unwrap_or_else(|x| x.into_inner());
     if let Some(rv) = workspaces.get(manifest_dir) {
         rv
+    } else if let Ok(root) = env::var("INSTA_WORKSPACE_ROOT") {
+        let path = Box::leak(Box::new(Path::new(manifest_dir).join(root)));
+        workspaces.insert(manifest_dir.to_string(), path.as_path());
+        workspaces.get(manifest_dir).unwrap()
     } else {
         #[derive(Deserialize)]
         struct Manifest {
@@ -196,6 +365,36 @@ pub fn get_cargo_workspace(manifest_dir: &str) -> &Path {
     }
 }
 
+/// Returns the workspace root the source paths of the crate are relative to.
+///
+/// This is the root set in the settings, `INSTA_WORKSPACE_ROOT` or the
+/// workspace root reported by cargo in that order.
+#[doc(hidden)]
+pub fn resolve_workspace_root(manifest_dir: &str) -> PathBuf {
+    match Settings::with(|settings| settings.workspace_root().map(|x| x.to_path_buf())) {
+        Some(root) => Path::new(manifest_dir).join(root),
+        None => get_cargo_workspace(manifest_dir).to_path_buf(),
+    }
+}
+
+/// Applies the source path remaps of the settings to a path from `file!()`.
+fn remap_source_path(file: &str) -> Cow<'_, str> {
+    Settings::with(|settings| {
+        for (from, to) in settings.source_path_remaps() {
+            if let Ok(rest) = Path::new(file).strip_prefix(from) {
+                return Cow::Owned(path_to_storage(to.join(rest)));
+            }
+        }
+        Cow::Borrowed(file)
+    })
+}
+
+/// Returns the absolute path of a source file reported by `file!()`.
+#[doc(hidden)]
+pub fn resolve_source_file(manifest_dir: &str, file: &str) -> PathBuf {
+    resolve_workspace_root(manifest_dir).join(&*remap_source_path(file))
+}
+
 fn print_changeset(changeset: Changeset, expr: Option<&str>) {
     let Changeset { diffs, .. } = changeset;
     #[derive(PartialEq, Debug)]
@@ -322,35 +521,74 @@ fn print_changeset(changeset: Changeset, expr: Option<&str>) {
     println!("────────────┴{:─^1$}", "", width.saturating_sub(13),);
 }
 
+/// Longest file name (without the `.snap` extension) snapshots are stored
+/// under.  Most file systems allow 255 bytes, the rest leaves room for the
+/// extension and the suffixes of pending snapshots.
+const MAX_SNAPSHOT_FILE_STEM: usize = 200;
+
+/// Shortens overly long snapshot file names.
+///
+/// Names over the limit are truncated and get a hash of the full name
+/// appended so that they stay unique.  The result only depends on the
+/// name, so saving, loading and reviewing all end up at the same file.
+#[doc(hidden)]
+pub fn shorten_snapshot_file_stem(stem: &str) -> Cow<'_, str> {
+    if stem.len() <= MAX_SNAPSHOT_FILE_STEM {
+        return Cow::Borrowed(stem);
+    }
+    let mut end = MAX_SNAPSHOT_FILE_STEM - 17;
+    while !stem.is_char_boundary(end) {
+        end -= 1;
+    }
+    Cow::Owned(format!("{}-{:016x}", &stem[..end], fnv1a(stem.as_bytes())))
+}
+
+#[doc(hidden)]
 pub fn get_snapshot_filename(
     module_path: &str,
     snapshot_name: &str,
     cargo_workspace: &Path,
+    manifest_dir: &str,
     base: &str,
 ) -> PathBuf {
     let root = Path::new(cargo_workspace);
     let base = Path::new(base);
     Settings::with(|settings| {
-        root.join(base.parent().unwrap())
-            .join(settings.snapshot_path())
-            .join({
-                use std::fmt::Write;
-                let mut f = String::new();
-                if settings.prepend_module_to_snapshot() {
-                    write!(&mut f, "{}__", module_path.replace("::", "__")).unwrap();
-                }
-                write!(
-                    &mut f,
-                    "{}.snap",
-                    snapshot_name.replace("/", "__").replace("\\", "__")
-                )
-                .unwrap();
-                f
-            })
+        let dir = match settings.snapshot_path_base() {
+            SnapshotPathBase::SourceFile => root.join(base.parent().unwrap()),
+            SnapshotPathBase::Crate => PathBuf::from(manifest_dir),
+            SnapshotPathBase::Workspace => root.to_path_buf(),
+        };
+        dir.join(settings.snapshot_path()).join({
+            use std::fmt::Write;
+            let mut f = String::new();
+            if settings.prepend_module_to_snapshot() {
+                write!(&mut f, "{}__", module_path.replace("::", "__")).unwrap();
+            }
+            write!(
+                &mut f,
+                "{}",
+                snapshot_name.replace("/", "__").replace("\\", "__")
+            )
+            .unwrap();
+            format!(
+                "{}.{}",
+                shorten_snapshot_file_stem(&f),
+                settings.snapshot_extension()
+            )
+        })
     })
 }
 
+/// Returns the path of the pending snapshot file for a snapshot file.
+fn get_pending_snapshot_filename(snapshot_file: &Path) -> PathBuf {
+    let mut rv = snapshot_file.as_os_str().to_owned();
+    rv.push(".new");
+    rv.into()
+}
+
 /// Prints the summary of a snapshot
+#[doc(hidden)]
 pub fn print_snapshot_summary(
     workspace_root: &Path,
     snapshot: &Snapshot,
@@ -375,6 +613,24 @@ pub fn print_snapshot_summary(
         println!("Snapshot: {}", style("<inline>").dim());
     }
 
+    if let Some(description) = snapshot.metadata().description() {
+        if description.contains('\n') {
+            println!("Description:");
+            for line in description.lines() {
+                println!("  {}", style(line).italic());
+            }
+        } else {
+            println!("Description: {}", style(description).italic());
+        }
+    }
+
+    if let Some(info) = snapshot.metadata().info() {
+        println!("Info:");
+        for line in info_to_string(info).lines() {
+            println!("  {}", style(line).dim());
+        }
+    }
+
     if let Some(ref value) = snapshot.metadata().get_relative_source(workspace_root) {
         println!(
             "Source: {}{}",
@@ -388,39 +644,55 @@ pub fn print_snapshot_summary(
     }
 
     if let Some(ref value) = snapshot.metadata().input_file() {
-        println!("Input file: {}", style(value).cyan());
-    }
-}
-
-/// Calculates `difference::Changeset` of given files.
-/// Falls back to manually constructed `Changeset`
-/// for large inputs to avoid OOM in `difference`.
-fn get_changeset(orig: &str, edit: &str) -> Changeset {
-    const FALLBACK_THRESHOLD: usize = 10_000_000;
-    let orig_cnt = orig.lines().count();
-    let edited_cnt = edit.lines().count();
-    if orig_cnt * edited_cnt > FALLBACK_THRESHOLD {
-        // `difference` crate will likely use hundreds of megabytes
-        // possibly leading to OOM.
-
-        let mut changeset = Changeset {
-            diffs: Vec::new(),
-            // following two fields will not be read anyway
-            split: String::new(),
-            distance: 0,
-        };
-        for line in orig.lines() {
-            changeset.diffs.push(Difference::Rem(line.to_string()));
-        }
-        for line in edit.lines() {
-            changeset.diffs.push(Difference::Add(line.to_string()));
-        }
-        return changeset;
+        println!(
+            "Input file: {}{}",
+            style(value).cyan(),
+            if workspace_root.join(value).exists() {
+                "".to_string()
+            } else {
+                format!(" {}", style("(deleted)").red())
+            }
+        );
+    }
+}
+
+/// Renders the info of a snapshot like it is stored in the metadata.
+fn info_to_string(info: &serde_yaml::Value) -> String {
+    match *info {
+        serde_yaml::Value::String(ref value) => value.clone(),
+        _ => serde_yaml::to_string(info)
+            .unwrap()
+            .trim_start_matches("---\n")
+            .trim_end()
+            .to_string(),
     }
-    Changeset::new(orig, edit, "\n")
+}
+
+/// Returns the input file of the settings relative to the workspace.
+///
+/// Relative paths are resolved against the crate and, for compatibility,
+/// the workspace root.  Input files outside of the workspace are not
+/// recorded.
+fn get_input_file(manifest_dir: &str, cargo_workspace: &Path) -> Option<String> {
+    let path = Settings::with(|settings| settings.input_file().map(|x| x.to_path_buf()))?;
+    let in_crate = Path::new(manifest_dir).join(&path);
+    let path = if in_crate.exists() {
+        in_crate
+    } else {
+        cargo_workspace.join(&path)
+    };
+    let path = path.canonicalize().unwrap_or(path);
+    let workspace = cargo_workspace
+        .canonicalize()
+        .unwrap_or_else(|_| cargo_workspace.to_path_buf());
+    path.strip_prefix(&workspace)
+        .or_else(|_| path.strip_prefix(cargo_workspace))
+        .ok()
+        .map(path_to_storage)
 }
 
 /// Prints a diff against an old snapshot.
+#[doc(hidden)]
 pub fn print_snapshot_diff(
     workspace_root: &Path,
     new: &Snapshot,
@@ -431,7 +703,7 @@ pub fn print_snapshot_diff(
     print_snapshot_summary(workspace_root, new, snapshot_file, line);
     let changeset = get_changeset(
         old_snapshot.as_ref().map_or("", |x| x.contents_str()),
-        &new.contents_str(),
+        new.contents_str(),
     );
     if old_snapshot.is_some() {
         println!("{}", style("-old snapshot").red());
@@ -442,6 +714,108 @@ pub fn print_snapshot_diff(
     print_changeset(changeset, new.metadata().expression.as_deref());
 }
 
+/// Prints a failed assertion as rustc JSON diagnostic.
+///
+/// This is the format `cargo check --message-format=json` emits for
+/// compiler messages which editors (eg: rust-analyzer's flycheck) can
+/// show inline at the assertion.
+fn print_snapshot_diagnostic(
+    new_snapshot: &Snapshot,
+    old_snapshot: Option<&Snapshot>,
+    line: u32,
+    snapshot_file: Option<&Path>,
+) {
+    println!(
+        "{}",
+        snapshot_diagnostic(new_snapshot, old_snapshot, line, snapshot_file)
+    );
+}
+
+fn snapshot_diagnostic(
+    new_snapshot: &Snapshot,
+    old_snapshot: Option<&Snapshot>,
+    line: u32,
+    snapshot_file: Option<&Path>,
+) -> serde_json::Value {
+    let file = new_snapshot.metadata().source().unwrap_or("");
+    let message = match (new_snapshot.snapshot_name(), old_snapshot) {
+        (Some(name), Some(_)) => format!("snapshot '{}' does not match", name),
+        (Some(name), None) => format!("snapshot '{}' is new", name),
+        (None, Some(_)) => "snapshot does not match".to_string(),
+        (None, None) => "snapshot is new".to_string(),
+    };
+
+    let mut rendered = format!("error: {}\n --> {}:{}\n", message, file, line);
+    if let Some(snapshot_file) = snapshot_file {
+        rendered.push_str(&format!("  = snapshot file: {}\n", snapshot_file.display()));
+    }
+    if let Some(description) = new_snapshot.metadata().description() {
+        let description = description.replace('\n', "\n                 ");
+        rendered.push_str(&format!("  = description: {}\n", description));
+    }
+    let changeset = get_changeset(
+        old_snapshot.map_or("", |x| x.contents_str()),
+        new_snapshot.contents_str(),
+    );
+    for diff in changeset.diffs {
+        let (prefix, text) = match diff {
+            Difference::Same(ref x) => (" ", x),
+            Difference::Add(ref x) => ("+", x),
+            Difference::Rem(ref x) => ("-", x),
+        };
+        for text_line in text.lines() {
+            rendered.push_str(&format!("{}{}\n", prefix, text_line));
+        }
+    }
+
+    let help = "run `cargo insta review` to review the new snapshot";
+    serde_json::json!({
+        "$message_type": "diagnostic",
+        "message": message,
+        "code": null,
+        "level": "error",
+        "spans": [{
+            "file_name": file,
+            "byte_start": 0,
+            "byte_end": 0,
+            "line_start": line,
+            "line_end": line,
+            "column_start": 1,
+            "column_end": 1,
+            "is_primary": true,
+            "text": [],
+            "label": null,
+            "suggested_replacement": null,
+            "suggestion_applicability": null,
+            "expansion": null,
+        }],
+        "children": [{
+            "message": help,
+            "code": null,
+            "level": "help",
+            "spans": [],
+            "children": [],
+            "rendered": null,
+        }],
+        "rendered": rendered,
+    })
+}
+
+/// Renders the difference of a failed snapshot assertion.
+///
+/// The default renderer prints a line based diff.  A custom renderer can be
+/// registered with [`Settings::set_diff_renderer`](../struct.Settings.html#method.set_diff_renderer)
+/// to substitute a different presentation (eg: a tree diff for structured
+/// formats).  It's only used for the output of failing assertions, how
+/// snapshots are stored and reviewed stays the same.
+pub trait DiffRenderer: Send + Sync {
+    /// Renders the difference between the old and the new snapshot contents.
+    ///
+    /// `old` is `None` if there is no stored snapshot yet.  The returned
+    /// string is printed below the snapshot summary.
+    fn render(&self, old: Option<&str>, new: &str) -> String;
+}
+
 fn print_snapshot_diff_with_title(
     workspace_root: &Path,
     new_snapshot: &Snapshot,
@@ -455,6 +829,17 @@ fn print_snapshot_diff_with_title(
         title = style(" Snapshot Differences ").bold(),
         width = width
     );
+    if let Some(renderer) = Settings::with(|settings| settings.diff_renderer()) {
+        print_snapshot_summary(workspace_root, new_snapshot, snapshot_file, Some(line));
+        println!(
+            "{}",
+            renderer.render(
+                old_snapshot.map(|x| x.contents_str()),
+                new_snapshot.contents_str()
+            )
+        );
+        return;
+    }
     print_snapshot_diff(
         workspace_root,
         new_snapshot,
@@ -520,9 +905,30 @@ impl<'a> From<&'a str> for ReferenceValue<'a> {
     }
 }
 
+impl<'a> From<Option<&'a String>> for ReferenceValue<'a> {
+    fn from(value: Option<&'a String>) -> ReferenceValue<'a> {
+        ReferenceValue::Named(value.map(|x| Cow::Borrowed(x.as_str())))
+    }
+}
+
+impl<'a> From<&'a String> for ReferenceValue<'a> {
+    fn from(value: &'a String) -> ReferenceValue<'a> {
+        ReferenceValue::Named(Some(Cow::Borrowed(value.as_str())))
+    }
+}
+
+impl<'a> From<Option<ReferenceValue<'a>>> for ReferenceValue<'a> {
+    fn from(value: Option<ReferenceValue<'a>>) -> ReferenceValue<'a> {
+        value.unwrap_or(ReferenceValue::Named(None))
+    }
+}
+
+#[doc(hidden)]
 pub enum ReferenceValue<'a> {
     Named(Option<Cow<'a, str>>),
     Inline(&'a str),
+    /// The full contents of a snapshot file embedded into the binary.
+    Embedded(&'a str),
 }
 
 #[cfg(feature = "backtrace")]
@@ -547,7 +953,11 @@ fn test_name_from_backtrace(module_path: &str) -> Result<String, &'static str> {
             if rv.ends_with("::{{closure}}") {
                 rv = &rv[..rv.len() - 13];
             }
-            return Ok(rv.to_string());
+            // test names are relative to the crate like thread names
+            return Ok(match rv.find("::") {
+                Some(idx) => rv[idx + 2..].to_string(),
+                None => rv.to_string(),
+            });
         }
     }
 
@@ -557,7 +967,39 @@ fn test_name_from_backtrace(module_path: &str) -> Result<String, &'static str> {
     )
 }
 
-fn generate_snapshot_name_for_thread(module_path: &str) -> Result<String, &'static str> {
+/// Checks if an assertion is run from a doctest.
+///
+/// rustdoc compiles every doctest as its own crate named `rust_out`.
+fn is_doctest(module_path: &str) -> bool {
+    module_path == "rust_out" || module_path.starts_with("rust_out::")
+}
+
+/// Derives the module path for snapshots of doctests from their file.
+///
+/// The module path of a doctest carries no information on where it was
+/// defined, so snapshots are named after the file instead (eg: doctests in
+/// `src/foo/bar.rs` use `doctest::foo::bar`).
+fn get_doctest_module_path(file: &str) -> String {
+    let path = file.replace('\\', "/");
+    let path = path.strip_prefix("src/").unwrap_or(&path);
+    let path = path.strip_suffix(".rs").unwrap_or(path);
+    format!("doctest::{}", path.replace('/', "::"))
+}
+
+/// Generates the name of an unnamed snapshot from the running test.
+///
+/// Returns the module path the snapshot file is named after along with the
+/// snapshot name.  A test name set in the settings wins over the name of
+/// the thread the test runs on.
+fn generate_snapshot_name_for_thread(
+    module_path: &str,
+) -> Result<(Cow<'_, str>, String), &'static str> {
+    if let Some(name) = Settings::with(|settings| settings.test_name().map(|x| x.to_string())) {
+        return Ok((
+            Cow::Borrowed(module_path),
+            generate_snapshot_name(module_path, &name),
+        ));
+    }
     let thread = thread::current();
     #[allow(unused_mut)]
     let mut name = Cow::Borrowed(
@@ -578,9 +1020,68 @@ fn generate_snapshot_name_for_thread(module_path: &str) -> Result<String, &'stat
                  names from the call stack.");
         }
     }
+    if !Settings::with(|settings| settings.name_from_test_path()) {
+        return Ok((
+            Cow::Borrowed(module_path),
+            generate_snapshot_name(module_path, &name),
+        ));
+    }
+    let (module_path, test_name) = resolve_test_path(module_path, &name);
+    let name = generate_snapshot_name(&module_path, &test_name);
+    Ok((module_path, name))
+}
+
+/// Resolves the path of a test (relative to the crate root) against the
+/// module the assertion is in.
+///
+/// Tests in or below the module of the assertion are named after the last
+/// segment of their path.  Generated tests in a module right below it (eg:
+/// the cases `rstest` puts into a module named after the test function)
+/// also include that module name so that cases of different tests do not
+/// clash.  If the assertion is in a helper outside of the test's module the
+/// module of the test is used as otherwise all tests calling the helper
+/// would share snapshots.
+fn resolve_test_path<'a>(module_path: &'a str, test_path: &str) -> (Cow<'a, str>, String) {
+    let strip = |x: &str| x.strip_prefix("test_").unwrap_or(x).to_string();
+    let (krate, rel_module) = match module_path.find("::") {
+        Some(idx) => (&module_path[..idx], &module_path[idx + 2..]),
+        None => (module_path, ""),
+    };
+    let rest = if rel_module.is_empty() {
+        Some(test_path)
+    } else {
+        test_path
+            .strip_prefix(rel_module)
+            .and_then(|x| x.strip_prefix("::"))
+    };
+    match rest {
+        Some(rest) => {
+            let name = match rest.split_once("::") {
+                Some((parent, name)) if !name.contains("::") => {
+                    format!("{}__{}", strip(parent), strip(name))
+                }
+                _ => strip(rest.rsplit("::").next().unwrap()),
+            };
+            (Cow::Borrowed(module_path), name)
+        }
+        None => {
+            let (test_module, name) = match test_path.rfind("::") {
+                Some(idx) => (&test_path[..idx], &test_path[idx + 2..]),
+                None => ("", test_path),
+            };
+            let module = if test_module.is_empty() {
+                krate.to_string()
+            } else {
+                format!("{}::{}", krate, test_module)
+            };
+            (Cow::Owned(module), strip(name))
+        }
+    }
+}
 
+fn generate_snapshot_name(module_path: &str, test_name: &str) -> String {
     // clean test name first
-    let mut name = name.rsplit("::").next().unwrap();
+    let mut name = test_name.rsplit("::").next().unwrap();
     if name.starts_with("test_") {
         name = &name[5..];
     }
@@ -600,7 +1101,7 @@ fn generate_snapshot_name_for_thread(module_path: &str) -> Result<String, &'stat
     };
     counters.insert(key, test_idx);
 
-    Ok(rv)
+    rv
 }
 
 /// Helper function that returns the real inline snapshot value from a given
@@ -636,9 +1137,8 @@ pub(super) fn get_inline_snapshot_value(frozen_value: &str) -> String {
                 }
             }
             if let Some(remainder) = line.get(indentation..) {
-                if remainder.starts_with('⋮') {
-                    // 3 because '⋮' is three utf-8 bytes long
-                    buf.push_str(&remainder[3..]);
+                if let Some(remainder) = remainder.strip_prefix('⋮') {
+                    buf.push_str(remainder);
                     buf.push('\n');
                 } else if remainder.trim().is_empty() {
                     continue;
@@ -832,6 +1332,34 @@ a
     );
 }
 
+/// Fails if another snapshot file in the same folder differs from the given
+/// one only in case.
+///
+/// Such files are the same file on case-insensitive file systems (the
+/// default on macOS and Windows) and would overwrite each other.
+fn check_case_collision(snapshot_file: &Path) -> Result<(), Box<dyn Error>> {
+    let (dir, name) = match (snapshot_file.parent(), snapshot_file.file_name()) {
+        (Some(dir), Some(name)) => (dir, name.to_string_lossy()),
+        _ => return Ok(()),
+    };
+    let names = [name.to_lowercase(), format!("{}.new", name.to_lowercase())];
+    for entry in fs::read_dir(dir).into_iter().flatten().flatten() {
+        let other = entry.file_name().to_string_lossy().into_owned();
+        let other_snap = other.strip_suffix(".new").unwrap_or(&other);
+        if names.contains(&other.to_lowercase()) && other_snap != name {
+            return Err(format!(
+                "snapshot file {} collides with {} as their names only differ in \
+                 case and they would overwrite each other on case-insensitive \
+                 file systems.  Rename one of the snapshots.",
+                snapshot_file.display(),
+                dir.join(&other).display(),
+            )
+            .into());
+        }
+    }
+    Ok(())
+}
+
 fn update_snapshots(
     snapshot_file: Option<&Path>,
     new: Snapshot,
@@ -839,14 +1367,29 @@ fn update_snapshots(
     line: u32,
     pending_snapshots: Option<PathBuf>,
     output_behavior: OutputBehavior,
-) -> Result<(), Box<dyn Error>> {
-    let unseen = snapshot_file.map_or(false, |x| fs::metadata(x).is_ok());
-    let should_print = output_behavior != OutputBehavior::Nothing;
+) -> Result<Stored, Box<dyn Error>> {
+    let unseen = snapshot_file.is_some_and(|x| fs::metadata(x).is_ok());
+    // diagnostics are meant to be machine readable, keep them free of noise
+    let should_print = output_behavior != OutputBehavior::Nothing
+        && output_behavior != OutputBehavior::Diagnostics;
+
+    let behavior = update_snapshot_behavior(unseen);
+    if let Some(snapshot_file) = snapshot_file {
+        if behavior != UpdateBehavior::NoUpdate {
+            check_case_collision(snapshot_file)?;
+        }
+    }
 
-    match update_snapshot_behavior(unseen) {
+    match behavior {
         UpdateBehavior::InPlace => {
             if let Some(ref snapshot_file) = snapshot_file {
                 new.save(snapshot_file)?;
+                // file systems with a coarse modification time could
+                // otherwise keep serving the old contents.
+                SNAPSHOT_CACHE
+                    .lock()
+                    .unwrap_or_else(|x| x.into_inner())
+                    .remove(*snapshot_file);
                 if should_print {
                     eprintln!(
                         "{} {}",
@@ -858,7 +1401,7 @@ fn update_snapshots(
                         style(snapshot_file.display()).cyan().underlined(),
                     );
                 }
-                return Ok(());
+                return Ok(Stored::InPlace);
             } else if should_print {
                 eprintln!(
                     "{}",
@@ -869,9 +1412,8 @@ fn update_snapshots(
             }
         }
         UpdateBehavior::NewFile => {
-            if let Some(ref snapshot_file) = snapshot_file {
-                let mut new_path = snapshot_file.to_path_buf();
-                new_path.set_extension("snap.new");
+            if let Some(snapshot_file) = snapshot_file {
+                let new_path = get_pending_snapshot_filename(snapshot_file);
                 new.save(&new_path)?;
                 if should_print {
                     eprintln!(
@@ -880,15 +1422,92 @@ fn update_snapshots(
                         style(new_path.display()).cyan().underlined(),
                     );
                 }
-            } else {
-                PendingInlineSnapshot::new(Some(new), old, line)
-                    .save(pending_snapshots.unwrap())?;
+                return Ok(Stored::Pending(new_path));
+            } else if let Some(pending_snapshots) = pending_snapshots {
+                PendingInlineSnapshot::new(Some(new), old, line).save(&pending_snapshots)?;
+                return Ok(Stored::Pending(pending_snapshots));
             }
         }
         UpdateBehavior::NoUpdate => {}
     }
 
-    Ok(())
+    Ok(Stored::Nowhere)
+}
+
+/// Where the new contents of a failed assertion were stored.
+#[derive(Debug)]
+enum Stored {
+    /// The snapshot was updated in place.
+    InPlace,
+    /// The new snapshot waits for review in this file.
+    Pending(PathBuf),
+    /// The new snapshot was not stored (eg: on CI).
+    Nowhere,
+    /// The new snapshot was rejected at the interactive prompt.
+    Rejected,
+}
+
+/// Returns the command suggested for reviewing snapshots.
+///
+/// `INSTA_REVIEW_HINT` can replace the command (eg: for other build
+/// systems) or disable the hints with `0`.
+fn review_command() -> Option<String> {
+    match env::var("INSTA_REVIEW_HINT").ok().as_deref() {
+        None | Some("") => Some("cargo insta review".into()),
+        Some("0") => None,
+        Some(cmd) => Some(cmd.into()),
+    }
+}
+
+/// Explains what to do about a failed assertion for the panic message.
+fn failure_hint(
+    workspace: &Path,
+    target: Option<&Path>,
+    stored: &Stored,
+    review_command: Option<&str>,
+) -> String {
+    let review_command = match review_command {
+        Some(cmd) => cmd,
+        None => return String::new(),
+    };
+    let display = |path: &Path| {
+        path.strip_prefix(workspace)
+            .unwrap_or(path)
+            .display()
+            .to_string()
+    };
+
+    let mut rv = String::new();
+    if let Some(target) = target {
+        rv.push_str(&format!("\n  snapshot file: {}", display(target)));
+    }
+    match *stored {
+        Stored::InPlace => {
+            rv.push_str("\n  the snapshot was updated in place, check the changes with git");
+        }
+        Stored::Pending(ref path) => {
+            rv.push_str(&format!("\n  new snapshot: {}", display(path)));
+            rv.push_str(&format!("\n  to review run: {}", review_command));
+            if review_command == "cargo insta review" {
+                rv.push_str("\n  to accept all run: cargo insta accept");
+            }
+        }
+        Stored::Nowhere => {
+            rv.push_str("\n  the new snapshot was not stored (INSTA_UPDATE=no or CI)");
+            if review_command == "cargo insta review" {
+                rv.push_str("\n  to review run: cargo insta test --review");
+            } else {
+                rv.push_str(&format!(
+                    "\n  to review run the tests with INSTA_UPDATE=new and then: {}",
+                    review_command
+                ));
+            }
+        }
+        Stored::Rejected => {
+            rv.push_str("\n  the new snapshot was rejected and not stored");
+        }
+    }
+    rv
 }
 
 /// If there is a suffix on the settings, append it to the snapshot name.
@@ -901,120 +1520,741 @@ fn add_suffix_to_snapshot_name(name: Cow<'_, str>) -> Cow<'_, str> {
     })
 }
 
+/// Compares against a snapshot embedded at compile time.
+///
+/// This never touches the file system or spawns cargo so it also works on
+/// targets like `wasm32-unknown-unknown`.  Mismatches are reported but
+/// there is nothing to update, the snapshot file has to be regenerated on
+/// a native target.
 #[allow(clippy::too_many_arguments)]
-pub fn assert_snapshot(
-    refval: ReferenceValue<'_>,
+fn assert_embedded_snapshot(
+    contents: &str,
     new_snapshot: &str,
     manifest_dir: &str,
     module_path: &str,
     file: &str,
     line: u32,
-    expr: &str,
+    expr: Option<&str>,
+    message: Option<fmt::Arguments<'_>>,
 ) -> Result<(), Box<dyn Error>> {
-    let cargo_workspace = get_cargo_workspace(manifest_dir);
-    let output_behavior = output_snapshot_behavior();
-
-    let (snapshot_name, snapshot_file, old, pending_snapshots) = match refval {
-        ReferenceValue::Named(snapshot_name) => {
-            let snapshot_name = match snapshot_name {
-                Some(snapshot_name) => add_suffix_to_snapshot_name(snapshot_name),
-                None => generate_snapshot_name_for_thread(module_path)
-                    .unwrap()
-                    .into(),
-            };
-            let snapshot_file =
-                get_snapshot_filename(module_path, &snapshot_name, &cargo_workspace, file);
-            let old = if fs::metadata(&snapshot_file).is_ok() {
-                Some(Snapshot::from_file(&snapshot_file)?)
-            } else {
-                None
-            };
-            (Some(snapshot_name), Some(snapshot_file), old, None)
-        }
-        ReferenceValue::Inline(contents) => {
-            let snapshot_name = generate_snapshot_name_for_thread(module_path)
-                .ok()
-                .map(Cow::Owned);
-            let mut filename = cargo_workspace.join(file);
-            filename.set_file_name(format!(
-                ".{}.pending-snap",
-                filename
-                    .file_name()
-                    .expect("no filename")
-                    .to_str()
-                    .expect("non unicode filename")
-            ));
-            (
-                snapshot_name,
-                None,
-                Some(Snapshot::from_components(
-                    module_path.replace("::", "__"),
-                    None,
-                    MetaData::default(),
-                    SnapshotContents::from_inline(contents),
-                )),
-                Some(filename),
-            )
-        }
-    };
-
-    let new_snapshot_contents: SnapshotContents = new_snapshot.into();
+    let module_name = module_path.replace("::", "__");
+    let old = Snapshot::from_str(contents, module_name.clone(), None)?;
+    let (trailing_whitespace, final_newline) = whitespace_policies(true);
     let new = Snapshot::from_components(
-        module_path.replace("::", "__"),
-        snapshot_name.as_ref().map(|x| x.to_string()),
+        module_name,
+        None,
         MetaData {
             source: Some(path_to_storage(file)),
-            expression: Some(expr.to_string()),
-            input_file: Settings::with(|settings| {
-                settings
-                    .input_file()
-                    .and_then(|x| cargo_workspace.join(x).canonicalize().ok())
-                    .and_then(|s| {
-                        s.strip_prefix(cargo_workspace)
-                            .ok()
-                            .map(|x| x.to_path_buf())
-                    })
-                    .map(path_to_storage)
-            }),
+            expression: expr.map(|x| x.to_string()),
+            description: None,
+            info: None,
+            input_file: None,
+            hash: None,
+            tags: Vec::new(),
+            jest_key: None,
+            extension: None,
+            binary_hash: None,
+            compression: None,
+            trailing_whitespace,
+            final_newline,
+            format_version: None,
         },
-        new_snapshot_contents,
+        new_snapshot.into(),
     );
 
-    // memoize the snapshot file if requested.
-    if let Some(ref snapshot_file) = snapshot_file {
-        memoize_snapshot_file(snapshot_file);
+    if contents_match(&old, &new) {
+        return Ok(());
     }
 
-    // if the snapshot matches we're done.
-    if let Some(ref old_snapshot) = old {
-        if old_snapshot.contents() == new.contents() {
-            // let's just make sure there are no more pending files lingering
-            // around.
-            if let Some(ref snapshot_file) = snapshot_file {
-                let mut snapshot_file = snapshot_file.clone();
-                snapshot_file.set_extension("snap.new");
-                fs::remove_file(snapshot_file).ok();
+    let workspace = Path::new(manifest_dir);
+    match output_snapshot_behavior() {
+        OutputBehavior::Summary => {
+            print_snapshot_summary_with_title(workspace, &new, Some(&old), line, None);
+        }
+        OutputBehavior::Diff => {
+            print_snapshot_diff_with_title(workspace, &new, Some(&old), line, None);
+        }
+        OutputBehavior::Diagnostics => {
+            print_snapshot_diagnostic(&new, Some(&old), line, None);
+        }
+        _ => {}
+    }
+
+    if should_fail_in_tests() {
+        panic!(
+            "embedded snapshot assertion failed in line {}{}",
+            line,
+            failure_context(message)
+        );
+    }
+
+    Ok(())
+}
+
+/// The context of a snapshot assertion.
+///
+/// The assertion macros fill this in from `module_path!`, `file!` and
+/// `line!` and derive the test name from the current thread.  Custom test
+/// harnesses (eg: ones built on `libtest-mimic`) that do not run tests on
+/// named threads can create it explicitly and pass it to
+/// [`assert_snapshot`](fn.assert_snapshot.html).
+#[derive(Clone, Debug)]
+pub struct SnapshotContext<'a> {
+    manifest_dir: &'a str,
+    module_path: &'a str,
+    file: &'a str,
+    line: u32,
+    test_name: Option<&'a str>,
+    expression: Option<&'a str>,
+}
+
+impl<'a> SnapshotContext<'a> {
+    /// Creates a new context.
+    ///
+    /// `manifest_dir` is the directory of the crate's `Cargo.toml`,
+    /// `module_path` is used for naming the snapshot file and `file` is
+    /// the test file relative to the workspace root.  Snapshots are stored
+    /// relative to that file.
+    pub fn new(
+        manifest_dir: &'a str,
+        module_path: &'a str,
+        file: &'a str,
+        line: u32,
+    ) -> SnapshotContext<'a> {
+        SnapshotContext {
+            manifest_dir,
+            module_path,
+            file,
+            line,
+            test_name: None,
+            expression: None,
+        }
+    }
+
+    /// Sets the name of the test used for unnamed snapshots.
+    ///
+    /// If not set the name is derived from the current thread as with the
+    /// assertion macros.
+    pub fn set_test_name(&mut self, name: &'a str) {
+        self.test_name = Some(name);
+    }
+
+    /// Sets the expression recorded in the snapshot metadata.
+    pub fn set_expression(&mut self, expr: &'a str) {
+        self.expression = Some(expr);
+    }
+}
+
+/// Asserts a snapshot without going through the assertion macros.
+///
+/// If `name` is `None` the snapshot is named after the test, unnamed
+/// snapshots within the same test are numbered.  This behaves like
+/// `assert_snapshot!` otherwise, including honoring the current settings
+/// and `INSTA_*` environment variables, and also panics on mismatches
+/// unless `INSTA_FORCE_PASS` is set.
+///
+/// ```no_run
+/// use insta::runtime::{assert_snapshot, SnapshotContext};
+///
+/// let mut ctx = SnapshotContext::new(
+///     env!("CARGO_MANIFEST_DIR"),
+///     "my_harness",
+///     "tests/harness.rs",
+///     1,
+/// );
+/// ctx.set_test_name("parse_empty_file");
+/// assert_snapshot(&ctx, None, "output of the test").unwrap();
+/// ```
+pub fn assert_snapshot(
+    ctx: &SnapshotContext<'_>,
+    name: Option<&str>,
+    value: &str,
+) -> Result<(), Box<dyn Error>> {
+    assert_snapshot_impl(
+        name.into(),
+        ctx.test_name,
+        value,
+        ctx.manifest_dir,
+        ctx.module_path,
+        ctx.file,
+        ctx.line,
+        ctx.expression,
+        None,
+        None,
+    )
+}
+
+#[doc(hidden)]
+#[allow(clippy::too_many_arguments)]
+pub fn assert_macro_snapshot(
+    refval: ReferenceValue<'_>,
+    new_snapshot: &str,
+    manifest_dir: &str,
+    module_path: &str,
+    file: &str,
+    line: u32,
+    expr: &str,
+    message: Option<fmt::Arguments<'_>>,
+) -> Result<(), Box<dyn Error>> {
+    assert_snapshot_impl(
+        refval,
+        None,
+        new_snapshot,
+        manifest_dir,
+        module_path,
+        file,
+        line,
+        Some(expr),
+        message,
+        None,
+    )
+}
+
+/// Describes a binary payload for the contents of its snapshot file.
+///
+/// The payload itself is stored in its own file, the snapshot records its
+/// size and hash so that changes show up in diffs and reviews.
+fn binary_to_text(data: &[u8]) -> String {
+    format!(
+        "size: {} bytes\nhash: fnv1a64:{:016x}",
+        data.len(),
+        fnv1a(data)
+    )
+}
+
+/// Compares a stored payload, see `Binary::compare`.
+type ComparePayload<'a> = dyn Fn(&[u8], &Path) -> bool + 'a;
+
+/// A payload that is stored in its own file next to the snapshot file.
+pub(crate) struct Binary<'a> {
+    /// The extension of the file the payload is stored in.
+    pub extension: &'a str,
+    pub data: &'a [u8],
+    /// Compares the stored payload instead of checking the bytes.
+    ///
+    /// It is called with the stored payload and the file it was read from.
+    pub compare: Option<&'a ComparePayload<'a>>,
+}
+
+/// Converts `\r\n` line endings to `\n` unless disabled in the settings.
+fn normalize_line_endings(s: &str) -> Cow<'_, str> {
+    if s.contains("\r\n") && Settings::with(|settings| settings.normalize_line_endings()) {
+        Cow::Owned(s.replace("\r\n", "\n"))
+    } else {
+        Cow::Borrowed(s)
+    }
+}
+
+/// Removes spaces and tabs at the end of every line.
+fn trim_trailing_whitespace(s: &str) -> Cow<'_, str> {
+    let is_trimmed = |line: &str| !line.ends_with(&[' ', '\t'][..]);
+    if s.split('\n').all(is_trimmed) {
+        return Cow::Borrowed(s);
+    }
+    let lines: Vec<_> = s
+        .split('\n')
+        .map(|line| line.trim_end_matches(&[' ', '\t'][..]))
+        .collect();
+    Cow::Owned(lines.join("\n"))
+}
+
+/// Returns the whitespace policies recorded in the metadata of new snapshots.
+///
+/// Only policies that differ from the defaults are recorded.  Inline
+/// snapshots cannot express a final newline, so preserving it only applies
+/// to snapshot files.
+fn whitespace_policies(is_file: bool) -> (Option<Whitespace>, Option<Whitespace>) {
+    Settings::with(|settings| {
+        (
+            Some(settings.trailing_whitespace()).filter(|x| *x != Whitespace::Preserve),
+            Some(settings.final_newline()).filter(|x| *x != Whitespace::Normalize && is_file),
+        )
+    })
+}
+
+/// Compares the contents of a new snapshot against the stored one.
+///
+/// The whitespace policies recorded in the metadata of the new snapshot
+/// decide how whitespace at the end of lines and of the contents is
+/// compared.  With wildcards enabled the stored contents are treated as a
+/// pattern.
+fn contents_match(old: &Snapshot, new: &Snapshot) -> bool {
+    let old_contents = match new.metadata().trailing_whitespace() {
+        Whitespace::Normalize => trim_trailing_whitespace(old.contents_str()),
+        Whitespace::Preserve => Cow::Borrowed(old.contents_str()),
+    };
+    let equal = match new.metadata().final_newline() {
+        Whitespace::Preserve => *old_contents == *new.contents_str(),
+        Whitespace::Normalize => old_contents.trim_end() == new.contents_str().trim_end(),
+    };
+    equal
+        || (Settings::with(|settings| settings.wildcards())
+            && old.contents().matches_with_wildcards(new.contents()))
+}
+
+/// Returns how the contents of a new snapshot file are compressed.
+#[cfg(feature = "compression")]
+fn compression_for(contents: &str) -> Option<String> {
+    let threshold = Settings::with(|settings| settings.compression_threshold())?;
+    if contents.len() > threshold {
+        Some("gzip".into())
+    } else {
+        None
+    }
+}
+
+#[cfg(not(feature = "compression"))]
+fn compression_for(_contents: &str) -> Option<String> {
+    None
+}
+
+/// Checks if a new snapshot matches including its payload.
+///
+/// `contents_match` is the result of comparing the contents of the
+/// snapshot files.  The stored payload is taken from `old` if it was loaded
+/// with it and read from the file otherwise.
+fn binary_matches(
+    binary: Option<&Binary<'_>>,
+    old: Option<&Snapshot>,
+    new: &Snapshot,
+    snapshot_file: Option<&Path>,
+    contents_match: bool,
+) -> bool {
+    let binary = match binary {
+        Some(binary) => binary,
+        None => return contents_match,
+    };
+    let binary_path = snapshot_file.and_then(|x| new.metadata().binary_path(x));
+    let stored = match old
+        .filter(|old| old.metadata().extension() == new.metadata().extension())
+        .and_then(|old| old.binary())
+    {
+        Some(stored) => Some(Cow::Borrowed(stored)),
+        None => binary_path
+            .as_ref()
+            .and_then(|x| fs::read(x).ok())
+            .map(Cow::Owned),
+    };
+    match (binary.compare, stored) {
+        (Some(compare), Some(stored)) => compare(&stored, binary_path.as_deref().unwrap()),
+        // snapshots stored without a payload can only be compared by contents
+        (Some(_), None) => contents_match,
+        (None, stored) => contents_match && stored.as_deref() == Some(binary.data),
+    }
+}
+
+/// Removes the payload of a pending snapshot file.
+fn remove_pending_binary(new: &Snapshot, pending_file: &Path) {
+    if let Some(binary_path) = new.metadata().binary_path(pending_file) {
+        fs::remove_file(binary_path).ok();
+    }
+}
+
+#[doc(hidden)]
+pub fn assert_binary_snapshot(
+    name: &str,
+    data: &[u8],
+    manifest_dir: &str,
+    module_path: &str,
+    file: &str,
+    line: u32,
+    expr: &str,
+) -> Result<(), Box<dyn Error>> {
+    let (name, extension) = match name.rsplit_once('.') {
+        Some((name, extension)) if !extension.is_empty() && extension != "new" => (name, extension),
+        _ => {
+            return Err(format!(
+                "binary snapshot name {:?} in {}:{} needs a file extension (eg: \".png\")",
+                name, file, line
+            )
+            .into())
+        }
+    };
+    let refval = if name.is_empty() {
+        AutoName.into()
+    } else {
+        name.into()
+    };
+    assert_snapshot_impl(
+        refval,
+        None,
+        &binary_to_text(data),
+        manifest_dir,
+        module_path,
+        file,
+        line,
+        Some(expr),
+        None,
+        Some(Binary {
+            extension,
+            data,
+            compare: None,
+        }),
+    )
+}
+
+/// The first value of every assertion in `allow_duplicates!` by location.
+type DuplicateValues = BTreeMap<(String, u32, String), String>;
+
+thread_local! {
+    static DUPLICATES: RefCell<Option<DuplicateValues>> = const { RefCell::new(None) };
+}
+
+#[doc(hidden)]
+pub fn with_allow_duplicates<R, F: FnOnce() -> R>(f: F) -> R {
+    struct Reset(bool);
+
+    impl Drop for Reset {
+        fn drop(&mut self) {
+            if self.0 {
+                DUPLICATES.with(|x| *x.borrow_mut() = None);
+            }
+        }
+    }
+
+    let _reset = Reset(DUPLICATES.with(|x| {
+        let mut duplicates = x.borrow_mut();
+        duplicates.is_none() && {
+            *duplicates = Some(BTreeMap::new());
+            true
+        }
+    }));
+    f()
+}
+
+/// Checks a repeated assertion in `allow_duplicates!`.
+///
+/// Returns `true` if the assertion already ran at the same location with
+/// the same value, in which case it is skipped.  The first run is asserted
+/// as usual so that auto generated names are only allocated once.
+#[allow(clippy::too_many_arguments)]
+fn check_duplicate(
+    refval: &ReferenceValue<'_>,
+    new_snapshot: &str,
+    manifest_dir: &str,
+    module_path: &str,
+    file: &str,
+    line: u32,
+    expr: Option<&str>,
+    message: Option<fmt::Arguments<'_>>,
+) -> Result<bool, Box<dyn Error>> {
+    let name = match *refval {
+        ReferenceValue::Named(Some(ref name)) => name.to_string(),
+        _ => String::new(),
+    };
+    let key = (file.to_string(), line, name.clone());
+    let first = DUPLICATES.with(|x| match *x.borrow_mut() {
+        Some(ref mut duplicates) => match duplicates.get(&key) {
+            Some(first) => Some(Some(first.clone())),
+            None => {
+                duplicates.insert(key, new_snapshot.to_string());
+                Some(None)
             }
-            // and add a null pending snapshot to a pending snapshot file if needed
-            if let Some(ref pending_snapshots) = pending_snapshots {
-                if fs::metadata(pending_snapshots).is_ok() {
-                    PendingInlineSnapshot::new(None, None, line).save(pending_snapshots)?;
+        },
+        None => None,
+    });
+    match first {
+        None | Some(None) => Ok(false),
+        Some(Some(ref first)) if first == new_snapshot => Ok(true),
+        Some(Some(first)) => {
+            let snapshot = |contents: &str| {
+                Snapshot::from_components(
+                    module_path.replace("::", "__"),
+                    Some(name.clone()).filter(|x| !x.is_empty()),
+                    MetaData {
+                        source: Some(path_to_storage(file)),
+                        expression: expr.map(|x| x.to_string()),
+                        ..MetaData::default()
+                    },
+                    contents.into(),
+                )
+            };
+            let workspace = get_cargo_workspace(manifest_dir);
+            let (old, new) = (snapshot(&first), snapshot(new_snapshot));
+            match output_snapshot_behavior() {
+                OutputBehavior::Summary => {
+                    print_snapshot_summary_with_title(workspace, &new, Some(&old), line, None);
+                }
+                OutputBehavior::Diff => {
+                    print_snapshot_diff_with_title(workspace, &new, Some(&old), line, None);
                 }
+                OutputBehavior::Diagnostics => {
+                    print_snapshot_diagnostic(&new, Some(&old), line, None);
+                }
+                _ => {}
             }
+            Err(format!(
+                "snapshot assertion in {}:{} was repeated with a different value in \
+                 allow_duplicates!{}",
+                file,
+                line,
+                failure_context(message)
+            )
+            .into())
+        }
+    }
+}
+
+/// Formats the custom message of a failed assertion for the panic.
+fn failure_context(message: Option<fmt::Arguments<'_>>) -> String {
+    match message {
+        Some(message) => format!(": {}", message),
+        None => String::new(),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn assert_snapshot_impl(
+    refval: ReferenceValue<'_>,
+    test_name: Option<&str>,
+    new_snapshot: &str,
+    manifest_dir: &str,
+    module_path: &str,
+    file: &str,
+    line: u32,
+    expr: Option<&str>,
+    message: Option<fmt::Arguments<'_>>,
+    binary: Option<Binary<'_>>,
+) -> Result<(), Box<dyn Error>> {
+    if let Some(err) = tool_config_error() {
+        return Err(err.into());
+    }
+    let new_snapshot = &*normalize_ansi_escapes(new_snapshot);
+    let new_snapshot = &*normalize_line_endings(new_snapshot);
+    let new_snapshot = &*match Settings::with(|settings| settings.trailing_whitespace()) {
+        Whitespace::Normalize => trim_trailing_whitespace(new_snapshot),
+        Whitespace::Preserve => Cow::Borrowed(new_snapshot),
+    };
+    #[cfg(feature = "filters")]
+    let new_snapshot = &*apply_filters(new_snapshot);
+    if new_snapshot.trim().is_empty() && !Settings::with(|settings| settings.allow_empty()) {
+        return Err(format!(
+            "snapshot in {}:{} is empty which is rejected as allow_empty is disabled{}",
+            file,
+            line,
+            failure_context(message)
+        )
+        .into());
+    }
+    if check_duplicate(
+        &refval,
+        new_snapshot,
+        manifest_dir,
+        module_path,
+        file,
+        line,
+        expr,
+        message,
+    )? {
+        return Ok(());
+    }
+    if let ReferenceValue::Embedded(contents) = refval {
+        return assert_embedded_snapshot(
+            contents,
+            new_snapshot,
+            manifest_dir,
+            module_path,
+            file,
+            line,
+            expr,
+            message,
+        );
+    }
+
+    let file = &*remap_source_path(file);
+    let cargo_workspace = &*resolve_workspace_root(manifest_dir);
+    let output_behavior = output_snapshot_behavior();
+    let doctest = is_doctest(module_path);
+    let doctest_module_path;
+    let module_path = if doctest {
+        doctest_module_path = get_doctest_module_path(file);
+        &doctest_module_path
+    } else {
+        module_path
+    };
+    let generate_snapshot_name_for_test = || match test_name {
+        Some(test_name) => Ok((
+            Cow::Borrowed(module_path),
+            generate_snapshot_name(module_path, test_name),
+        )),
+        // doctests run on the main thread of their own process, so there is
+        // no test name and counters would clash across doctests of a file.
+        None if doctest => Ok((
+            Cow::Borrowed(module_path),
+            generate_snapshot_name(module_path, &format!("line_{}", line)),
+        )),
+        None => generate_snapshot_name_for_thread(module_path),
+    };
 
-            if force_update_snapshots() {
-                update_snapshots(
-                    snapshot_file.as_deref(),
-                    new,
-                    old,
-                    line,
-                    pending_snapshots,
-                    output_behavior,
-                )?;
+    let require_full_match = Settings::with(|settings| settings.require_full_match());
+    let mut streamed_match = false;
+    let mut snapshot_module = Cow::Borrowed(module_path);
+    let (snapshot_name, snapshot_file, old, pending_snapshots) = match refval {
+        ReferenceValue::Embedded(..) => unreachable!(),
+        ReferenceValue::Named(snapshot_name) => {
+            let snapshot_name = match snapshot_name {
+                Some(snapshot_name) => add_suffix_to_snapshot_name(snapshot_name),
+                None => {
+                    let (module, name) = generate_snapshot_name_for_test().unwrap();
+                    snapshot_module = module;
+                    name.into()
+                }
+            };
+            let snapshot_file = get_snapshot_filename(
+                &snapshot_module,
+                &snapshot_name,
+                cargo_workspace,
+                manifest_dir,
+                file,
+            );
+            if require_full_match {
+                check_full_match(&snapshot_file)?;
             }
+            // large snapshots are compared without loading them and only
+            // loaded for the diff if they do not match.
+            // streaming ignores carriage returns at the end of lines and
+            // the final newline
+            let old = if !force_update_snapshots()
+                && Settings::with(|settings| {
+                    settings.normalize_line_endings()
+                        && settings.final_newline() == Whitespace::Normalize
+                })
+                && is_large_snapshot_file(&snapshot_file)
+                && Snapshot::file_matches(&snapshot_file, new_snapshot).unwrap_or(false)
+            {
+                streamed_match = true;
+                None
+            } else {
+                match load_snapshot_cached(&snapshot_file) {
+                    Ok(old) => old,
+                    // corrupted files are treated like missing ones so that
+                    // they are replaced according to the update mode.
+                    Err(err) => {
+                        eprintln!("{} {}", style("warning:").bold().yellow(), err);
+                        None
+                    }
+                }
+            };
+            (Some(snapshot_name), Some(snapshot_file), old, None)
+        }
+        ReferenceValue::Inline(contents) => {
+            if require_full_match && contents.trim_start().starts_with('⋮') {
+                return Err(format!(
+                    "inline snapshot in {}:{} uses a legacy format which is \
+                     rejected as require_full_match is set",
+                    file, line
+                )
+                .into());
+            }
+            let snapshot_name = generate_snapshot_name_for_test()
+                .ok()
+                .map(|(_, name)| Cow::Owned(name));
+            let mut filename = cargo_workspace.join(file);
+            filename.set_file_name(format!(
+                ".{}.pending-snap",
+                filename
+                    .file_name()
+                    .expect("no filename")
+                    .to_str()
+                    .expect("non unicode filename")
+            ));
+            (
+                snapshot_name,
+                None,
+                Some(Snapshot::from_components(
+                    module_path.replace("::", "__"),
+                    None,
+                    MetaData::default(),
+                    SnapshotContents::from_inline(contents),
+                )),
+                // line numbers reported in doctests do not match the source
+                // file, so inline snapshots there can only be compared.
+                if doctest { None } else { Some(filename) },
+            )
+        }
+    };
 
-            return Ok(());
+    let new_snapshot_contents: SnapshotContents = new_snapshot.into();
+    let (trailing_whitespace, final_newline) = whitespace_policies(snapshot_file.is_some());
+    let mut new = Snapshot::from_components(
+        snapshot_module.replace("::", "__"),
+        snapshot_name.as_ref().map(|x| x.to_string()),
+        MetaData {
+            source: Some(path_to_storage(file)),
+            expression: expr
+                .filter(|_| !Settings::with(|settings| settings.omit_expression()))
+                .map(|x| x.to_string()),
+            description: Settings::with(|settings| settings.description().map(|x| x.to_string())),
+            info: Settings::with(|settings| settings.info().cloned()),
+            input_file: get_input_file(manifest_dir, cargo_workspace),
+            hash: None,
+            tags: Settings::with(|settings| settings.tags().to_vec()),
+            jest_key: None,
+            extension: binary.as_ref().map(|x| x.extension.to_string()),
+            binary_hash: None,
+            compression: snapshot_file
+                .as_ref()
+                .and_then(|_| compression_for(new_snapshot)),
+            trailing_whitespace,
+            final_newline,
+            format_version: None,
+        },
+        new_snapshot_contents,
+    );
+    if let Some(ref binary) = binary {
+        new.set_binary(binary.data.to_vec());
+    }
+
+    // memoize the snapshot file if requested.
+    if let Some(ref snapshot_file) = snapshot_file {
+        memoize_snapshot_file(snapshot_file);
+    }
+    record_snapshot_usage(
+        test_name,
+        manifest_dir,
+        module_path,
+        file,
+        line,
+        snapshot_file.as_deref(),
+    );
+
+    // if the snapshot matches we're done.
+    let contents_match =
+        streamed_match || old.as_ref().is_some_and(|old| contents_match(old, &new));
+    let matches = binary_matches(
+        binary.as_ref(),
+        old.as_ref(),
+        &new,
+        snapshot_file.as_deref(),
+        contents_match,
+    );
+    if matches {
+        // let's just make sure there are no more pending files lingering
+        // around.
+        if let Some(ref snapshot_file) = snapshot_file {
+            let snapshot_file = get_pending_snapshot_filename(snapshot_file);
+            remove_pending_binary(&new, &snapshot_file);
+            fs::remove_file(snapshot_file).ok();
+        }
+        // and add a null pending snapshot to a pending snapshot file if needed
+        if let Some(ref pending_snapshots) = pending_snapshots {
+            if fs::metadata(pending_snapshots).is_ok() {
+                PendingInlineSnapshot::new(None, None, line).save(pending_snapshots)?;
+            }
+        }
+
+        if force_update_snapshots() {
+            update_snapshots(
+                snapshot_file.as_deref(),
+                new,
+                old,
+                line,
+                pending_snapshots,
+                output_behavior,
+            )?;
         }
+
+        return Ok(());
     }
 
     match output_behavior {
@@ -1036,32 +2276,560 @@ pub fn assert_snapshot(
                 snapshot_file.as_deref(),
             );
         }
+        OutputBehavior::Diagnostics => {
+            print_snapshot_diagnostic(&new, old.as_ref(), line, snapshot_file.as_deref());
+        }
         _ => {}
     }
 
-    update_snapshots(
-        snapshot_file.as_deref(),
-        new,
-        old,
-        line,
-        pending_snapshots,
-        output_behavior,
-    )?;
+    let missing = require_full_match
+        && old.is_none()
+        && snapshot_file
+            .as_deref()
+            .is_some_and(|x| fs::metadata(x).is_err());
 
-    if output_behavior != OutputBehavior::Nothing {
-        println!(
-            "{hint}",
-            hint = style("To update snapshots run `cargo insta review`").dim(),
+    // inline snapshots can only be updated by cargo-insta.
+    let choice = match snapshot_file {
+        Some(_) if output_behavior != OutputBehavior::Nothing => prompt_for_snapshot(),
+        _ => None,
+    };
+    let stored = match choice {
+        Some(PromptChoice::Accept) => {
+            let snapshot_file = snapshot_file.as_deref().unwrap();
+            check_case_collision(snapshot_file)?;
+            new.save(snapshot_file)?;
+            SNAPSHOT_CACHE
+                .lock()
+                .unwrap_or_else(|x| x.into_inner())
+                .remove(snapshot_file);
+            let pending = get_pending_snapshot_filename(snapshot_file);
+            remove_pending_binary(&new, &pending);
+            fs::remove_file(pending).ok();
+            eprintln!(
+                "{} {}",
+                style("accepted snapshot").green(),
+                style(snapshot_file.display()).cyan().underlined(),
+            );
+            return Ok(());
+        }
+        Some(PromptChoice::Reject) => Stored::Rejected,
+        Some(PromptChoice::Skip) | None => update_snapshots(
+            snapshot_file.as_deref(),
+            new,
+            old,
+            line,
+            pending_snapshots,
+            output_behavior,
+        )?,
+    };
+
+    let review_command = review_command();
+    if output_behavior != OutputBehavior::Nothing && output_behavior != OutputBehavior::Diagnostics
+    {
+        if let Some(ref cmd) = review_command {
+            println!(
+                "{hint}",
+                hint = style(format!("To update snapshots run `{}`", cmd)).dim(),
+            );
+        }
+    }
+
+    if missing {
+        panic!(
+            "snapshot file {} does not exist and require_full_match is set{}{}",
+            snapshot_file.as_deref().unwrap().display(),
+            failure_context(message),
+            failure_hint(cargo_workspace, None, &stored, review_command.as_deref(),)
         );
     }
 
     if should_fail_in_tests() {
         panic!(
-            "snapshot assertion for '{}' failed in line {}",
-            snapshot_name.as_ref().map_or("unnamed snapshot", |x| &*x),
-            line
+            "snapshot assertion for '{}' failed in line {}{}{}",
+            snapshot_name.as_ref().map_or("unnamed snapshot", |x| x),
+            line,
+            failure_context(message),
+            failure_hint(
+                cargo_workspace,
+                snapshot_file.as_deref(),
+                &stored,
+                review_command.as_deref(),
+            )
         );
     }
 
     Ok(())
 }
+
+/// Returns the file pending changes to a golden file are stored in.
+///
+/// `cargo-insta` picks these up for review and writes the accepted
+/// contents to the golden file.
+fn get_golden_pending_path(golden_file: &Path) -> PathBuf {
+    let mut rv = golden_file.to_path_buf();
+    rv.set_file_name(format!(
+        ".{}.pending-golden",
+        golden_file
+            .file_name()
+            .expect("no filename")
+            .to_str()
+            .expect("non unicode filename")
+    ));
+    rv
+}
+
+/// Compares a value against an external golden file.
+///
+/// Unlike snapshots the golden file holds nothing but the value and is
+/// compared byte by byte.  The path is relative to the crate.
+#[doc(hidden)]
+#[allow(clippy::too_many_arguments)]
+pub fn assert_golden_file(
+    new_value: &str,
+    golden_file: &str,
+    manifest_dir: &str,
+    module_path: &str,
+    file: &str,
+    line: u32,
+    expr: &str,
+) -> Result<(), Box<dyn Error>> {
+    if let Some(err) = tool_config_error() {
+        return Err(err.into());
+    }
+    let new_value = &*normalize_ansi_escapes(new_value);
+    let file = &*remap_source_path(file);
+    let cargo_workspace = &*resolve_workspace_root(manifest_dir);
+    let output_behavior = output_snapshot_behavior();
+    let golden_path = Path::new(manifest_dir).join(golden_file);
+    let pending_path = get_golden_pending_path(&golden_path);
+    let old_value = fs::read_to_string(&golden_path).ok();
+    memoize_snapshot_file(&golden_path);
+    record_snapshot_usage(
+        None,
+        manifest_dir,
+        module_path,
+        file,
+        line,
+        Some(&golden_path),
+    );
+
+    if old_value.as_deref() == Some(new_value) {
+        fs::remove_file(&pending_path).ok();
+        return Ok(());
+    }
+
+    let make_snapshot = |contents: &str| {
+        Snapshot::from_components(
+            module_path.replace("::", "__"),
+            Some(golden_file.to_string()),
+            MetaData {
+                source: Some(path_to_storage(file)),
+                expression: Some(expr.to_string()),
+                description: None,
+                info: None,
+                input_file: None,
+                hash: None,
+                tags: Vec::new(),
+                jest_key: None,
+                extension: None,
+                binary_hash: None,
+                compression: None,
+                trailing_whitespace: None,
+                final_newline: None,
+                format_version: None,
+            },
+            contents.into(),
+        )
+    };
+    let new = make_snapshot(new_value);
+    let old = old_value.as_deref().map(make_snapshot);
+
+    match output_behavior {
+        OutputBehavior::Summary => {
+            print_snapshot_summary_with_title(
+                cargo_workspace,
+                &new,
+                old.as_ref(),
+                line,
+                Some(&golden_path),
+            );
+        }
+        OutputBehavior::Diff => {
+            print_snapshot_diff_with_title(
+                cargo_workspace,
+                &new,
+                old.as_ref(),
+                line,
+                Some(&golden_path),
+            );
+        }
+        OutputBehavior::Diagnostics => {
+            print_snapshot_diagnostic(&new, old.as_ref(), line, Some(&golden_path));
+        }
+        _ => {}
+    }
+
+    let should_print = output_behavior != OutputBehavior::Nothing
+        && output_behavior != OutputBehavior::Diagnostics;
+    let stored = match update_snapshot_behavior(old.is_none()) {
+        UpdateBehavior::InPlace => {
+            if let Some(folder) = golden_path.parent() {
+                fs::create_dir_all(folder)?;
+            }
+            fs::write(&golden_path, new_value)?;
+            fs::remove_file(&pending_path).ok();
+            if should_print {
+                eprintln!(
+                    "{} {}",
+                    style("updated golden file").green(),
+                    style(golden_path.display()).cyan().underlined(),
+                );
+            }
+            return Ok(());
+        }
+        UpdateBehavior::NewFile => {
+            if let Some(folder) = pending_path.parent() {
+                fs::create_dir_all(folder)?;
+            }
+            PendingInlineSnapshot::save_batch(
+                &pending_path,
+                &[PendingInlineSnapshot::new(Some(new), old, line)],
+            )?;
+            if should_print {
+                eprintln!(
+                    "{} {}",
+                    style("stored new golden file").green(),
+                    style(pending_path.display()).cyan().underlined(),
+                );
+            }
+            Stored::Pending(pending_path)
+        }
+        UpdateBehavior::NoUpdate => Stored::Nowhere,
+    };
+
+    let review_command = review_command();
+    if should_print {
+        if let Some(ref cmd) = review_command {
+            println!(
+                "{hint}",
+                hint = style(format!("To update snapshots run `{}`", cmd)).dim(),
+            );
+        }
+    }
+
+    if should_fail_in_tests() {
+        panic!(
+            "golden file assertion for '{}' failed in line {}{}",
+            golden_file,
+            line,
+            failure_hint(
+                cargo_workspace,
+                Some(&golden_path),
+                &stored,
+                review_command.as_deref(),
+            )
+        );
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_failure_hint() {
+    let workspace = Path::new("/ws");
+    let target = Path::new("/ws/tests/snapshots/a__b.snap");
+    let pending = Stored::Pending(PathBuf::from("/ws/tests/snapshots/a__b.snap.new"));
+    assert_eq!(
+        failure_hint(
+            workspace,
+            Some(target),
+            &pending,
+            Some("cargo insta review")
+        ),
+        "\n  snapshot file: tests/snapshots/a__b.snap\
+         \n  new snapshot: tests/snapshots/a__b.snap.new\
+         \n  to review run: cargo insta review\
+         \n  to accept all run: cargo insta accept"
+    );
+    assert_eq!(
+        failure_hint(
+            workspace,
+            None,
+            &Stored::Nowhere,
+            Some("bazel run //:review")
+        ),
+        "\n  the new snapshot was not stored (INSTA_UPDATE=no or CI)\
+         \n  to review run the tests with INSTA_UPDATE=new and then: bazel run //:review"
+    );
+    assert_eq!(
+        failure_hint(
+            workspace,
+            Some(target),
+            &Stored::Rejected,
+            Some("cargo insta review")
+        ),
+        "\n  snapshot file: tests/snapshots/a__b.snap\
+         \n  the new snapshot was rejected and not stored"
+    );
+    assert_eq!(failure_hint(workspace, Some(target), &pending, None), "");
+}
+
+#[test]
+fn test_snapshot_diagnostic() {
+    let snapshot = |contents: &str, description: Option<&str>| {
+        Snapshot::from_components(
+            "test".into(),
+            Some("name".into()),
+            MetaData {
+                source: Some("tests/test_foo.rs".into()),
+                expression: None,
+                description: description.map(|x| x.to_string()),
+                info: None,
+                input_file: None,
+                hash: None,
+                tags: Vec::new(),
+                jest_key: None,
+                extension: None,
+                binary_hash: None,
+                compression: None,
+                trailing_whitespace: None,
+                final_newline: None,
+                format_version: None,
+            },
+            contents.into(),
+        )
+    };
+    let diagnostic = snapshot_diagnostic(
+        &snapshot("a\nc", Some("the letters")),
+        Some(&snapshot("a\nb", None)),
+        42,
+        Some(Path::new("tests/snapshots/test__name.snap")),
+    );
+    assert_eq!(diagnostic["message"], "snapshot 'name' does not match");
+    assert_eq!(diagnostic["spans"][0]["file_name"], "tests/test_foo.rs");
+    assert_eq!(diagnostic["spans"][0]["line_start"], 42);
+    assert_eq!(diagnostic["children"][0]["level"], "help");
+    assert_eq!(
+        diagnostic["rendered"],
+        "error: snapshot 'name' does not match\n --> tests/test_foo.rs:42\n  \
+         = snapshot file: tests/snapshots/test__name.snap\n  \
+         = description: the letters\n a\n-b\n+c\n"
+    );
+}
+
+#[test]
+fn test_info_to_string() {
+    let info = serde_yaml::to_value(vec![("input", "a.txt"), ("mode", "strict")]).unwrap();
+    assert_eq!(
+        info_to_string(&info),
+        "- - input\n  - a.txt\n- - mode\n  - strict"
+    );
+    let info = serde_yaml::to_value("plain text").unwrap();
+    assert_eq!(info_to_string(&info), "plain text");
+}
+
+#[test]
+fn test_generate_snapshot_name_counts_per_test() {
+    let module_path = "insta::runtime::naming";
+    assert_eq!(
+        generate_snapshot_name(module_path, "tests::test_foo"),
+        "foo"
+    );
+    assert_eq!(
+        generate_snapshot_name(module_path, "tests::test_foo"),
+        "foo-2"
+    );
+    assert_eq!(generate_snapshot_name(module_path, "tests::bar"), "bar");
+    assert_eq!(
+        generate_snapshot_name(module_path, "tests::test_foo"),
+        "foo-3"
+    );
+    assert_eq!(
+        generate_snapshot_name("insta::other", "tests::test_foo"),
+        "foo"
+    );
+}
+
+#[test]
+fn test_doctest_module_path() {
+    assert!(is_doctest("rust_out"));
+    assert!(!is_doctest("rust_outlier"));
+    assert_eq!(get_doctest_module_path("src/lib.rs"), "doctest::lib");
+    assert_eq!(
+        get_doctest_module_path("src/foo/bar.rs"),
+        "doctest::foo::bar"
+    );
+}
+
+#[test]
+fn test_load_snapshot_cached() {
+    let path = env::temp_dir().join(format!("insta-cache-{}.snap", std::process::id()));
+    fs::write(&path, "---\nsource: test.rs\n---\nfirst\n").unwrap();
+    let first = load_snapshot_cached(&path).unwrap().unwrap();
+    assert_eq!(first.contents_str(), "first");
+    assert!(SNAPSHOT_CACHE.lock().unwrap().contains_key(&path));
+    assert_eq!(
+        load_snapshot_cached(&path).unwrap().unwrap().contents_str(),
+        "first"
+    );
+
+    fs::write(&path, "---\nsource: test.rs\n---\nchanged\n").unwrap();
+    assert_eq!(
+        load_snapshot_cached(&path).unwrap().unwrap().contents_str(),
+        "changed"
+    );
+
+    fs::remove_file(&path).unwrap();
+    assert!(load_snapshot_cached(&path).unwrap().is_none());
+}
+
+#[test]
+fn test_resolve_test_path() {
+    let resolve = |module_path, test_path| {
+        let (module, name) = resolve_test_path(module_path, test_path);
+        (module.into_owned(), name)
+    };
+    assert_eq!(
+        resolve("krate::tests", "tests::test_foo"),
+        ("krate::tests".into(), "foo".into())
+    );
+    assert_eq!(resolve("krate", "test_foo"), ("krate".into(), "foo".into()));
+    // cases generated in a nested module
+    assert_eq!(
+        resolve("krate::tests", "tests::test_parse::case_1"),
+        ("krate::tests".into(), "parse__case_1".into())
+    );
+    assert_eq!(
+        resolve("krate", "foo::bar::baz"),
+        ("krate".into(), "baz".into())
+    );
+    // helper outside of the module of the test
+    assert_eq!(
+        resolve("krate::helpers", "tests::test_foo"),
+        ("krate::tests".into(), "foo".into())
+    );
+    assert_eq!(
+        resolve("krate::helpers", "test_foo"),
+        ("krate".into(), "foo".into())
+    );
+    assert_eq!(
+        resolve("krate::tests", "tests_more::test_foo"),
+        ("krate::tests_more".into(), "foo".into())
+    );
+}
+
+#[test]
+fn test_shorten_snapshot_file_stem() {
+    assert_eq!(shorten_snapshot_file_stem("foo__bar"), "foo__bar");
+
+    let long = format!("krate__{}", "ä".repeat(150));
+    let short = shorten_snapshot_file_stem(&long);
+    assert!(short.len() <= MAX_SNAPSHOT_FILE_STEM);
+    assert!(short.starts_with("krate__ää"));
+    assert_eq!(short, shorten_snapshot_file_stem(&long));
+    assert_ne!(short, shorten_snapshot_file_stem(&format!("{}x", long)));
+
+    let filename = get_snapshot_filename(
+        "krate",
+        long.strip_prefix("krate__").unwrap(),
+        Path::new("/ws"),
+        "/ws/foo",
+        "tests/foo.rs",
+    );
+    assert_eq!(
+        filename.file_name().unwrap().to_str().unwrap(),
+        format!("{}.snap", short)
+    );
+}
+
+#[test]
+fn test_check_case_collision() {
+    let dir = env::temp_dir().join(format!("insta-case-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("krate__Foo.snap.new"), "").unwrap();
+
+    assert!(check_case_collision(&dir.join("krate__Foo.snap")).is_ok());
+    assert!(check_case_collision(&dir.join("krate__bar.snap")).is_ok());
+    let err = check_case_collision(&dir.join("krate__foo.snap")).unwrap_err();
+    assert!(err.to_string().contains("krate__Foo.snap.new"));
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_snapshot_path_base() {
+    let filename = |base| {
+        let mut settings = Settings::new();
+        settings.set_snapshot_path("testdata");
+        settings.set_snapshot_path_base(base);
+        let mut rv = None;
+        settings.bind(|| {
+            rv = Some(get_snapshot_filename(
+                "krate",
+                "name",
+                Path::new("/ws"),
+                "/ws/krate",
+                "krate/tests/foo.rs",
+            ));
+        });
+        rv.unwrap()
+    };
+    assert_eq!(
+        filename(SnapshotPathBase::SourceFile),
+        Path::new("/ws/krate/tests/testdata/krate__name.snap")
+    );
+    assert_eq!(
+        filename(SnapshotPathBase::Crate),
+        Path::new("/ws/krate/testdata/krate__name.snap")
+    );
+    assert_eq!(
+        filename(SnapshotPathBase::Workspace),
+        Path::new("/ws/testdata/krate__name.snap")
+    );
+}
+
+#[test]
+fn test_snapshot_extension() {
+    let mut settings = Settings::new();
+    settings.set_snapshot_extension(".snap.json");
+    assert_eq!(settings.snapshot_extension(), "snap.json");
+    let mut rv = None;
+    settings.bind(|| {
+        rv = Some(get_snapshot_filename(
+            "krate",
+            "name",
+            Path::new("/ws"),
+            "/ws/krate",
+            "krate/tests/foo.rs",
+        ));
+    });
+    let snapshot_file = rv.unwrap();
+    assert_eq!(
+        snapshot_file,
+        Path::new("/ws/krate/tests/snapshots/krate__name.snap.json")
+    );
+    assert_eq!(
+        get_pending_snapshot_filename(&snapshot_file),
+        Path::new("/ws/krate/tests/snapshots/krate__name.snap.json.new")
+    );
+}
+
+#[test]
+fn test_normalize_line_endings() {
+    assert_eq!(normalize_line_endings("a\r\nb\r\n"), "a\nb\n");
+    assert_eq!(normalize_line_endings("a\rb"), "a\rb");
+    let mut settings = Settings::new();
+    settings.set_normalize_line_endings(false);
+    settings.bind(|| {
+        assert_eq!(normalize_line_endings("a\r\nb"), "a\r\nb");
+    });
+}
+
+#[test]
+fn test_trim_trailing_whitespace() {
+    assert!(matches!(
+        trim_trailing_whitespace("a\nb\n"),
+        Cow::Borrowed(_)
+    ));
+    assert_eq!(trim_trailing_whitespace("a  \nb\t\n  c \n"), "a\nb\n  c\n");
+}
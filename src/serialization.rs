@@ -1,10 +1,54 @@
+use std::io;
+
 use serde::de::value::Error as ValueError;
 use serde::Serialize;
+use serde_json::ser::Formatter;
 
-use crate::content::{Content, ContentSerializer};
+use crate::content::{parse_json, with_interned_keys, Content, ContentSerializer};
 use crate::settings::Settings;
 
-pub enum SerializationFormat {
+use crate::settings::EnumRepr;
+
+#[cfg(feature = "csv")]
+use crate::settings::CsvQuoteStyle;
+
+/// Renders values in a custom snapshot format.
+///
+/// This is used with [`assert_custom_snapshot!`](macro.assert_custom_snapshot.html)
+/// to plug in formats insta does not ship with (eg: the printer of an
+/// intermediate representation).  The serializer is given the value as
+/// [`Content`] after redactions and the other settings were applied, so
+/// naming, storage and review work like for the built-in formats:
+///
+/// ```rust
+/// use insta::{assert_custom_snapshot, Content, SnapshotSerializer};
+///
+/// struct Lines;
+///
+/// impl SnapshotSerializer for Lines {
+///     fn serialize(&self, content: &Content) -> String {
+///         match *content {
+///             Content::Seq(ref items) => items
+///                 .iter()
+///                 .map(|x| format!("- {}", x.as_str().unwrap_or("?")))
+///                 .collect::<Vec<_>>()
+///                 .join("\n"),
+///             _ => "?".into(),
+///         }
+///     }
+/// }
+///
+/// assert_custom_snapshot!(Lines, vec!["a", "b"], @r###"
+/// - a
+/// - b
+/// "###);
+/// ```
+pub trait SnapshotSerializer {
+    /// Returns the snapshot contents for the content of a value.
+    fn serialize(&self, content: &Content) -> String;
+}
+
+pub enum SerializationFormat<'a> {
     #[cfg(feature = "csv")]
     Csv,
     #[cfg(feature = "ron")]
@@ -13,6 +57,8 @@ pub enum SerializationFormat {
     Toml,
     Yaml,
     Json,
+    JsonCompact,
+    Custom(&'a dyn SnapshotSerializer),
 }
 
 pub enum SnapshotLocation {
@@ -20,39 +66,218 @@ pub enum SnapshotLocation {
     File,
 }
 
+/// Rewrites externally tagged enums into the given representation.
+fn apply_enum_repr(content: Content, repr: &EnumRepr) -> Content {
+    fn tagged(tag: &str, variant: &'static str) -> Vec<(Content, Content)> {
+        vec![(Content::from(tag), Content::from(variant))]
+    }
+
+    fn fields_to_entries(fields: Vec<(&'static str, Content)>) -> Vec<(Content, Content)> {
+        fields
+            .into_iter()
+            .map(|(k, v)| (Content::from(k), v))
+            .collect()
+    }
+
+    match *repr {
+        EnumRepr::External => content,
+        EnumRepr::Internal { ref tag } => content.map(&mut |node| match node {
+            Content::UnitVariant(_, _, variant) => Content::Map(tagged(tag, variant)),
+            Content::StructVariant(_, _, variant, fields) => {
+                let mut entries = tagged(tag, variant);
+                entries.extend(fields_to_entries(fields));
+                Content::Map(entries)
+            }
+            Content::NewtypeVariant(name, idx, variant, inner) => match *inner {
+                Content::Struct(_, fields) => {
+                    let mut entries = tagged(tag, variant);
+                    entries.extend(fields_to_entries(fields));
+                    Content::Map(entries)
+                }
+                Content::Map(map) => {
+                    let mut entries = tagged(tag, variant);
+                    entries.extend(map);
+                    Content::Map(entries)
+                }
+                inner => Content::NewtypeVariant(name, idx, variant, Box::new(inner)),
+            },
+            other => other,
+        }),
+        EnumRepr::Adjacent {
+            ref tag,
+            content: ref content_key,
+        } => content.map(&mut |node| {
+            let (variant, value) = match node {
+                Content::UnitVariant(_, _, variant) => return Content::Map(tagged(tag, variant)),
+                Content::NewtypeVariant(_, _, variant, inner) => (variant, *inner),
+                Content::TupleVariant(_, _, variant, fields) => (variant, Content::Seq(fields)),
+                Content::StructVariant(_, _, variant, fields) => {
+                    (variant, Content::Struct(variant, fields))
+                }
+                other => return other,
+            };
+            let mut entries = tagged(tag, variant);
+            entries.push((Content::from(content_key.as_str()), value));
+            Content::Map(entries)
+        }),
+    }
+}
+
+/// The struct name `serde_json::value::RawValue` serializes as.
+const RAW_VALUE_TOKEN: &str = "$serde_json::private::RawValue";
+
+/// The struct name [`Documents`] serializes as.
+const DOCUMENTS_TOKEN: &str = "$insta::private::Documents";
+
+/// Values that YAML snapshots store as a stream of documents.
+///
+/// This is created with [`documents`].
+pub struct Documents<T>(Vec<T>);
+
+impl<T: Serialize> Serialize for Documents<T> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_newtype_struct(DOCUMENTS_TOKEN, &self.0)
+    }
+}
+
+/// Collects values to snapshot them as a YAML multi-document stream.
+///
+/// `assert_yaml_snapshot!` stores every value as its own document
+/// separated by `---` lines instead of wrapping them in a list.  Other
+/// formats store the values as a sequence and redactions select the
+/// values like the items of a sequence (eg: `[].id`):
+///
+/// ```no_run
+/// # use insta::*;
+/// # let events = vec!["started", "stopped"];
+/// assert_yaml_snapshot!(documents(events.iter()));
+/// ```
+pub fn documents<I>(values: I) -> Documents<I::Item>
+where
+    I: IntoIterator,
+    I::Item: Serialize,
+{
+    Documents(values.into_iter().collect())
+}
+
+/// Replaces `serde_json::value::RawValue`s with their parsed contents.
+///
+/// Without this raw values would show up as a struct holding an escaped
+/// JSON string in all formats other than JSON.
+fn expand_raw_json(content: Content) -> Content {
+    content.map(&mut |node| match node {
+        Content::Struct(RAW_VALUE_TOKEN, fields) if fields.len() == 1 => {
+            match fields[0].1.as_str().and_then(parse_json) {
+                Some(parsed) => parsed,
+                None => Content::Struct(RAW_VALUE_TOKEN, fields),
+            }
+        }
+        other => other,
+    })
+}
+
+// the location only matters for YAML.
+#[cfg(feature = "csv")]
+fn is_csv_record(content: &Content) -> bool {
+    matches!(
+        *content,
+        Content::Struct(..)
+            | Content::Map(..)
+            | Content::Seq(..)
+            | Content::Tuple(..)
+            | Content::TupleStruct(..)
+    )
+}
+
 pub fn serialize_content(
     mut content: Content,
-    format: SerializationFormat,
+    format: SerializationFormat<'_>,
     location: SnapshotLocation,
 ) -> String {
+    content = expand_raw_json(content);
     content = Settings::with(|settings| {
         if settings.sort_maps() {
             content.sort_maps();
         }
         #[cfg(feature = "redactions")]
         {
-            for (selector, redaction) in settings.iter_redactions() {
-                content = selector.redact(content, redaction);
+            content = crate::redaction::with_placeholders(|| {
+                for (selector, redaction) in settings.iter_redactions() {
+                    content = selector.redact(content, redaction);
+                }
+                content
+            });
+        }
+        #[cfg(any(feature = "chrono", feature = "time"))]
+        {
+            if settings.redact_timestamps() {
+                content = crate::timestamp::redact_timestamps(content);
             }
         }
+        if matches!(
+            format,
+            SerializationFormat::Json | SerializationFormat::JsonCompact
+        ) {
+            content = apply_enum_repr(content, settings.json_enum_repr());
+        }
         content
     });
 
     match format {
         SerializationFormat::Yaml => {
-            let serialized = serde_yaml::to_string(&content).unwrap();
+            let serialized = match content {
+                Content::NewtypeStruct(name, inner) if name == DOCUMENTS_TOKEN => match *inner {
+                    Content::Seq(ref items) => items
+                        .iter()
+                        .map(to_yaml_document)
+                        .collect::<Vec<_>>()
+                        .join("\n"),
+                    ref other => to_yaml_document(other),
+                },
+                ref content => to_yaml_document(content),
+            };
+            if serialized.is_empty() {
+                return serialized;
+            }
             match location {
                 SnapshotLocation::Inline => serialized,
                 SnapshotLocation::File => serialized[4..].to_string(),
             }
         }
-        SerializationFormat::Json => serde_json::to_string_pretty(&content).unwrap(),
+        SerializationFormat::Json => serialize_json_pretty(&content),
+        SerializationFormat::JsonCompact => serde_json::to_string(&content).unwrap(),
         #[cfg(feature = "csv")]
         SerializationFormat::Csv => {
             let mut buf = Vec::with_capacity(128);
             {
-                let mut writer = csv::Writer::from_writer(&mut buf);
-                writer.serialize(&content).unwrap();
+                let mut writer = Settings::with(|settings| {
+                    csv::WriterBuilder::new()
+                        .delimiter(settings.csv_delimiter())
+                        .quote_style(match settings.csv_quote_style() {
+                            CsvQuoteStyle::Always => csv::QuoteStyle::Always,
+                            CsvQuoteStyle::Necessary => csv::QuoteStyle::Necessary,
+                            CsvQuoteStyle::NonNumeric => csv::QuoteStyle::NonNumeric,
+                            CsvQuoteStyle::Never => csv::QuoteStyle::Never,
+                        })
+                        .has_headers(settings.csv_has_headers())
+                        .terminator(if settings.csv_crlf() {
+                            csv::Terminator::CRLF
+                        } else {
+                            csv::Terminator::Any(b'\n')
+                        })
+                        .from_writer(&mut buf)
+                });
+                // sequences of records are written as one record per item
+                // sharing the header of the first one.  Sequences of scalars
+                // stay a single row as before.
+                match content {
+                    Content::Seq(ref records) if records.iter().all(is_csv_record) => {
+                        for record in records {
+                            writer.serialize(record).unwrap();
+                        }
+                    }
+                    ref record => writer.serialize(record).unwrap(),
+                }
                 writer.flush().unwrap();
             }
             String::from_utf8(buf).unwrap()
@@ -62,8 +287,15 @@ pub fn serialize_content(
             let mut buf = Vec::new();
             let mut config = ron::ser::PrettyConfig::new();
             config.new_line = "\n".to_string();
-            config.indentor = "  ".to_string();
-            let mut serializer = ron::ser::Serializer::new(&mut buf, Some(config), true).unwrap();
+            let struct_names = Settings::with(|settings| {
+                config.indentor = settings.ron_indent().to_string();
+                if let Some(limit) = settings.ron_depth_limit() {
+                    config.depth_limit = limit;
+                }
+                settings.ron_struct_names()
+            });
+            let mut serializer =
+                ron::ser::Serializer::new(&mut buf, Some(config), struct_names).unwrap();
             content.serialize(&mut serializer).unwrap();
             String::from_utf8(buf).unwrap()
         }
@@ -75,16 +307,232 @@ pub fn serialize_content(
             }
             rv
         }
+        SerializationFormat::Custom(serializer) => serializer.serialize(&content),
+    }
+}
+
+/// Serializes content into a YAML document starting with `---`.
+fn to_yaml_document(content: &Content) -> String {
+    let mut serialized = serde_yaml::to_string(content).unwrap();
+    // newer serde_yaml versions end the document with a newline
+    if serialized.ends_with('\n') {
+        serialized.truncate(serialized.len() - 1);
+    }
+    serialized
+}
+
+/// Pretty prints JSON like `serde_json::to_string_pretty`.
+///
+/// Unlike the built-in pretty formatter this one expands raw JSON
+/// fragments (`serde_json::value::RawValue`) at the current indentation
+/// so that streamed output matches the output of the content tree.  The
+/// indentation width is configurable.
+struct SnapshotJsonFormatter {
+    current_indent: usize,
+    indent_width: usize,
+    has_value: bool,
+}
+
+impl SnapshotJsonFormatter {
+    fn new(current_indent: usize, indent_width: usize) -> SnapshotJsonFormatter {
+        SnapshotJsonFormatter {
+            current_indent,
+            indent_width,
+            has_value: false,
+        }
+    }
+
+    fn indent<W: ?Sized + io::Write>(&self, writer: &mut W) -> io::Result<()> {
+        for _ in 0..self.current_indent * self.indent_width {
+            writer.write_all(b" ")?;
+        }
+        Ok(())
+    }
+}
+
+impl Formatter for SnapshotJsonFormatter {
+    fn begin_array<W: ?Sized + io::Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        self.current_indent += 1;
+        self.has_value = false;
+        writer.write_all(b"[")
+    }
+
+    fn end_array<W: ?Sized + io::Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        self.current_indent -= 1;
+        if self.has_value {
+            writer.write_all(b"\n")?;
+            self.indent(writer)?;
+        }
+        writer.write_all(b"]")
+    }
+
+    fn begin_array_value<W: ?Sized + io::Write>(
+        &mut self,
+        writer: &mut W,
+        first: bool,
+    ) -> io::Result<()> {
+        writer.write_all(if first { b"\n" } else { b",\n" })?;
+        self.indent(writer)
+    }
+
+    fn end_array_value<W: ?Sized + io::Write>(&mut self, _writer: &mut W) -> io::Result<()> {
+        self.has_value = true;
+        Ok(())
+    }
+
+    fn begin_object<W: ?Sized + io::Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        self.current_indent += 1;
+        self.has_value = false;
+        writer.write_all(b"{")
+    }
+
+    fn end_object<W: ?Sized + io::Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        self.current_indent -= 1;
+        if self.has_value {
+            writer.write_all(b"\n")?;
+            self.indent(writer)?;
+        }
+        writer.write_all(b"}")
+    }
+
+    fn begin_object_key<W: ?Sized + io::Write>(
+        &mut self,
+        writer: &mut W,
+        first: bool,
+    ) -> io::Result<()> {
+        writer.write_all(if first { b"\n" } else { b",\n" })?;
+        self.indent(writer)
+    }
+
+    fn begin_object_value<W: ?Sized + io::Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(b": ")
+    }
+
+    fn end_object_value<W: ?Sized + io::Write>(&mut self, _writer: &mut W) -> io::Result<()> {
+        self.has_value = true;
+        Ok(())
+    }
+
+    fn write_raw_fragment<W: ?Sized + io::Write>(
+        &mut self,
+        writer: &mut W,
+        fragment: &str,
+    ) -> io::Result<()> {
+        let parsed = match parse_json(fragment) {
+            Some(parsed) => parsed,
+            None => return writer.write_all(fragment.as_bytes()),
+        };
+        // buffered so that the writer type does not nest on every level
+        let mut buf = Vec::new();
+        let formatter = SnapshotJsonFormatter::new(self.current_indent, self.indent_width);
+        let mut serializer = serde_json::Serializer::with_formatter(&mut buf, formatter);
+        parsed.serialize(&mut serializer).map_err(io::Error::from)?;
+        writer.write_all(&buf)
+    }
+}
+
+/// Can the value be serialized directly without going through [`Content`]?
+///
+/// This is only possible if no setting needs to inspect or rewrite the
+/// serialized tree.
+fn can_stream(format: &SerializationFormat<'_>) -> bool {
+    if !matches!(format, SerializationFormat::Json) {
+        return false;
+    }
+    Settings::with(|settings| {
+        #[cfg(feature = "redactions")]
+        {
+            if settings.iter_redactions().next().is_some() {
+                return false;
+            }
+        }
+        #[cfg(any(feature = "chrono", feature = "time"))]
+        {
+            if settings.redact_timestamps() {
+                return false;
+            }
+        }
+        !settings.sort_maps() && *settings.json_enum_repr() == EnumRepr::External
+    })
+}
+
+/// Joins arrays of scalars in pretty printed JSON onto one line if that
+/// line is at most `width` characters wide.
+///
+/// Scalars are always written on a single line and never end in `[` or
+/// `{` so the items of such arrays can be found line by line.
+fn join_json_arrays(json: &str, width: usize) -> String {
+    let lines: Vec<&str> = json.lines().collect();
+    let mut rv = String::with_capacity(json.len());
+    let mut idx = 0;
+    while idx < lines.len() {
+        let line = lines[idx];
+        if line.ends_with('[') {
+            let indent = line.len() - line.trim_start().len();
+            let mut items = vec![];
+            let mut joined = None;
+            for (offset, item) in lines[idx + 1..].iter().enumerate() {
+                let trimmed = item.trim_start();
+                if trimmed.starts_with(']') && item.len() - trimmed.len() == indent {
+                    joined = Some((
+                        format!("{}{}{}", line, items.join(", "), trimmed),
+                        idx + offset + 2,
+                    ));
+                    break;
+                }
+                let value = trimmed.strip_suffix(',').unwrap_or(trimmed);
+                if value.ends_with(['[', '{']) || value.starts_with([']', '}']) {
+                    break;
+                }
+                items.push(value);
+            }
+            if let Some((joined, next)) = joined.filter(|x| x.0.chars().count() <= width) {
+                rv.push_str(&joined);
+                rv.push('\n');
+                idx = next;
+                continue;
+            }
+        }
+        rv.push_str(line);
+        rv.push('\n');
+        idx += 1;
+    }
+    rv.pop();
+    rv
+}
+
+/// Serializes a value to pretty printed JSON following the settings.
+///
+/// This does not need a content tree so values that do not need to be
+/// rewritten can be streamed through it directly.
+fn serialize_json_pretty<S: Serialize + ?Sized>(s: &S) -> String {
+    let (indent_width, array_width) =
+        Settings::with(|settings| (settings.json_indent(), settings.json_array_width()));
+    let mut buf = Vec::with_capacity(128);
+    let mut serializer = serde_json::Serializer::with_formatter(
+        &mut buf,
+        SnapshotJsonFormatter::new(0, indent_width),
+    );
+    s.serialize(&mut serializer).unwrap();
+    let rv = String::from_utf8(buf).unwrap();
+    match array_width {
+        Some(width) => join_json_arrays(&rv, width),
+        None => rv,
     }
 }
 
 pub fn serialize_value<S: Serialize>(
     s: &S,
-    format: SerializationFormat,
+    format: SerializationFormat<'_>,
     location: SnapshotLocation,
 ) -> String {
+    // values that do not need to be rewritten are written straight to the
+    // output which avoids holding a copy of large values in memory.
+    if can_stream(&format) {
+        return serialize_json_pretty(s);
+    }
     let serializer = ContentSerializer::<ValueError>::new();
-    let content = Serialize::serialize(s, serializer).unwrap();
+    let content = with_interned_keys(|| Serialize::serialize(s, serializer)).unwrap();
     serialize_content(content, format, location)
 }
 
@@ -92,13 +540,69 @@ pub fn serialize_value<S: Serialize>(
 pub fn serialize_value_redacted<S: Serialize>(
     s: &S,
     redactions: &[(crate::redaction::Selector, crate::redaction::Redaction)],
-    format: SerializationFormat,
+    format: SerializationFormat<'_>,
     location: SnapshotLocation,
 ) -> String {
     let serializer = ContentSerializer::<ValueError>::new();
-    let mut content = Serialize::serialize(s, serializer).unwrap();
-    for (selector, redaction) in redactions {
-        content = selector.redact(content, &redaction);
+    let content = with_interned_keys(|| Serialize::serialize(s, serializer)).unwrap();
+    let mut content = expand_raw_json(content);
+    // inline redactions and the ones from the settings share placeholders
+    crate::redaction::with_placeholders(|| {
+        for (selector, redaction) in redactions {
+            content = selector.redact(content, redaction);
+        }
+        serialize_content(content, format, location)
+    })
+}
+
+#[test]
+fn test_streaming_json_matches_content() {
+    #[derive(Serialize)]
+    struct Value {
+        floats: Vec<f64>,
+        nested: serde_json::Value,
+        raw: Box<serde_json::value::RawValue>,
     }
-    serialize_content(content, format, location)
+
+    let value = Value {
+        floats: vec![1.5, 2.0, -0.0],
+        nested: serde_json::json!({"a": {"b": [], "c": {}}}),
+        raw: serde_json::value::RawValue::from_string(r#"{"b": [1, {"c": null}]}"#.into()).unwrap(),
+    };
+    let content = Serialize::serialize(&value, ContentSerializer::<ValueError>::new()).unwrap();
+    assert_eq!(
+        serialize_json_pretty(&value),
+        serialize_content(content, SerializationFormat::Json, SnapshotLocation::File)
+    );
+}
+
+#[test]
+fn test_join_json_arrays() {
+    let json = serde_json::to_string_pretty(&serde_json::json!({
+        "matrix": [[1, 2], [3, 4]],
+        "names": ["a", "b,", "[c"],
+        "long": ["aaaaaaaaaa", "bbbbbbbbbb", "cccccccccc"],
+        "objects": [{"a": 1}],
+    }))
+    .unwrap();
+    assert_eq!(
+        join_json_arrays(&json, 30),
+        r#"{
+  "long": [
+    "aaaaaaaaaa",
+    "bbbbbbbbbb",
+    "cccccccccc"
+  ],
+  "matrix": [
+    [1, 2],
+    [3, 4]
+  ],
+  "names": ["a", "b,", "[c"],
+  "objects": [
+    {
+      "a": 1
+    }
+  ]
+}"#
+    );
 }
@@ -1,28 +1,97 @@
 use lazy_static::lazy_static;
 use std::cell::RefCell;
 use std::future::Future;
+use std::marker::PhantomData;
 use std::path::{Path, PathBuf};
 use std::pin::Pin;
 use std::sync::Arc;
 use std::task::{Context, Poll};
+#[cfg(feature = "redactions")]
+use std::{collections::HashMap, sync::Mutex};
+
+use serde::{Deserialize, Serialize};
 
+use crate::config::tool_config;
+use crate::runtime::DiffRenderer;
+
+#[cfg(feature = "filters")]
+use crate::filters::Filters;
 #[cfg(feature = "redactions")]
 use crate::{
-    content::Content,
-    redaction::{dynamic_redaction, ContentPath, Redaction, Selector},
+    content::{Content, ContentPath},
+    redaction::{
+        conditional_redaction, dynamic_redaction, Redaction, Selector, SelectorParseError,
+    },
 };
 
 lazy_static! {
-    static ref DEFAULT_SETTINGS: Arc<ActualSettings> = Arc::new(ActualSettings {
-        sort_maps: false,
-        snapshot_path: "snapshots".into(),
-        snapshot_suffix: "".into(),
-        input_file: None,
-        prepend_module_to_snapshot: true,
-        #[cfg(feature = "redactions")]
-        redactions: Redactions::default(),
+    static ref DEFAULT_SETTINGS: Arc<ActualSettings> = Arc::new({
+        let mut settings = ActualSettings {
+            sort_maps: false,
+            snapshot_path: "snapshots".into(),
+            snapshot_path_base: SnapshotPathBase::SourceFile,
+            snapshot_update: None,
+            force_pass: None,
+            snapshot_suffix: "".into(),
+            snapshot_extension: "snap".into(),
+            test_name: None,
+            name_from_test_path: false,
+            tags: Vec::new(),
+            description: None,
+            info: None,
+            input_file: None,
+            workspace_root: None,
+            source_path_remaps: Vec::new(),
+            prepend_module_to_snapshot: true,
+            require_full_match: false,
+            omit_expression: false,
+            allow_empty: true,
+            wildcards: false,
+            normalize_line_endings: true,
+            trailing_whitespace: Whitespace::Preserve,
+            final_newline: Whitespace::Normalize,
+            json_enum_repr: EnumRepr::External,
+            json_indent: 2,
+            json_array_width: None,
+            ansi_escapes: AnsiEscapes::Keep,
+            diff_renderer: None,
+            #[cfg(feature = "csv")]
+            csv_delimiter: b',',
+            #[cfg(feature = "csv")]
+            csv_quote_style: CsvQuoteStyle::Necessary,
+            #[cfg(feature = "csv")]
+            csv_has_headers: true,
+            #[cfg(feature = "csv")]
+            csv_crlf: false,
+            #[cfg(feature = "ron")]
+            ron_indent: "  ".into(),
+            #[cfg(feature = "ron")]
+            ron_struct_names: true,
+            #[cfg(feature = "ron")]
+            ron_depth_limit: None,
+            #[cfg(any(feature = "chrono", feature = "time"))]
+            redact_timestamps: false,
+            #[cfg(feature = "image")]
+            image_threshold: 0.0,
+            #[cfg(feature = "image")]
+            image_max_diff_ratio: 0.0,
+            #[cfg(feature = "compression")]
+            compression_threshold: None,
+            #[cfg(feature = "redactions")]
+            redactions: Redactions::default(),
+            #[cfg(feature = "filters")]
+            filters: Filters::default(),
+        };
+        tool_config().apply_to_settings(&mut settings);
+        settings
     });
 }
+#[cfg(feature = "redactions")]
+lazy_static! {
+    static ref REDACTION_SETS: Mutex<HashMap<String, Redactions>> =
+        Mutex::new(tool_config().redaction_sets());
+}
+
 thread_local!(static CURRENT_SETTINGS: RefCell<Settings> = RefCell::new(Settings::new()));
 
 /// Represents stored redactions.
@@ -36,22 +105,525 @@ impl<'a> From<Vec<(&'a str, Redaction)>> for Redactions {
         Redactions(
             value
                 .into_iter()
-                .map(|x| (Selector::parse(x.0).unwrap().make_static(), Arc::new(x.1)))
+                .map(|x| (parse_selector(x.0).make_static(), Arc::new(x.1)))
                 .collect(),
         )
     }
 }
 
+#[cfg(feature = "redactions")]
+impl Redactions {
+    pub(crate) fn push<R: Into<Redaction>>(&mut self, selector: Selector, replacement: R) {
+        self.0
+            .push((selector.make_static(), Arc::new(replacement.into())));
+    }
+}
+
+/// Parses a selector panicking with the full diagnostics if it's invalid.
+#[cfg(feature = "redactions")]
+pub(crate) fn parse_selector(selector: &str) -> Selector<'_> {
+    match Selector::parse(selector) {
+        Ok(selector) => selector,
+        Err(err) => panic!("{:?} is an {}", selector, err),
+    }
+}
+
+/// Registers a named set of redactions.
+///
+/// The set can then be applied by name with
+/// [`Settings::apply_redaction_set`] or the `redaction_set` key of
+/// `with_settings!`.  This lets a crate define redactions that are shared
+/// by many tests once.  Registering a set again replaces it so it's fine
+/// to register sets from a helper that every test calls.  Sets can also
+/// be defined in `insta.toml`.
+///
+/// ```rust
+/// # use insta::{register_redaction_set, with_settings};
+/// register_redaction_set("timestamps", vec![
+///     (".created_at", "[timestamp]".into()),
+///     (".**.updated_at", "[timestamp]".into()),
+/// ]);
+/// with_settings!({redaction_set => "timestamps"}, {
+///     // ...
+/// });
+/// ```
+#[cfg(feature = "redactions")]
+pub fn register_redaction_set<R: Into<Redactions>>(name: &str, redactions: R) {
+    REDACTION_SETS
+        .lock()
+        .unwrap()
+        .insert(name.to_string(), redactions.into());
+}
+
+/// Controls how enum variants are represented in JSON snapshots.
+///
+/// By default enums are serialized the way their `Serialize` implementation
+/// emits them, which for types without serde attributes is the externally
+/// tagged representation.  The other modes rewrite externally tagged enums
+/// into the equivalent of serde's `#[serde(tag = "...")]` and
+/// `#[serde(tag = "...", content = "...")]` representations.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum EnumRepr {
+    /// `{"Variant": {...}}` (the default)
+    External,
+    /// `{"<tag>": "Variant", ...}`
+    ///
+    /// Newtype variants holding something other than a struct or map as
+    /// well as tuple variants cannot be represented this way and stay
+    /// externally tagged.
+    Internal { tag: String },
+    /// `{"<tag>": "Variant", "<content>": {...}}`
+    Adjacent { tag: String, content: String },
+}
+
+/// Controls what a relative snapshot path is relative to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SnapshotPathBase {
+    /// The folder of the source file with the assertion (the default).
+    SourceFile,
+    /// The folder of the crate's `Cargo.toml`.
+    Crate,
+    /// The workspace root.
+    Workspace,
+}
+
+/// Controls how snapshots are updated, like the values of `INSTA_UPDATE`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SnapshotUpdate {
+    /// `No` for CI environments or `New` otherwise.
+    Auto,
+    /// Overwrite old snapshot files with new ones.
+    Always,
+    /// Like `Always` for new snapshots and `New` for others.
+    Unseen,
+    /// Write new snapshots into `.snap.new` files.
+    New,
+    /// Do not update snapshot files at all.
+    No,
+}
+
+impl SnapshotUpdate {
+    /// Returns the equivalent value of `INSTA_UPDATE`.
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            SnapshotUpdate::Auto => "auto",
+            SnapshotUpdate::Always => "always",
+            SnapshotUpdate::Unseen => "unseen",
+            SnapshotUpdate::New => "new",
+            SnapshotUpdate::No => "no",
+        }
+    }
+}
+
+/// Controls how whitespace at the end of lines or snapshots is handled.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Whitespace {
+    /// Keep the whitespace and compare it.
+    Preserve,
+    /// Remove the whitespace before comparing.
+    Normalize,
+}
+
+/// Controls how ANSI escape sequences in snapshots are handled.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AnsiEscapes {
+    /// Keep escape sequences as they are (the default).
+    Keep,
+    /// Remove all escape sequences.
+    Strip,
+    /// Replace colors and styles with readable tokens like `<fg:red>`.
+    Tokens,
+}
+
+/// Controls when fields in CSV snapshots are quoted.
+#[cfg(feature = "csv")]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CsvQuoteStyle {
+    /// Quote every field.
+    Always,
+    /// Only quote fields that contain a delimiter, quote or newline (the default).
+    Necessary,
+    /// Quote all fields that are not numbers.
+    NonNumeric,
+    /// Never quote fields, even if that produces invalid CSV.
+    Never,
+}
+
 #[derive(Clone)]
 #[doc(hidden)]
 pub struct ActualSettings {
     pub sort_maps: bool,
     pub snapshot_path: PathBuf,
+    pub snapshot_path_base: SnapshotPathBase,
+    pub snapshot_update: Option<SnapshotUpdate>,
+    pub force_pass: Option<bool>,
     pub snapshot_suffix: String,
+    pub snapshot_extension: String,
+    pub test_name: Option<String>,
+    pub name_from_test_path: bool,
+    pub tags: Vec<String>,
+    pub description: Option<String>,
+    pub info: Option<serde_yaml::Value>,
     pub input_file: Option<PathBuf>,
+    pub workspace_root: Option<PathBuf>,
+    pub source_path_remaps: Vec<(PathBuf, PathBuf)>,
     pub prepend_module_to_snapshot: bool,
+    pub require_full_match: bool,
+    pub omit_expression: bool,
+    pub allow_empty: bool,
+    pub wildcards: bool,
+    pub normalize_line_endings: bool,
+    pub trailing_whitespace: Whitespace,
+    pub final_newline: Whitespace,
+    pub json_enum_repr: EnumRepr,
+    pub json_indent: usize,
+    pub json_array_width: Option<usize>,
+    pub ansi_escapes: AnsiEscapes,
+    pub diff_renderer: Option<Arc<dyn DiffRenderer>>,
+    #[cfg(feature = "csv")]
+    pub csv_delimiter: u8,
+    #[cfg(feature = "csv")]
+    pub csv_quote_style: CsvQuoteStyle,
+    #[cfg(feature = "csv")]
+    pub csv_has_headers: bool,
+    #[cfg(feature = "csv")]
+    pub csv_crlf: bool,
+    #[cfg(feature = "ron")]
+    pub ron_indent: String,
+    #[cfg(feature = "ron")]
+    pub ron_struct_names: bool,
+    #[cfg(feature = "ron")]
+    pub ron_depth_limit: Option<usize>,
+    #[cfg(any(feature = "chrono", feature = "time"))]
+    pub redact_timestamps: bool,
+    #[cfg(feature = "image")]
+    pub image_threshold: f64,
+    #[cfg(feature = "image")]
+    pub image_max_diff_ratio: f64,
+    #[cfg(feature = "compression")]
+    pub compression_threshold: Option<usize>,
     #[cfg(feature = "redactions")]
     pub redactions: Redactions,
+    #[cfg(feature = "filters")]
+    pub filters: Filters,
+}
+
+/// The setters used by `with_settings!`.
+///
+/// They are named after the settings keys and work like the `set_*`
+/// methods on `Settings`.
+impl ActualSettings {
+    pub fn sort_maps(&mut self, value: bool) {
+        self.sort_maps = value;
+    }
+
+    pub fn prepend_module_to_snapshot(&mut self, value: bool) {
+        self.prepend_module_to_snapshot = value;
+    }
+
+    pub fn require_full_match(&mut self, value: bool) {
+        self.require_full_match = value;
+    }
+
+    pub fn omit_expression(&mut self, value: bool) {
+        self.omit_expression = value;
+    }
+
+    pub fn allow_empty(&mut self, value: bool) {
+        self.allow_empty = value;
+    }
+
+    pub fn wildcards(&mut self, value: bool) {
+        self.wildcards = value;
+    }
+
+    pub fn normalize_line_endings(&mut self, value: bool) {
+        self.normalize_line_endings = value;
+    }
+
+    pub fn trailing_whitespace(&mut self, value: Whitespace) {
+        self.trailing_whitespace = value;
+    }
+
+    pub fn final_newline(&mut self, value: Whitespace) {
+        self.final_newline = value;
+    }
+
+    pub fn ansi_escapes(&mut self, value: AnsiEscapes) {
+        self.ansi_escapes = value;
+    }
+
+    pub fn json_enum_repr(&mut self, value: EnumRepr) {
+        self.json_enum_repr = value;
+    }
+
+    pub fn json_indent(&mut self, value: usize) {
+        self.json_indent = value;
+    }
+
+    pub fn json_array_width(&mut self, value: Option<usize>) {
+        self.json_array_width = value;
+    }
+
+    pub fn diff_renderer<R: DiffRenderer + 'static>(&mut self, renderer: R) {
+        self.diff_renderer = Some(Arc::new(renderer));
+    }
+
+    pub fn snapshot_suffix<I: Into<String>>(&mut self, suffix: I) {
+        self.snapshot_suffix = suffix.into();
+    }
+
+    pub fn snapshot_extension<S: Into<String>>(&mut self, extension: S) {
+        let extension = extension.into();
+        self.snapshot_extension = extension.trim_start_matches('.').to_string();
+    }
+
+    pub fn test_name<I: Into<String>>(&mut self, name: I) {
+        self.test_name = Some(name.into());
+    }
+
+    pub fn name_from_test_path(&mut self, value: bool) {
+        self.name_from_test_path = value;
+    }
+
+    pub fn description<S: Into<String>>(&mut self, value: S) {
+        self.description = Some(value.into());
+    }
+
+    pub fn info<S: Serialize>(&mut self, s: &S) {
+        self.info = Some(serde_yaml::to_value(s).expect("failed to serialize snapshot info"));
+    }
+
+    pub fn input_file<P: AsRef<Path>>(&mut self, p: P) {
+        self.input_file = Some(p.as_ref().to_path_buf());
+    }
+
+    pub fn workspace_root<P: AsRef<Path>>(&mut self, p: P) {
+        self.workspace_root = Some(p.as_ref().to_path_buf());
+    }
+
+    #[cfg(feature = "csv")]
+    pub fn csv_delimiter(&mut self, value: u8) {
+        self.csv_delimiter = value;
+    }
+
+    #[cfg(feature = "csv")]
+    pub fn csv_quote_style(&mut self, value: CsvQuoteStyle) {
+        self.csv_quote_style = value;
+    }
+
+    #[cfg(feature = "csv")]
+    pub fn csv_has_headers(&mut self, value: bool) {
+        self.csv_has_headers = value;
+    }
+
+    #[cfg(feature = "csv")]
+    pub fn csv_crlf(&mut self, value: bool) {
+        self.csv_crlf = value;
+    }
+
+    #[cfg(feature = "ron")]
+    pub fn ron_indent<I: Into<String>>(&mut self, value: I) {
+        self.ron_indent = value.into();
+    }
+
+    #[cfg(feature = "ron")]
+    pub fn ron_struct_names(&mut self, value: bool) {
+        self.ron_struct_names = value;
+    }
+
+    #[cfg(feature = "ron")]
+    pub fn ron_depth_limit(&mut self, value: Option<usize>) {
+        self.ron_depth_limit = value;
+    }
+
+    #[cfg(any(feature = "chrono", feature = "time"))]
+    pub fn redact_timestamps(&mut self, value: bool) {
+        self.redact_timestamps = value;
+    }
+
+    #[cfg(feature = "image")]
+    pub fn image_threshold(&mut self, value: f64) {
+        self.image_threshold = value;
+    }
+
+    #[cfg(feature = "image")]
+    pub fn image_max_diff_ratio(&mut self, value: f64) {
+        self.image_max_diff_ratio = value;
+    }
+
+    #[cfg(feature = "compression")]
+    pub fn compression_threshold(&mut self, value: Option<usize>) {
+        self.compression_threshold = value;
+    }
+
+    #[cfg(feature = "redactions")]
+    pub fn redactions<R: Into<Redactions>>(&mut self, redactions: R) {
+        self.redactions = redactions.into();
+    }
+
+    #[cfg(feature = "redactions")]
+    pub fn redaction_set(&mut self, name: &str) {
+        let set = match REDACTION_SETS.lock().unwrap().get(name) {
+            Some(set) => set.clone(),
+            None => panic!("unknown redaction set {:?}", name),
+        };
+        for redaction in set.0 {
+            if !self
+                .redactions
+                .0
+                .iter()
+                .any(|x| Arc::ptr_eq(&x.1, &redaction.1))
+            {
+                self.redactions.0.push(redaction);
+            }
+        }
+    }
+
+    #[cfg(feature = "filters")]
+    pub fn filters<F: Into<Filters>>(&mut self, filters: F) {
+        self.filters = filters.into();
+    }
+
+    pub fn snapshot_path<P: AsRef<Path>>(&mut self, path: P) {
+        self.snapshot_path = path.as_ref().to_path_buf();
+    }
+
+    pub fn snapshot_path_base(&mut self, value: SnapshotPathBase) {
+        self.snapshot_path_base = value;
+    }
+
+    pub fn snapshot_update(&mut self, value: SnapshotUpdate) {
+        self.snapshot_update = Some(value);
+    }
+
+    pub fn force_pass(&mut self, value: bool) {
+        self.force_pass = Some(value);
+    }
+
+    pub fn tags<I, S>(&mut self, tags: I)
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.tags = Vec::new();
+        for tag in tags {
+            let tag = tag.into();
+            if !self.tags.contains(&tag) {
+                self.tags.push(tag);
+            }
+        }
+    }
+
+    #[cfg(feature = "redactions")]
+    pub(crate) fn add_redaction<R: Into<Redaction>>(&mut self, selector: Selector, replacement: R) {
+        self.redactions.push(selector, replacement);
+    }
+
+    pub(crate) fn merge(&mut self, other: &ActualSettings) {
+        let defaults = &**DEFAULT_SETTINGS;
+        macro_rules! merge_fields {
+            ($($(#[$attr:meta])* $field:ident,)*) => {$(
+                $(#[$attr])*
+                {
+                    if other.$field != defaults.$field {
+                        self.$field = other.$field.clone();
+                    }
+                }
+            )*}
+        }
+        merge_fields!(
+            sort_maps,
+            snapshot_path,
+            snapshot_path_base,
+            snapshot_update,
+            force_pass,
+            snapshot_suffix,
+            snapshot_extension,
+            test_name,
+            name_from_test_path,
+            description,
+            info,
+            input_file,
+            workspace_root,
+            prepend_module_to_snapshot,
+            require_full_match,
+            omit_expression,
+            allow_empty,
+            wildcards,
+            normalize_line_endings,
+            trailing_whitespace,
+            final_newline,
+            json_enum_repr,
+            json_indent,
+            json_array_width,
+            ansi_escapes,
+            #[cfg(feature = "csv")]
+            csv_delimiter,
+            #[cfg(feature = "csv")]
+            csv_quote_style,
+            #[cfg(feature = "csv")]
+            csv_has_headers,
+            #[cfg(feature = "csv")]
+            csv_crlf,
+            #[cfg(feature = "ron")]
+            ron_indent,
+            #[cfg(feature = "ron")]
+            ron_struct_names,
+            #[cfg(feature = "ron")]
+            ron_depth_limit,
+            #[cfg(any(feature = "chrono", feature = "time"))]
+            redact_timestamps,
+            #[cfg(feature = "image")]
+            image_threshold,
+            #[cfg(feature = "image")]
+            image_max_diff_ratio,
+            #[cfg(feature = "compression")]
+            compression_threshold,
+        );
+        if other.diff_renderer.is_some() {
+            self.diff_renderer = other.diff_renderer.clone();
+        }
+        for tag in &other.tags {
+            if !self.tags.contains(tag) {
+                self.tags.push(tag.clone());
+            }
+        }
+        for remap in &other.source_path_remaps {
+            if !self.source_path_remaps.contains(remap) {
+                self.source_path_remaps.push(remap.clone());
+            }
+        }
+        #[cfg(feature = "redactions")]
+        for redaction in &other.redactions.0 {
+            // redactions both settings inherited (eg: from insta.toml) are
+            // only kept once
+            if !self
+                .redactions
+                .0
+                .iter()
+                .any(|x| Arc::ptr_eq(&x.1, &redaction.1))
+            {
+                self.redactions.0.push(redaction.clone());
+            }
+        }
+        #[cfg(feature = "filters")]
+        self.filters.extend(&other.filters);
+    }
+
+    pub fn source_path_remaps<I, A, B>(&mut self, remaps: I)
+    where
+        I: IntoIterator<Item = (A, B)>,
+        A: AsRef<Path>,
+        B: AsRef<Path>,
+    {
+        self.source_path_remaps = remaps
+            .into_iter()
+            .map(|(from, to)| (from.as_ref().to_path_buf(), to.as_ref().to_path_buf()))
+            .collect();
+    }
 }
 
 /// Configures how insta operates at test time.
@@ -65,6 +637,9 @@ pub struct ActualSettings {
 /// unordered structures are used through settings.
 ///
 /// Settings can also be configured with the `with_settings!` macro.
+/// To apply settings for the rest of a block without a closure use
+/// [`bind_to_scope`](Settings::bind_to_scope) which returns a guard that
+/// restores the previous settings when dropped.
 ///
 /// Example:
 ///
@@ -100,10 +675,42 @@ impl Settings {
     }
 
     /// Returns a copy of the current settings.
+    ///
+    /// Helpers should start from this to extend the settings bound by the
+    /// caller (eg: add one redaction) and bind the result, instead of
+    /// starting from the defaults and losing the caller's configuration.
     pub fn clone_current() -> Settings {
         Settings::with(|x| x.clone())
     }
 
+    /// Merges other settings into these settings.
+    ///
+    /// Redactions, tags and source path remaps of `other` are added to the
+    /// ones already set.  All other settings of `other` replace the current
+    /// values unless they are at their default.  This lets helpers layer
+    /// settings, for instance per module overrides on top of crate wide
+    /// redactions:
+    ///
+    /// ```rust
+    /// # use insta::Settings;
+    /// fn crate_settings() -> Settings {
+    ///     let mut settings = Settings::clone_current();
+    ///     settings.set_sort_maps(true);
+    ///     settings
+    /// }
+    ///
+    /// let mut module_settings = Settings::new();
+    /// module_settings.set_snapshot_suffix("v2");
+    ///
+    /// let mut settings = crate_settings();
+    /// settings.merge(&module_settings);
+    /// assert!(settings.sort_maps());
+    /// assert_eq!(settings.snapshot_suffix(), Some("v2"));
+    /// ```
+    pub fn merge(&mut self, other: &Settings) {
+        self._private_inner_mut().merge(&other.inner);
+    }
+
     /// Internal helper for macros
     #[doc(hidden)]
     pub fn _private_inner_mut(&mut self) -> &mut ActualSettings {
@@ -113,11 +720,12 @@ impl Settings {
     /// Enables forceful sorting of maps before serialization.
     ///
     /// Note that this only applies to snapshots that undergo serialization
-    /// (eg: does not work for `assert_debug_snapshot!`.)
+    /// (eg: does not work for `assert_debug_snapshot!`.)  Struct fields are
+    /// never sorted, they always appear in declaration order.
     ///
     /// The default value is `false`.
     pub fn set_sort_maps(&mut self, value: bool) {
-        self._private_inner_mut().sort_maps = value;
+        self._private_inner_mut().sort_maps(value);
     }
 
     /// Returns the current value for map sorting.
@@ -125,7 +733,7 @@ impl Settings {
         self.inner.sort_maps
     }
 
-    /// Disbales prepending of modules to the snapshot filename.
+    /// Disables prepending of modules to the snapshot filename.
     ///
     /// By default the filename of a snapshot is `<module>__<name>.snap`.
     /// Setting this flag to `false` changes the snapshot filename to just
@@ -133,7 +741,7 @@ impl Settings {
     ///
     /// The default value is `true`.
     pub fn set_prepend_module_to_snapshot(&mut self, value: bool) {
-        self._private_inner_mut().prepend_module_to_snapshot = value;
+        self._private_inner_mut().prepend_module_to_snapshot(value);
     }
 
     /// Returns the current value for module name prepending.
@@ -141,6 +749,216 @@ impl Settings {
         self.inner.prepend_module_to_snapshot
     }
 
+    /// Only accepts snapshots that match exactly what the assertion expects.
+    ///
+    /// When enabled snapshots in a legacy format (old style metadata headers
+    /// and `⋮` prefixed inline snapshots) are rejected instead of being
+    /// read leniently, and an assertion whose snapshot file does not exist
+    /// fails even if `INSTA_FORCE_PASS` is set.  This makes sure that
+    /// renamed tests or modules cannot silently start writing new snapshots
+    /// while the old ones linger.
+    ///
+    /// The default value is `false`.
+    pub fn set_require_full_match(&mut self, value: bool) {
+        self._private_inner_mut().require_full_match(value);
+    }
+
+    /// Returns whether snapshots have to match exactly.
+    pub fn require_full_match(&self) -> bool {
+        self.inner.require_full_match
+    }
+
+    /// Disables writing the asserted expression into snapshot files.
+    ///
+    /// Without the expression in the metadata refactoring the code of an
+    /// assertion (for instance renaming a variable) does not change the
+    /// snapshot files when they are next written.
+    ///
+    /// The default value is `false`.
+    pub fn set_omit_expression(&mut self, value: bool) {
+        self._private_inner_mut().omit_expression(value);
+    }
+
+    /// Returns whether the expression is omitted from snapshot files.
+    pub fn omit_expression(&self) -> bool {
+        self.inner.omit_expression
+    }
+
+    /// Allows or rejects empty snapshots.
+    ///
+    /// An empty snapshot (or one with only whitespace) usually means that
+    /// the code under test produced no output by accident.  When disabled
+    /// such assertions fail instead of storing the empty snapshot.
+    ///
+    /// The default value is `true`.
+    pub fn set_allow_empty(&mut self, value: bool) {
+        self._private_inner_mut().allow_empty(value);
+    }
+
+    /// Returns whether empty snapshots are allowed.
+    pub fn allow_empty(&self) -> bool {
+        self.inner.allow_empty
+    }
+
+    /// Enables wildcards in stored snapshots.
+    ///
+    /// With wildcards enabled `[..]` in a stored snapshot matches any text
+    /// within a line and a line consisting of `...` matches any number of
+    /// lines.  This allows volatile fragments such as durations or absolute
+    /// paths to match loosely without filtering the value first:
+    ///
+    /// ```text
+    /// Compiling demo v0.1.0 ([..])
+    /// ...
+    /// Finished in [..]s
+    /// ```
+    ///
+    /// A new value matching the stored snapshot leaves the file untouched.
+    /// Accepting a changed value replaces the wildcards with the new value.
+    ///
+    /// The default value is `false`.
+    pub fn set_wildcards(&mut self, value: bool) {
+        self._private_inner_mut().wildcards(value);
+    }
+
+    /// Returns whether wildcards in stored snapshots are enabled.
+    pub fn wildcards(&self) -> bool {
+        self.inner.wildcards
+    }
+
+    /// Enables or disables the normalization of line endings.
+    ///
+    /// When enabled `\r\n` line endings are converted to `\n` in new
+    /// snapshots and in the snapshot files they are compared against, so
+    /// that checkouts with `core.autocrlf` do not fail every assertion.
+    /// Disable it if carriage returns are part of what is asserted (eg:
+    /// with `set_csv_crlf`).
+    ///
+    /// The default value is `true`.
+    pub fn set_normalize_line_endings(&mut self, value: bool) {
+        self._private_inner_mut().normalize_line_endings(value);
+    }
+
+    /// Returns whether line endings are normalized.
+    pub fn normalize_line_endings(&self) -> bool {
+        self.inner.normalize_line_endings
+    }
+
+    /// Sets how spaces and tabs at the end of lines are handled.
+    ///
+    /// With `Whitespace::Normalize` they are removed from new snapshots and
+    /// ignored in stored ones, so editors that trim trailing whitespace do
+    /// not invalidate snapshots.  The policy is recorded in the metadata of
+    /// snapshot files.
+    ///
+    /// The default value is `Whitespace::Preserve`.
+    pub fn set_trailing_whitespace(&mut self, value: Whitespace) {
+        self._private_inner_mut().trailing_whitespace(value);
+    }
+
+    /// Returns how spaces and tabs at the end of lines are handled.
+    pub fn trailing_whitespace(&self) -> Whitespace {
+        self.inner.trailing_whitespace
+    }
+
+    /// Sets how the newline at the end of a snapshot is handled.
+    ///
+    /// With `Whitespace::Normalize` the whitespace at the end of a snapshot
+    /// (including the final newline) is not compared.  With
+    /// `Whitespace::Preserve` a snapshot file only matches if it ends with
+    /// the same newlines.  The policy is recorded in the metadata of
+    /// snapshot files.  It does not apply to inline snapshots which cannot
+    /// express a final newline.
+    ///
+    /// The default value is `Whitespace::Normalize`.
+    pub fn set_final_newline(&mut self, value: Whitespace) {
+        self._private_inner_mut().final_newline(value);
+    }
+
+    /// Returns how the newline at the end of a snapshot is handled.
+    pub fn final_newline(&self) -> Whitespace {
+        self.inner.final_newline
+    }
+
+    /// Sets how ANSI escape sequences in snapshots are handled.
+    ///
+    /// Command line tools that color their output emit escape sequences
+    /// which are hard to review in snapshots.  They can either be removed
+    /// or replaced with readable tokens such as `<bold>` or `<fg:red>` (see
+    /// `ansi_to_tokens`) before the snapshot is compared.
+    ///
+    /// The default value is `AnsiEscapes::Keep`.
+    pub fn set_ansi_escapes(&mut self, value: AnsiEscapes) {
+        self._private_inner_mut().ansi_escapes(value);
+    }
+
+    /// Returns the current handling of ANSI escape sequences.
+    pub fn ansi_escapes(&self) -> AnsiEscapes {
+        self.inner.ansi_escapes
+    }
+
+    /// Sets the representation of enums in JSON snapshots.
+    ///
+    /// This is applied independently of the serde attributes of the type
+    /// and only affects enums that serialize externally tagged.
+    ///
+    /// The default value is `EnumRepr::External`.
+    pub fn set_json_enum_repr(&mut self, value: EnumRepr) {
+        self._private_inner_mut().json_enum_repr(value);
+    }
+
+    /// Returns the current representation of enums in JSON snapshots.
+    pub fn json_enum_repr(&self) -> &EnumRepr {
+        &self.inner.json_enum_repr
+    }
+
+    /// Sets the number of spaces JSON snapshots are indented with.
+    ///
+    /// The default value is `2`.
+    pub fn set_json_indent(&mut self, value: usize) {
+        self._private_inner_mut().json_indent(value);
+    }
+
+    /// Returns the number of spaces JSON snapshots are indented with.
+    pub fn json_indent(&self) -> usize {
+        self.inner.json_indent
+    }
+
+    /// Sets the width up to which arrays in JSON snapshots are written on
+    /// one line.
+    ///
+    /// Arrays that only hold numbers, strings, booleans or nulls are joined
+    /// onto a single line (`[1, 2, 3]`) if that line including the
+    /// indentation is at most this many characters wide.  The default is
+    /// `None` which writes every item on its own line.
+    pub fn set_json_array_width(&mut self, value: Option<usize>) {
+        self._private_inner_mut().json_array_width(value);
+    }
+
+    /// Returns the width up to which arrays in JSON snapshots are written on
+    /// one line.
+    pub fn json_array_width(&self) -> Option<usize> {
+        self.inner.json_array_width
+    }
+
+    /// Replaces the renderer for diffs of failed assertions.
+    ///
+    /// See [`DiffRenderer`](runtime/trait.DiffRenderer.html) for more
+    /// information.
+    pub fn set_diff_renderer<R: DiffRenderer + 'static>(&mut self, renderer: R) {
+        self._private_inner_mut().diff_renderer(renderer);
+    }
+
+    /// Restores the default diff renderer.
+    pub fn remove_diff_renderer(&mut self) {
+        self._private_inner_mut().diff_renderer = None;
+    }
+
+    /// Returns the custom diff renderer if one is set.
+    pub(crate) fn diff_renderer(&self) -> Option<Arc<dyn DiffRenderer>> {
+        self.inner.diff_renderer.clone()
+    }
+
     /// Sets the snapshot suffix.
     ///
     /// The snapshot suffix is added to all snapshot names with an `@` sign
@@ -149,7 +967,7 @@ impl Settings {
     /// This is useful to separate snapshots if you want to use test
     /// parameterization.
     pub fn set_snapshot_suffix<I: Into<String>>(&mut self, suffix: I) {
-        self._private_inner_mut().snapshot_suffix = suffix.into();
+        self._private_inner_mut().snapshot_suffix(suffix);
     }
 
     /// Removes the snapshot suffix.
@@ -166,14 +984,150 @@ impl Settings {
         }
     }
 
+    /// Sets the extension of snapshot files.
+    ///
+    /// The default is `"snap"`.  Extensions with multiple parts (eg:
+    /// `"snap.json"`) let editors pick the syntax highlighting of the
+    /// format.  Pending snapshots get `.new` appended as usual.  `cargo
+    /// insta` picks up the extension configured in `insta.toml`, an
+    /// extension only set in code has to be passed with `--extensions`.
+    pub fn set_snapshot_extension<S: Into<String>>(&mut self, extension: S) {
+        self._private_inner_mut().snapshot_extension(extension);
+    }
+
+    /// Returns the extension of snapshot files.
+    pub fn snapshot_extension(&self) -> &str {
+        &self.inner.snapshot_extension
+    }
+
+    /// Sets the name of the test unnamed snapshots are named after.
+    ///
+    /// By default the name is taken from the thread the test runs on.  This
+    /// overrides it for tests that run assertions on other threads or whose
+    /// generated names are not useful.  The snapshots are named like for a
+    /// test of that name in the module of the assertion.
+    pub fn set_test_name<I: Into<String>>(&mut self, name: I) {
+        self._private_inner_mut().test_name(name);
+    }
+
+    /// Removes the test name so that it is inferred again.
+    pub fn remove_test_name(&mut self) {
+        self._private_inner_mut().test_name = None;
+    }
+
+    /// Returns the explicitly set test name.
+    pub fn test_name(&self) -> Option<&str> {
+        self.inner.test_name.as_deref()
+    }
+
+    /// Names unnamed snapshots after the full path of the test.
+    ///
+    /// By default unnamed snapshots are named after the last segment of the
+    /// test name and stored next to the module of the assertion.  With this
+    /// enabled an assertion in a helper function outside of the test's
+    /// module is named after the module of the test instead of the
+    /// helper's, and tests generated into a module named after a test
+    /// function (eg: `rstest` cases) include that module name so that the
+    /// cases of different tests do not clash.
+    ///
+    /// This changes the names of existing snapshots, which is why it is not
+    /// the default.
+    ///
+    /// The default value is `false`.
+    pub fn set_name_from_test_path(&mut self, value: bool) {
+        self._private_inner_mut().name_from_test_path(value);
+    }
+
+    /// Returns whether unnamed snapshots are named after the test path.
+    pub fn name_from_test_path(&self) -> bool {
+        self.inner.name_from_test_path
+    }
+
+    /// Sets the tags stored with new snapshots.
+    ///
+    /// Tags (eg: `slow` or `api-contract`) end up in the snapshot metadata
+    /// and let `cargo insta review --tag` and `cargo insta coverage --tag`
+    /// work on a subset of the snapshots.
+    pub fn set_tags<I, S>(&mut self, tags: I)
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self._private_inner_mut().tags(tags);
+    }
+
+    /// Adds a tag to the tags stored with new snapshots.
+    pub fn add_tag<S: Into<String>>(&mut self, tag: S) {
+        let tag = tag.into();
+        let tags = &mut self._private_inner_mut().tags;
+        if !tags.contains(&tag) {
+            tags.push(tag);
+        }
+    }
+
+    /// Removes all tags.
+    pub fn remove_tags(&mut self) {
+        self._private_inner_mut().tags = Vec::new();
+    }
+
+    /// Returns the current tags.
+    pub fn tags(&self) -> &[String] {
+        &self.inner.tags
+    }
+
+    /// Sets the description stored with new snapshots.
+    ///
+    /// The description is shown during review to explain what the snapshot
+    /// asserts.  Like all metadata it is not compared, so changing it does
+    /// not fail assertions.
+    pub fn set_description<S: Into<String>>(&mut self, value: S) {
+        self._private_inner_mut().description(value);
+    }
+
+    /// Removes the description.
+    pub fn remove_description(&mut self) {
+        self._private_inner_mut().description = None;
+    }
+
+    /// Returns the current description.
+    pub fn description(&self) -> Option<&str> {
+        self.inner.description.as_deref()
+    }
+
+    /// Sets additional information stored with new snapshots.
+    ///
+    /// The value is serialized into the `info` field of the snapshot
+    /// metadata and shown below the description during review and when an
+    /// assertion fails.  This is useful to record the input a snapshot was
+    /// created from.  Like the description it is not compared.
+    pub fn set_info<S: Serialize>(&mut self, s: &S) {
+        self._private_inner_mut().info(s);
+    }
+
+    /// Removes the info.
+    pub fn remove_info(&mut self) {
+        self._private_inner_mut().info = None;
+    }
+
+    /// Returns `true` if info is set.
+    pub fn has_info(&self) -> bool {
+        self.inner.info.is_some()
+    }
+
+    pub(crate) fn info(&self) -> Option<&serde_yaml::Value> {
+        self.inner.info.as_ref()
+    }
+
     /// Sets the input file reference.
     ///
     /// This value is completely unused by the snapshot testing system but
     /// it lets you store some meta data with a snapshot that refers you back
-    /// to the input file.  The path stored here is made relative to the
-    /// workspace root before storing with the snapshot.
+    /// to the input file.  A relative path is relative to the crate like
+    /// other paths in tests.  The path stored here is made relative to the
+    /// workspace root before storing with the snapshot.  The review shows
+    /// the input file and marks it if it was deleted since.
     pub fn set_input_file<P: AsRef<Path>>(&mut self, p: P) {
-        self._private_inner_mut().input_file = Some(p.as_ref().to_path_buf());
+        self._private_inner_mut().input_file(p);
     }
 
     /// Removes the input file reference.
@@ -186,6 +1140,224 @@ impl Settings {
         self.inner.input_file.as_deref()
     }
 
+    /// Sets the workspace root.
+    ///
+    /// The paths reported by `file!()` are relative to the workspace root
+    /// which insta normally asks `cargo metadata` for.  Build systems that
+    /// do not run tests through cargo (eg: Bazel) can set it here or with
+    /// the `INSTA_WORKSPACE_ROOT` environment variable.  A relative path is
+    /// resolved against the directory of the crate under test.
+    pub fn set_workspace_root<P: AsRef<Path>>(&mut self, p: P) {
+        self._private_inner_mut().workspace_root(p);
+    }
+
+    /// Removes the workspace root override.
+    pub fn remove_workspace_root(&mut self) {
+        self._private_inner_mut().workspace_root = None;
+    }
+
+    /// Returns the workspace root override.
+    pub fn workspace_root(&self) -> Option<&Path> {
+        self.inner.workspace_root.as_deref()
+    }
+
+    /// Remaps a prefix of source file paths.
+    ///
+    /// Source paths as reported by `file!()` that start with `from` are
+    /// rewritten to start with `to` before the snapshot location is
+    /// resolved and before they are stored in the snapshot.  This is used
+    /// when the compiler sees sources under a different path than the one
+    /// they have in the workspace, for instance because a build sandbox
+    /// places them under `external/my_crate/`.  The first matching remap
+    /// wins.
+    pub fn add_source_path_remap<A: AsRef<Path>, B: AsRef<Path>>(&mut self, from: A, to: B) {
+        self._private_inner_mut()
+            .source_path_remaps
+            .push((from.as_ref().to_path_buf(), to.as_ref().to_path_buf()));
+    }
+
+    /// Removes all source path remaps.
+    pub fn clear_source_path_remaps(&mut self) {
+        self._private_inner_mut().source_path_remaps.clear();
+    }
+
+    /// Returns the source path remaps.
+    pub fn source_path_remaps(&self) -> &[(PathBuf, PathBuf)] {
+        &self.inner.source_path_remaps
+    }
+
+    /// Sets the field delimiter of CSV snapshots.
+    ///
+    /// The default value is `b','`.
+    #[cfg(feature = "csv")]
+    pub fn set_csv_delimiter(&mut self, value: u8) {
+        self._private_inner_mut().csv_delimiter(value);
+    }
+
+    /// Returns the field delimiter of CSV snapshots.
+    #[cfg(feature = "csv")]
+    pub fn csv_delimiter(&self) -> u8 {
+        self.inner.csv_delimiter
+    }
+
+    /// Sets the quoting style of CSV snapshots.
+    ///
+    /// The default value is `CsvQuoteStyle::Necessary`.
+    #[cfg(feature = "csv")]
+    pub fn set_csv_quote_style(&mut self, value: CsvQuoteStyle) {
+        self._private_inner_mut().csv_quote_style(value);
+    }
+
+    /// Returns the quoting style of CSV snapshots.
+    #[cfg(feature = "csv")]
+    pub fn csv_quote_style(&self) -> CsvQuoteStyle {
+        self.inner.csv_quote_style
+    }
+
+    /// Enables or disables the header row of CSV snapshots.
+    ///
+    /// The default value is `true`.
+    #[cfg(feature = "csv")]
+    pub fn set_csv_has_headers(&mut self, value: bool) {
+        self._private_inner_mut().csv_has_headers(value);
+    }
+
+    /// Returns `true` if CSV snapshots have a header row.
+    #[cfg(feature = "csv")]
+    pub fn csv_has_headers(&self) -> bool {
+        self.inner.csv_has_headers
+    }
+
+    /// Terminates records in CSV snapshots with `\r\n` instead of `\n`.
+    ///
+    /// The default value is `false`.
+    #[cfg(feature = "csv")]
+    pub fn set_csv_crlf(&mut self, value: bool) {
+        self._private_inner_mut().csv_crlf(value);
+    }
+
+    /// Returns `true` if records in CSV snapshots are terminated with `\r\n`.
+    #[cfg(feature = "csv")]
+    pub fn csv_crlf(&self) -> bool {
+        self.inner.csv_crlf
+    }
+
+    /// Sets the string used for one level of indentation in RON snapshots.
+    ///
+    /// The default value is two spaces.
+    #[cfg(feature = "ron")]
+    pub fn set_ron_indent<I: Into<String>>(&mut self, value: I) {
+        self._private_inner_mut().ron_indent(value);
+    }
+
+    /// Returns the indentation of RON snapshots.
+    #[cfg(feature = "ron")]
+    pub fn ron_indent(&self) -> &str {
+        &self.inner.ron_indent
+    }
+
+    /// Enables or disables struct names in RON snapshots.
+    ///
+    /// When disabled structs are written as `(a: 1)` instead of `Foo(a: 1)`.
+    /// The default value is `true`.
+    #[cfg(feature = "ron")]
+    pub fn set_ron_struct_names(&mut self, value: bool) {
+        self._private_inner_mut().ron_struct_names(value);
+    }
+
+    /// Returns `true` if struct names are included in RON snapshots.
+    #[cfg(feature = "ron")]
+    pub fn ron_struct_names(&self) -> bool {
+        self.inner.ron_struct_names
+    }
+
+    /// Sets the nesting depth after which RON snapshots stop pretty printing.
+    ///
+    /// Collections and structs nested deeper than this are written on a
+    /// single line which keeps small leaf collections compact.  The default
+    /// is `None` which pretty prints at every level.
+    #[cfg(feature = "ron")]
+    pub fn set_ron_depth_limit(&mut self, value: Option<usize>) {
+        self._private_inner_mut().ron_depth_limit(value);
+    }
+
+    /// Returns the nesting depth after which RON snapshots stop pretty printing.
+    #[cfg(feature = "ron")]
+    pub fn ron_depth_limit(&self) -> Option<usize> {
+        self.inner.ron_depth_limit
+    }
+
+    /// Enables or disables the redaction of all timestamps.
+    ///
+    /// When enabled every string in a serialized snapshot that parses as a
+    /// datetime the way `chrono` or `time` serialize them is replaced with
+    /// `[timestamp]`.  This saves maintaining a selector for every datetime
+    /// field.  The default value is `false`.
+    #[cfg(any(feature = "chrono", feature = "time"))]
+    pub fn set_redact_timestamps(&mut self, value: bool) {
+        self._private_inner_mut().redact_timestamps(value);
+    }
+
+    /// Returns the current value for timestamp redaction.
+    #[cfg(any(feature = "chrono", feature = "time"))]
+    pub fn redact_timestamps(&self) -> bool {
+        self.inner.redact_timestamps
+    }
+
+    /// Sets how different a pixel of an image snapshot may look.
+    ///
+    /// The value ranges from `0.0` to `1.0` and is compared against the
+    /// perceived color difference of each pixel (measured in the YIQ color
+    /// space like `pixelmatch` does).  Pixels that differ by more count as
+    /// changed, see `set_image_max_diff_ratio`.  The default value is `0.0`
+    /// which only tolerates changes that are not visible (eg: the color of
+    /// fully transparent pixels).
+    #[cfg(feature = "image")]
+    pub fn set_image_threshold(&mut self, value: f64) {
+        self._private_inner_mut().image_threshold(value);
+    }
+
+    /// Returns how different a pixel of an image snapshot may look.
+    #[cfg(feature = "image")]
+    pub fn image_threshold(&self) -> f64 {
+        self.inner.image_threshold
+    }
+
+    /// Sets the fraction of pixels of an image snapshot that may change.
+    ///
+    /// An image still matches its snapshot if at most this fraction of
+    /// its pixels changed by more than the threshold (eg: `0.01` for one
+    /// percent).  Images of a different size never match.  The default
+    /// value is `0.0`.
+    #[cfg(feature = "image")]
+    pub fn set_image_max_diff_ratio(&mut self, value: f64) {
+        self._private_inner_mut().image_max_diff_ratio(value);
+    }
+
+    /// Returns the fraction of pixels of an image snapshot that may change.
+    #[cfg(feature = "image")]
+    pub fn image_max_diff_ratio(&self) -> f64 {
+        self.inner.image_max_diff_ratio
+    }
+
+    /// Sets the size in bytes above which snapshot files are compressed.
+    ///
+    /// Snapshots with larger contents are stored compressed with gzip (as
+    /// base64 so the file stays text).  They are decompressed transparently
+    /// when compared and reviewed and diffs of failing assertions show the
+    /// decompressed contents.  The default is `None` which never compresses.
+    /// Inline snapshots are never compressed.
+    #[cfg(feature = "compression")]
+    pub fn set_compression_threshold(&mut self, value: Option<usize>) {
+        self._private_inner_mut().compression_threshold(value);
+    }
+
+    /// Returns the size in bytes above which snapshot files are compressed.
+    #[cfg(feature = "compression")]
+    pub fn compression_threshold(&self) -> Option<usize> {
+        self.inner.compression_threshold
+    }
+
     /// Registers redactions that should be applied.
     ///
     /// This can be useful if redactions must be shared across multiple
@@ -193,12 +1365,37 @@ impl Settings {
     ///
     /// Note that this only applies to snapshots that undergo serialization
     /// (eg: does not work for `assert_debug_snapshot!`.)
+    ///
+    /// Panics if the selector is invalid, see
+    /// [`try_add_redaction`](Settings::try_add_redaction).
     #[cfg(feature = "redactions")]
     pub fn add_redaction<R: Into<Redaction>>(&mut self, selector: &str, replacement: R) {
-        self._private_inner_mut().redactions.0.push((
-            Selector::parse(selector).unwrap().make_static(),
-            Arc::new(replacement.into()),
-        ));
+        self._private_inner_mut()
+            .add_redaction(parse_selector(selector), replacement);
+    }
+
+    /// Registers a redaction unless the selector is invalid.
+    ///
+    /// This works like [`add_redaction`](Settings::add_redaction) but returns
+    /// the parse error instead of panicking.  The error points at the
+    /// offending part of the selector:
+    ///
+    /// ```rust
+    /// # use insta::Settings;
+    /// let mut settings = Settings::new();
+    /// let err = settings.try_add_redaction(".foo[", "[foo]").unwrap_err();
+    /// assert_eq!(err.span(), (5, 5));
+    /// ```
+    #[cfg(feature = "redactions")]
+    pub fn try_add_redaction<R: Into<Redaction>>(
+        &mut self,
+        selector: &str,
+        replacement: R,
+    ) -> Result<(), SelectorParseError> {
+        let selector = Selector::parse(selector)?;
+        self._private_inner_mut()
+            .add_redaction(selector, replacement);
+        Ok(())
     }
 
     /// Registers a replacement callback.
@@ -217,12 +1414,53 @@ impl Settings {
         self.add_redaction(selector, dynamic_redaction(func));
     }
 
+    /// Registers a redaction that only applies if a predicate holds.
+    ///
+    /// The predicate receives the selected value and its path.  Values it
+    /// returns `false` for are kept as they are.
+    ///
+    /// This is a shortcut to `add_redaction(conditional_redaction(...))`;
+    #[cfg(feature = "redactions")]
+    pub fn add_conditional_redaction<P, R>(&mut self, selector: &str, predicate: P, replacement: R)
+    where
+        P: Fn(&Content, ContentPath<'_>) -> bool + Send + Sync + 'static,
+        R: Into<Redaction>,
+    {
+        self.add_redaction(selector, conditional_redaction(predicate, replacement));
+    }
+
+    /// Registers a redaction that removes the selected values.
+    ///
+    /// Instead of replacing the value with a placeholder the matched map
+    /// entry, struct field or sequence item is left out of the snapshot.
+    ///
+    /// This is a shortcut to `add_redaction(Redaction::Remove)`:
+    ///
+    /// ```rust
+    /// # use insta::Settings;
+    /// let mut settings = Settings::new();
+    /// settings.add_removal(".debug_info");
+    /// ```
+    #[cfg(feature = "redactions")]
+    pub fn add_removal(&mut self, selector: &str) {
+        self.add_redaction(selector, Redaction::Remove);
+    }
+
+    /// Adds the redactions of a named set.
+    ///
+    /// Sets are registered with [`register_redaction_set`] or defined in
+    /// `insta.toml`.  Panics if no set with the name exists.
+    #[cfg(feature = "redactions")]
+    pub fn apply_redaction_set(&mut self, name: &str) {
+        self._private_inner_mut().redaction_set(name);
+    }
+
     /// Replaces the currently set redactions.
     ///
     /// The default set is empty.
     #[cfg(feature = "redactions")]
     pub fn set_redactions<R: Into<Redactions>>(&mut self, redactions: R) {
-        self._private_inner_mut().redactions = redactions.into();
+        self._private_inner_mut().redactions(redactions);
     }
 
     /// Removes all redactions.
@@ -233,12 +1471,43 @@ impl Settings {
 
     /// Iterate over the redactions.
     #[cfg(feature = "redactions")]
-    pub(crate) fn iter_redactions(&self) -> impl Iterator<Item = (&Selector, &Redaction)> {
-        self.inner
-            .redactions
-            .0
-            .iter()
-            .map(|&(ref a, ref b)| (a, &**b))
+    pub(crate) fn iter_redactions(&self) -> impl Iterator<Item = (&Selector<'_>, &Redaction)> {
+        self.inner.redactions.0.iter().map(|(a, b)| (a, &**b))
+    }
+
+    /// Registers a filter that is applied to the snapshot contents.
+    ///
+    /// Filters are regular expressions whose matches are replaced before
+    /// the snapshot is compared.  They work for all snapshots, including
+    /// the ones that do not undergo serialization like the ones of
+    /// `assert_snapshot!` and `assert_debug_snapshot!`.  The replacement can
+    /// refer to capture groups with `$1` or `${name}`.  Filters are applied
+    /// in the order they were added.
+    ///
+    /// Panics if the regular expression is invalid.
+    #[cfg(feature = "filters")]
+    pub fn add_filter<S: Into<String>>(&mut self, regex: &str, replacement: S) {
+        self._private_inner_mut().filters.add(regex, replacement);
+    }
+
+    /// Replaces the currently set filters.
+    ///
+    /// The default set is empty.
+    #[cfg(feature = "filters")]
+    pub fn set_filters<F: Into<Filters>>(&mut self, filters: F) {
+        self._private_inner_mut().filters(filters);
+    }
+
+    /// Removes all filters.
+    #[cfg(feature = "filters")]
+    pub fn clear_filters(&mut self) {
+        self._private_inner_mut().filters.clear();
+    }
+
+    /// Returns the current filters.
+    #[cfg(feature = "filters")]
+    pub(crate) fn filters(&self) -> &Filters {
+        &self.inner.filters
     }
 
     /// Sets the snapshot path.
@@ -247,7 +1516,7 @@ impl Settings {
     ///
     /// Defaults to `snapshots`.
     pub fn set_snapshot_path<P: AsRef<Path>>(&mut self, path: P) {
-        self._private_inner_mut().snapshot_path = path.as_ref().to_path_buf();
+        self._private_inner_mut().snapshot_path(path);
     }
 
     /// Returns the snapshot path.
@@ -255,6 +1524,57 @@ impl Settings {
         &self.inner.snapshot_path
     }
 
+    /// Sets what a relative snapshot path is relative to.
+    ///
+    /// By default the snapshot path is relative to the folder of the source
+    /// file of the test.  To keep all snapshots of a crate or a workspace in
+    /// a single folder (eg: a top-level `testdata/`) it can be made relative
+    /// to the crate or the workspace root instead.
+    ///
+    /// The default value is `SnapshotPathBase::SourceFile`.
+    pub fn set_snapshot_path_base(&mut self, value: SnapshotPathBase) {
+        self._private_inner_mut().snapshot_path_base(value);
+    }
+
+    /// Returns what a relative snapshot path is relative to.
+    pub fn snapshot_path_base(&self) -> SnapshotPathBase {
+        self.inner.snapshot_path_base
+    }
+
+    /// Sets how snapshots are updated.
+    ///
+    /// This overrides `INSTA_UPDATE` (and `behavior.update` in `insta.toml`)
+    /// for the assertions within the bound settings only, which unlike
+    /// setting the environment variable does not affect tests running in
+    /// parallel.
+    ///
+    /// By default the environment variable decides.
+    pub fn set_snapshot_update(&mut self, value: SnapshotUpdate) {
+        self._private_inner_mut().snapshot_update(value);
+    }
+
+    /// Returns how snapshots are updated if set.
+    pub fn snapshot_update(&self) -> Option<SnapshotUpdate> {
+        self.inner.snapshot_update
+    }
+
+    /// Sets whether failed assertions pass.
+    ///
+    /// This overrides `INSTA_FORCE_PASS` (and `behavior.force_pass` in
+    /// `insta.toml`) for the assertions within the bound settings only.
+    /// Tests that expect an assertion to fail can disable it so that they
+    /// also fail under `cargo insta test`.
+    ///
+    /// By default the environment variable decides.
+    pub fn set_force_pass(&mut self, value: bool) {
+        self._private_inner_mut().force_pass(value);
+    }
+
+    /// Returns whether failed assertions pass if set.
+    pub fn force_pass(&self) -> Option<bool> {
+        self.inner.force_pass
+    }
+
     /// Runs a function with the current settings bound to the thread.
     pub fn bind<F: FnOnce()>(&self, f: F) {
         CURRENT_SETTINGS.with(|x| {
@@ -272,7 +1592,10 @@ impl Settings {
 
     /// Like `bind` but for futures.
     ///
-    /// This lets you bind settings for the duration of a future like this:
+    /// The settings are bound again every time the future is polled, so they
+    /// stay in effect across `.await` points even if the executor moves the
+    /// future to another thread (as in `#[tokio::test]`).  This lets you bind
+    /// settings for the duration of a future like this:
     ///
     /// ```rust
     /// # use insta::Settings;
@@ -310,6 +1633,28 @@ impl Settings {
         BindingFuture(self.inner.clone(), future)
     }
 
+    /// Binds the settings to the current thread until the guard is dropped.
+    ///
+    /// This is useful to apply settings for the rest of a test function
+    /// without wrapping it in a closure.  Once the returned guard is dropped
+    /// the previously bound settings are restored:
+    ///
+    /// ```rust
+    /// # use insta::Settings;
+    /// let mut settings = Settings::clone_current();
+    /// settings.set_sort_maps(true);
+    /// let _guard = settings.bind_to_scope();
+    /// // do assertions here
+    /// ```
+    pub fn bind_to_scope(&self) -> SettingsBindDropGuard {
+        CURRENT_SETTINGS.with(|x| {
+            let mut current = x.borrow_mut();
+            let old = current.inner.clone();
+            current.inner = self.inner.clone();
+            SettingsBindDropGuard(Some(old), PhantomData)
+        })
+    }
+
     /// Binds the settings to the current thread permanently.
     pub fn bind_to_thread(&self) {
         CURRENT_SETTINGS.with(|x| {
@@ -319,6 +1664,21 @@ impl Settings {
 
     /// Runs a function with the current settings.
     pub(crate) fn with<R, F: FnOnce(&Settings) -> R>(f: F) -> R {
-        CURRENT_SETTINGS.with(|x| f(&*x.borrow()))
+        CURRENT_SETTINGS.with(|x| f(&x.borrow()))
+    }
+}
+
+/// Restores the previous settings when dropped.
+///
+/// Returned by [`Settings::bind_to_scope`].  The guard is bound to the
+/// thread the settings were bound on and can not be sent to other threads.
+#[must_use = "the settings are restored as soon as the guard is dropped"]
+pub struct SettingsBindDropGuard(Option<Arc<ActualSettings>>, PhantomData<*const ()>);
+
+impl Drop for SettingsBindDropGuard {
+    fn drop(&mut self) {
+        CURRENT_SETTINGS.with(|x| {
+            x.borrow_mut().inner = self.0.take().unwrap();
+        })
     }
 }
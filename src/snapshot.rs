@@ -8,6 +8,58 @@ use lazy_static::lazy_static;
 use serde::{Deserialize, Serialize};
 
 use super::runtime::get_inline_snapshot_value;
+use crate::settings::{Settings, Whitespace};
+use crate::utils::fnv1a;
+
+/// Snapshot files larger than this are compared while they are read and
+/// store a hash of their contents.
+pub(crate) const LARGE_SNAPSHOT_SIZE: usize = 1024 * 1024;
+
+/// The version of the snapshot file format written by this version.
+///
+/// Files without a version are from before the format was versioned (with
+/// a YAML or the even older plain header) and are read all the same.  They
+/// are upgraded with `cargo insta migrate`.
+pub const FORMAT_VERSION: u32 = 1;
+
+/// Hashes snapshot contents the way they are compared.
+fn contents_hash(contents: &str) -> String {
+    format!("fnv1a64:{:016x}", fnv1a(contents.trim_end().as_bytes()))
+}
+
+#[cfg(feature = "compression")]
+fn compress(compression: &str, contents: &str) -> Result<String, Box<dyn Error>> {
+    match compression {
+        "gzip" => Ok(crate::compression::compress(contents)),
+        _ => Err(format!("unsupported compression {:?}", compression).into()),
+    }
+}
+
+#[cfg(feature = "compression")]
+fn decompress(compression: &str, stored: &str) -> Result<String, Box<dyn Error>> {
+    match compression {
+        "gzip" => crate::compression::decompress(stored),
+        _ => Err(format!("unsupported compression {:?}", compression).into()),
+    }
+}
+
+#[cfg(not(feature = "compression"))]
+fn compress(compression: &str, _contents: &str) -> Result<String, Box<dyn Error>> {
+    Err(format!(
+        "the snapshot is compressed with {} which needs the compression feature of insta",
+        compression
+    )
+    .into())
+}
+
+#[cfg(not(feature = "compression"))]
+fn decompress(compression: &str, _stored: &str) -> Result<String, Box<dyn Error>> {
+    compress(compression, "")
+}
+
+fn binary_hash(data: &[u8]) -> String {
+    format!("fnv1a64:{:016x}", fnv1a(data))
+}
 
 lazy_static! {
     static ref RUN_ID: String = {
@@ -76,9 +128,42 @@ pub struct MetaData {
     /// Optionally the expression that created the snapshot.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub(crate) expression: Option<String>,
+    /// Describes what the snapshot asserts for reviewers.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) description: Option<String>,
+    /// Additional information for reviewers.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) info: Option<serde_yaml::Value>,
     /// Reference to the input file.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub(crate) input_file: Option<String>,
+    /// Hash of the contents, only stored for large snapshots.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) hash: Option<String>,
+    /// Tags to select snapshots by.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) tags: Vec<String>,
+    /// The key of the Jest snapshot the snapshot was imported from.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) jest_key: Option<String>,
+    /// The extension of the file a binary payload is stored in.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) extension: Option<String>,
+    /// Hash of the binary payload to detect corrupted payload files.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) binary_hash: Option<String>,
+    /// How the contents are compressed (only `gzip` is supported).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) compression: Option<String>,
+    /// How trailing whitespace of lines is compared, if not preserved.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) trailing_whitespace: Option<Whitespace>,
+    /// How the final newline is compared, if not normalized.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) final_newline: Option<Whitespace>,
+    /// The version of the file format, missing in unversioned files.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) format_version: Option<u32>,
 }
 
 impl MetaData {
@@ -92,6 +177,11 @@ impl MetaData {
         self.expression.as_deref()
     }
 
+    /// Returns the description of the snapshot.
+    pub fn description(&self) -> Option<&str> {
+        self.description.as_deref()
+    }
+
     /// Returns the relative source path.
     pub fn get_relative_source(&self, base: &Path) -> Option<PathBuf> {
         self.source.as_ref().map(|source| {
@@ -103,10 +193,62 @@ impl MetaData {
         })
     }
 
+    /// Returns the additional information of the snapshot.
+    pub fn info(&self) -> Option<&serde_yaml::Value> {
+        self.info.as_ref()
+    }
+
     /// Returns the input file reference.
     pub fn input_file(&self) -> Option<&str> {
         self.input_file.as_deref()
     }
+
+    /// Returns the key of the Jest snapshot the snapshot was imported from.
+    pub fn jest_key(&self) -> Option<&str> {
+        self.jest_key.as_deref()
+    }
+
+    /// Returns the tags of the snapshot.
+    pub fn tags(&self) -> &[String] {
+        &self.tags
+    }
+
+    /// Returns the extension of the binary payload.
+    pub fn extension(&self) -> Option<&str> {
+        self.extension.as_deref()
+    }
+
+    /// Returns how the contents are compressed in the snapshot file.
+    pub fn compression(&self) -> Option<&str> {
+        self.compression.as_deref()
+    }
+
+    /// Returns how trailing whitespace of lines is compared.
+    pub fn trailing_whitespace(&self) -> Whitespace {
+        self.trailing_whitespace.unwrap_or(Whitespace::Preserve)
+    }
+
+    /// Returns how the final newline is compared.
+    pub fn final_newline(&self) -> Whitespace {
+        self.final_newline.unwrap_or(Whitespace::Normalize)
+    }
+
+    /// Returns the version of the file format or `0` for unversioned files.
+    pub fn format_version(&self) -> u32 {
+        self.format_version.unwrap_or(0)
+    }
+
+    /// Returns the file the binary payload of a snapshot file is stored in.
+    ///
+    /// The payload lives next to the snapshot file with the extension
+    /// appended (eg: `foo.snap.png` or `foo.snap.new.png` while pending).
+    pub fn binary_path(&self, snapshot_file: &Path) -> Option<PathBuf> {
+        let extension = self.extension.as_ref()?;
+        let mut rv = snapshot_file.as_os_str().to_owned();
+        rv.push(".");
+        rv.push(extension);
+        Some(rv.into())
+    }
 }
 
 /// A helper to work with stored snapshots.
@@ -117,95 +259,174 @@ pub struct Snapshot {
     snapshot_name: Option<String>,
     metadata: MetaData,
     snapshot: SnapshotContents,
+    #[serde(skip)]
+    binary: Option<Vec<u8>>,
 }
 
 impl Snapshot {
     /// Loads a snapshot from a file.
+    ///
+    /// Files that cannot be parsed (eg: because of merge conflicts or
+    /// truncation) fail with an error naming the file and the problem.  If
+    /// the metadata records the hash of a binary payload the payload is
+    /// loaded too (and fails the same way if it does not match).
     pub fn from_file<P: AsRef<Path>>(p: P) -> Result<Snapshot, Box<dyn Error>> {
-        let mut f = BufReader::new(fs::File::open(p.as_ref())?);
-        let mut buf = String::new();
+        let buf = fs::read_to_string(p.as_ref())?;
 
-        f.read_line(&mut buf)?;
-
-        // yaml format
-        let metadata = if buf.trim_end() == "---" {
-            loop {
-                let read = f.read_line(&mut buf)?;
-                if read == 0 {
-                    break;
-                }
-                if buf[buf.len() - read..].trim_end() == "---" {
-                    buf.truncate(buf.len() - read);
-                    break;
-                }
-            }
-            serde_yaml::from_str(&buf)?
-        // legacy format
-        } else {
-            let mut rv = MetaData::default();
-            loop {
-                buf.clear();
-                let read = f.read_line(&mut buf)?;
-                if read == 0 || buf.trim_end().is_empty() {
-                    buf.truncate(buf.len() - read);
-                    break;
-                }
-                let mut iter = buf.splitn(2, ':');
-                if let Some(key) = iter.next() {
-                    if let Some(value) = iter.next() {
-                        let value = value.trim();
-                        match key.to_lowercase().as_str() {
-                            "expression" => rv.expression = Some(value.to_string()),
-                            "source" => rv.source = Some(value.into()),
-                            _ => {}
-                        }
-                    }
-                }
-            }
-            rv
+        // suffixes can contain dots (eg: the file names of `glob!`), so
+        // only the extension (and `.new` of pending files) is cut off.
+        let file_name = p
+            .as_ref()
+            .file_name()
+            .ok_or_else(|| format!("{} is not a snapshot file", p.as_ref().display()))?
+            .to_str()
+            .unwrap_or("");
+        let file_name = file_name.strip_suffix(".new").unwrap_or(file_name);
+        let stem = file_name.rsplit_once('.').map_or(file_name, |x| x.0);
+        // extensions with multiple parts (eg: `.snap.json`)
+        let stem = stem.strip_suffix(".snap").unwrap_or(stem);
+        // without the module prepended the whole file name is the name
+        let (module_name, snapshot_name) = match stem.split_once("__") {
+            Some((module_name, snapshot_name)) => (module_name, snapshot_name),
+            None => ("<unknown>", stem),
         };
+        let module_name = module_name.to_string();
+        let snapshot_name = Some(snapshot_name.to_string());
 
-        buf.clear();
-        for (idx, line) in f.lines().enumerate() {
-            let line = line?;
-            if idx > 0 {
-                buf.push('\n');
-            }
-            buf.push_str(&line);
+        let mut snapshot = Snapshot::from_str(&buf, module_name, snapshot_name).map_err(|err| {
+            format!(
+                "snapshot file {} is corrupted: {}",
+                p.as_ref().display(),
+                err
+            )
+        })?;
+        if snapshot.metadata.format_version() > FORMAT_VERSION {
+            return Err(format!(
+                "snapshot file {} uses format version {} but this version of insta \
+                 only supports up to {} (upgrade insta)",
+                p.as_ref().display(),
+                snapshot.metadata.format_version(),
+                FORMAT_VERSION
+            )
+            .into());
+        }
+        if snapshot.metadata.binary_hash.is_some() {
+            snapshot.binary = snapshot.read_binary(p.as_ref())?;
         }
+        Ok(snapshot)
+    }
 
-        let module_name = p
-            .as_ref()
-            .file_name()
-            .unwrap()
-            .to_str()
-            .unwrap_or("")
-            .split("__")
-            .next()
-            .unwrap_or("<unknown>")
-            .to_string();
+    /// Parses a snapshot from the contents of a snapshot file.
+    ///
+    /// This is also used for snapshots embedded into the test binary which
+    /// have no file to derive the module and snapshot name from.  The body
+    /// after the header is sliced out of `contents` and copied once into the
+    /// snapshot contents (files with `\r\n` line endings are copied while
+    /// normalizing them unless disabled with
+    /// `Settings::set_normalize_line_endings`).
+    pub(crate) fn from_str(
+        contents: &str,
+        module_name: String,
+        snapshot_name: Option<String>,
+    ) -> Result<Snapshot, Box<dyn Error>> {
+        let mut rest = contents.as_bytes();
+        let metadata = read_metadata(&mut rest);
+        let header_len = contents.len() - rest.len();
+        // only the header is checked as the contents can legitimately hold
+        // the markers (eg: snapshots of the output of diff or merge tools).
+        if contents.starts_with("<<<<<<< ") || has_conflict_markers(&contents[..header_len]) {
+            return Err("the file contains merge conflict markers".into());
+        }
+        let metadata = metadata?;
 
-        let snapshot_name = p
-            .as_ref()
-            .file_name()
-            .unwrap()
-            .to_str()
-            .unwrap_or("")
-            .split('.')
-            .next()
-            .unwrap_or("")
-            .splitn(2, "__")
-            .nth(1)
-            .map(|x| x.to_string());
+        let mut body = &contents[header_len..];
+        // the line ending of the last line is not part of the contents
+        if let Some(stripped) = body.strip_suffix("\r\n") {
+            body = stripped;
+        } else if let Some(stripped) = body.strip_suffix('\n') {
+            body = stripped;
+        }
+        let mut body = if body.contains("\r\n")
+            && Settings::with(|settings| settings.normalize_line_endings())
+        {
+            body.replace("\r\n", "\n")
+        } else {
+            body.to_string()
+        };
+        if let Some(ref compression) = metadata.compression {
+            body = decompress(compression, &body)?;
+        }
+        if let Some(ref hash) = metadata.hash {
+            if *hash != contents_hash(&body) {
+                return Err("the contents do not match the hash in the metadata \
+                            (truncated file?)"
+                    .into());
+            }
+        }
 
         Ok(Snapshot::from_components(
             module_name,
             snapshot_name,
             metadata,
-            buf.into(),
+            body.into(),
         ))
     }
 
+    /// Checks if the contents of a snapshot file match without loading it.
+    ///
+    /// The file is compared line by line so that large snapshots do not
+    /// need to be held in memory.  The comparison is the same as the one of
+    /// [`SnapshotContents`].
+    pub(crate) fn file_matches<P: AsRef<Path>>(
+        p: P,
+        contents: &str,
+    ) -> Result<bool, Box<dyn Error>> {
+        let mut f = BufReader::new(fs::File::open(p.as_ref())?);
+        let metadata = read_metadata(&mut f)?;
+
+        // compressed contents can only be compared after loading the file
+        if metadata.compression.is_some() {
+            return Ok(false);
+        }
+
+        // a differing hash settles it, an equal one still needs the bytes
+        // compared to rule out collisions.
+        if let Some(ref hash) = metadata.hash {
+            if *hash != contents_hash(contents) {
+                return Ok(false);
+            }
+        }
+
+        let expected = contents.trim_end();
+        let mut pos = 0;
+        for (idx, line) in f.lines().enumerate() {
+            let line = line?;
+            let mut rest = line.as_str();
+            if idx > 0 && pos < expected.len() {
+                if expected.as_bytes()[pos] != b'\n' {
+                    return Ok(false);
+                }
+                pos += 1;
+            }
+            if pos < expected.len() {
+                let n = rest.len().min(expected.len() - pos);
+                if expected.as_bytes()[pos..pos + n] != rest.as_bytes()[..n] {
+                    return Ok(false);
+                }
+                pos += n;
+                rest = match rest.get(n..) {
+                    Some(rest) => rest,
+                    None => return Ok(false),
+                };
+            }
+            // anything after the expected contents must be trailing whitespace
+            if !rest.trim_end().is_empty() {
+                return Ok(false);
+            }
+        }
+        Ok(pos == expected.len())
+    }
+
     /// Creates an empty snapshot.
     pub(crate) fn from_components(
         module_name: String,
@@ -218,7 +439,59 @@ impl Snapshot {
             snapshot_name,
             metadata,
             snapshot,
+            binary: None,
+        }
+    }
+
+    /// Attaches a binary payload that is saved next to the snapshot file.
+    pub(crate) fn set_binary(&mut self, binary: Vec<u8>) {
+        self.binary = Some(binary);
+    }
+
+    /// Returns the binary payload if the snapshot was created with one.
+    ///
+    /// Snapshots loaded from files only carry the payload if their metadata
+    /// records its hash, otherwise it is read with
+    /// [`read_binary`](#method.read_binary).
+    pub fn binary(&self) -> Option<&[u8]> {
+        self.binary.as_deref()
+    }
+
+    /// Reads the binary payload stored next to a snapshot file.
+    ///
+    /// Returns `None` if the snapshot has no payload or the payload file
+    /// does not exist.  If the metadata records a hash of the payload it is
+    /// verified so that corrupted files (or Git LFS pointers that were not
+    /// smudged) are reported instead of compared.
+    pub fn read_binary<P: AsRef<Path>>(
+        &self,
+        snapshot_file: P,
+    ) -> Result<Option<Vec<u8>>, Box<dyn Error>> {
+        let binary_path = match self.metadata.binary_path(snapshot_file.as_ref()) {
+            Some(binary_path) => binary_path,
+            None => return Ok(None),
+        };
+        let data = match fs::read(&binary_path) {
+            Ok(data) => data,
+            Err(_) => return Ok(None),
+        };
+        if let Some(ref hash) = self.metadata.binary_hash {
+            if *hash != binary_hash(&data) {
+                let reason = if data.starts_with(b"version https://git-lfs.github.com/spec/") {
+                    "it is a Git LFS pointer (run `git lfs pull`)"
+                } else {
+                    "it does not match the hash in the metadata (corrupted file?)"
+                };
+                return Err(format!(
+                    "binary payload {} of snapshot {} is invalid: {}",
+                    binary_path.display(),
+                    snapshot_file.as_ref().display(),
+                    reason
+                )
+                .into());
+            }
         }
+        Ok(Some(data))
     }
 
     /// Returns the module name.
@@ -246,20 +519,104 @@ impl Snapshot {
         &self.snapshot.0
     }
 
-    pub(crate) fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), Box<dyn Error>> {
+    /// Saves the snapshot to a file.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), Box<dyn Error>> {
         let path = path.as_ref();
         if let Some(folder) = path.parent() {
-            fs::create_dir_all(&folder)?;
+            fs::create_dir_all(folder)?;
+        }
+        let mut f = fs::File::create(path)?;
+        let mut metadata = self.metadata.clone();
+        metadata.format_version = Some(FORMAT_VERSION);
+        metadata.hash = if self.contents_str().len() > LARGE_SNAPSHOT_SIZE {
+            Some(contents_hash(self.contents_str()))
+        } else {
+            None
+        };
+        if let Some(ref binary) = self.binary {
+            metadata.binary_hash = Some(binary_hash(binary));
         }
-        let mut f = fs::File::create(&path)?;
-        serde_yaml::to_writer(&mut f, &self.metadata)?;
+        // newer serde_yaml versions end the document with a newline
+        f.write_all(serde_yaml::to_string(&metadata)?.trim_end().as_bytes())?;
         f.write_all(b"\n---\n")?;
-        f.write_all(self.contents_str().as_bytes())?;
+        match self.metadata.compression {
+            Some(ref compression) => {
+                f.write_all(compress(compression, self.contents_str())?.as_bytes())?
+            }
+            None => f.write_all(self.contents_str().as_bytes())?,
+        }
         f.write_all(b"\n")?;
+        if let (Some(binary), Some(binary_path)) =
+            (self.binary.as_ref(), self.metadata.binary_path(path))
+        {
+            fs::write(binary_path, binary)?;
+        }
         Ok(())
     }
 }
 
+/// Checks for the markers git leaves in files with merge conflicts.
+fn has_conflict_markers(contents: &str) -> bool {
+    let mut state = 0;
+    for line in contents.lines() {
+        state = match state {
+            0 if line.starts_with("<<<<<<< ") => 1,
+            1 if line == "=======" => 2,
+            2 if line.starts_with(">>>>>>> ") => return true,
+            state => state,
+        };
+    }
+    false
+}
+
+/// Reads the metadata header of a snapshot file.
+///
+/// Afterwards the reader is positioned at the start of the contents.
+fn read_metadata<R: BufRead>(f: &mut R) -> Result<MetaData, Box<dyn Error>> {
+    let mut buf = String::new();
+
+    f.read_line(&mut buf)?;
+
+    // yaml format
+    if buf.trim_end() == "---" {
+        loop {
+            let read = f.read_line(&mut buf)?;
+            if read == 0 {
+                return Err("the metadata header is not terminated (truncated file?)".into());
+            }
+            if buf[buf.len() - read..].trim_end() == "---" {
+                buf.truncate(buf.len() - read);
+                break;
+            }
+        }
+        Ok(serde_yaml::from_str(&buf)?)
+    // legacy format
+    } else {
+        let mut rv = MetaData::default();
+        // the first line was already read above
+        let mut read = buf.len();
+        loop {
+            if read == 0 || buf.trim_end().is_empty() {
+                break;
+            }
+            let mut iter = buf.splitn(2, ':');
+            if let Some(key) = iter.next() {
+                if let Some(value) = iter.next() {
+                    let value = value.trim();
+                    match key.to_lowercase().as_str() {
+                        "expression" => rv.expression = Some(value.to_string()),
+                        "source" => rv.source = Some(value.into()),
+                        _ => {}
+                    }
+                }
+            }
+            buf.clear();
+            read = f.read_line(&mut buf)?;
+        }
+        Ok(rv)
+    }
+}
+
 /// The contents of a Snapshot
 // Could be Cow, but I think limited savings
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -292,7 +649,7 @@ impl SnapshotContents {
                         )
                     })
                     // `lines` removes the final line ending - add back
-                    .chain(Some(format!("\n{:width$}", "", width = indentation)).into_iter()),
+                    .chain(Some(format!("\n{:width$}", "", width = indentation))),
             );
         } else {
             out.push_str(contents);
@@ -302,6 +659,60 @@ impl SnapshotContents {
 
         out
     }
+
+    /// Checks if a value matches these contents treated as a pattern.
+    ///
+    /// `[..]` matches any text within a line and a line of only `...`
+    /// matches any number of lines.  Trailing whitespace is ignored like
+    /// for the exact comparison.
+    pub fn matches_with_wildcards(&self, value: &SnapshotContents) -> bool {
+        fn lines_match(pattern: &[&str], lines: &[&str]) -> bool {
+            match pattern.split_first() {
+                None => lines.is_empty(),
+                Some((&"...", rest)) => {
+                    (0..=lines.len()).any(|idx| lines_match(rest, &lines[idx..]))
+                }
+                Some((first, rest)) => match lines.split_first() {
+                    Some((line, lines)) => line_matches(first, line) && lines_match(rest, lines),
+                    None => false,
+                },
+            }
+        }
+
+        fn line_matches(pattern: &str, line: &str) -> bool {
+            let mut parts = pattern.split("[..]");
+            let first = parts.next().unwrap_or("");
+            let mut rest = match line.strip_prefix(first) {
+                Some(rest) => rest,
+                None => return false,
+            };
+            let mut parts = parts.peekable();
+            while let Some(part) = parts.next() {
+                if parts.peek().is_none() {
+                    return rest.len() >= part.len() && rest.ends_with(part);
+                }
+                match rest.find(part) {
+                    Some(idx) => rest = &rest[idx + part.len()..],
+                    None => return false,
+                }
+            }
+            rest.is_empty()
+        }
+
+        let pattern = self
+            .0
+            .trim_end()
+            .lines()
+            .map(str::trim_end)
+            .collect::<Vec<_>>();
+        let lines = value
+            .0
+            .trim_end()
+            .lines()
+            .map(str::trim_end)
+            .collect::<Vec<_>>();
+        lines_match(&pattern, &lines)
+    }
 }
 
 impl From<&str> for SnapshotContents {
@@ -328,6 +739,82 @@ impl PartialEq for SnapshotContents {
     }
 }
 
+#[test]
+fn test_matches_with_wildcards() {
+    let matches = |pattern: &str, value: &str| {
+        SnapshotContents::from(pattern).matches_with_wildcards(&value.into())
+    };
+    assert!(matches("took [..]ms", "took 42ms"));
+    assert!(matches("took [..]ms", "took ms"));
+    assert!(!matches("took [..]ms", "took 42s"));
+    assert!(!matches("took [..]ms", "took 4\n2ms"));
+    assert!(matches("[..]/src/[..].rs:[..]", "/home/user/src/lib.rs:12"));
+    assert!(matches("a[..]a", "aa"));
+    assert!(!matches("a[..]a", "a"));
+    assert!(matches("first\n...\nlast", "first\nlast"));
+    assert!(matches("first\n...\nlast", "first\n1\n2\nlast"));
+    assert!(!matches("first\n...\nlast", "first\n1\n2"));
+    assert!(matches("...", ""));
+    assert!(matches("exact\n", "exact  \n\n"));
+    assert!(!matches("exact", "exact\nmore"));
+}
+
+#[test]
+fn test_snapshot_file_matches() {
+    let path = std::env::temp_dir().join(format!("insta-matches-{}.snap", std::process::id()));
+    fs::write(&path, "---\nsource: test.rs\n---\nfirst\n  second\n\n").unwrap();
+    assert!(Snapshot::file_matches(&path, "first\n  second").unwrap());
+    assert!(Snapshot::file_matches(&path, "first\n  second\n\n  ").unwrap());
+    assert!(!Snapshot::file_matches(&path, "first\n  secon").unwrap());
+    assert!(!Snapshot::file_matches(&path, "first\n  second\nthird").unwrap());
+    assert!(!Snapshot::file_matches(&path, "first second").unwrap());
+    assert!(!Snapshot::file_matches(&path, "").unwrap());
+
+    let large = "x".repeat(LARGE_SNAPSHOT_SIZE + 1);
+    let snapshot = Snapshot::from_components(
+        "test".into(),
+        None,
+        MetaData::default(),
+        large.as_str().into(),
+    );
+    snapshot.save(&path).unwrap();
+    let loaded = Snapshot::from_file(&path).unwrap();
+    assert_eq!(loaded.metadata().hash, Some(contents_hash(&large)));
+    assert_eq!(loaded.contents_str(), large);
+    assert!(Snapshot::file_matches(&path, &large).unwrap());
+    assert!(!Snapshot::file_matches(&path, &large[1..]).unwrap());
+
+    fs::write(&path, "Expression: x\n\nlegacy\n").unwrap();
+    assert!(Snapshot::file_matches(&path, "legacy").unwrap());
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_snapshot_from_file_line_endings() {
+    let path = std::env::temp_dir().join(format!("insta-endings-{}.snap", std::process::id()));
+    fs::write(&path, "---\nsource: test.rs\n---\nfirst\n\nsecond\n").unwrap();
+    let snapshot = Snapshot::from_file(&path).unwrap();
+    assert_eq!(snapshot.metadata().source.as_deref(), Some("test.rs"));
+    assert_eq!(snapshot.contents_str(), "first\n\nsecond");
+
+    fs::write(
+        &path,
+        "---\r\nsource: test.rs\r\n---\r\nfirst\r\nsecond\r\n",
+    )
+    .unwrap();
+    let snapshot = Snapshot::from_file(&path).unwrap();
+    assert_eq!(snapshot.metadata().source.as_deref(), Some("test.rs"));
+    assert_eq!(snapshot.contents_str(), "first\nsecond");
+
+    let mut settings = Settings::new();
+    settings.set_normalize_line_endings(false);
+    settings.bind(|| {
+        let snapshot = Snapshot::from_file(&path).unwrap();
+        assert_eq!(snapshot.contents_str(), "first\r\nsecond");
+    });
+    fs::remove_file(&path).unwrap();
+}
+
 #[test]
 fn test_snapshot_contents() {
     let snapshot_contents = SnapshotContents("testing".to_string());
@@ -382,3 +869,165 @@ b"[1..];
     let t = "ab";
     assert_eq!(SnapshotContents(t.to_string()).to_inline(0), r##""ab""##);
 }
+
+#[test]
+fn test_snapshot_from_file_corrupted() {
+    let path = std::env::temp_dir().join(format!("insta-corrupted-{}.snap", std::process::id()));
+    let error = |contents: &str| {
+        fs::write(&path, contents).unwrap();
+        Snapshot::from_file(&path).unwrap_err().to_string()
+    };
+
+    let err =
+        error("---\n<<<<<<< HEAD\nsource: a.rs\n=======\nsource: b.rs\n>>>>>>> branch\n---\nold\n");
+    assert!(err.contains(&path.display().to_string()));
+    assert!(err.ends_with("the file contains merge conflict markers"));
+    assert!(
+        error("<<<<<<< HEAD\n---\nsource: a.rs\n---\nold\n=======\n>>>>>>> branch\n")
+            .ends_with("the file contains merge conflict markers")
+    );
+    assert!(error("---\nsource: a.rs\n").ends_with("not terminated (truncated file?)"));
+    let hash = contents_hash("complete");
+    assert!(error(&format!("---\nhash: \"{}\"\n---\ncomp\n", hash))
+        .ends_with("do not match the hash in the metadata (truncated file?)"));
+
+    fs::write(&path, format!("---\nhash: \"{}\"\n---\ncomplete\n", hash)).unwrap();
+    assert_eq!(
+        Snapshot::from_file(&path).unwrap().contents_str(),
+        "complete"
+    );
+
+    // the contents can be the output of a merge or diff tool
+    let merged = "<<<<<<< HEAD\nold\n=======\nnew\n>>>>>>> branch";
+    fs::write(&path, format!("---\nsource: a.rs\n---\n{}\n", merged)).unwrap();
+    assert_eq!(Snapshot::from_file(&path).unwrap().contents_str(), merged);
+    fs::remove_file(&path).ok();
+
+    let parent = std::env::temp_dir().join("..");
+    assert!(Snapshot::from_file(&parent).is_err());
+}
+
+#[test]
+fn test_snapshot_format_version() {
+    let path = std::env::temp_dir().join(format!("insta-version-{}.snap", std::process::id()));
+    fs::write(
+        &path,
+        "Created: 2019-01-01\nExpression: vec![1]\nSource: a.rs\n\n[1]\n",
+    )
+    .unwrap();
+    let legacy = Snapshot::from_file(&path).unwrap();
+    assert_eq!(legacy.metadata().format_version(), 0);
+
+    legacy.save(&path).unwrap();
+    assert_eq!(
+        fs::read_to_string(&path).unwrap(),
+        "---\nsource: a.rs\nexpression: \"vec![1]\"\nformat_version: 1\n---\n[1]\n"
+    );
+    let migrated = Snapshot::from_file(&path).unwrap();
+    assert_eq!(migrated.metadata().format_version(), FORMAT_VERSION);
+    assert_eq!(migrated.contents(), legacy.contents());
+
+    fs::write(&path, "---\nformat_version: 99\n---\nfuture\n").unwrap();
+    let err = Snapshot::from_file(&path).unwrap_err().to_string();
+    assert!(err.ends_with(
+        "uses format version 99 but this version of insta only supports up to 1 (upgrade insta)"
+    ));
+    fs::remove_file(&path).ok();
+}
+
+#[test]
+fn test_snapshot_binary_hash() {
+    let path = std::env::temp_dir().join(format!("insta-binary-{}.snap", std::process::id()));
+    let binary_path = path.with_extension("snap.bin");
+    let mut snapshot = Snapshot::from_components(
+        "test".into(),
+        Some("binary".into()),
+        MetaData {
+            extension: Some("bin".into()),
+            ..MetaData::default()
+        },
+        "size: 3 bytes".to_string().into(),
+    );
+    snapshot.set_binary(b"\x00\x01\x02".to_vec());
+    snapshot.save(&path).unwrap();
+    let loaded = Snapshot::from_file(&path).unwrap();
+    assert_eq!(
+        loaded.metadata().binary_hash.as_deref(),
+        Some(binary_hash(b"\x00\x01\x02").as_str())
+    );
+    assert_eq!(
+        loaded.read_binary(&path).unwrap().as_deref(),
+        Some(&b"\x00\x01\x02"[..])
+    );
+    assert_eq!(loaded.binary(), Some(&b"\x00\x01\x02"[..]));
+
+    fs::write(&binary_path, b"\x00\x01").unwrap();
+    let err = Snapshot::from_file(&path).unwrap_err().to_string();
+    assert!(err.ends_with("does not match the hash in the metadata (corrupted file?)"));
+    fs::write(
+        &binary_path,
+        "version https://git-lfs.github.com/spec/v1\noid sha256:0\nsize 3\n",
+    )
+    .unwrap();
+    let err = Snapshot::from_file(&path).unwrap_err().to_string();
+    assert!(err.ends_with("it is a Git LFS pointer (run `git lfs pull`)"));
+
+    // a missing payload is not an error, the assertion reports it as changed
+    fs::remove_file(&binary_path).unwrap();
+    assert!(loaded.read_binary(&path).unwrap().is_none());
+    fs::remove_file(&path).ok();
+}
+
+#[test]
+#[cfg(feature = "compression")]
+fn test_snapshot_compression() {
+    let path = std::env::temp_dir().join(format!("insta-compressed-{}.snap", std::process::id()));
+    let contents = "a long line of generated output\n".repeat(500);
+    let snapshot = Snapshot::from_components(
+        "test".into(),
+        Some("compressed".into()),
+        MetaData {
+            compression: Some("gzip".into()),
+            ..MetaData::default()
+        },
+        contents.clone().into(),
+    );
+    snapshot.save(&path).unwrap();
+    let stored = fs::read_to_string(&path).unwrap();
+    assert!(stored.starts_with("---\ncompression: gzip\nformat_version: 1\n---\nH4sI"));
+    assert!(stored.len() < contents.len() / 10);
+
+    let loaded = Snapshot::from_file(&path).unwrap();
+    assert_eq!(loaded.contents(), snapshot.contents());
+    assert!(!Snapshot::file_matches(&path, &contents).unwrap());
+
+    fs::write(&path, "---\ncompression: zstd\n---\nxyz\n").unwrap();
+    let err = Snapshot::from_file(&path).unwrap_err().to_string();
+    assert!(err.ends_with("unsupported compression \"zstd\""));
+    fs::remove_file(&path).ok();
+}
+
+#[test]
+fn test_snapshot_from_file_suffix_names() {
+    let snapshot =
+        Snapshot::from_file("tests/snapshots/test_glob__basic_globbing@hello.txt.snap").unwrap();
+    assert_eq!(snapshot.module_name(), "test_glob");
+    assert_eq!(snapshot.snapshot_name(), Some("basic_globbing@hello.txt"));
+
+    let path = std::env::temp_dir().join(format!(
+        "insta-suffix-{}__name@case.postgres.snap.new",
+        std::process::id()
+    ));
+    snapshot.save(&path).unwrap();
+    let pending = Snapshot::from_file(&path).unwrap();
+    assert_eq!(pending.snapshot_name(), Some("name@case.postgres"));
+
+    let snapshot =
+        Snapshot::from_file("tests/snapshots/snapshot_no_module_prepending.snap").unwrap();
+    assert_eq!(snapshot.module_name(), "<unknown>");
+    assert_eq!(
+        snapshot.snapshot_name(),
+        Some("snapshot_no_module_prepending")
+    );
+    fs::remove_file(&path).ok();
+}
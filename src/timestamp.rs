@@ -0,0 +1,69 @@
+use crate::content::Content;
+
+/// Does the string look like a serialized `chrono` datetime?
+#[cfg(feature = "chrono")]
+fn is_chrono_timestamp(s: &str) -> bool {
+    chrono::DateTime::parse_from_rfc3339(s).is_ok() || s.parse::<chrono::NaiveDateTime>().is_ok()
+}
+
+/// Does the string look like a serialized `time` datetime?
+#[cfg(feature = "time")]
+fn is_time_timestamp(s: &str) -> bool {
+    use time::format_description::well_known::Rfc3339;
+    use time::macros::format_description;
+
+    // the formats `time` serializes datetimes with by default
+    const OFFSET_FORMAT: &[time::format_description::FormatItem<'_>] = format_description!(
+        "[year]-[month]-[day] [hour]:[minute]:[second].[subsecond] \
+         [offset_hour sign:mandatory]:[offset_minute]:[offset_second]"
+    );
+    const PRIMITIVE_FORMAT: &[time::format_description::FormatItem<'_>] =
+        format_description!("[year]-[month]-[day] [hour]:[minute]:[second].[subsecond]");
+
+    time::OffsetDateTime::parse(s, &Rfc3339).is_ok()
+        || time::OffsetDateTime::parse(s, OFFSET_FORMAT).is_ok()
+        || time::PrimitiveDateTime::parse(s, PRIMITIVE_FORMAT).is_ok()
+}
+
+fn is_timestamp(s: &str) -> bool {
+    #[cfg(feature = "chrono")]
+    {
+        if is_chrono_timestamp(s) {
+            return true;
+        }
+    }
+    #[cfg(feature = "time")]
+    {
+        if is_time_timestamp(s) {
+            return true;
+        }
+    }
+    false
+}
+
+/// Replaces all serialized datetimes with `[timestamp]`.
+pub(crate) fn redact_timestamps(content: Content) -> Content {
+    content.map(&mut |node| match node {
+        Content::String(ref s) if is_timestamp(s) => Content::from("[timestamp]"),
+        other => other,
+    })
+}
+
+#[test]
+fn test_is_timestamp() {
+    #[cfg(feature = "chrono")]
+    {
+        assert!(is_timestamp("2020-02-03T14:15:16Z"));
+        assert!(is_timestamp("2020-02-03T14:15:16.123+02:00"));
+        assert!(is_timestamp("2020-02-03T14:15:16.123"));
+    }
+    #[cfg(feature = "time")]
+    {
+        assert!(is_timestamp("2020-02-03T14:15:16Z"));
+        assert!(is_timestamp("2020-02-03 14:15:16.0 +00:00:00"));
+        assert!(is_timestamp("2020-02-03 14:15:16.5"));
+    }
+    assert!(!is_timestamp("2020-02-03"));
+    assert!(!is_timestamp("14:15:16"));
+    assert!(!is_timestamp("hello"));
+}
@@ -0,0 +1,109 @@
+use std::borrow::Cow;
+use std::fmt::Display;
+use std::rc::Rc;
+use std::sync::Arc;
+
+/// Renders a value into the text stored in a snapshot.
+///
+/// This is what [`assert_snapshot!`](macro.assert_snapshot.html) uses for
+/// its value.  It's implemented for strings but domain types can implement
+/// it to provide their preferred rendering (eg: an AST pretty printer)
+/// without having to abuse `Debug` or implement `Serialize`.  Values of
+/// other types implementing `Display` are rendered with `Display`, an
+/// implementation of this trait takes precedence:
+///
+/// ```rust
+/// use insta::{assert_snapshot, ToSnapshot};
+///
+/// struct Expr(Vec<&'static str>);
+///
+/// impl ToSnapshot for Expr {
+///     fn to_snapshot(&self) -> String {
+///         self.0.join(" + ")
+///     }
+/// }
+///
+/// assert_snapshot!(Expr(vec!["a", "b"]), @"a + b");
+/// ```
+pub trait ToSnapshot {
+    /// Returns the snapshot contents for this value.
+    fn to_snapshot(&self) -> String;
+}
+
+impl ToSnapshot for str {
+    fn to_snapshot(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl ToSnapshot for String {
+    fn to_snapshot(&self) -> String {
+        self.clone()
+    }
+}
+
+impl ToSnapshot for Cow<'_, str> {
+    fn to_snapshot(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl ToSnapshot for Box<str> {
+    fn to_snapshot(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl ToSnapshot for Rc<str> {
+    fn to_snapshot(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl ToSnapshot for Arc<str> {
+    fn to_snapshot(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl<T: ToSnapshot + ?Sized> ToSnapshot for &T {
+    fn to_snapshot(&self) -> String {
+        (**self).to_snapshot()
+    }
+}
+
+impl<T: ToSnapshot + ?Sized> ToSnapshot for &mut T {
+    fn to_snapshot(&self) -> String {
+        (**self).to_snapshot()
+    }
+}
+
+/// Wraps the value of `assert_snapshot!` to pick its rendering.
+///
+/// The macro calls `render_snapshot` on a reference to this.  Method
+/// resolution finds `RenderToSnapshot` before it has to auto-reference for
+/// `RenderDisplay`, so `ToSnapshot` wins over `Display`.
+#[doc(hidden)]
+pub struct SnapshotValue<'a, T: ?Sized>(pub &'a T);
+
+#[doc(hidden)]
+pub trait RenderToSnapshot {
+    fn render_snapshot(&self) -> String;
+}
+
+impl<T: ToSnapshot + ?Sized> RenderToSnapshot for SnapshotValue<'_, T> {
+    fn render_snapshot(&self) -> String {
+        self.0.to_snapshot()
+    }
+}
+
+#[doc(hidden)]
+pub trait RenderDisplay {
+    fn render_snapshot(&self) -> String;
+}
+
+impl<T: Display + ?Sized> RenderDisplay for &SnapshotValue<'_, T> {
+    fn render_snapshot(&self) -> String {
+        self.0.to_string()
+    }
+}
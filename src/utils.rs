@@ -1,5 +1,18 @@
 use std::env;
 
+/// Computes the 64 bit FNV-1a hash of the given bytes.
+///
+/// This is used instead of the hasher of the standard library as the
+/// hash ends up in snapshot files and has to be stable.
+pub fn fnv1a(bytes: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for &byte in bytes {
+        hash ^= u64::from(byte);
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    hash
+}
+
 /// Are we running in in a CI environment?
 pub fn is_ci() -> bool {
     env::var("CI").is_ok() || env::var("TF_BUILD").is_ok()
@@ -22,7 +35,7 @@ mod fake_colors {
     }
 
     impl<D> FakeStyledObject<D> {
-        style_attr!(red green yellow cyan bold dim underlined);
+        style_attr!(red green yellow cyan bold dim italic underlined);
     }
 
     impl<D: std::fmt::Display> std::fmt::Display for FakeStyledObject<D> {
@@ -38,3 +51,9 @@ mod fake_colors {
 
 #[cfg(not(feature = "colors"))]
 pub use self::fake_colors::*;
+
+#[test]
+fn test_fnv1a() {
+    assert_eq!(fnv1a(b""), 0xcbf2_9ce4_8422_2325);
+    assert_eq!(fnv1a(b"a"), 0xaf63_dc4c_8601_ec8c);
+}
@@ -15,6 +15,57 @@ fn test_unnamed_debug_vector() {
     assert_debug_snapshot!(vec![1, 2, 3, 4, 5]);
 }
 
+#[test]
+fn test_failure_message() {
+    let case = "three items";
+    assert_debug_snapshot!("debug_vector", vec![1, 2, 3], "case: {}", case);
+    assert_yaml_snapshot!("yaml_vector", vec![1, 2, 3], "case: {}", case);
+    assert_debug_snapshot!(_, vec![1, 2, 3], "case: {}", case);
+    assert_json_snapshot!(vec![1, 2, 3], @"[\n  1,\n  2,\n  3\n]", "case: {}", case);
+}
+
+struct Unformattable;
+
+impl fmt::Display for Unformattable {
+    fn fmt(&self, _f: &mut fmt::Formatter) -> fmt::Result {
+        panic!("the message of a passing assertion was formatted");
+    }
+}
+
+#[test]
+fn test_failure_message_is_lazy() {
+    insta::assert_snapshot!("value", @"value", "{}", Unformattable);
+}
+
+#[test]
+fn test_debug_expr() {
+    let path = std::env::temp_dir().join(format!("insta-debug-expr-{}", std::process::id()));
+    let mut settings = insta::Settings::clone_current();
+    settings.set_snapshot_path(&path);
+    settings.set_snapshot_update(insta::SnapshotUpdate::Always);
+    settings.set_force_pass(true);
+    settings.bind(|| {
+        // the expression recorded in the metadata can still be passed third
+        // as long as it is not a string literal which is taken as message.
+        let expr = "custom expression";
+        insta::assert_snapshot!("debug_expr", "value", expr);
+        insta::assert_snapshot!("message", "value", "a message");
+    });
+    let expression = |name: &str| {
+        let snapshot = insta::Snapshot::from_file(path.join(name)).unwrap();
+        snapshot.metadata().expression().map(|x| x.to_string())
+    };
+    assert_eq!(
+        expression("test_basic__debug_expr.snap").as_deref(),
+        Some("custom expression")
+    );
+    assert_eq!(
+        expression("test_basic__message.snap").as_deref(),
+        Some("\"value\"")
+    );
+    std::fs::remove_dir_all(&path).unwrap();
+}
+
 #[test]
 fn test_yaml_vector() {
     assert_yaml_snapshot!("yaml_vector", vec![1, 2, 3]);
@@ -47,6 +98,30 @@ mod nested {
     }
 }
 
+mod helpers {
+    pub fn check(value: &str) {
+        insta::assert_snapshot!(value);
+    }
+}
+
+#[test]
+fn test_snapshot_from_helper() {
+    let mut settings = insta::Settings::clone_current();
+    settings.set_name_from_test_path(true);
+    settings.bind(|| {
+        helpers::check("named after the test");
+    });
+}
+
+#[test]
+fn test_explicit_test_name() {
+    let mut settings = insta::Settings::clone_current();
+    settings.set_test_name("renamed");
+    settings.bind(|| {
+        insta::assert_snapshot!("named after the explicit test name");
+    });
+}
+
 struct TestDisplay;
 
 impl fmt::Display for TestDisplay {
@@ -67,3 +142,139 @@ fn test_unnamed_display() {
     assert_display_snapshot!(td);
     assert_display_snapshot!("whatever");
 }
+
+#[test]
+fn test_float_formatting() {
+    let floats = (
+        0.1f32,
+        1.0f64 / 3.0,
+        5e-324f64,
+        f32::MIN_POSITIVE,
+        1e300f64,
+        -0.0f64,
+        100.0f64,
+    );
+    assert_yaml_snapshot!(floats, @r###"
+    ---
+    - 0.1
+    - 0.3333333333333333
+    - 5e-324
+    - 1.1754944e-38
+    - 1e300
+    - -0.0
+    - 100.0
+    "###);
+    assert_json_snapshot!(floats, @r###"
+    [
+      0.1,
+      0.3333333333333333,
+      5e-324,
+      1.1754944e-38,
+      1e+300,
+      -0.0,
+      100.0
+    ]
+    "###);
+}
+
+#[test]
+fn test_to_snapshot() {
+    use insta::{assert_snapshot, ToSnapshot};
+
+    enum Ast {
+        Num(i64),
+        Add(Box<Ast>, Box<Ast>),
+    }
+
+    impl ToSnapshot for Ast {
+        fn to_snapshot(&self) -> String {
+            match *self {
+                Ast::Num(num) => num.to_string(),
+                Ast::Add(ref a, ref b) => format!("({} + {})", a.to_snapshot(), b.to_snapshot()),
+            }
+        }
+    }
+
+    let ast = Ast::Add(
+        Box::new(Ast::Num(1)),
+        Box::new(Ast::Add(Box::new(Ast::Num(2)), Box::new(Ast::Num(3)))),
+    );
+    assert_snapshot!(ast, @"(1 + (2 + 3))");
+    assert_snapshot!(&ast, @"(1 + (2 + 3))");
+}
+
+#[test]
+fn test_display_values() {
+    use insta::{assert_snapshot, ToSnapshot};
+    use std::fmt;
+
+    struct Point(i32, i32);
+
+    impl fmt::Display for Point {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "({}, {})", self.0, self.1)
+        }
+    }
+
+    struct Version(u32, u32);
+
+    impl fmt::Display for Version {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "v{}.{}", self.0, self.1)
+        }
+    }
+
+    impl ToSnapshot for Version {
+        fn to_snapshot(&self) -> String {
+            format!("major: {}\nminor: {}", self.0, self.1)
+        }
+    }
+
+    assert_snapshot!(Point(1, -2), @"(1, -2)");
+    assert_snapshot!(&Point(1, -2), @"(1, -2)");
+    assert_snapshot!(42, @"42");
+    assert_snapshot!(Version(1, 2), @r###"
+    major: 1
+    minor: 2
+    "###);
+}
+
+#[test]
+fn test_yaml_documents() {
+    assert_yaml_snapshot!(insta::documents(vec!["started", "stopped"]));
+    assert_yaml_snapshot!("empty_documents", insta::documents(Vec::<u32>::new()));
+}
+
+#[test]
+fn test_computed_names() {
+    for (id, input) in [(1, "a,b"), (2, "c")] {
+        let fields: Vec<_> = input.split(',').collect();
+        let debug_name = format!("split-{}-debug", id);
+        let yaml_name = format!("split-{}-yaml", id);
+        insta::assert_snapshot!(format!("split-{}-text", id), fields.join("\n"));
+        assert_debug_snapshot!(&debug_name, fields);
+        assert_yaml_snapshot!(yaml_name.as_str(), fields);
+    }
+}
+
+#[test]
+fn test_allow_duplicates() {
+    insta::allow_duplicates! {
+        for _ in 0..3 {
+            assert_debug_snapshot!(vec![1, 2, 3]);
+            insta::assert_snapshot!("same", @"same");
+        }
+    }
+    // every assertion in the loop only allocated one auto generated name
+    assert_debug_snapshot!(vec![1, 2]);
+}
+
+#[test]
+#[should_panic(expected = "was repeated with a different value in allow_duplicates!")]
+fn test_allow_duplicates_mismatch() {
+    insta::allow_duplicates! {
+        for value in ["a", "b"] {
+            insta::assert_snapshot!(value, @"a");
+        }
+    }
+}
@@ -0,0 +1,36 @@
+use insta::{assert_binary_snapshot, Settings, SnapshotUpdate};
+
+fn payload() -> Vec<u8> {
+    (0..=255u8).chain([0, 0xff, b'\n', b'\r']).collect()
+}
+
+#[test]
+fn test_binary_snapshot() {
+    assert_binary_snapshot!(".bin", payload());
+    let stored = std::fs::read("tests/snapshots/test_binary__binary_snapshot.snap.bin").unwrap();
+    assert_eq!(stored, payload());
+}
+
+#[test]
+fn test_named_binary_snapshot() {
+    assert_binary_snapshot!("payload.bin", payload());
+}
+
+#[test]
+#[should_panic(expected = "snapshot assertion for 'payload' failed")]
+fn test_binary_mismatch() {
+    let mut settings = Settings::clone_current();
+    settings.set_snapshot_update(SnapshotUpdate::No);
+    settings.set_force_pass(false);
+    let mut changed = payload();
+    changed[0] = 1;
+    settings.bind(|| {
+        assert_binary_snapshot!("payload.bin", changed);
+    });
+}
+
+#[test]
+#[should_panic(expected = "needs a file extension")]
+fn test_binary_snapshot_without_extension() {
+    assert_binary_snapshot!("payload", payload());
+}
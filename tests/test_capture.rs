@@ -0,0 +1,43 @@
+#![cfg(unix)]
+
+use insta::{assert_snapshot, capture_output};
+use std::io::Write;
+use std::process::Command;
+
+#[test]
+fn test_capture_output() {
+    let ((), output) = capture_output(|| {
+        let mut stdout = std::io::stdout();
+        writeln!(stdout, "Usage: tool [OPTIONS]").unwrap();
+        writeln!(std::io::stderr(), "error: missing argument").unwrap();
+        Command::new("echo").arg("from a child").status().unwrap();
+    });
+    assert_snapshot!(output.stdout, @r###"
+    Usage: tool [OPTIONS]
+    from a child
+    "###);
+    assert_snapshot!(output.stderr, @"error: missing argument");
+}
+
+#[test]
+#[should_panic(expected = "boom")]
+fn test_capture_output_restores_on_panic() {
+    capture_output(|| panic!("boom"));
+}
+
+#[test]
+fn test_capture_output_print_macros() {
+    // the test harness captures `println!` on the test thread itself, so
+    // the macro output only reaches stdout with `--nocapture`.
+    let nocapture = std::env::args().any(|arg| arg == "--nocapture")
+        || std::env::var_os("RUST_TEST_NOCAPTURE").is_some();
+    let ((), output) = capture_output(|| {
+        println!("from println");
+        writeln!(std::io::stdout(), "from stdout").unwrap();
+    });
+    if nocapture {
+        assert_eq!(output.stdout, "from println\nfrom stdout\n");
+    } else {
+        assert_eq!(output.stdout, "from stdout\n");
+    }
+}
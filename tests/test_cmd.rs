@@ -0,0 +1,65 @@
+#![cfg(all(feature = "cmd", unix))]
+
+use std::process::Command;
+
+use insta::assert_cmd_snapshot;
+
+fn sh(script: &str) -> Command {
+    let mut cmd = Command::new("sh");
+    cmd.arg("-c").arg(script);
+    cmd
+}
+
+#[test]
+fn test_cmd_success() {
+    assert_cmd_snapshot!(sh("echo hello; echo world"), @r###"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    hello
+    world
+    ----- stderr -----
+    "###);
+}
+
+#[test]
+fn test_cmd_failure() {
+    let mut cmd = sh("echo 'no such file' >&2; exit 3");
+    assert_cmd_snapshot!(&mut cmd, @r###"
+    success: false
+    exit_code: 3
+    ----- stdout -----
+    ----- stderr -----
+    no such file
+    "###);
+}
+
+#[cfg(feature = "redactions")]
+#[test]
+fn test_cmd_redactions() {
+    assert_cmd_snapshot!(sh("date"), {
+        ".stdout" => "[date]",
+    }, @r###"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    [date]
+    ----- stderr -----
+    "###);
+}
+
+#[cfg(feature = "filters")]
+#[test]
+fn test_cmd_filters() {
+    let mut settings = insta::Settings::clone_current();
+    settings.add_filter(r"took \d+ms", "took [duration]");
+    settings.bind(|| {
+        assert_cmd_snapshot!(sh("echo 'done, took 42ms'"), @r###"
+        success: true
+        exit_code: 0
+        ----- stdout -----
+        done, took [duration]
+        ----- stderr -----
+        "###);
+    });
+}
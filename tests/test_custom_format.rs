@@ -0,0 +1,62 @@
+use insta::{assert_custom_snapshot, Content, SnapshotSerializer};
+use serde::Serialize;
+
+/// Prints values as `key = value` lines like a simple config format.
+struct KeyValue;
+
+impl SnapshotSerializer for KeyValue {
+    fn serialize(&self, content: &Content) -> String {
+        let fields = match *content {
+            Content::Struct(_, ref fields) => fields,
+            _ => return "<not a struct>".into(),
+        };
+        fields
+            .iter()
+            .map(|(key, value)| match value.as_str() {
+                Some(s) => format!("{} = {:?}", key, s),
+                None => format!("{} = {}", key, value.as_u64().unwrap_or(0)),
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+#[derive(Serialize)]
+struct Server {
+    host: &'static str,
+    port: u64,
+}
+
+#[test]
+fn test_custom_format() {
+    let server = Server {
+        host: "localhost",
+        port: 8080,
+    };
+    assert_custom_snapshot!(KeyValue, server, @r###"
+    host = "localhost"
+    port = 8080
+    "###);
+    assert_custom_snapshot!(KeyValue, vec![1, 2], @"<not a struct>");
+}
+
+#[cfg(feature = "redactions")]
+#[test]
+fn test_custom_format_redactions() {
+    let server = Server {
+        host: "10.0.0.12",
+        port: 8080,
+    };
+    assert_custom_snapshot!(KeyValue, server, {".host" => "[host]"}, @r###"
+    host = "[host]"
+    port = 8080
+    "###);
+    insta::with_settings!({sort_maps => true}, {
+        assert_custom_snapshot!(KeyValue, server, {
+            ".port" => "[port]",
+        }, @r###"
+        host = "10.0.0.12"
+        port = "[port]"
+        "###);
+    });
+}
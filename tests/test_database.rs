@@ -0,0 +1,48 @@
+#![cfg(all(feature = "_test-sqlite", feature = "redactions"))]
+
+use insta::{assert_yaml_snapshot, rows_to_content};
+use sqlx::Connection;
+
+fn query(sql: &str) -> Vec<sqlx::sqlite::SqliteRow> {
+    futures_executor::block_on(async {
+        let mut conn = sqlx::SqliteConnection::connect("sqlite::memory:")
+            .await
+            .unwrap();
+        sqlx::query(sql).fetch_all(&mut conn).await.unwrap()
+    })
+}
+
+#[test]
+fn test_rows() {
+    let rows = query(
+        "select 1 as id, 'Jane' as name, 1.5 as score, null as deleted, x'cafe' as avatar \
+         union all \
+         select 2, 'John', 2, 'yes', null",
+    );
+    assert_yaml_snapshot!(rows_to_content(&rows), {
+        "[].id" => "[id]",
+    }, @r###"
+    ---
+    - id: "[id]"
+      name: Jane
+      score: 1.5
+      deleted: ~
+      avatar:
+        - 202
+        - 254
+    - id: "[id]"
+      name: John
+      score: 2
+      deleted: "yes"
+      avatar: ~
+    "###);
+}
+
+#[test]
+fn test_no_rows() {
+    let rows = query("select 1 as id where 1 = 0");
+    assert_yaml_snapshot!(rows_to_content(&rows), @r###"
+    ---
+    []
+    "###);
+}
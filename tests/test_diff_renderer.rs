@@ -0,0 +1,45 @@
+use insta::{assert_debug_snapshot, embedded_snapshot, DiffRenderer, Settings};
+use std::panic;
+use std::sync::{Arc, Mutex};
+
+type Calls = Vec<(Option<String>, String)>;
+
+#[derive(Clone, Default)]
+struct RecordingRenderer(Arc<Mutex<Calls>>);
+
+impl DiffRenderer for RecordingRenderer {
+    fn render(&self, old: Option<&str>, new: &str) -> String {
+        self.0
+            .lock()
+            .unwrap()
+            .push((old.map(|x| x.to_string()), new.to_string()));
+        format!("custom diff ({} lines)", new.lines().count())
+    }
+}
+
+#[test]
+fn test_custom_diff_renderer() {
+    let renderer = RecordingRenderer::default();
+    let mut settings = Settings::new();
+    settings.set_diff_renderer(renderer.clone());
+    settings.set_force_pass(false);
+
+    let rv = panic::catch_unwind(panic::AssertUnwindSafe(|| {
+        settings.bind(|| {
+            assert_debug_snapshot!(
+                embedded_snapshot!("snapshots/test_basic__debug_vector.snap"),
+                vec![1, 2]
+            );
+        });
+    }));
+    assert!(rv.is_err());
+
+    let calls = renderer.0.lock().unwrap();
+    assert_eq!(
+        *calls,
+        vec![(
+            Some("[\n    1,\n    2,\n    3,\n]".to_string()),
+            "[\n    1,\n    2,\n]".to_string()
+        )]
+    );
+}
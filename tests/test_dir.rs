@@ -0,0 +1,107 @@
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+use insta::{assert_dir_snapshot, assert_snapshot, DirSnapshot};
+
+fn make_tree(name: &str) -> PathBuf {
+    let root = env::temp_dir().join(format!("insta-dir-{}-{}", name, std::process::id()));
+    fs::remove_dir_all(&root).ok();
+    fs::create_dir_all(root.join("src/bin")).unwrap();
+    fs::create_dir_all(root.join("cache/objects")).unwrap();
+    fs::create_dir_all(root.join("empty")).unwrap();
+    fs::write(root.join("Cargo.toml"), "[package]\nname = \"generated\"\n").unwrap();
+    fs::write(root.join("Cargo.lock"), "# generated\n").unwrap();
+    fs::write(root.join("src/lib.rs"), "mod a;\nmod b;\nmod c;\nmod d;\n").unwrap();
+    fs::write(root.join("src/bin/main.rs"), "fn main() {}\n").unwrap();
+    fs::write(root.join("cache/objects/1"), "cached\n").unwrap();
+    fs::write(root.join("logo.png"), b"\x89PNG\r\n\x1a\n\xff\xfe").unwrap();
+    root
+}
+
+#[test]
+fn test_dir_snapshot() {
+    let root = make_tree("default");
+    assert_dir_snapshot!(&root, @r###"
+    === Cargo.lock ===
+    # generated
+    === Cargo.toml ===
+    [package]
+    name = "generated"
+    === cache/objects/1 ===
+    cached
+    === empty/ ===
+    === logo.png ===
+    <binary, 10 bytes>
+    === src/bin/main.rs ===
+    fn main() {}
+    === src/lib.rs ===
+    mod a;
+    mod b;
+    mod c;
+    mod d;
+    "###);
+    fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn test_dir_snapshot_limits() {
+    let root = make_tree("limits");
+    assert_snapshot!(DirSnapshot::new(&root)
+        .ignore("*.lock")
+        .ignore("cache/**")
+        .ignore("empty")
+        .max_file_size(28)
+        .max_lines(2), @r###"
+    === Cargo.toml ===
+    <29 bytes>
+    === cache/ ===
+    === logo.png ===
+    <binary, 10 bytes>
+    === src/bin/main.rs ===
+    fn main() {}
+    === src/lib.rs ===
+    mod a;
+    mod b;
+    <2 more lines>
+    "###);
+    fs::remove_dir_all(&root).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn test_dir_snapshot_symlinks() {
+    let root = make_tree("symlinks");
+    std::os::unix::fs::symlink("..", root.join("src/parent")).unwrap();
+    std::os::unix::fs::symlink("lib.rs", root.join("src/alias.rs")).unwrap();
+    assert_snapshot!(DirSnapshot::new(root.join("src")), @r###"
+    === alias.rs ===
+    <symlink to lib.rs>
+    === bin/main.rs ===
+    fn main() {}
+    === lib.rs ===
+    mod a;
+    mod b;
+    mod c;
+    mod d;
+    === parent ===
+    <symlink to ..>
+    "###);
+    fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn test_dir_snapshot_all_ignored() {
+    let root = make_tree("all-ignored");
+    assert_snapshot!(DirSnapshot::new(&root)
+        .ignore("*.lock")
+        .ignore("*.toml")
+        .ignore("*.png")
+        .ignore("src/**")
+        .ignore("cache/objects/*"), @r###"
+    === cache/objects/ ===
+    === empty/ ===
+    === src/ ===
+    "###);
+    fs::remove_dir_all(&root).unwrap();
+}
@@ -0,0 +1,46 @@
+use insta::{assert_debug_snapshot, assert_yaml_snapshot, embedded_snapshot, Settings};
+
+#[test]
+fn test_embedded_debug() {
+    assert_debug_snapshot!(
+        embedded_snapshot!("snapshots/test_basic__debug_vector.snap"),
+        vec![1, 2, 3]
+    );
+}
+
+#[test]
+fn test_embedded_serialized() {
+    assert_yaml_snapshot!(
+        embedded_snapshot!("snapshots/test_basic__yaml_vector.snap"),
+        vec![1, 2, 3]
+    );
+}
+
+#[test]
+#[should_panic(expected = "embedded snapshot assertion failed")]
+fn test_embedded_mismatch() {
+    let mut settings = Settings::clone_current();
+    settings.set_force_pass(false);
+    settings.bind(|| {
+        assert_debug_snapshot!(
+            embedded_snapshot!("snapshots/test_basic__debug_vector.snap"),
+            vec![1, 2]
+        );
+    });
+}
+
+#[test]
+#[should_panic(expected = "failed in line 39: case: two items")]
+fn test_embedded_mismatch_message() {
+    let mut settings = Settings::clone_current();
+    settings.set_force_pass(false);
+    let case = "two items";
+    settings.bind(|| {
+        assert_debug_snapshot!(
+            embedded_snapshot!("snapshots/test_basic__debug_vector.snap"),
+            vec![1, 2],
+            "case: {}",
+            case
+        );
+    });
+}
@@ -0,0 +1,45 @@
+#![cfg(feature = "filters")]
+
+use insta::{assert_debug_snapshot, assert_snapshot, with_settings, Settings};
+
+#[test]
+fn test_filters() {
+    let mut settings = Settings::new();
+    settings.add_filter(r"/tmp/\.tmp\w+", "[TEMP_DIR]");
+    settings.add_filter(r"\b(\d+)ms\b", "[duration]");
+    settings.bind(|| {
+        assert_snapshot!(
+            "wrote /tmp/.tmpA8dz3/report.txt in 125ms",
+            @"wrote [TEMP_DIR]/report.txt in [duration]"
+        );
+        assert_debug_snapshot!(
+            vec!["/tmp/.tmpQ0r1b/a", "/tmp/.tmpX7pe2/b"],
+            @r###"
+        [
+            "[TEMP_DIR]/a",
+            "[TEMP_DIR]/b",
+        ]
+        "###
+        );
+    });
+}
+
+#[test]
+fn test_filters_macro() {
+    with_settings!({filters => vec![
+        (r"0x[0-9a-f]+", "[ptr]"),
+        (r"(?P<name>\w+)@example\.com", "${name}@[domain]"),
+    ]}, {
+        assert_snapshot!("alice@example.com at 0x7ffd1c", @"alice@[domain] at [ptr]");
+    });
+}
+
+#[test]
+fn test_clear_filters() {
+    let mut settings = Settings::new();
+    settings.add_filter("secret", "[redacted]");
+    settings.clear_filters();
+    settings.bind(|| {
+        assert_snapshot!("secret", @"secret");
+    });
+}
@@ -0,0 +1,28 @@
+use insta::{assert_matches_file, Settings, SnapshotUpdate};
+
+fn greeting(name: &str) -> String {
+    format!("Hello, {}!\nGoodbye.\n", name)
+}
+
+#[test]
+fn test_matches_file() {
+    assert_matches_file!(greeting("World"), "tests/golden/greeting.txt");
+}
+
+#[test]
+fn test_computed_path() {
+    for name in ["greeting"] {
+        assert_matches_file!(greeting("World"), format!("tests/golden/{}.txt", name));
+    }
+}
+
+#[test]
+#[should_panic(expected = "golden file assertion for 'tests/golden/greeting.txt' failed")]
+fn test_trailing_newline_matters() {
+    let mut settings = Settings::clone_current();
+    settings.set_snapshot_update(SnapshotUpdate::No);
+    settings.set_force_pass(false);
+    settings.bind(|| {
+        assert_matches_file!(greeting("World").trim_end(), "tests/golden/greeting.txt");
+    });
+}
@@ -0,0 +1,77 @@
+#![cfg(feature = "html")]
+
+use insta::assert_html_snapshot;
+
+#[test]
+fn test_html_normalization() {
+    let html = r#"<!doctype html>
+<html lang=en><head>
+<meta charset="utf-8"><title>Users</title>
+<style>
+    body { margin: 0; }
+        ul { padding: 0; }
+</style>
+</head>
+<body>
+  <!-- rendered by the template -->
+  <ul id="users"   class='list'>
+    <li data-id="1" class="user admin">Jane
+       Doe</li>
+    <li data-id="2" class="user" hidden>John   Doe<br/>
+    <img src="john.png" alt="a &quot;quote&quot;">
+  </ul>
+  <pre>  keep
+    this</pre>
+  <p>1 < 2 and <b>bold</b> text</p>
+</body></html>"#;
+    assert_html_snapshot!(html, @r###"
+    <!DOCTYPE html>
+    <html lang="en">
+      <head>
+        <meta charset="utf-8">
+        <title>Users</title>
+        <style>
+          body { margin: 0; }
+              ul { padding: 0; }
+        </style>
+      </head>
+      <body>
+        <!-- rendered by the template -->
+        <ul class="list" id="users">
+          <li class="user admin" data-id="1">Jane Doe</li>
+          <li class="user" data-id="2" hidden>
+            John Doe
+            <br>
+            <img alt="a &quot;quote&quot;" src="john.png">
+          </li>
+        </ul>
+        <pre>  keep
+        this</pre>
+        <p>
+          1 < 2 and
+          <b>bold</b>
+          text
+        </p>
+      </body>
+    </html>
+    "###);
+}
+
+#[test]
+fn test_html_whitespace_does_not_matter() {
+    let compact = "<div id=a class=b><p>Hello <em>World</em></p></div>";
+    let spaced =
+        "<div  class='b'   id='a'>\n  <p>\n    Hello\n    <em> World </em>\n  </p>\n</div>\n";
+    assert_eq!(
+        insta::_macro_support::html_to_text(compact),
+        insta::_macro_support::html_to_text(spaced)
+    );
+    assert_html_snapshot!(spaced, @r###"
+    <div class="b" id="a">
+      <p>
+        Hello
+        <em>World</em>
+      </p>
+    </div>
+    "###);
+}
@@ -0,0 +1,110 @@
+#![cfg(feature = "http")]
+
+use insta::assert_response_snapshot;
+
+fn json_response() -> http::Response<String> {
+    http::Response::builder()
+        .status(201)
+        .header("content-type", "application/json; charset=utf-8")
+        .header("date", "Tue, 15 Nov 1994 08:12:31 GMT")
+        .header("x-request-id", "9f1c2b3a")
+        .body(r#"{"id":42,"name":"Jane","tags":["admin"]}"#.to_string())
+        .unwrap()
+}
+
+#[test]
+fn test_json_response() {
+    assert_response_snapshot!(json_response(), @r###"
+    ---
+    status: 201
+    headers:
+      content-type: application/json; charset=utf-8
+      date: "[redacted]"
+      x-request-id: "[redacted]"
+    body:
+      id: 42
+      name: Jane
+      tags:
+        - admin
+    "###);
+}
+
+#[test]
+fn test_text_response() {
+    let response = http::Response::builder()
+        .status(404)
+        .header("content-type", "text/plain")
+        .body(b"Not Found".to_vec())
+        .unwrap();
+    assert_response_snapshot!(response, @r###"
+    ---
+    status: 404
+    headers:
+      content-type: text/plain
+    body: Not Found
+    "###);
+}
+
+#[cfg(feature = "redactions")]
+#[test]
+fn test_response_redactions() {
+    assert_response_snapshot!(json_response(), {
+        ".body.id" => "[id]",
+    }, @r###"
+    ---
+    status: 201
+    headers:
+      content-type: application/json; charset=utf-8
+      date: "[redacted]"
+      x-request-id: "[redacted]"
+    body:
+      id: "[id]"
+      name: Jane
+      tags:
+        - admin
+    "###);
+}
+
+struct ClientResponse {
+    status: u16,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+
+impl insta::HttpResponse for ClientResponse {
+    fn status(&self) -> u16 {
+        self.status
+    }
+
+    fn headers(&self) -> Vec<(&str, &[u8])> {
+        self.headers
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_bytes()))
+            .collect()
+    }
+
+    fn body(&self) -> &[u8] {
+        &self.body
+    }
+}
+
+#[test]
+fn test_custom_response() {
+    let response = ClientResponse {
+        status: 200,
+        headers: vec![
+            ("Content-Type".into(), "application/json".into()),
+            ("X-Request-Id".into(), "9f1c2b3a".into()),
+        ],
+        body: br#"{"ok":true}"#.to_vec(),
+    };
+    assert_response_snapshot!(response, @r###"
+    ---
+    status: 200
+    headers:
+      Content-Type: application/json
+      X-Request-Id: "[redacted]"
+    body:
+      ok: true
+    "###);
+}
@@ -0,0 +1,56 @@
+#![cfg(feature = "image")]
+
+use image::codecs::png::{CompressionType, FilterType, PngEncoder};
+use image::{ExtendedColorType, ImageEncoder, Rgb, RgbImage};
+use insta::assert_image_snapshot;
+
+fn encode(compression: CompressionType, filter: FilterType, alpha: bool) -> Vec<u8> {
+    let image = RgbImage::from_fn(4, 3, |x, y| Rgb([x as u8 * 60, y as u8 * 80, 128]));
+    let mut buf = Vec::new();
+    let encoder = PngEncoder::new_with_quality(&mut buf, compression, filter);
+    if alpha {
+        let rgba = image::DynamicImage::ImageRgb8(image).into_rgba8();
+        encoder
+            .write_image(rgba.as_raw(), 4, 3, ExtendedColorType::Rgba8)
+            .unwrap();
+    } else {
+        encoder
+            .write_image(image.as_raw(), 4, 3, ExtendedColorType::Rgb8)
+            .unwrap();
+    }
+    buf
+}
+
+#[test]
+fn test_reencoded_image() {
+    let fast = encode(CompressionType::Fast, FilterType::NoFilter, false);
+    let best = encode(CompressionType::Best, FilterType::Paeth, true);
+    assert_ne!(fast, best);
+    assert_image_snapshot!(fast, @r###"
+    size: 4x3
+    pixels: fnv1a64:827ae5c1fd0dd2d5
+    "###);
+    assert_image_snapshot!(best, @r###"
+    size: 4x3
+    pixels: fnv1a64:827ae5c1fd0dd2d5
+    "###);
+}
+
+#[test]
+fn test_image_snapshot_with_tolerance() {
+    let reference = encode(CompressionType::Fast, FilterType::NoFilter, false);
+    assert_image_snapshot!("gradient", reference);
+
+    let mut image = RgbImage::from_fn(4, 3, |x, y| Rgb([x as u8 * 60, y as u8 * 80, 128]));
+    image.put_pixel(1, 1, Rgb([61, 81, 127]));
+    let mut changed = Vec::new();
+    PngEncoder::new(&mut changed)
+        .write_image(image.as_raw(), 4, 3, ExtendedColorType::Rgb8)
+        .unwrap();
+    insta::with_settings!({image_threshold => 0.05, image_max_diff_ratio => 0.0}, {
+        assert_image_snapshot!("gradient", changed);
+    });
+    insta::with_settings!({image_threshold => 0.0, image_max_diff_ratio => 0.1}, {
+        assert_image_snapshot!("gradient", changed);
+    });
+}
@@ -4,7 +4,10 @@ use insta::assert_csv_snapshot;
 use insta::assert_ron_snapshot;
 #[cfg(feature = "toml")]
 use insta::assert_toml_snapshot;
-use insta::{assert_debug_snapshot, assert_json_snapshot, assert_snapshot, assert_yaml_snapshot};
+use insta::{
+    assert_compact_debug_snapshot, assert_compact_json_snapshot, assert_debug_snapshot,
+    assert_json_snapshot, assert_snapshot, assert_yaml_snapshot,
+};
 use serde::Serialize;
 use std::thread;
 
@@ -75,6 +78,39 @@ fn test_csv_inline() {
     "###);
 }
 
+#[cfg(feature = "csv")]
+#[test]
+fn test_csv_records_inline() {
+    #[derive(Serialize)]
+    pub struct Row {
+        stage: &'static str,
+        rows: u32,
+        note: &'static str,
+    }
+
+    assert_csv_snapshot!(vec![
+        Row { stage: "extract", rows: 1200, note: "" },
+        Row { stage: "transform", rows: 1187, note: "dropped 13, invalid" },
+    ], @r###"
+    stage,rows,note
+    extract,1200,
+    transform,1187,"dropped 13, invalid"
+    "###);
+
+    insta::with_settings!({csv_delimiter => b';'}, {
+        assert_csv_snapshot!(vec![Row { stage: "load", rows: 1187, note: "ok" }], @r###"
+        stage;rows;note
+        load;1187;ok
+        "###);
+    });
+}
+
+#[cfg(feature = "csv")]
+#[test]
+fn test_csv_scalars_inline() {
+    assert_csv_snapshot!(vec![1, 2, 3], @"1,2,3");
+}
+
 #[cfg(feature = "ron")]
 #[test]
 fn test_ron_inline() {
@@ -135,6 +171,31 @@ fn test_json_inline() {
     "###);
 }
 
+#[cfg(feature = "redactions")]
+#[test]
+fn test_json_inline_redacted() {
+    assert_json_snapshot!(serde_json::json!({"id": 42, "name": "Jane"}), {
+        ".id" => "[id]"
+    }, @r###"
+    {
+      "id": "[id]",
+      "name": "Jane"
+    }
+    "###);
+}
+
+#[test]
+fn test_compact_json_inline() {
+    #[derive(Serialize)]
+    pub struct Point {
+        x: i32,
+        y: i32,
+    }
+
+    assert_compact_json_snapshot!(vec!["foo", "bar"], @r###"["foo","bar"]"###);
+    assert_compact_json_snapshot!(Point { x: 3, y: -1 }, @r###"{"x":3,"y":-1}"###);
+}
+
 #[test]
 fn test_yaml_inline() {
     #[derive(Serialize)]
@@ -180,6 +241,83 @@ fn test_yaml_inline_redacted() {
     "###);
 }
 
+#[test]
+fn test_yaml_documents_inline() {
+    #[derive(Serialize)]
+    pub struct Event {
+        kind: &'static str,
+        id: u32,
+    }
+
+    let events = vec![
+        Event {
+            kind: "connect",
+            id: 1,
+        },
+        Event {
+            kind: "disconnect",
+            id: 2,
+        },
+    ];
+    assert_yaml_snapshot!(insta::documents(&events), @r###"
+    ---
+    kind: connect
+    id: 1
+    ---
+    kind: disconnect
+    id: 2
+    "###);
+    assert_json_snapshot!(insta::documents(&events), @r###"
+    [
+      {
+        "kind": "connect",
+        "id": 1
+      },
+      {
+        "kind": "disconnect",
+        "id": 2
+      }
+    ]
+    "###);
+    #[cfg(feature = "redactions")]
+    assert_yaml_snapshot!(insta::documents(&events), {
+        "[].id" => "[id]"
+    }, @r###"
+    ---
+    kind: connect
+    id: "[id]"
+    ---
+    kind: disconnect
+    id: "[id]"
+    "###);
+}
+
+#[test]
+fn test_compact_debug_inline() {
+    #[derive(Debug)]
+    #[allow(dead_code)]
+    struct Pixel {
+        x: u32,
+        y: u32,
+        rgb: (u8, u8, u8),
+    }
+
+    let pixels = vec![
+        Pixel {
+            x: 0,
+            y: 0,
+            rgb: (255, 0, 0),
+        },
+        Pixel {
+            x: 1,
+            y: 0,
+            rgb: (0, 255, 0),
+        },
+    ];
+    assert_compact_debug_snapshot!(pixels, @"[Pixel { x: 0, y: 0, rgb: (255, 0, 0) }, Pixel { x: 1, y: 0, rgb: (0, 255, 0) }]");
+    assert_compact_debug_snapshot!(Some("text"), @r###"Some("text")"###);
+}
+
 #[test]
 fn test_non_basic_plane() {
     assert_snapshot!("a 😀oeu", @"a 😀oeu");
@@ -0,0 +1,24 @@
+#![cfg(feature = "proptest")]
+
+use insta::assert_counterexample_snapshot;
+use proptest::prelude::*;
+use proptest::test_runner::TestCaseError;
+
+#[test]
+fn test_passing_property() {
+    assert_counterexample_snapshot!("passing", 0u32..100, |x| {
+        prop_assert!(x < 100);
+        Ok(())
+    });
+}
+
+#[test]
+#[should_panic(expected = "property failed")]
+fn test_failing_property() {
+    assert_counterexample_snapshot!("below_fifty", (0u32..100, 0u32..10), |(a, _)| {
+        if a >= 50 {
+            return Err(TestCaseError::fail(format!("{} is too large", a)));
+        }
+        Ok(())
+    });
+}
@@ -0,0 +1,54 @@
+#![cfg(feature = "prost")]
+
+use insta::assert_protobuf_snapshot;
+use prost_reflect::prost_types::{
+    field_descriptor_proto::{Label, Type},
+    DescriptorProto, FieldDescriptorProto, FileDescriptorProto,
+};
+use prost_reflect::{DescriptorPool, DynamicMessage, MessageDescriptor};
+
+fn field(name: &str, number: i32, ty: Type, label: Label) -> FieldDescriptorProto {
+    FieldDescriptorProto {
+        name: Some(name.into()),
+        number: Some(number),
+        r#type: Some(ty as i32),
+        label: Some(label as i32),
+        ..Default::default()
+    }
+}
+
+fn user_descriptor() -> MessageDescriptor {
+    let file = FileDescriptorProto {
+        name: Some("user.proto".into()),
+        package: Some("test".into()),
+        syntax: Some("proto3".into()),
+        message_type: vec![DescriptorProto {
+            name: Some("User".into()),
+            field: vec![
+                field("id", 1, Type::Uint64, Label::Optional),
+                field("name", 2, Type::String, Label::Optional),
+                field("roles", 3, Type::String, Label::Repeated),
+            ],
+            ..Default::default()
+        }],
+        ..Default::default()
+    };
+    let mut pool = DescriptorPool::new();
+    pool.add_file_descriptor_proto(file).unwrap();
+    pool.get_message_by_name("test.User").unwrap()
+}
+
+#[test]
+fn test_protobuf_text_format() {
+    // fields are written in field number order regardless of input order
+    let user = DynamicMessage::parse_text_format(
+        user_descriptor(),
+        r#"roles: ["admin", "dev"] name: "Jane" id: 42"#,
+    )
+    .unwrap();
+    assert_protobuf_snapshot!(user, @r###"
+    id: 42
+    name: "Jane"
+    roles: ["admin", "dev"]
+    "###);
+}
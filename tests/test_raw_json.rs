@@ -0,0 +1,45 @@
+use insta::{assert_json_snapshot, assert_yaml_snapshot};
+use serde::Serialize;
+use serde_json::value::RawValue;
+
+#[derive(Serialize)]
+struct Response {
+    status: u16,
+    body: Box<RawValue>,
+}
+
+fn make_response() -> Response {
+    Response {
+        status: 200,
+        body: RawValue::from_string(r#"{"zeta": 1, "alpha": [true, null]}"#.to_string()).unwrap(),
+    }
+}
+
+#[test]
+fn test_raw_value_yaml() {
+    assert_yaml_snapshot!(make_response(), @r###"
+    ---
+    status: 200
+    body:
+      zeta: 1
+      alpha:
+        - true
+        - ~
+    "###);
+}
+
+#[test]
+fn test_raw_value_json() {
+    assert_json_snapshot!(make_response(), @r###"
+    {
+      "status": 200,
+      "body": {
+        "zeta": 1,
+        "alpha": [
+          true,
+          null
+        ]
+      }
+    }
+    "###);
+}
@@ -2,7 +2,8 @@
 
 use insta::_macro_support::Selector;
 use insta::{
-    assert_debug_snapshot, assert_json_snapshot, assert_yaml_snapshot, with_settings, Settings,
+    assert_debug_snapshot, assert_json_snapshot, assert_snapshot, assert_yaml_snapshot,
+    with_settings, Settings,
 };
 use serde::Serialize;
 
@@ -147,6 +148,159 @@ fn test_with_random_value_json_settings() {
     });
 }
 
+#[test]
+fn test_merged_settings() {
+    let mut crate_settings = Settings::new();
+    crate_settings.add_redaction(".id", "[id]");
+    crate_settings.set_sort_maps(true);
+
+    let mut module_settings = Settings::new();
+    module_settings.add_redaction(".extra", "[extra]");
+    module_settings.set_snapshot_suffix("merged");
+
+    let mut settings = crate_settings.clone();
+    settings.merge(&module_settings);
+    settings.merge(&crate_settings);
+    assert!(settings.sort_maps());
+    assert_eq!(settings.snapshot_suffix(), Some("merged"));
+    settings.bind(|| {
+        assert_json_snapshot!(
+            &User {
+                id: 122,
+                username: "jason_doe".to_string(),
+                email: Email("jason@example.com".to_string()),
+                extra: "ssn goes here".to_string(),
+            },
+            @r###"
+        {
+          "id": "[id]",
+          "username": "jason_doe",
+          "email": "jason@example.com",
+          "extra": "[extra]"
+        }
+        "###
+        );
+    });
+}
+
+#[test]
+fn test_extend_current_settings() {
+    fn assert_user_snapshot(user: &User) {
+        let mut settings = Settings::clone_current();
+        settings.add_redaction(".extra", "[extra]");
+        settings.bind(|| {
+            assert_json_snapshot!(user, @r###"
+            {
+              "id": "[id]",
+              "username": "jason_doe",
+              "email": "jason@example.com",
+              "extra": "[extra]"
+            }
+            "###);
+        });
+    }
+
+    let mut settings = Settings::new();
+    settings.add_redaction(".id", "[id]");
+    settings.bind(|| {
+        assert_user_snapshot(&User {
+            id: 42,
+            username: "jason_doe".to_string(),
+            email: Email("jason@example.com".to_string()),
+            extra: "ssn goes here".to_string(),
+        });
+    });
+}
+
+#[test]
+fn test_key_regex_redaction() {
+    let value = serde_json::json!({
+        "created_at": "2021-01-01",
+        "name": "foo",
+        "items": [
+            {"updated_at": "2021-01-02", "at": "home"},
+        ],
+    });
+    assert_json_snapshot!(value, {
+        ".**.{/_at$/}" => "[timestamp]",
+    }, @r###"
+    {
+      "created_at": "[timestamp]",
+      "items": [
+        {
+          "at": "home",
+          "updated_at": "[timestamp]"
+        }
+      ],
+      "name": "foo"
+    }
+    "###);
+
+    assert!(Selector::parse(".{/[/}").is_err());
+}
+
+#[test]
+fn test_sorted_redaction() {
+    let value = serde_json::json!({
+        "members": ["mia", "bob", "alice"],
+        "groups": [{"id": 2}, {"id": 1}],
+        "name": "team",
+    });
+    assert_json_snapshot!(value, {
+        ".members" => insta::sorted_redaction(),
+        ".groups" => insta::sorted_redaction(),
+        ".name" => insta::sorted_redaction(),
+    }, @r###"
+    {
+      "groups": [
+        {
+          "id": 1
+        },
+        {
+          "id": 2
+        }
+      ],
+      "members": [
+        "alice",
+        "bob",
+        "mia"
+      ],
+      "name": "team"
+    }
+    "###);
+}
+
+#[test]
+fn test_rounded_redaction() {
+    #[derive(Serialize)]
+    struct Stats {
+        mean: f64,
+        points: Vec<(f32, f64)>,
+        count: u32,
+    }
+
+    let stats = Stats {
+        mean: 1.0 / 3.0,
+        points: vec![(0.1 + 0.2, 2.0f64.sqrt())],
+        count: 3,
+    };
+    assert_json_snapshot!(stats, {
+        ".mean" => insta::rounded_redaction(3),
+        ".points" => insta::rounded_redaction(2),
+    }, @r###"
+    {
+      "mean": 0.333,
+      "points": [
+        [
+          0.3,
+          1.41
+        ]
+      ],
+      "count": 3
+    }
+    "###);
+}
+
 #[test]
 fn test_with_callbacks() {
     let mut settings = Settings::new();
@@ -248,3 +402,410 @@ fn test_redact_recursive() {
     }
     "###);
 }
+
+#[test]
+fn test_redact_deep_through_sequences() {
+    let value = serde_json::json!({
+        "id": 1,
+        "teams": [
+            {"id": 2, "members": [{"id": 3, "name": "alice"}]},
+            {"id": 4, "members": [], "meta": {"lead": {"id": 5}}},
+        ],
+    });
+
+    assert_json_snapshot!(value, {
+        ".**.id" => "[id]",
+    }, @r###"
+    {
+      "id": "[id]",
+      "teams": [
+        {
+          "id": "[id]",
+          "members": [
+            {
+              "id": "[id]",
+              "name": "alice"
+            }
+          ]
+        },
+        {
+          "id": "[id]",
+          "members": [],
+          "meta": {
+            "lead": {
+              "id": "[id]"
+            }
+          }
+        }
+      ]
+    }
+    "###);
+}
+
+#[test]
+fn test_redact_map_keys() {
+    let mut requests = std::collections::BTreeMap::new();
+    requests.insert("req-8f3a", "done");
+    requests.insert("req-0b1c", "pending");
+    let value = serde_json::json!({
+        "requests": requests,
+        "total": 2,
+    });
+
+    assert_json_snapshot!(value, {
+        ".requests.{keys}" => insta::dynamic_redaction(|key, _| {
+            assert!(key.as_str().unwrap().starts_with("req-"));
+            "[request-id]"
+        }),
+    }, @r###"
+    {
+      "requests": {
+        "[request-id]": "pending",
+        "[request-id]": "done"
+      },
+      "total": 2
+    }
+    "###);
+
+    assert!(Selector::parse(".{keys}.foo").is_err());
+}
+
+#[test]
+fn test_redact_slices() {
+    let value = serde_json::json!({
+        "events": [1, 2, 3, 4, 5],
+        "rows": [1, 2, 3],
+        "tail": [1, 2, 3, 4],
+        "middle": [1, 2, 3, 4],
+    });
+
+    assert_json_snapshot!(value, {
+        ".events[3:]" => "[later]",
+        ".rows[:2]" => "[first]",
+        ".tail[-2:]" => "[last]",
+        ".middle[1:-1]" => "[inner]",
+    }, @r###"
+    {
+      "events": [
+        1,
+        2,
+        3,
+        "[later]",
+        "[later]"
+      ],
+      "middle": [
+        1,
+        "[inner]",
+        "[inner]",
+        4
+      ],
+      "rows": [
+        "[first]",
+        "[first]",
+        3
+      ],
+      "tail": [
+        1,
+        2,
+        "[last]",
+        "[last]"
+      ]
+    }
+    "###);
+}
+
+#[test]
+fn test_removal() {
+    #[derive(Serialize)]
+    pub struct Build {
+        name: &'static str,
+        debug_info: Vec<&'static str>,
+    }
+
+    let value = serde_json::json!({
+        "build": Build {
+            name: "release",
+            debug_info: vec!["host", "pid"],
+        },
+        "env": {"HOME": "/home/user", "PATH": "/bin"},
+        "steps": ["check", "tmp", "test"],
+    });
+
+    let mut settings = Settings::new();
+    settings.add_removal(".build.debug_info");
+    settings.add_removal(".env.HOME");
+    settings.add_redaction(".steps[1]", insta::internals::Redaction::Remove);
+    settings.bind(|| {
+        assert_json_snapshot!(value, @r###"
+        {
+          "build": {
+            "name": "release"
+          },
+          "env": {
+            "PATH": "/bin"
+          },
+          "steps": [
+            "check",
+            "test"
+          ]
+        }
+        "###);
+    });
+}
+
+#[test]
+fn test_placeholder_redaction() {
+    let value = serde_json::json!({
+        "users": [{"id": "u-93f1"}, {"id": "u-07ac"}],
+        "posts": [
+            {"author_id": "u-07ac", "id": 4411},
+            {"author_id": "u-93f1", "id": 4412},
+            {"author_id": "u-07ac", "id": 4413},
+        ],
+    });
+
+    let mut settings = Settings::new();
+    settings.add_redaction(".posts[].id", insta::placeholder_redaction("post"));
+    settings.bind(|| {
+        assert_json_snapshot!(value, {
+            ".users[].id" => insta::placeholder_redaction("user"),
+            ".posts[].author_id" => insta::placeholder_redaction("user"),
+        }, @r###"
+        {
+          "posts": [
+            {
+              "author_id": "[user:2]",
+              "id": "[post:1]"
+            },
+            {
+              "author_id": "[user:1]",
+              "id": "[post:2]"
+            },
+            {
+              "author_id": "[user:2]",
+              "id": "[post:3]"
+            }
+          ],
+          "users": [
+            {
+              "id": "[user:1]"
+            },
+            {
+              "id": "[user:2]"
+            }
+          ]
+        }
+        "###);
+
+        // every snapshot is numbered on its own
+        let value = serde_json::json!({"posts": [value["posts"][2]]});
+        assert_json_snapshot!(value, @r###"
+        {
+          "posts": [
+            {
+              "author_id": "u-07ac",
+              "id": "[post:1]"
+            }
+          ]
+        }
+        "###);
+    });
+}
+
+#[test]
+fn test_redaction_sets() {
+    insta::register_redaction_set(
+        "timestamps",
+        vec![
+            (".created_at", "[timestamp]".into()),
+            (".**.updated_at", "[timestamp]".into()),
+        ],
+    );
+    let value = serde_json::json!({
+        "created_at": "2024-03-01T10:00:00Z",
+        "owner": {"id": 42, "updated_at": "2024-03-02T08:30:00Z"},
+    });
+
+    let mut settings = Settings::new();
+    settings.apply_redaction_set("timestamps");
+    // applying a set twice does not add its redactions again
+    settings.apply_redaction_set("timestamps");
+    settings.bind(|| {
+        assert_json_snapshot!(value, @r###"
+        {
+          "created_at": "[timestamp]",
+          "owner": {
+            "id": 42,
+            "updated_at": "[timestamp]"
+          }
+        }
+        "###);
+    });
+
+    with_settings!({redaction_set => "timestamps"}, {
+        assert_json_snapshot!(value["owner"], @r###"
+        {
+          "id": 42,
+          "updated_at": "[timestamp]"
+        }
+        "###);
+    });
+}
+
+#[test]
+#[should_panic(expected = "unknown redaction set")]
+fn test_unknown_redaction_set() {
+    Settings::new().apply_redaction_set("missing");
+}
+
+#[test]
+fn test_matchers() {
+    use insta::matchers;
+
+    let value = serde_json::json!({
+        "id": "9a3f6c2e-4b1d-4e8a-9c7f-2d5b8e1a0f63",
+        "created_at": "2024-03-01T10:00:00.123+01:00",
+        "day": "2024-03-01",
+        "workdir": "/tmp/build-3817/out",
+    });
+    assert_json_snapshot!(value, {
+        ".id" => matchers::uuid(),
+        ".created_at" => matchers::iso_timestamp(),
+        ".day" => matchers::iso_timestamp(),
+        ".workdir" => matchers::unix_path(),
+    }, @r###"
+    {
+      "created_at": "[timestamp]",
+      "day": "[timestamp]",
+      "id": "[uuid]",
+      "workdir": "[path]"
+    }
+    "###);
+}
+
+#[test]
+#[should_panic(expected = "expected a UUID at .user.id, got String(\"42\")")]
+fn test_matcher_mismatch() {
+    let value = serde_json::json!({"user": {"id": "42"}});
+    assert_json_snapshot!(value, {
+        ".user.id" => insta::matchers::uuid(),
+    }, @"");
+}
+
+#[test]
+fn test_try_add_redaction() {
+    let mut settings = Settings::new();
+    assert!(settings.try_add_redaction(".id", "[id]").is_ok());
+
+    let err = settings.try_add_redaction(".foo[", "[foo]").unwrap_err();
+    assert_eq!(err.span(), (5, 5));
+    assert_eq!(err.column(), 6);
+    assert_snapshot!(err.to_string(), @r###"
+    invalid selector
+     --> 1:6
+      |
+    1 | .foo[
+      |      ^---
+      |
+      = expected an integer or a quoted string
+    "###);
+}
+
+#[test]
+#[should_panic(expected = "\".users[].**.**\" is an invalid selector")]
+fn test_add_invalid_redaction() {
+    Settings::new().add_redaction(".users[].**.**", "[x]");
+}
+
+#[test]
+fn test_union_selectors() {
+    let value = serde_json::json!({
+        "created_at": "2024-03-01T10:00:00Z",
+        "updated_at": "2024-03-02T08:30:00Z",
+        "deleted_at": null,
+        "name": "report",
+    });
+
+    let mut settings = Settings::new();
+    settings.add_redaction(".created_at, .updated_at, .deleted_at", "[ts]");
+    settings.bind(|| {
+        assert_json_snapshot!(value, @r###"
+        {
+          "created_at": "[ts]",
+          "deleted_at": "[ts]",
+          "name": "report",
+          "updated_at": "[ts]"
+        }
+        "###);
+    });
+}
+
+#[test]
+fn test_conditional_redaction() {
+    let value = serde_json::json!({
+        "sessions": [
+            {"user": "alice", "token": "eyJhbGciOi.value.sig"},
+            {"user": "bob", "token": null},
+        ],
+    });
+
+    let mut settings = Settings::new();
+    settings.add_conditional_redaction(".sessions[].token", |value, _| !value.is_nil(), "[token]");
+    settings.add_conditional_redaction(
+        ".sessions[].user",
+        |_, path| path.to_string() == ".sessions.1.user",
+        insta::dynamic_redaction(|value, _| format!("<{}>", value.as_str().unwrap())),
+    );
+    settings.bind(|| {
+        assert_json_snapshot!(value, @r###"
+        {
+          "sessions": [
+            {
+              "token": "[token]",
+              "user": "alice"
+            },
+            {
+              "token": null,
+              "user": "<bob>"
+            }
+          ]
+        }
+        "###);
+    });
+}
+
+#[test]
+fn test_embedded_json() {
+    #[derive(Serialize)]
+    pub struct Message {
+        id: u32,
+        payload: String,
+    }
+
+    assert_yaml_snapshot!(&Message {
+        id: 42,
+        payload: r#"{"kind":"ping","data":[1,2]}"#.into(),
+    }, {
+        ".payload" => insta::embedded_json(),
+    }, @r###"
+    ---
+    id: 42
+    payload:
+      kind: ping
+      data:
+        - 1
+        - 2
+    "###);
+}
+
+#[test]
+fn test_redaction_with_message() {
+    let case = "numeric id";
+    assert_json_snapshot!(serde_json::json!({"id": 42}), {
+        ".id" => "[id]",
+    }, @r###"
+    {
+      "id": "[id]"
+    }
+    "###, "case: {}", case);
+}
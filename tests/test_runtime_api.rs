@@ -0,0 +1,26 @@
+use insta::runtime::{assert_snapshot, SnapshotContext};
+use std::thread;
+
+#[test]
+fn test_runtime_api_explicit_name() {
+    let ctx = SnapshotContext::new(env!("CARGO_MANIFEST_DIR"), "harness", file!(), line!());
+    assert_snapshot(&ctx, Some("explicit"), "Hello from a custom harness!").unwrap();
+}
+
+#[test]
+fn test_runtime_api_unnamed_thread() {
+    // custom harnesses frequently run tests on unnamed threads, the test
+    // name from the context is used instead.
+    thread::Builder::new()
+        .spawn(|| {
+            let mut ctx =
+                SnapshotContext::new(env!("CARGO_MANIFEST_DIR"), "harness", file!(), line!());
+            ctx.set_test_name("harness::test_parse");
+            ctx.set_expression("parse(input)");
+            assert_snapshot(&ctx, None, "first").unwrap();
+            assert_snapshot(&ctx, None, "second").unwrap();
+        })
+        .unwrap()
+        .join()
+        .unwrap();
+}
@@ -1,4 +1,7 @@
-use insta::{assert_yaml_snapshot, with_settings, Settings};
+use insta::{
+    assert_json_snapshot, assert_yaml_snapshot, with_settings, Settings, SnapshotUpdate, Whitespace,
+};
+use serde::Serialize;
 use std::collections::HashMap;
 
 #[test]
@@ -42,6 +45,93 @@ fn test_bound_to_thread() {
     "###);
 }
 
+#[test]
+fn test_bound_to_scope() {
+    let mut map = HashMap::new();
+    map.insert("a", "first value");
+    map.insert("b", "second value");
+    map.insert("c", "third value");
+    map.insert("d", "fourth value");
+
+    {
+        let mut settings = Settings::new();
+        settings.set_sort_maps(true);
+        let _guard = settings.bind_to_scope();
+        assert!(Settings::clone_current().sort_maps());
+        assert_yaml_snapshot!(&map, @r###"
+        ---
+        a: first value
+        b: second value
+        c: third value
+        d: fourth value
+        "###);
+    }
+
+    assert!(!Settings::clone_current().sort_maps());
+}
+
+#[test]
+fn test_bound_async_across_threads() {
+    use std::future::Future;
+    use std::pin::Pin;
+    use std::sync::Arc;
+    use std::task::{Context, Poll, Wake, Waker};
+
+    /// Returns pending on the first poll like a future waiting for IO.
+    struct YieldOnce(bool);
+
+    impl Future for YieldOnce {
+        type Output = ();
+
+        fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<()> {
+            if self.0 {
+                Poll::Ready(())
+            } else {
+                self.0 = true;
+                cx.waker().wake_by_ref();
+                Poll::Pending
+            }
+        }
+    }
+
+    struct NoopWaker;
+
+    impl Wake for NoopWaker {
+        fn wake(self: Arc<Self>) {}
+    }
+
+    let mut settings = Settings::new();
+    settings.set_sort_maps(true);
+    let mut future = Box::pin(settings.bind_async(async {
+        YieldOnce(false).await;
+        let mut map = HashMap::new();
+        map.insert("a", "first value");
+        map.insert("b", "second value");
+        map.insert("c", "third value");
+        assert_yaml_snapshot!(&map, @r###"
+        ---
+        a: first value
+        b: second value
+        c: third value
+        "###);
+    }));
+
+    let waker = Waker::from(Arc::new(NoopWaker));
+    let mut cx = Context::from_waker(&waker);
+    assert!(future.as_mut().poll(&mut cx).is_pending());
+    assert!(!Settings::clone_current().sort_maps());
+
+    // finish the future on another thread as multi threaded executors do
+    std::thread::spawn(move || {
+        let waker = Waker::from(Arc::new(NoopWaker));
+        let mut cx = Context::from_waker(&waker);
+        assert!(future.as_mut().poll(&mut cx).is_ready());
+        assert!(!Settings::clone_current().sort_maps());
+    })
+    .join()
+    .unwrap();
+}
+
 #[test]
 fn test_settings_macro() {
     let mut map = HashMap::new();
@@ -61,6 +151,28 @@ fn test_settings_macro() {
     });
 }
 
+#[test]
+fn test_settings_macro_all_keys() {
+    with_settings!({sort_maps => true}, {
+        with_settings!({
+            snapshot_suffix => "nested",
+            description => "nested settings",
+            tags => vec!["a", "b", "a"],
+            input_file => "tests/test_settings.rs",
+        }, {
+            let settings = Settings::clone_current();
+            assert!(settings.sort_maps());
+            assert_eq!(settings.snapshot_suffix(), Some("nested"));
+            assert_eq!(settings.description(), Some("nested settings"));
+            assert_eq!(settings.tags(), ["a", "b"]);
+            assert_eq!(
+                settings.input_file(),
+                Some(std::path::Path::new("tests/test_settings.rs"))
+            );
+        });
+    });
+}
+
 #[test]
 fn test_snapshot_path() {
     with_settings!({snapshot_path => "snapshots2"}, {
@@ -74,3 +186,520 @@ fn test_snapshot_no_module_prepending() {
         assert_yaml_snapshot!(vec![1, 2, 3]);
     });
 }
+
+#[test]
+fn test_sort_maps_keeps_struct_order() {
+    #[derive(Serialize)]
+    struct Invoice {
+        number: u32,
+        customer: &'static str,
+        amount: u32,
+    }
+
+    let mut map = HashMap::new();
+    map.insert(
+        "second",
+        Invoice {
+            number: 2,
+            customer: "Bob",
+            amount: 10,
+        },
+    );
+    map.insert(
+        "first",
+        Invoice {
+            number: 1,
+            customer: "Alice",
+            amount: 42,
+        },
+    );
+
+    with_settings!({sort_maps => true}, {
+        assert_json_snapshot!(&map, @r###"
+        {
+          "first": {
+            "number": 1,
+            "customer": "Alice",
+            "amount": 42
+          },
+          "second": {
+            "number": 2,
+            "customer": "Bob",
+            "amount": 10
+          }
+        }
+        "###);
+    });
+}
+
+#[test]
+fn test_json_enum_repr() {
+    use insta::EnumRepr;
+
+    #[derive(Serialize)]
+    enum Event {
+        Started,
+        Moved { x: i32, y: i32 },
+        Renamed(String),
+        Resized(u32, u32),
+    }
+
+    let events = vec![
+        Event::Started,
+        Event::Moved { x: 1, y: 2 },
+        Event::Renamed("foo".into()),
+        Event::Resized(10, 20),
+    ];
+
+    let mut settings = Settings::new();
+    settings.set_json_enum_repr(EnumRepr::Internal { tag: "type".into() });
+    settings.bind(|| {
+        assert_json_snapshot!(&events, @r###"
+        [
+          {
+            "type": "Started"
+          },
+          {
+            "type": "Moved",
+            "x": 1,
+            "y": 2
+          },
+          {
+            "Renamed": "foo"
+          },
+          {
+            "Resized": [
+              10,
+              20
+            ]
+          }
+        ]
+        "###);
+    });
+
+    settings.set_json_enum_repr(EnumRepr::Adjacent {
+        tag: "t".into(),
+        content: "c".into(),
+    });
+    settings.bind(|| {
+        assert_json_snapshot!(&events, @r###"
+        [
+          {
+            "t": "Started"
+          },
+          {
+            "t": "Moved",
+            "c": {
+              "x": 1,
+              "y": 2
+            }
+          },
+          {
+            "t": "Renamed",
+            "c": "foo"
+          },
+          {
+            "t": "Resized",
+            "c": [
+              10,
+              20
+            ]
+          }
+        ]
+        "###);
+    });
+}
+
+#[cfg(feature = "csv")]
+#[test]
+fn test_csv_options() {
+    use insta::{assert_csv_snapshot, CsvQuoteStyle};
+
+    #[derive(Serialize)]
+    pub struct Row {
+        id: u32,
+        name: &'static str,
+    }
+
+    let row = Row { id: 1, name: "a;b" };
+
+    let mut settings = Settings::new();
+    settings.set_csv_delimiter(b';');
+    settings.bind(|| {
+        assert_csv_snapshot!(&row, @r###"
+        id;name
+        1;"a;b"
+        "###);
+    });
+
+    settings.set_csv_quote_style(CsvQuoteStyle::NonNumeric);
+    settings.set_csv_has_headers(false);
+    settings.bind(|| {
+        assert_csv_snapshot!(&row, @r###"1;"a;b""###);
+    });
+
+    settings.set_csv_crlf(true);
+    settings.set_csv_has_headers(true);
+    settings.bind(|| {
+        assert_eq!(
+            insta::_macro_support::serialize_value(
+                &row,
+                insta::_macro_support::SerializationFormat::Csv,
+                insta::_macro_support::SnapshotLocation::File,
+            ),
+            "\"id\";\"name\"\r\n1;\"a;b\"\r\n"
+        );
+    });
+}
+
+#[cfg(feature = "ron")]
+#[test]
+fn test_ron_options() {
+    use insta::assert_ron_snapshot;
+
+    #[derive(Serialize)]
+    pub struct Sprite {
+        name: &'static str,
+        frames: Vec<u32>,
+    }
+
+    let mut settings = Settings::new();
+    settings.set_ron_indent("    ");
+    settings.set_ron_struct_names(false);
+    settings.set_ron_depth_limit(Some(1));
+    settings.bind(|| {
+        assert_ron_snapshot!(Sprite {
+            name: "hero",
+            frames: vec![1, 2, 3],
+        }, @r###"
+        (
+            name: "hero",
+            frames: [1,2,3],
+        )
+        "###);
+    });
+}
+
+#[cfg(all(feature = "chrono", feature = "time"))]
+#[test]
+fn test_redact_timestamps() {
+    #[derive(Serialize)]
+    pub struct Event {
+        name: &'static str,
+        created: chrono::DateTime<chrono::Utc>,
+        local: chrono::NaiveDateTime,
+        updated: time::OffsetDateTime,
+        history: Vec<time::PrimitiveDateTime>,
+        day: chrono::NaiveDate,
+    }
+
+    let mut settings = Settings::new();
+    settings.set_redact_timestamps(true);
+    settings.bind(|| {
+        assert_yaml_snapshot!(Event {
+            name: "deploy",
+            created: chrono::DateTime::from_timestamp(1_600_000_000, 0).unwrap(),
+            local: chrono::DateTime::from_timestamp(1_600_000_000, 5_000_000)
+                .unwrap()
+                .naive_utc(),
+            updated: time::macros::datetime!(2020-09-13 12:26:40 UTC),
+            history: vec![time::macros::datetime!(2020-09-13 12:26:40.5)],
+            day: chrono::NaiveDate::from_ymd_opt(2020, 9, 13).unwrap(),
+        }, @r###"
+        ---
+        name: deploy
+        created: "[timestamp]"
+        local: "[timestamp]"
+        updated: "[timestamp]"
+        history:
+          - "[timestamp]"
+        day: 2020-09-13
+        "###);
+    });
+}
+
+#[test]
+fn test_ansi_escapes() {
+    use insta::{assert_snapshot, AnsiEscapes};
+
+    let output = "\x1b[1;32mok\x1b[0m: 3 passed, \x1b[31m1 failed\x1b[39m";
+
+    let mut settings = Settings::new();
+    settings.set_ansi_escapes(AnsiEscapes::Strip);
+    settings.bind(|| {
+        assert_snapshot!(output, @"ok: 3 passed, 1 failed");
+    });
+
+    settings.set_ansi_escapes(AnsiEscapes::Tokens);
+    settings.bind(|| {
+        assert_snapshot!(output, @"<bold><fg:green>ok<reset>: 3 passed, <fg:red>1 failed</fg>");
+    });
+}
+
+#[test]
+fn test_workspace_root_and_source_remap() {
+    use insta::assert_snapshot;
+
+    // resolves to the same location as without settings
+    let mut settings = Settings::new();
+    settings.set_workspace_root("tests");
+    settings.add_source_path_remap("tests", "");
+    settings.bind(|| {
+        assert_snapshot!("remapped", "located through the remapped source path");
+    });
+}
+
+#[test]
+fn test_require_full_match_accepts_exact_snapshots() {
+    let mut settings = Settings::new();
+    settings.set_require_full_match(true);
+    settings.bind(|| {
+        assert_yaml_snapshot!(vec![1, 2, 3], @r###"
+        ---
+        - 1
+        - 2
+        - 3
+        "###);
+    });
+}
+
+#[test]
+#[should_panic(expected = "uses a legacy format")]
+fn test_require_full_match_rejects_legacy_snapshots() {
+    let mut settings = Settings::new();
+    settings.set_require_full_match(true);
+    settings.bind(|| {
+        insta::assert_snapshot!("legacy", "legacy");
+    });
+}
+
+#[test]
+#[should_panic(expected = "does not exist and require_full_match is set")]
+fn test_require_full_match_fails_on_missing_snapshots() {
+    let mut settings = Settings::new();
+    settings.set_snapshot_update(SnapshotUpdate::No);
+    settings.set_require_full_match(true);
+    settings.bind(|| {
+        insta::assert_snapshot!("does_not_exist", "value");
+    });
+}
+
+#[test]
+fn test_tags() {
+    let mut settings = Settings::new();
+    settings.set_tags(["api-contract", "slow"]);
+    settings.add_tag("slow");
+    assert_eq!(settings.tags(), ["api-contract", "slow"]);
+    settings.bind(|| {
+        assert_yaml_snapshot!("tagged", vec![1, 2, 3]);
+    });
+
+    let snapshot =
+        insta::Snapshot::from_file("tests/snapshots/test_settings__tagged.snap").unwrap();
+    assert_eq!(snapshot.metadata().tags(), ["api-contract", "slow"]);
+}
+
+#[test]
+fn test_description() {
+    let mut settings = Settings::new();
+    settings.set_description("the list of supported versions");
+    settings.bind(|| {
+        assert_yaml_snapshot!("described", vec![1, 2, 3]);
+    });
+
+    let snapshot =
+        insta::Snapshot::from_file("tests/snapshots/test_settings__described.snap").unwrap();
+    assert_eq!(
+        snapshot.metadata().description(),
+        Some("the list of supported versions")
+    );
+
+    // the description is metadata only and never compared
+    settings.set_description("a changed description");
+    settings.bind(|| {
+        assert_yaml_snapshot!("described", vec![1, 2, 3]);
+    });
+}
+
+#[test]
+fn test_info() {
+    #[derive(Serialize)]
+    struct Info {
+        input: &'static str,
+        strict: bool,
+    }
+
+    let mut settings = Settings::new();
+    settings.set_info(&Info {
+        input: "versions.txt",
+        strict: true,
+    });
+    assert!(settings.has_info());
+    settings.bind(|| {
+        assert_yaml_snapshot!("with_info", vec![1, 2, 3]);
+    });
+
+    let snapshot =
+        insta::Snapshot::from_file("tests/snapshots/test_settings__with_info.snap").unwrap();
+    let info = snapshot.metadata().info().unwrap();
+    assert_eq!(info["input"].as_str(), Some("versions.txt"));
+    assert_eq!(info["strict"].as_bool(), Some(true));
+}
+
+#[test]
+fn test_snapshot_extension() {
+    let mut settings = Settings::new();
+    settings.set_snapshot_extension("snap.json");
+    settings.bind(|| {
+        assert_json_snapshot!("custom_extension", vec![1, 2, 3]);
+    });
+
+    let snapshot =
+        insta::Snapshot::from_file("tests/snapshots/test_settings__custom_extension.snap.json")
+            .unwrap();
+    assert_eq!(snapshot.module_name(), "test_settings");
+    assert_eq!(snapshot.snapshot_name(), Some("custom_extension"));
+}
+
+#[test]
+fn test_normalize_line_endings() {
+    insta::assert_snapshot!("first\r\nsecond\r\n", @r###"
+    first
+    second
+    "###);
+}
+
+#[test]
+fn test_whitespace_policies() {
+    let mut settings = Settings::new();
+    settings.set_trailing_whitespace(Whitespace::Normalize);
+    settings.bind(|| {
+        insta::assert_snapshot!("trimmed", "first  \nsecond\t\n");
+    });
+
+    let snapshot =
+        insta::Snapshot::from_file("tests/snapshots/test_settings__trimmed.snap").unwrap();
+    assert_eq!(
+        snapshot.metadata().trailing_whitespace(),
+        Whitespace::Normalize
+    );
+    assert_eq!(snapshot.metadata().final_newline(), Whitespace::Normalize);
+    assert_eq!(snapshot.contents_str(), "first\nsecond\n");
+
+    settings.set_final_newline(Whitespace::Preserve);
+    settings.bind(|| {
+        insta::assert_snapshot!("final_newline", "first\nsecond\n");
+    });
+    let snapshot =
+        insta::Snapshot::from_file("tests/snapshots/test_settings__final_newline.snap").unwrap();
+    assert_eq!(snapshot.metadata().final_newline(), Whitespace::Preserve);
+    assert_eq!(snapshot.contents_str(), "first\nsecond\n");
+}
+
+#[test]
+fn test_omit_expression() {
+    let mut settings = Settings::new();
+    settings.set_omit_expression(true);
+    settings.bind(|| {
+        assert_yaml_snapshot!("omitted_expression", vec![1, 2, 3]);
+    });
+
+    let snapshot =
+        insta::Snapshot::from_file("tests/snapshots/test_settings__omitted_expression.snap")
+            .unwrap();
+    assert_eq!(snapshot.metadata().expression(), None);
+}
+
+#[test]
+fn test_input_file() {
+    let mut settings = Settings::new();
+    settings.set_input_file("tests/inputs/hello.txt");
+    settings.bind(|| {
+        assert_yaml_snapshot!("with_input_file", "Hello World!");
+    });
+
+    let snapshot =
+        insta::Snapshot::from_file("tests/snapshots/test_settings__with_input_file.snap").unwrap();
+    assert_eq!(
+        snapshot.metadata().input_file(),
+        Some("tests/inputs/hello.txt")
+    );
+}
+
+#[test]
+fn test_wildcards() {
+    let output = format!(
+        "Compiling demo v0.1.0 ({})\nwarning: unused variable\nwarning: unused import\nFinished in {}s",
+        std::env::current_dir().unwrap().display(),
+        std::process::id()
+    );
+    with_settings!({wildcards => true}, {
+        insta::assert_snapshot!(output, @r###"
+        Compiling demo v0.1.0 ([..])
+        ...
+        Finished in [..]s
+        "###);
+    });
+}
+
+#[test]
+#[should_panic(expected = "is empty which is rejected as allow_empty is disabled")]
+fn test_disallow_empty() {
+    let mut settings = Settings::new();
+    settings.set_allow_empty(false);
+    settings.bind(|| {
+        insta::assert_snapshot!("  \n", @"");
+    });
+}
+
+#[test]
+fn test_json_formatting() {
+    #[derive(Serialize)]
+    pub struct Series {
+        name: &'static str,
+        points: Vec<u32>,
+        labels: Vec<&'static str>,
+    }
+
+    let value = Series {
+        name: "latency",
+        points: vec![12, 15, 11, 40],
+        labels: vec!["p50", "p90", "p99", "p999", "max", "min", "mean", "stddev"],
+    };
+    with_settings!({json_indent => 4, json_array_width => Some(40)}, {
+        assert_json_snapshot!(value, @r###"
+        {
+            "name": "latency",
+            "points": [12, 15, 11, 40],
+            "labels": [
+                "p50",
+                "p90",
+                "p99",
+                "p999",
+                "max",
+                "min",
+                "mean",
+                "stddev"
+            ]
+        }
+        "###);
+    });
+}
+
+#[test]
+#[cfg(feature = "compression")]
+fn test_compression_threshold() {
+    let lines: Vec<_> = (0..200)
+        .map(|idx| format!("generated line {}", idx))
+        .collect();
+    let mut settings = Settings::new();
+    settings.set_compression_threshold(Some(1024));
+    settings.bind(|| {
+        insta::assert_snapshot!("compressed", lines.join("\n"));
+    });
+
+    let snapshot =
+        insta::Snapshot::from_file("tests/snapshots/test_settings__compressed.snap").unwrap();
+    assert_eq!(snapshot.metadata().compression(), Some("gzip"));
+    assert_eq!(snapshot.contents_str(), lines.join("\n"));
+}
@@ -0,0 +1,44 @@
+#![cfg(feature = "macros")]
+
+use insta::{assert_yaml_snapshot, Settings};
+use std::collections::HashMap;
+
+fn make_map() -> HashMap<&'static str, &'static str> {
+    let mut map = HashMap::new();
+    map.insert("a", "first value");
+    map.insert("b", "second value");
+    map.insert("c", "third value");
+    map
+}
+
+#[test]
+#[insta::settings(sort_maps = true, snapshot_suffix = "v2")]
+fn test_settings_attribute() {
+    assert_eq!(Settings::clone_current().snapshot_suffix(), Some("v2"));
+    assert_yaml_snapshot!(make_map(), @r###"
+    ---
+    a: first value
+    b: second value
+    c: third value
+    "###);
+}
+
+#[test]
+#[insta::settings(tags = ["attribute"])]
+fn test_settings_attribute_result() -> Result<(), String> {
+    assert_eq!(Settings::clone_current().tags(), ["attribute"]);
+    "42".parse::<u32>().map_err(|x| x.to_string())?;
+    Ok(())
+}
+
+#[test]
+fn test_settings_attribute_async() {
+    #[insta::settings(sort_maps = true)]
+    async fn check() {
+        async {}.await;
+        assert!(Settings::clone_current().sort_maps());
+    }
+
+    futures_executor::block_on(check());
+    assert!(!Settings::clone_current().sort_maps());
+}
@@ -0,0 +1,35 @@
+#![cfg(feature = "tracing")]
+
+use insta::{assert_snapshot, capture_tracing};
+use tracing::{debug, info, info_span, warn, Level};
+
+fn process(items: &[u32]) {
+    let span = info_span!(target: "worker", "process", count = items.len());
+    let _guard = span.enter();
+    for item in items {
+        let span = info_span!(target: "worker", "item", id = item);
+        let _guard = span.enter();
+        if *item > 1 {
+            warn!(target: "worker", value = item, "too large");
+        } else {
+            debug!(target: "worker", "ok");
+        }
+    }
+    info!(target: "worker", "done");
+}
+
+#[test]
+fn test_capture_tracing() {
+    let output = capture_tracing(Level::DEBUG, || process(&[1, 2]));
+    assert_snapshot!(output, @r###"
+    DEBUG worker: process{count=2}: item{id=1}: ok
+     WARN worker: process{count=2}: item{id=2}: too large value=2
+     INFO worker: process{count=2}: done
+    "###);
+}
+
+#[test]
+fn test_capture_tracing_filtered() {
+    let output = capture_tracing(Level::WARN, || process(&[1, 2]));
+    assert_snapshot!(output, @" WARN worker: too large value=2");
+}
@@ -0,0 +1,99 @@
+#![cfg(feature = "xml")]
+
+use insta::assert_xml_snapshot;
+
+const FEED: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<!-- generated -->
+<feed xmlns="http://www.w3.org/2005/Atom"><title>Example</title>
+  <entry lang="en"   id='1'><title>First</title><updated>2024-01-01T10:00:00Z</updated>
+  <link href="/1" rel="alternate"/></entry>
+  <entry id="2"><title>Second &amp; last</title>
+     <updated>2024-02-01T10:00:00Z</updated>
+     <summary><![CDATA[<b>bold</b>]]></summary>
+  </entry>
+</feed>"#;
+
+#[test]
+fn test_xml_canonical() {
+    assert_xml_snapshot!(FEED, @r###"
+    <?xml version="1.0" encoding="utf-8"?>
+    <!-- generated -->
+    <feed xmlns="http://www.w3.org/2005/Atom">
+      <title>Example</title>
+      <entry id="1" lang="en">
+        <title>First</title>
+        <updated>2024-01-01T10:00:00Z</updated>
+        <link href="/1" rel="alternate"/>
+      </entry>
+      <entry id="2">
+        <title>Second &amp; last</title>
+        <updated>2024-02-01T10:00:00Z</updated>
+        <summary>
+          <![CDATA[<b>bold</b>]]>
+        </summary>
+      </entry>
+    </feed>
+    "###);
+}
+
+#[test]
+fn test_xml_soap() {
+    let envelope = "<soap:Envelope xmlns:soap='http://schemas.xmlsoap.org/soap/envelope/'>\
+        <soap:Body><GetUser><Id>42</Id><Empty></Empty></GetUser></soap:Body></soap:Envelope>";
+    assert_xml_snapshot!(envelope, @r###"
+    <soap:Envelope xmlns:soap="http://schemas.xmlsoap.org/soap/envelope/">
+      <soap:Body>
+        <GetUser>
+          <Id>42</Id>
+          <Empty/>
+        </GetUser>
+      </soap:Body>
+    </soap:Envelope>
+    "###);
+}
+
+#[cfg(feature = "redactions")]
+#[test]
+fn test_xml_redactions() {
+    assert_xml_snapshot!(FEED, {
+        ".feed.entry.updated" => "[timestamp]",
+        r#".feed.entry["@id"]"# => "[id]",
+        ".feed.entry.summary" => "[summary]",
+    }, @r###"
+    <?xml version="1.0" encoding="utf-8"?>
+    <!-- generated -->
+    <feed xmlns="http://www.w3.org/2005/Atom">
+      <title>Example</title>
+      <entry id="[id]" lang="en">
+        <title>First</title>
+        <updated>[timestamp]</updated>
+        <link href="/1" rel="alternate"/>
+      </entry>
+      <entry id="[id]">
+        <title>Second &amp; last</title>
+        <updated>[timestamp]</updated>
+        <summary>[summary]</summary>
+      </entry>
+    </feed>
+    "###);
+}
+
+#[cfg(feature = "redactions")]
+#[test]
+fn test_xml_redactions_escaped() {
+    assert_xml_snapshot!(r#"<user name="Jane &quot;J&quot; Doe"><token>s3cr3t</token></user>"#, {
+        ".user.token" => "<redacted & removed>",
+        r#".user["@name"]"# => r#"<"name">"#,
+    }, @r###"
+    <user name="&lt;&quot;name&quot;&gt;">
+      <token>&lt;redacted &amp; removed&gt;</token>
+    </user>
+    "###);
+}
+
+#[test]
+fn test_xml_entities() {
+    assert_xml_snapshot!("<p a='&#x3C;&apos;&#62;'>&lt;b&gt; &#38; &unknown; &amp</p>", @r###"
+    <p a="&lt;'&gt;">&lt;b&gt; &amp; &amp;unknown; &amp;amp</p>
+    "###);
+}